@@ -146,13 +146,13 @@ async fn create_tournament(
 		.validate(|| validators::range(req.max_participants, 2, 256, "max_participants"))
 		.build_unit();
 
-	if let Err(validation_errors) = validation_result {
-		if let Some(first_error) = validation_errors.first() {
-			return Err(ApiError::validation_with_field(
-				&first_error.message,
-				first_error.field.as_deref().unwrap_or("unknown"),
-			));
-		}
+	if let Err(validation_errors) = validation_result
+		&& let Some(first_error) = validation_errors.first()
+	{
+		return Err(ApiError::validation_with_field(
+			&first_error.message,
+			first_error.field.as_deref().unwrap_or("unknown"),
+		));
 	}
 
 	// Simulate tournament business logic errors
@@ -218,9 +218,10 @@ async fn rate_limited_endpoint() -> ActixResult<HttpResponse, ApiError> {
 	let should_rate_limit = std::env::var("DEMO_RATE_LIMIT").unwrap_or("false".to_string()) == "true";
 
 	if should_rate_limit {
-		return Err(ApiError::RateLimit {
-			message: "Too many requests. Please try again in 60 seconds".to_string(),
-		});
+		return Err(ApiError::rate_limit(
+			"Too many requests. Please try again in 60 seconds",
+			60,
+		));
 	}
 
 	Ok(HttpResponse::Ok().json(serde_json::json!({
@@ -230,6 +231,7 @@ async fn rate_limited_endpoint() -> ActixResult<HttpResponse, ApiError> {
 }
 
 /// Demonstration of error handling with ApiResult helper
+#[allow(dead_code)]
 async fn complex_operation() -> ApiResult<String> {
 	// Simulate multiple operations that can fail
 	validate_permissions()?;
@@ -265,13 +267,13 @@ async fn health_check() -> ActixResult<HttpResponse, ApiError> {
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
 	// Initialize logging
-	logging::init();
-	logging::startup_info(8080);
+	logging::init(&Default::default());
+	logging::startup_info("0.0.0.0", 8080);
 
 	println!("\n🎯 Error Handling Demo Server");
 	println!("================================");
 	println!("Try these endpoints to see different error types:");
-	println!("");
+	println!();
 	println!("✅ Success cases:");
 	println!("  curl -X GET http://localhost:8080/health");
 	println!("  curl -X GET http://localhost:8080/users/valid_user");
@@ -280,7 +282,7 @@ async fn main() -> std::io::Result<()> {
 	println!(
 		"    -d '{{\"username\":\"testuser\",\"email\":\"test@example.com\",\"password\":\"Password123\"}}'"
 	);
-	println!("");
+	println!();
 	println!("❌ Error cases:");
 	println!("  curl -X GET http://localhost:8080/users/nonexistent    # 404 Not Found");
 	println!("  curl -X DELETE http://localhost:8080/users/unauthorized # 403 Forbidden");
@@ -294,11 +296,11 @@ async fn main() -> std::io::Result<()> {
 	println!(
 		"    -d '{{\"name\":\"forbidden tournament\",\"description\":\"test\",\"max_participants\":300}}'"
 	);
-	println!("");
+	println!();
 	println!("🔧 Environment variables:");
 	println!("  DEMO_EXTERNAL_FAIL=true   # Makes /sync endpoint fail");
 	println!("  DEMO_RATE_LIMIT=true      # Makes /rate-limited endpoint fail");
-	println!("");
+	println!();
 
 	HttpServer::new(|| {
 		App::new()
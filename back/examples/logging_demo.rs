@@ -13,10 +13,10 @@ use std::time::Instant;
 
 fn main() {
 	// Initialize the logging system
-	logging::init();
+	logging::init(&Default::default());
 
 	// Demonstrate startup logging
-	logging::startup_info(4000);
+	logging::startup_info("0.0.0.0", 4000);
 
 	// Simulate database operations
 	simulate_database_operations();
@@ -25,7 +25,7 @@ struct PersonData {
 #[actix_web::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
 	// Initialize logging
-	logging::init();
+	logging::init(&Default::default());
 
 	println!("🦀 SurrealDB .await? Pattern Demo");
 	println!("==================================");
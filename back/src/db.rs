@@ -0,0 +1,173 @@
+//! Database handle shared by the services
+//!
+//! Services and repositories take a [`Database`] (see [`crate::AppState::db`])
+//! instead of reaching for the global client, so every query the API runs
+//! goes through this one type, and each one is timed by a
+//! [`logging::db_span`] labelled with the kind of statement it starts with.
+
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use surrealdb::engine::any::Any;
+use surrealdb::error::{Api, Db};
+use surrealdb::{RecordId, Response, Surreal, method};
+
+use crate::utils::error::ApiResult;
+use crate::utils::logging;
+
+/// Cloneable handle on the database connection
+#[derive(Clone)]
+pub struct Database {
+	client: Surreal<Any>,
+}
+
+impl Database {
+	pub fn new(client: Surreal<Any>) -> Self {
+		Self { client }
+	}
+
+	/// Start a query; bind its parameters and await it
+	pub fn query(&self, sql: impl AsRef<str>) -> Query<'_> {
+		let sql = sql.as_ref();
+		Query {
+			operation: operation(sql),
+			inner: self.client.query(sql.to_owned()),
+		}
+	}
+
+	/// Fetch a single record
+	pub async fn select<T: DeserializeOwned>(&self, id: &RecordId) -> surrealdb::Result<Option<T>> {
+		let _span = logging::db_span("select");
+		self.client.select(id.clone()).await
+	}
+
+	/// Delete a single record, returning it
+	pub async fn delete<T: DeserializeOwned>(&self, id: &RecordId) -> surrealdb::Result<Option<T>> {
+		let _span = logging::db_span("delete");
+		self.client.delete(id.clone()).await
+	}
+}
+
+/// Query built by [`Database::query`], timed while it runs
+pub struct Query<'r> {
+	operation: &'static str,
+	inner: method::Query<'r, Any>,
+}
+
+impl Query<'_> {
+	/// Bind parameters to the query
+	pub fn bind(self, bindings: impl Serialize + 'static) -> Self {
+		Self {
+			inner: self.inner.bind(bindings),
+			..self
+		}
+	}
+}
+
+impl<'r> IntoFuture for Query<'r> {
+	type Output = surrealdb::Result<Response>;
+	type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'r>>;
+
+	fn into_future(self) -> Self::IntoFuture {
+		Box::pin(async move {
+			let _span = logging::db_span(self.operation);
+			self.inner.await
+		})
+	}
+}
+
+/// Like [`Response::check`], but returns the error that failed the transaction
+///
+/// When a statement fails inside `BEGIN`/`COMMIT`, SurrealDB reports every
+/// other statement of the transaction as not executed; `check` would return
+/// one of those instead of the cause (a `THROW`, a unique index...).
+pub fn check_transaction(mut response: Response) -> ApiResult<Response> {
+	let mut errors: Vec<(usize, surrealdb::Error)> = response.take_errors().into_iter().collect();
+	errors.sort_by_key(|(index, _)| *index);
+	let cause = errors
+		.iter()
+		.position(|(_, error)| !not_executed(error))
+		.unwrap_or_default();
+	match errors.into_iter().nth(cause) {
+		Some((_, error)) => Err(error.into()),
+		None => Ok(response),
+	}
+}
+
+/// Whether a statement was skipped because its transaction failed
+fn not_executed(error: &surrealdb::Error) -> bool {
+	match error {
+		surrealdb::Error::Db(Db::QueryNotExecuted | Db::QueryNotExecutedDetail { .. }) => true,
+		surrealdb::Error::Api(Api::Query(message)) => {
+			message.starts_with("The query was not executed due to a failed transaction")
+		}
+		_ => false,
+	}
+}
+
+/// Span label for a query: its leading keyword, so the label set stays small
+fn operation(sql: &str) -> &'static str {
+	let keyword = sql
+		.split(|c: char| c.is_whitespace() || c == ';')
+		.find(|word| !word.is_empty())
+		.unwrap_or_default();
+	match keyword.to_ascii_uppercase().as_str() {
+		"SELECT" => "select",
+		"CREATE" => "create",
+		"INSERT" => "insert",
+		"UPDATE" => "update",
+		"UPSERT" => "upsert",
+		"DELETE" => "delete",
+		"RELATE" => "relate",
+		"BEGIN" => "transaction",
+		"LET" | "RETURN" | "FOR" | "IF" => "script",
+		_ => "query",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::DB;
+	use crate::test_utils;
+
+	#[test]
+	fn test_queries_are_labelled_by_their_leading_statement() {
+		assert_eq!(operation("SELECT * FROM poll"), "select");
+		assert_eq!(operation("\n\t\tupdate $poll SET voters += 1"), "update");
+		assert_eq!(
+			operation("BEGIN TRANSACTION; CREATE vote; COMMIT"),
+			"transaction"
+		);
+		assert_eq!(operation("LET $x = 1; RETURN $x"), "script");
+		assert_eq!(operation("INFO FOR DB"), "query");
+		assert_eq!(operation("BEGIN;"), "transaction");
+		assert_eq!(operation(""), "query");
+	}
+
+	#[actix_web::test]
+	async fn test_check_transaction_returns_the_thrown_error() {
+		test_utils::setup();
+		let db = Database::new(DB.clone());
+
+		let response = db
+			.query(
+				"BEGIN TRANSACTION;
+				CREATE scratch:one;
+				THROW 'Closed';
+				COMMIT TRANSACTION;",
+			)
+			.await
+			.unwrap();
+		let err = check_transaction(response).unwrap_err();
+		assert_eq!(err.to_string(), "Conflict: Closed");
+
+		let created: Option<serde_json::Value> = db
+			.select(&RecordId::from(("scratch", "one")))
+			.await
+			.unwrap();
+		assert!(created.is_none());
+	}
+}
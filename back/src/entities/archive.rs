@@ -0,0 +1,280 @@
+//! Versioned, self-describing tournament archive format
+//!
+//! An archive carries a tournament's settings, participants and matches
+//! (with results). Records reference each other through the ids they had on
+//! the exporting instance; the importer creates fresh records and remaps
+//! those references. Users are matched by username, since user ids differ
+//! between instances; exports never carry email addresses.
+//!
+//! Every format change appends a step to [`UPGRADES`] that rewrites an
+//! archive of the previous version, which bumps [`ARCHIVE_VERSION`], so
+//! archives of any older version still import.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{MatchStatus, Tournament};
+
+/// Value of the `format` field identifying a tournament archive
+pub const ARCHIVE_FORMAT: &str = "liga-muertos/tournament";
+
+/// Version written by this build, one past the last upgrade step
+pub const ARCHIVE_VERSION: u32 = UPGRADES.len() as u32 + 1;
+
+/// Rewrites an archive of version N into version N + 1
+pub type ArchiveUpgrade = fn(Value) -> Result<Value, String>;
+
+/// Upgrade steps; `UPGRADES[i]` turns version `i + 1` into version `i + 2`
+pub const UPGRADES: &[ArchiveUpgrade] = &[];
+
+/// Complete tournament archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TournamentArchive {
+	/// Always [`ARCHIVE_FORMAT`]
+	pub format: String,
+	pub version: u32,
+	/// Version of the application that wrote the archive
+	pub generator: String,
+	pub exported_at: DateTime<Utc>,
+	pub tournament: ArchivedTournament,
+	pub participants: Vec<ArchivedParticipant>,
+	pub matches: Vec<ArchivedMatch>,
+}
+
+/// Tournament settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedTournament {
+	/// Id on the exporting instance
+	pub id: String,
+	pub name: String,
+	pub description: String,
+	pub published: bool,
+	pub season: Option<String>,
+	pub created_at: DateTime<Utc>,
+}
+
+/// A participant and the user behind it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedParticipant {
+	/// Id on the exporting instance, referenced by matches
+	pub id: String,
+	/// User id on the exporting instance
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub user: Option<String>,
+	pub username: Option<String>,
+	/// Only filled in by third-party exports that carry one (see
+	/// `services::importers`); exported archives leave it out
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub email: Option<String>,
+	pub joined_at: DateTime<Utc>,
+}
+
+/// A match with its schedule and result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedMatch {
+	/// Id on the exporting instance
+	pub id: String,
+	pub round: u32,
+	pub position: u32,
+	/// Participant id (as in [`ArchivedParticipant::id`])
+	pub home: Option<String>,
+	pub away: Option<String>,
+	pub status: MatchStatus,
+	pub scheduled_at: Option<DateTime<Utc>>,
+	pub duration_minutes: u32,
+	pub started_at: Option<DateTime<Utc>>,
+	pub result: Option<ArchivedResult>,
+}
+
+/// Match score; who reported it is not kept across instances
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedResult {
+	pub home_score: u32,
+	pub away_score: u32,
+	pub reported_at: DateTime<Utc>,
+	pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+/// Where an imported document comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportSource {
+	/// A [`TournamentArchive`] written by this application
+	#[default]
+	Archive,
+	/// Challonge API v1 tournament JSON with participants and matches
+	Challonge,
+	/// start.gg GraphQL `event` query result with entrants and sets
+	#[serde(rename = "startgg")]
+	StartGg,
+}
+
+/// Field of a third-party export that has no counterpart here
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnmappedField {
+	/// Path in the source document, e.g. `participants[].seed`
+	pub field: String,
+	/// How many records carried it
+	pub occurrences: usize,
+	pub reason: String,
+}
+
+/// Archive built from a third-party export
+#[derive(Debug, Clone)]
+pub struct ConvertedArchive {
+	pub archive: TournamentArchive,
+	pub unmapped: Vec<UnmappedField>,
+}
+
+/// Outcome of importing a tournament
+#[derive(Debug, Clone, Serialize)]
+pub struct TournamentImportReport {
+	pub source: ImportSource,
+	pub dry_run: bool,
+	/// Created tournament (never on dry runs)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub tournament: Option<Tournament>,
+	/// What would be imported (dry runs only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub archive: Option<TournamentArchive>,
+	pub unmapped: Vec<UnmappedField>,
+}
+
+/// Why an archive can't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchiveError {
+	NotAnArchive,
+	UnsupportedVersion(u32),
+	Upgrade { from: u32, message: String },
+	Invalid(String),
+}
+
+impl std::fmt::Display for ArchiveError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ArchiveError::NotAnArchive => write!(f, "Not a tournament archive"),
+			ArchiveError::UnsupportedVersion(version) => write!(
+				f,
+				"Archive version {version} is not supported (newest is {ARCHIVE_VERSION})"
+			),
+			ArchiveError::Upgrade { from, message } => {
+				write!(
+					f,
+					"Failed to upgrade archive from version {from}: {message}"
+				)
+			}
+			ArchiveError::Invalid(message) => write!(f, "Invalid archive: {message}"),
+		}
+	}
+}
+
+impl TournamentArchive {
+	/// Read an archive of any supported version
+	pub fn from_json(value: Value) -> Result<Self, ArchiveError> {
+		Self::from_json_with(value, UPGRADES)
+	}
+
+	/// Read an archive, upgrading it with the given steps
+	pub fn from_json_with(
+		mut value: Value,
+		upgrades: &[ArchiveUpgrade],
+	) -> Result<Self, ArchiveError> {
+		if value.get("format").and_then(Value::as_str) != Some(ARCHIVE_FORMAT) {
+			return Err(ArchiveError::NotAnArchive);
+		}
+		let version = value
+			.get("version")
+			.and_then(Value::as_u64)
+			.ok_or(ArchiveError::NotAnArchive)? as u32;
+
+		let latest = upgrades.len() as u32 + 1;
+		if version == 0 || version > latest {
+			return Err(ArchiveError::UnsupportedVersion(version));
+		}
+
+		for (step, upgrade) in upgrades.iter().enumerate().skip(version as usize - 1) {
+			let from = step as u32 + 1;
+			value = upgrade(value).map_err(|message| ArchiveError::Upgrade { from, message })?;
+			value["version"] = Value::from(from + 1);
+		}
+
+		serde_json::from_value(value).map_err(|e| ArchiveError::Invalid(e.to_string()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	fn current() -> Value {
+		json!({
+			"format": ARCHIVE_FORMAT,
+			"version": ARCHIVE_VERSION,
+			"generator": "0.1.0",
+			"exported_at": "2025-11-02T00:00:00Z",
+			"tournament": {
+				"id": "tournament:copa",
+				"name": "Copa Catrina",
+				"description": "Día de Muertos cup",
+				"published": true,
+				"season": "2025-apertura",
+				"created_at": "2025-10-01T00:00:00Z"
+			},
+			"participants": [
+				{ "id": "participant:a", "user": "user:ana", "username": "ana", "joined_at": "2025-10-02T00:00:00Z" }
+			],
+			"matches": [{
+				"id": "match:m1", "round": 1, "position": 1,
+				"home": "participant:a", "away": null,
+				"status": "pending", "scheduled_at": null, "duration_minutes": 60,
+				"started_at": null, "result": null
+			}]
+		})
+	}
+
+	#[test]
+	fn test_reads_current_version() {
+		let archive = TournamentArchive::from_json(current()).unwrap();
+		assert_eq!(archive.tournament.name, "Copa Catrina");
+		assert_eq!(archive.matches[0].home.as_deref(), Some("participant:a"));
+	}
+
+	#[test]
+	fn test_rejects_foreign_and_future_documents() {
+		let err = TournamentArchive::from_json(json!({ "name": "Copa" })).unwrap_err();
+		assert_eq!(err, ArchiveError::NotAnArchive);
+
+		let mut future = current();
+		future["version"] = json!(ARCHIVE_VERSION + 1);
+		let err = TournamentArchive::from_json(future).unwrap_err();
+		assert_eq!(err, ArchiveError::UnsupportedVersion(ARCHIVE_VERSION + 1));
+	}
+
+	#[test]
+	fn test_older_versions_are_upgraded_step_by_step() {
+		// Pretend the current format is version 2, whose predecessor called
+		// `duration_minutes` `length`
+		fn rename_length(mut value: Value) -> Result<Value, String> {
+			for game in value["matches"].as_array_mut().ok_or("matches missing")? {
+				let length = game
+					.as_object_mut()
+					.and_then(|game| game.remove("length"))
+					.ok_or("length missing")?;
+				game["duration_minutes"] = length;
+			}
+			Ok(value)
+		}
+
+		let mut old = current();
+		old["version"] = json!(1);
+		let game = old["matches"][0].as_object_mut().unwrap();
+		let length = game.remove("duration_minutes").unwrap();
+		game.insert("length".to_string(), length);
+
+		let archive = TournamentArchive::from_json_with(old, &[rename_length]).unwrap();
+		assert_eq!(archive.version, 2);
+		assert_eq!(archive.matches[0].duration_minutes, 60);
+	}
+}
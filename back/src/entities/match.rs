@@ -0,0 +1,271 @@
+//! Match entity definitions for tournament fixtures and results
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::RecordId;
+
+use super::{MAX_SLOT_MINUTES, MIN_SLOT_MINUTES};
+use crate::utils::error::validation::{Validate, ValidationErrors, ValidationResult, validators};
+use crate::utils::time::TimeSlot;
+
+/// Length of a match slot when none is given
+pub const DEFAULT_MATCH_MINUTES: u32 = 60;
+
+/// Full match record as stored in the database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Match {
+	pub id: RecordId,
+	pub tournament: RecordId,
+	/// Round number (1-based)
+	pub round: u32,
+	/// Order of the match within its round
+	pub position: u32,
+	/// Home participant (None while the slot is still to be decided)
+	pub home: Option<RecordId>,
+	/// Away participant (None while the slot is still to be decided)
+	pub away: Option<RecordId>,
+	pub status: MatchStatus,
+	/// Planned kickoff time
+	pub scheduled_at: Option<DateTime<Utc>>,
+	/// Length of the slot booked for the match
+	#[serde(default = "default_match_minutes")]
+	pub duration_minutes: u32,
+	/// Bumped whenever the kickoff time or the status visible in calendars changes
+	#[serde(default)]
+	pub schedule_revision: u32,
+	/// Actual kickoff time
+	pub started_at: Option<DateTime<Utc>>,
+	pub result: Option<MatchResult>,
+	pub created_at: DateTime<Utc>,
+	pub updated_at: DateTime<Utc>,
+}
+
+impl Match {
+	/// Kickoff time used for locking: the actual start, or the planned one
+	pub fn kickoff(&self) -> Option<DateTime<Utc>> {
+		self.started_at.or(self.scheduled_at)
+	}
+
+	/// Check whether the match has kicked off at the given instant
+	pub fn has_started(&self, now: DateTime<Utc>) -> bool {
+		!matches!(self.status, MatchStatus::Pending | MatchStatus::Scheduled)
+			|| self.kickoff().is_some_and(|kickoff| kickoff <= now)
+	}
+
+	/// Time slot booked for the match, once scheduled
+	pub fn slot(&self) -> Option<TimeSlot> {
+		self
+			.scheduled_at
+			.map(|starts_at| TimeSlot::starting_at(starts_at, self.duration_minutes))
+	}
+
+	/// Whether both participants are known
+	pub fn is_ready(&self) -> bool {
+		self.home.is_some() && self.away.is_some()
+	}
+
+	/// Side a participant plays on, if any
+	pub fn side_of(&self, participant: &RecordId) -> Option<MatchSide> {
+		if self.home.as_ref() == Some(participant) {
+			Some(MatchSide::Home)
+		} else if self.away.as_ref() == Some(participant) {
+			Some(MatchSide::Away)
+		} else {
+			None
+		}
+	}
+}
+
+/// Data for creating a new match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateMatchData {
+	pub round: u32,
+	pub position: u32,
+	pub home: Option<RecordId>,
+	pub away: Option<RecordId>,
+	pub scheduled_at: Option<DateTime<Utc>>,
+	pub duration_minutes: Option<u32>,
+}
+
+impl Validate for CreateMatchData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		if self.home.is_some() && self.home == self.away {
+			errors.add_error(
+				"A participant cannot play against itself",
+				"away",
+				"SAME_PARTICIPANT",
+			);
+		}
+		if let Some(minutes) = self.duration_minutes {
+			errors.check(validators::range(
+				minutes as i32,
+				MIN_SLOT_MINUTES as i32,
+				MAX_SLOT_MINUTES as i32,
+				"duration_minutes",
+			));
+		}
+		errors.into_result()
+	}
+}
+
+fn default_match_minutes() -> u32 {
+	DEFAULT_MATCH_MINUTES
+}
+
+/// Data for reporting a match score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportMatchData {
+	pub home_score: u32,
+	pub away_score: u32,
+}
+
+/// Any pair of scores is a valid report
+impl Validate for ReportMatchData {
+	fn validate(&self) -> ValidationResult<()> {
+		Ok(())
+	}
+}
+
+/// Match status enumeration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStatus {
+	#[default]
+	Pending,
+	Scheduled,
+	InProgress,
+	/// Score reported, waiting for confirmation
+	Reported,
+	/// Score confirmed, result is final
+	Completed,
+	Cancelled,
+}
+
+/// Side of a match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchSide {
+	Home,
+	Away,
+}
+
+/// Outcome of a match from the home side's perspective
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchOutcome {
+	Home,
+	Away,
+	Draw,
+}
+
+impl MatchOutcome {
+	/// Outcome implied by a score line
+	pub fn from_score(home_score: u32, away_score: u32) -> Self {
+		match home_score.cmp(&away_score) {
+			std::cmp::Ordering::Greater => MatchOutcome::Home,
+			std::cmp::Ordering::Less => MatchOutcome::Away,
+			std::cmp::Ordering::Equal => MatchOutcome::Draw,
+		}
+	}
+}
+
+/// Reported (and eventually confirmed) match score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchResult {
+	pub home_score: u32,
+	pub away_score: u32,
+	pub reported_by: RecordId,
+	pub reported_at: DateTime<Utc>,
+	pub confirmed_by: Option<RecordId>,
+	pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+impl MatchResult {
+	/// Outcome of the reported score
+	pub fn outcome(&self) -> MatchOutcome {
+		MatchOutcome::from_score(self.home_score, self.away_score)
+	}
+
+	/// Whether the result has been confirmed and is final
+	pub fn is_confirmed(&self) -> bool {
+		self.confirmed_at.is_some()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::{Duration, Utc};
+	use surrealdb::RecordId;
+
+	fn fixture(status: MatchStatus, scheduled_at: Option<DateTime<Utc>>) -> Match {
+		Match {
+			id: RecordId::from(("match", "match123")),
+			tournament: RecordId::from(("tournament", "tourney123")),
+			round: 1,
+			position: 1,
+			home: Some(RecordId::from(("participant", "home"))),
+			away: Some(RecordId::from(("participant", "away"))),
+			status,
+			scheduled_at,
+			duration_minutes: DEFAULT_MATCH_MINUTES,
+			schedule_revision: 0,
+			started_at: None,
+			result: None,
+			created_at: Utc::now(),
+			updated_at: Utc::now(),
+		}
+	}
+
+	#[test]
+	fn test_match_has_started() {
+		let now = Utc::now();
+
+		let upcoming = fixture(MatchStatus::Scheduled, Some(now + Duration::hours(1)));
+		assert!(!upcoming.has_started(now));
+
+		let past_kickoff = fixture(MatchStatus::Scheduled, Some(now - Duration::minutes(1)));
+		assert!(past_kickoff.has_started(now));
+
+		let unscheduled = fixture(MatchStatus::Pending, None);
+		assert!(!unscheduled.has_started(now));
+
+		let in_progress = fixture(MatchStatus::InProgress, None);
+		assert!(in_progress.has_started(now));
+	}
+
+	#[test]
+	fn test_match_side_of() {
+		let game = fixture(MatchStatus::Pending, None);
+		assert_eq!(
+			game.side_of(&RecordId::from(("participant", "home"))),
+			Some(MatchSide::Home)
+		);
+		assert_eq!(
+			game.side_of(&RecordId::from(("participant", "away"))),
+			Some(MatchSide::Away)
+		);
+		assert_eq!(
+			game.side_of(&RecordId::from(("participant", "other"))),
+			None
+		);
+		assert!(game.is_ready());
+	}
+
+	#[test]
+	fn test_match_outcome_from_score() {
+		assert_eq!(MatchOutcome::from_score(2, 1), MatchOutcome::Home);
+		assert_eq!(MatchOutcome::from_score(0, 3), MatchOutcome::Away);
+		assert_eq!(MatchOutcome::from_score(1, 1), MatchOutcome::Draw);
+	}
+
+	#[test]
+	fn test_match_status_serialization() {
+		let json = serde_json::to_string(&MatchStatus::InProgress).unwrap();
+		assert_eq!(json, "\"in_progress\"");
+
+		let deserialized: MatchStatus = serde_json::from_str("\"completed\"").unwrap();
+		assert_eq!(deserialized, MatchStatus::Completed);
+	}
+}
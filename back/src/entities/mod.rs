@@ -6,13 +6,27 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+pub mod archive;
+pub mod r#match;
 pub mod participant;
+pub mod poll;
+pub mod prediction;
+pub mod schedule;
 pub mod tournament;
+pub mod twitch;
 pub mod user;
+pub mod webhook;
 
+pub use archive::*;
+pub use r#match::*;
 pub use participant::*;
+pub use poll::*;
+pub use prediction::*;
+pub use schedule::*;
 pub use tournament::*;
+pub use twitch::*;
 pub use user::*;
+pub use webhook::*;
 
 /// Common fields that appear in many entities
 #[derive(Debug, Clone, Serialize, Deserialize)]
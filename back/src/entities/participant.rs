@@ -2,6 +2,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use surrealdb::RecordId;
 
 /// Full participant record as stored in the database
@@ -57,6 +58,63 @@ pub struct FullParticipantInfo {
 	pub joined_at: DateTime<Utc>,
 }
 
+/// One row of a participant bulk import (CSV or JSON)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ParticipantImportRow {
+	pub username: Option<String>,
+	pub email: Option<String>,
+}
+
+/// Outcome of importing one row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRowResult {
+	/// 1-based position of the row among the data rows
+	pub row: usize,
+	pub username: Option<String>,
+	pub email: Option<String>,
+	/// User the row resolved to
+	pub user: Option<RecordId>,
+	/// Validation errors by field (see `ValidationErrors::to_field_map`)
+	#[serde(skip_serializing_if = "HashMap::is_empty")]
+	pub errors: HashMap<String, Vec<String>>,
+}
+
+impl ImportRowResult {
+	pub fn is_valid(&self) -> bool {
+		self.errors.is_empty() && self.user.is_some()
+	}
+}
+
+/// Report returned by a participant bulk import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParticipantImportReport {
+	pub dry_run: bool,
+	/// Whether the participants were created (never on dry runs or errors)
+	pub committed: bool,
+	pub total: usize,
+	pub valid: usize,
+	pub invalid: usize,
+	pub rows: Vec<ImportRowResult>,
+}
+
+impl ParticipantImportReport {
+	pub fn new(rows: Vec<ImportRowResult>, dry_run: bool) -> Self {
+		let valid = rows.iter().filter(|row| row.is_valid()).count();
+		Self {
+			dry_run,
+			committed: false,
+			total: rows.len(),
+			valid,
+			invalid: rows.len() - valid,
+			rows,
+		}
+	}
+
+	pub fn has_errors(&self) -> bool {
+		self.invalid > 0
+	}
+}
+
 /// Participant status enumeration
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
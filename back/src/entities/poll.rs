@@ -0,0 +1,310 @@
+//! Poll entity definitions for live polls run during broadcasts
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::RecordId;
+
+use crate::utils::error::validation::{
+	Validate, ValidationError, ValidationErrors, ValidationResult, validators,
+};
+
+/// Smallest number of options a poll can offer
+pub const MIN_POLL_OPTIONS: usize = 2;
+
+/// Largest number of options a poll can offer
+pub const MAX_POLL_OPTIONS: usize = 10;
+
+/// Full poll record as stored in the database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Poll {
+	pub id: RecordId,
+	pub tournament: RecordId,
+	/// Match the poll is about, if any
+	pub r#match: Option<RecordId>,
+	pub question: String,
+	pub kind: PollKind,
+	/// Option labels; votes refer to them by index
+	pub options: Vec<String>,
+	/// When voting opens (immediately when None)
+	pub opens_at: Option<DateTime<Utc>>,
+	/// When voting closes (stays open until closed manually when None)
+	pub closes_at: Option<DateTime<Utc>>,
+	pub created_by: RecordId,
+	/// Final tally, archived once the poll is closed
+	pub results: Option<PollTally>,
+	/// Votes per option, counted as ballots come in
+	#[serde(default, skip_serializing)]
+	pub votes: Vec<u32>,
+	/// Number of users who voted
+	#[serde(default, skip_serializing)]
+	pub voters: u32,
+	pub created_at: DateTime<Utc>,
+	pub updated_at: DateTime<Utc>,
+}
+
+impl Poll {
+	/// Final tally once archived, the running count before
+	pub fn tally(&self) -> PollTally {
+		match &self.results {
+			Some(results) => results.clone(),
+			None => PollTally::from_counts(&self.options, &self.votes, self.voters),
+		}
+	}
+
+	/// Status of the poll at the given instant
+	pub fn status(&self, now: DateTime<Utc>) -> PollStatus {
+		if self.results.is_some() || self.closes_at.is_some_and(|closes_at| closes_at <= now) {
+			PollStatus::Closed
+		} else if self.opens_at.is_some_and(|opens_at| opens_at > now) {
+			PollStatus::Scheduled
+		} else {
+			PollStatus::Open
+		}
+	}
+
+	/// Check a ballot against the poll kind and options
+	pub fn check_choices(&self, choices: &[u32]) -> Result<(), &'static str> {
+		if choices.is_empty() {
+			return Err("Pick at least one option");
+		}
+		if self.kind == PollKind::Single && choices.len() > 1 {
+			return Err("This poll only allows one choice");
+		}
+		if choices
+			.iter()
+			.any(|&choice| choice as usize >= self.options.len())
+		{
+			return Err("Unknown poll option");
+		}
+		let mut unique = choices.to_vec();
+		unique.sort_unstable();
+		unique.dedup();
+		if unique.len() != choices.len() {
+			return Err("The same option was picked twice");
+		}
+		Ok(())
+	}
+}
+
+/// Whether voters pick one option or several
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PollKind {
+	#[default]
+	Single,
+	Multiple,
+}
+
+/// Lifecycle of a poll, derived from its open and close times
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PollStatus {
+	Scheduled,
+	Open,
+	Closed,
+}
+
+/// Data for creating a new poll
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePollData {
+	pub question: String,
+	#[serde(default)]
+	pub kind: PollKind,
+	pub options: Vec<String>,
+	/// Match (key) the poll is about
+	pub r#match: Option<String>,
+	pub opens_at: Option<DateTime<Utc>>,
+	pub closes_at: Option<DateTime<Utc>>,
+}
+
+impl Validate for CreatePollData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		errors.check(validators::length(self.question.trim(), 3, 200, "question"));
+
+		if !(MIN_POLL_OPTIONS..=MAX_POLL_OPTIONS).contains(&self.options.len()) {
+			errors.add(
+				ValidationError::with_field(
+					&format!("A poll needs between {MIN_POLL_OPTIONS} and {MAX_POLL_OPTIONS} options"),
+					"options",
+					"OPTION_COUNT",
+				)
+				.with_param("min", MIN_POLL_OPTIONS)
+				.with_param("max", MAX_POLL_OPTIONS),
+			);
+		}
+		for option in &self.options {
+			errors.check(validators::length(option.trim(), 1, 100, "options"));
+		}
+
+		if let (Some(opens_at), Some(closes_at)) = (self.opens_at, self.closes_at)
+			&& closes_at <= opens_at
+		{
+			errors.add_error(
+				"A poll must close after it opens",
+				"closes_at",
+				"CLOSES_BEFORE_OPENS",
+			);
+		}
+		errors.into_result()
+	}
+}
+
+/// Data for casting a vote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoteData {
+	/// Indexes of the picked options
+	pub choices: Vec<u32>,
+}
+
+/// Only the checks that don't need the poll; see [`Poll::check_choices`]
+impl Validate for VoteData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		if self.choices.is_empty() {
+			errors.add_error("Pick at least one option", "choices", "NO_CHOICE");
+		}
+		errors.into_result()
+	}
+}
+
+/// A single user's ballot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollVote {
+	pub id: RecordId,
+	pub poll: RecordId,
+	pub user: RecordId,
+	pub choices: Vec<u32>,
+	pub created_at: DateTime<Utc>,
+}
+
+/// Votes received by one option
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OptionTally {
+	pub option: u32,
+	pub label: String,
+	pub votes: u32,
+	/// Share of voters who picked this option (0-100)
+	pub percentage: f64,
+}
+
+/// Current (or final) count of a poll
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PollTally {
+	pub options: Vec<OptionTally>,
+	/// Number of users who voted
+	pub voters: u32,
+}
+
+impl PollTally {
+	/// Tally from the votes per option and the number of voters
+	///
+	/// Percentages are relative to the number of voters, so on multi-choice
+	/// polls they can add up to more than 100.
+	pub fn from_counts(options: &[String], votes: &[u32], voters: u32) -> Self {
+		let options = options
+			.iter()
+			.enumerate()
+			.map(|(index, label)| {
+				let votes = votes.get(index).copied().unwrap_or(0);
+				OptionTally {
+					option: index as u32,
+					label: label.clone(),
+					votes,
+					percentage: if voters == 0 {
+						0.0
+					} else {
+						f64::from(votes) * 100.0 / f64::from(voters)
+					},
+				}
+			})
+			.collect();
+
+		Self { options, voters }
+	}
+}
+
+/// Poll together with its live tally, as shown to viewers and overlays
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollView {
+	#[serde(flatten)]
+	pub poll: Poll,
+	pub status: PollStatus,
+	pub tally: PollTally,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::Duration;
+
+	fn poll(kind: PollKind) -> Poll {
+		Poll {
+			id: RecordId::from(("poll", "p1")),
+			tournament: RecordId::from(("tournament", "t1")),
+			r#match: None,
+			question: "Who takes the series?".to_string(),
+			kind,
+			options: vec![
+				"Catrina".to_string(),
+				"Calaca".to_string(),
+				"Nobody".to_string(),
+			],
+			opens_at: None,
+			closes_at: None,
+			created_by: RecordId::from(("user", "organizer")),
+			results: None,
+			votes: vec![3, 2, 1],
+			voters: 4,
+			created_at: Utc::now(),
+			updated_at: Utc::now(),
+		}
+	}
+
+	#[test]
+	fn test_poll_status_follows_open_and_close_times() {
+		let now = Utc::now();
+		let mut poll = poll(PollKind::Single);
+		assert_eq!(poll.status(now), PollStatus::Open);
+
+		poll.opens_at = Some(now + Duration::minutes(5));
+		assert_eq!(poll.status(now), PollStatus::Scheduled);
+
+		poll.opens_at = Some(now - Duration::minutes(5));
+		poll.closes_at = Some(now - Duration::seconds(1));
+		assert_eq!(poll.status(now), PollStatus::Closed);
+
+		poll.closes_at = None;
+		poll.results = Some(PollTally::from_counts(&poll.options, &[], 0));
+		assert_eq!(poll.status(now), PollStatus::Closed);
+	}
+
+	#[test]
+	fn test_check_choices() {
+		let single = poll(PollKind::Single);
+		assert!(single.check_choices(&[1]).is_ok());
+		assert!(single.check_choices(&[]).is_err());
+		assert!(single.check_choices(&[0, 1]).is_err());
+		assert!(single.check_choices(&[3]).is_err());
+
+		let multiple = poll(PollKind::Multiple);
+		assert!(multiple.check_choices(&[0, 2]).is_ok());
+		assert!(multiple.check_choices(&[2, 2]).is_err());
+	}
+
+	#[test]
+	fn test_tally_counts_voters_and_options() {
+		let mut poll = poll(PollKind::Multiple);
+		let tally = poll.tally();
+
+		assert_eq!(tally.voters, 4);
+		let votes: Vec<u32> = tally.options.iter().map(|option| option.votes).collect();
+		assert_eq!(votes, vec![3, 2, 1]);
+		assert_eq!(tally.options[0].percentage, 75.0);
+		assert_eq!(tally.options[2].label, "Nobody");
+
+		// Archived results win over the running count
+		poll.results = Some(PollTally::from_counts(&poll.options, &[], 0));
+		assert_eq!(poll.tally().voters, 0);
+	}
+}
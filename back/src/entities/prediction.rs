@@ -0,0 +1,251 @@
+//! Prediction entity definitions for viewer match predictions
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::RecordId;
+
+use super::{MatchOutcome, MatchResult};
+use crate::utils::error::validation::{Validate, ValidationErrors, ValidationResult};
+
+/// Points awarded for predicting the right outcome (home win, away win or draw)
+pub const OUTCOME_POINTS: u32 = 3;
+
+/// Extra points awarded when the exact score was also predicted
+pub const EXACT_SCORE_BONUS: u32 = 2;
+
+/// Full prediction record as stored in the database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Prediction {
+	pub id: RecordId,
+	pub r#match: RecordId,
+	pub tournament: RecordId,
+	pub user: RecordId,
+	pub outcome: MatchOutcome,
+	pub home_score: Option<u32>,
+	pub away_score: Option<u32>,
+	/// Points earned, set once the match result is confirmed
+	pub points: Option<u32>,
+	pub created_at: DateTime<Utc>,
+	pub updated_at: DateTime<Utc>,
+}
+
+impl Prediction {
+	/// Score this prediction against a confirmed result
+	pub fn score(&self, result: &MatchResult) -> u32 {
+		score_prediction(self.outcome, self.home_score, self.away_score, result)
+	}
+}
+
+/// Score a predicted outcome (and optional exact score) against a result
+pub fn score_prediction(
+	outcome: MatchOutcome,
+	home_score: Option<u32>,
+	away_score: Option<u32>,
+	result: &MatchResult,
+) -> u32 {
+	if outcome != result.outcome() {
+		return 0;
+	}
+
+	let exact = home_score == Some(result.home_score) && away_score == Some(result.away_score);
+	if exact {
+		OUTCOME_POINTS + EXACT_SCORE_BONUS
+	} else {
+		OUTCOME_POINTS
+	}
+}
+
+/// Data for making (or changing) a prediction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PredictMatchData {
+	pub outcome: MatchOutcome,
+	pub home_score: Option<u32>,
+	pub away_score: Option<u32>,
+}
+
+impl PredictMatchData {
+	/// The exact score, when given, must agree with the predicted outcome
+	pub fn is_consistent(&self) -> bool {
+		match (self.home_score, self.away_score) {
+			(Some(home), Some(away)) => MatchOutcome::from_score(home, away) == self.outcome,
+			(None, None) => true,
+			_ => false,
+		}
+	}
+}
+
+impl Validate for PredictMatchData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		if !self.is_consistent() {
+			errors.add_error(
+				"The predicted score must include both sides and agree with the predicted outcome",
+				"home_score",
+				"INCONSISTENT_SCORE",
+			);
+		}
+		errors.into_result()
+	}
+}
+
+/// One row of a prediction leaderboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PredictionStanding {
+	pub rank: u32,
+	pub user: RecordId,
+	pub username: Option<String>,
+	pub points: u32,
+	pub predictions: u32,
+	pub correct: u32,
+	pub exact: u32,
+}
+
+/// Sort standings by points (then exact hits, then correct picks) and assign
+/// competition ranks, so tied users share a rank ("1, 1, 3")
+pub fn rank_standings(mut standings: Vec<PredictionStanding>) -> Vec<PredictionStanding> {
+	standings.sort_by(|a, b| {
+		b.points
+			.cmp(&a.points)
+			.then(b.exact.cmp(&a.exact))
+			.then(b.correct.cmp(&a.correct))
+			.then(a.predictions.cmp(&b.predictions))
+	});
+
+	let mut previous: Option<(u32, u32, u32, u32)> = None;
+	let mut rank = 0;
+	for (index, standing) in standings.iter_mut().enumerate() {
+		let key = (
+			standing.points,
+			standing.exact,
+			standing.correct,
+			standing.predictions,
+		);
+		if previous != Some(key) {
+			rank = index as u32 + 1;
+			previous = Some(key);
+		}
+		standing.rank = rank;
+	}
+
+	standings
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::Utc;
+	use surrealdb::RecordId;
+
+	fn result(home_score: u32, away_score: u32) -> MatchResult {
+		MatchResult {
+			home_score,
+			away_score,
+			reported_by: RecordId::from(("user", "reporter")),
+			reported_at: Utc::now(),
+			confirmed_by: None,
+			confirmed_at: Some(Utc::now()),
+		}
+	}
+
+	fn standing(user: &str, points: u32, exact: u32, correct: u32) -> PredictionStanding {
+		PredictionStanding {
+			rank: 0,
+			user: RecordId::from(("user", user)),
+			username: None,
+			points,
+			predictions: 5,
+			correct,
+			exact,
+		}
+	}
+
+	#[test]
+	fn test_score_prediction() {
+		let final_score = result(2, 1);
+
+		assert_eq!(
+			score_prediction(MatchOutcome::Home, Some(2), Some(1), &final_score),
+			OUTCOME_POINTS + EXACT_SCORE_BONUS
+		);
+		assert_eq!(
+			score_prediction(MatchOutcome::Home, Some(3), Some(0), &final_score),
+			OUTCOME_POINTS
+		);
+		assert_eq!(
+			score_prediction(MatchOutcome::Home, None, None, &final_score),
+			OUTCOME_POINTS
+		);
+		assert_eq!(
+			score_prediction(MatchOutcome::Draw, Some(1), Some(1), &final_score),
+			0
+		);
+		assert_eq!(
+			score_prediction(MatchOutcome::Draw, None, None, &result(0, 0)),
+			OUTCOME_POINTS
+		);
+	}
+
+	#[test]
+	fn test_predict_match_data_consistency() {
+		let consistent = PredictMatchData {
+			outcome: MatchOutcome::Away,
+			home_score: Some(0),
+			away_score: Some(2),
+		};
+		assert!(consistent.is_consistent());
+
+		let contradictory = PredictMatchData {
+			outcome: MatchOutcome::Home,
+			home_score: Some(0),
+			away_score: Some(2),
+		};
+		assert!(!contradictory.is_consistent());
+
+		let half_score = PredictMatchData {
+			outcome: MatchOutcome::Home,
+			home_score: Some(1),
+			away_score: None,
+		};
+		assert!(!half_score.is_consistent());
+	}
+
+	#[test]
+	fn test_rank_standings_with_ties() {
+		let ranked = rank_standings(vec![
+			standing("c", 6, 0, 2),
+			standing("a", 10, 1, 3),
+			standing("b", 10, 1, 3),
+			standing("d", 10, 2, 2),
+		]);
+
+		let order: Vec<(String, u32)> = ranked
+			.iter()
+			.map(|s| (s.user.key().to_string(), s.rank))
+			.collect();
+
+		assert_eq!(order[0], ("d".to_string(), 1));
+		assert_eq!(order[1].1, 2);
+		assert_eq!(order[2].1, 2);
+		assert_eq!(order[3], ("c".to_string(), 4));
+	}
+
+	#[test]
+	fn test_prediction_match_field_serialization() {
+		let prediction = Prediction {
+			id: RecordId::from(("prediction", "p1")),
+			r#match: RecordId::from(("match", "m1")),
+			tournament: RecordId::from(("tournament", "t1")),
+			user: RecordId::from(("user", "u1")),
+			outcome: MatchOutcome::Draw,
+			home_score: None,
+			away_score: None,
+			points: None,
+			created_at: Utc::now(),
+			updated_at: Utc::now(),
+		};
+
+		let json = serde_json::to_value(&prediction).unwrap();
+		assert!(json.get("match").is_some());
+		assert_eq!(json["outcome"], "draw");
+	}
+}
@@ -0,0 +1,386 @@
+//! Scheduling entity definitions: organizer availability, slot planning and
+//! reschedule proposals
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::RecordId;
+
+use super::DEFAULT_MATCH_MINUTES;
+use crate::utils::error::validation::{Validate, ValidationErrors, ValidationResult, validators};
+use crate::utils::time::{self, TimeSlot};
+
+/// Shortest slot the scheduler accepts
+pub const MIN_SLOT_MINUTES: u32 = 10;
+
+/// Longest slot the scheduler accepts
+pub const MAX_SLOT_MINUTES: u32 = 24 * 60;
+
+/// Time window in which the organizer can host matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailabilityWindow {
+	pub id: RecordId,
+	pub tournament: RecordId,
+	pub starts_at: DateTime<Utc>,
+	pub ends_at: DateTime<Utc>,
+	pub created_at: DateTime<Utc>,
+}
+
+impl AvailabilityWindow {
+	pub fn slot(&self) -> TimeSlot {
+		TimeSlot::new(self.starts_at, self.ends_at)
+	}
+}
+
+/// Data for adding an availability window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAvailabilityData {
+	pub starts_at: DateTime<Utc>,
+	pub ends_at: DateTime<Utc>,
+}
+
+impl Validate for CreateAvailabilityData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		if self.ends_at <= self.starts_at {
+			errors.add_error(
+				"An availability window must end after it starts",
+				"ends_at",
+				"ENDS_BEFORE_STARTS",
+			);
+		}
+		errors.into_result()
+	}
+}
+
+/// Options for automatically scheduling a tournament's pending matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoScheduleData {
+	/// Length of each match slot
+	#[serde(default = "default_slot_minutes")]
+	pub slot_minutes: u32,
+	/// How many matches can be played at the same time
+	#[serde(default = "default_parallel")]
+	pub parallel: u32,
+	/// Only schedule matches of this round
+	pub round: Option<u32>,
+}
+
+impl Validate for AutoScheduleData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		errors.check(validators::range(
+			self.slot_minutes as i32,
+			MIN_SLOT_MINUTES as i32,
+			MAX_SLOT_MINUTES as i32,
+			"slot_minutes",
+		));
+		errors.check(validators::range(self.parallel as i32, 1, 64, "parallel"));
+		errors.into_result()
+	}
+}
+
+fn default_slot_minutes() -> u32 {
+	DEFAULT_MATCH_MINUTES
+}
+
+fn default_parallel() -> u32 {
+	1
+}
+
+/// Data for scheduling a single match by hand
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleMatchData {
+	pub scheduled_at: DateTime<Utc>,
+	pub duration_minutes: Option<u32>,
+}
+
+impl Validate for ScheduleMatchData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		if let Some(minutes) = self.duration_minutes {
+			errors.check(validators::range(
+				minutes as i32,
+				MIN_SLOT_MINUTES as i32,
+				MAX_SLOT_MINUTES as i32,
+				"duration_minutes",
+			));
+		}
+		errors.into_result()
+	}
+}
+
+/// A match to place in the schedule
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchedulingItem {
+	pub r#match: RecordId,
+	/// Users playing the match
+	pub players: Vec<RecordId>,
+}
+
+/// A slot already taken by one of the players
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookedSlot {
+	pub r#match: RecordId,
+	pub players: Vec<RecordId>,
+	pub slot: TimeSlot,
+}
+
+/// A player booked in two overlapping matches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleConflict {
+	pub player: RecordId,
+	pub r#match: RecordId,
+	pub conflicting_match: RecordId,
+	pub slot: TimeSlot,
+}
+
+/// Find the players of `players` already booked in a slot overlapping `slot`
+pub fn find_conflicts(
+	game: &RecordId,
+	players: &[RecordId],
+	slot: &TimeSlot,
+	booked: &[BookedSlot],
+) -> Vec<ScheduleConflict> {
+	booked
+		.iter()
+		.filter(|other| &other.r#match != game && other.slot.overlaps(slot))
+		.flat_map(|other| {
+			players
+				.iter()
+				.filter(|player| other.players.contains(player))
+				.map(|player| ScheduleConflict {
+					player: player.clone(),
+					r#match: game.clone(),
+					conflicting_match: other.r#match.clone(),
+					slot: other.slot,
+				})
+		})
+		.collect()
+}
+
+/// Outcome of automatic scheduling
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchedulePlan {
+	pub assignments: Vec<SlotAssignment>,
+	/// Matches that didn't fit in any window without a conflict
+	pub unscheduled: Vec<RecordId>,
+}
+
+/// A match placed in a slot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlotAssignment {
+	pub r#match: RecordId,
+	pub slot: TimeSlot,
+}
+
+/// Place matches into the earliest free slots of the availability windows
+///
+/// Matches are taken in the given order. A slot hosts at most `parallel`
+/// matches and never two matches sharing a player, including matches the
+/// players already have booked elsewhere.
+pub fn plan_schedule(
+	items: &[SchedulingItem],
+	windows: &[TimeSlot],
+	slot_minutes: u32,
+	parallel: u32,
+	booked: &[BookedSlot],
+) -> SchedulePlan {
+	let mut slots: Vec<TimeSlot> = windows
+		.iter()
+		.flat_map(|window| window.split(slot_minutes))
+		.collect();
+	slots.sort_by_key(|slot| slot.starts_at);
+	slots.dedup();
+
+	let mut booked = booked.to_vec();
+	let mut plan = SchedulePlan::default();
+
+	for item in items {
+		let free = slots.iter().find(|slot| {
+			let in_slot = plan
+				.assignments
+				.iter()
+				.filter(|assignment| assignment.slot.overlaps(slot))
+				.count();
+			in_slot < parallel as usize
+				&& find_conflicts(&item.r#match, &item.players, slot, &booked).is_empty()
+		});
+
+		match free {
+			Some(slot) => {
+				booked.push(BookedSlot {
+					r#match: item.r#match.clone(),
+					players: item.players.clone(),
+					slot: *slot,
+				});
+				plan.assignments.push(SlotAssignment {
+					r#match: item.r#match.clone(),
+					slot: *slot,
+				});
+			}
+			None => plan.unscheduled.push(item.r#match.clone()),
+		}
+	}
+
+	plan
+}
+
+/// Scheduled match rendered for a viewer's timezone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledMatch {
+	pub r#match: RecordId,
+	pub round: u32,
+	pub position: u32,
+	pub home: Option<RecordId>,
+	pub away: Option<RecordId>,
+	/// Slot in UTC
+	pub slot: TimeSlot,
+	/// IANA timezone used for the local times
+	pub timezone: String,
+	pub local_starts_at: String,
+	pub local_ends_at: String,
+}
+
+/// State of a reschedule proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RescheduleStatus {
+	Pending,
+	Accepted,
+	Declined,
+	/// Superseded by a newer proposal or by the organizer rescheduling
+	Withdrawn,
+}
+
+/// A player's request to move a match to another time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RescheduleProposal {
+	pub id: RecordId,
+	pub r#match: RecordId,
+	pub proposed_by: RecordId,
+	pub scheduled_at: DateTime<Utc>,
+	pub reason: Option<String>,
+	pub status: RescheduleStatus,
+	pub responded_by: Option<RecordId>,
+	pub responded_at: Option<DateTime<Utc>>,
+	pub created_at: DateTime<Utc>,
+}
+
+/// Data for proposing a new kickoff time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposeRescheduleData {
+	pub scheduled_at: DateTime<Utc>,
+	pub reason: Option<String>,
+}
+
+impl Validate for ProposeRescheduleData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		if time::is_past(&self.scheduled_at) {
+			errors.add_error(
+				"The proposed time must be in the future",
+				"scheduled_at",
+				"IN_THE_PAST",
+			);
+		}
+		errors.into_result()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::Duration;
+
+	fn user(key: &str) -> RecordId {
+		RecordId::from(("user", key))
+	}
+
+	fn item(key: &str, players: &[&str]) -> SchedulingItem {
+		SchedulingItem {
+			r#match: RecordId::from(("match", key)),
+			players: players.iter().map(|player| user(player)).collect(),
+		}
+	}
+
+	#[test]
+	fn test_find_conflicts_reports_shared_players() {
+		let start = Utc::now();
+		let booked = vec![BookedSlot {
+			r#match: RecordId::from(("match", "other")),
+			players: vec![user("ana"), user("beto")],
+			slot: TimeSlot::starting_at(start, 60),
+		}];
+
+		let overlapping = TimeSlot::starting_at(start + Duration::minutes(30), 60);
+		let game = RecordId::from(("match", "m1"));
+		let conflicts = find_conflicts(&game, &[user("ana"), user("caro")], &overlapping, &booked);
+		assert_eq!(conflicts.len(), 1);
+		assert_eq!(conflicts[0].player, user("ana"));
+
+		let later = TimeSlot::starting_at(start + Duration::minutes(60), 60);
+		assert!(find_conflicts(&game, &[user("ana")], &later, &booked).is_empty());
+
+		// A match never conflicts with its own booking
+		let own = RecordId::from(("match", "other"));
+		assert!(find_conflicts(&own, &[user("ana")], &overlapping, &booked).is_empty());
+	}
+
+	#[test]
+	fn test_plan_schedule_avoids_player_conflicts() {
+		let start = Utc::now();
+		let windows = [TimeSlot::starting_at(start, 120)];
+		let items = [
+			item("m1", &["ana", "beto"]),
+			item("m2", &["caro", "dani"]),
+			item("m3", &["ana", "caro"]),
+		];
+
+		let plan = plan_schedule(&items, &windows, 60, 2, &[]);
+		assert!(plan.unscheduled.is_empty());
+		assert_eq!(plan.assignments[0].slot.starts_at, start);
+		assert_eq!(plan.assignments[1].slot.starts_at, start);
+		assert_eq!(
+			plan.assignments[2].slot.starts_at,
+			start + Duration::minutes(60)
+		);
+	}
+
+	#[test]
+	fn test_plan_schedule_respects_capacity_and_bookings() {
+		let start = Utc::now();
+		let windows = [TimeSlot::starting_at(start, 60)];
+		let booked = [BookedSlot {
+			r#match: RecordId::from(("match", "elsewhere")),
+			players: vec![user("dani")],
+			slot: TimeSlot::starting_at(start, 60),
+		}];
+		let items = [item("m1", &["ana", "beto"]), item("m2", &["caro", "dani"])];
+
+		let plan = plan_schedule(&items, &windows, 60, 2, &booked);
+		assert_eq!(plan.assignments.len(), 1);
+		assert_eq!(plan.unscheduled, vec![RecordId::from(("match", "m2"))]);
+
+		let plan = plan_schedule(&items[..1], &windows, 60, 0, &[]);
+		assert_eq!(plan.unscheduled.len(), 1);
+	}
+
+	#[test]
+	fn test_auto_schedule_data_bounds() {
+		let data = AutoScheduleData {
+			slot_minutes: 5,
+			parallel: 0,
+			round: None,
+		};
+		let fields = data.validate().unwrap_err().to_field_map();
+		assert!(fields.contains_key("slot_minutes"));
+		assert!(fields.contains_key("parallel"));
+
+		let data = AutoScheduleData {
+			slot_minutes: DEFAULT_MATCH_MINUTES,
+			parallel: 2,
+			round: Some(1),
+		};
+		assert!(data.validate().is_ok());
+	}
+}
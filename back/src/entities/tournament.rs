@@ -1,9 +1,11 @@
 //! Tournament entity definitions for tournament management
 
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use surrealdb::RecordId;
 
+use crate::utils::error::validation::{Validate, ValidationErrors, ValidationResult, validators};
+
 /// Full tournament record as stored in the database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tournament {
@@ -11,6 +13,9 @@ pub struct Tournament {
 	pub name: String,
 	pub description: String,
 	pub published: bool,
+	/// Season the tournament belongs to (e.g. "2025-apertura")
+	#[serde(default)]
+	pub season: Option<String>,
 	pub created_by: RecordId,
 	pub created_at: DateTime<Utc>,
 	pub updated_at: DateTime<Utc>,
@@ -22,6 +27,24 @@ pub struct CreateTournamentData {
 	pub name: String,
 	pub description: String,
 	pub published: Option<bool>,
+	pub season: Option<String>,
+}
+
+impl Validate for CreateTournamentData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		errors.check(validators::tournament_name(&self.name, "name"));
+		errors.check(validators::length(
+			&self.description,
+			0,
+			2000,
+			"description",
+		));
+		if let Some(season) = &self.season {
+			errors.check(validators::length(season, 1, 50, "season"));
+		}
+		errors.into_result()
+	}
 }
 
 /// Data for updating an existing tournament
@@ -30,6 +53,39 @@ pub struct UpdateTournamentData {
 	pub name: Option<String>,
 	pub description: Option<String>,
 	pub published: Option<bool>,
+	/// `null` clears the season; leaving the field out keeps it
+	#[serde(
+		default,
+		deserialize_with = "clearable",
+		skip_serializing_if = "Option::is_none"
+	)]
+	pub season: Option<Option<String>>,
+}
+
+/// Read a present field, even `null`, as `Some`, so that an explicit `null`
+/// can be told apart from a missing field (`None`, through `default`)
+fn clearable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: Deserialize<'de>,
+{
+	Option::<T>::deserialize(deserializer).map(Some)
+}
+
+impl Validate for UpdateTournamentData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		if let Some(name) = &self.name {
+			errors.check(validators::tournament_name(name, "name"));
+		}
+		if let Some(description) = &self.description {
+			errors.check(validators::length(description, 0, 2000, "description"));
+		}
+		if let Some(Some(season)) = &self.season {
+			errors.check(validators::length(season, 1, 50, "season"));
+		}
+		errors.into_result()
+	}
 }
 
 /// Public tournament information (for listing)
@@ -98,6 +154,7 @@ mod tests {
 			name: "Test Tournament".to_string(),
 			description: "A test tournament".to_string(),
 			published: true,
+			season: Some("2025-apertura".to_string()),
 			created_by: RecordId::from(("user", "creator123")),
 			created_at: Utc::now(),
 			updated_at: Utc::now(),
@@ -119,6 +176,7 @@ mod tests {
 			name: "New Tournament".to_string(),
 			description: "A new tournament".to_string(),
 			published: Some(false),
+			season: None,
 		};
 
 		let json = serde_json::to_string(&data).unwrap();
@@ -135,6 +193,7 @@ mod tests {
 			name: Some("Updated Tournament".to_string()),
 			description: None,
 			published: Some(true),
+			season: None,
 		};
 
 		assert!(data.name.is_some());
@@ -142,6 +201,21 @@ mod tests {
 		assert!(data.published.is_some());
 	}
 
+	#[test]
+	fn test_update_tournament_data_tells_null_from_missing_season() {
+		let parse = |json| serde_json::from_value::<UpdateTournamentData>(json).unwrap();
+
+		assert_eq!(parse(serde_json::json!({})).season, None);
+		assert_eq!(
+			parse(serde_json::json!({ "season": null })).season,
+			Some(None)
+		);
+		assert_eq!(
+			parse(serde_json::json!({ "season": "2025" })).season,
+			Some(Some("2025".to_string()))
+		);
+	}
+
 	#[test]
 	fn test_tournament_status_default() {
 		let status = TournamentStatus::default();
@@ -173,4 +247,26 @@ mod tests {
 		let deserialized: TournamentType = serde_json::from_str(&json).unwrap();
 		matches!(deserialized, TournamentType::DoubleElimination);
 	}
+
+	#[test]
+	fn test_create_data_reports_every_invalid_field() {
+		let data = CreateTournamentData {
+			name: "ab".to_string(),
+			description: "x".repeat(2001),
+			published: None,
+			season: Some(String::new()),
+		};
+		let fields = data.validate().unwrap_err().to_field_map();
+		assert!(fields.contains_key("name"));
+		assert!(fields.contains_key("description"));
+		assert!(fields.contains_key("season"));
+
+		let update = UpdateTournamentData {
+			name: None,
+			description: Some("Copa de otoño".to_string()),
+			published: Some(true),
+			season: None,
+		};
+		assert!(update.validate().is_ok());
+	}
 }
@@ -0,0 +1,116 @@
+//! Twitch account entity definitions for linked streaming channels
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::RecordId;
+
+use crate::utils::error::validation::{Validate, ValidationErrors, ValidationResult, validators};
+
+/// Twitch account linked to a user, with the last known profile and live status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwitchAccount {
+	pub id: RecordId,
+	pub user: RecordId,
+	pub twitch_id: String,
+	pub login: String,
+	pub display_name: String,
+	pub profile_image_url: Option<String>,
+	pub is_live: bool,
+	pub stream_title: Option<String>,
+	pub game_name: Option<String>,
+	pub viewer_count: Option<u32>,
+	pub live_started_at: Option<DateTime<Utc>>,
+	pub linked_at: DateTime<Utc>,
+	pub refreshed_at: DateTime<Utc>,
+}
+
+/// Data for linking a Twitch account
+///
+/// The access token is the user's own Twitch OAuth token (as issued through
+/// Clerk's Twitch connection); it proves ownership of the account and is not
+/// stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkTwitchAccountData {
+	pub access_token: String,
+}
+
+impl Validate for LinkTwitchAccountData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		errors.check(validators::required(
+			Some(&self.access_token),
+			"access_token",
+		));
+		errors.into_result()
+	}
+}
+
+/// Live status of a channel, as exposed to viewers and overlays
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelStatus {
+	pub twitch_id: String,
+	pub login: String,
+	pub display_name: String,
+	pub is_live: bool,
+	pub stream_title: Option<String>,
+	pub game_name: Option<String>,
+	pub viewer_count: Option<u32>,
+	pub live_started_at: Option<DateTime<Utc>>,
+	pub refreshed_at: DateTime<Utc>,
+}
+
+impl ChannelStatus {
+	/// Public channel URL on twitch.tv
+	pub fn channel_url(&self) -> String {
+		format!("https://www.twitch.tv/{}", self.login)
+	}
+}
+
+impl From<TwitchAccount> for ChannelStatus {
+	fn from(account: TwitchAccount) -> Self {
+		Self {
+			twitch_id: account.twitch_id,
+			login: account.login,
+			display_name: account.display_name,
+			is_live: account.is_live,
+			stream_title: account.stream_title,
+			game_name: account.game_name,
+			viewer_count: account.viewer_count,
+			live_started_at: account.live_started_at,
+			refreshed_at: account.refreshed_at,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::Utc;
+	use surrealdb::RecordId;
+
+	#[test]
+	fn test_twitch_account_to_channel_status() {
+		let account = TwitchAccount {
+			id: RecordId::from(("twitch_account", "acc123")),
+			user: RecordId::from(("user", "user123")),
+			twitch_id: "141981764".to_string(),
+			login: "twitchdev".to_string(),
+			display_name: "TwitchDev".to_string(),
+			profile_image_url: None,
+			is_live: true,
+			stream_title: Some("Liga night".to_string()),
+			game_name: Some("EA Sports FC 25".to_string()),
+			viewer_count: Some(42),
+			live_started_at: Some(Utc::now()),
+			linked_at: Utc::now(),
+			refreshed_at: Utc::now(),
+		};
+
+		let status: ChannelStatus = account.clone().into();
+
+		assert!(status.is_live);
+		assert_eq!(status.login, account.login);
+		assert_eq!(status.viewer_count, Some(42));
+		assert_eq!(status.channel_url(), "https://www.twitch.tv/twitchdev");
+	}
+}
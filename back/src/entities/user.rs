@@ -4,12 +4,18 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use surrealdb::RecordId;
 
+use crate::utils::error::validation::{Validate, ValidationErrors, ValidationResult, validators};
+use crate::utils::time;
+
 /// Full user record as stored in the database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
 	pub id: RecordId,
 	pub username: String,
 	pub email: String,
+	/// IANA timezone used to display times (e.g. "America/Mexico_City")
+	#[serde(default)]
+	pub timezone: Option<String>,
 	pub created_at: DateTime<Utc>,
 	pub updated_at: DateTime<Utc>,
 }
@@ -21,6 +27,15 @@ pub struct CreateUserData {
 	pub email: String,
 }
 
+impl Validate for CreateUserData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		errors.check(validators::username(&self.username, "username"));
+		errors.check(validators::email(&self.email, "email"));
+		errors.into_result()
+	}
+}
+
 /// Data for updating an existing user
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateUserData {
@@ -28,6 +43,35 @@ pub struct UpdateUserData {
 	pub email: Option<String>,
 }
 
+impl Validate for UpdateUserData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		if let Some(username) = &self.username {
+			errors.check(validators::username(username, "username"));
+		}
+		if let Some(email) = &self.email {
+			errors.check(validators::email(email, "email"));
+		}
+		errors.into_result()
+	}
+}
+
+/// Data for setting the user's display timezone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateTimezoneData {
+	pub timezone: String,
+}
+
+impl Validate for UpdateTimezoneData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		if time::parse_timezone(&self.timezone).is_none() {
+			errors.add_error("Unknown IANA timezone", "timezone", "INVALID_TIMEZONE");
+		}
+		errors.into_result()
+	}
+}
+
 /// Public user information (without sensitive data)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PublicUser {
@@ -73,6 +117,7 @@ mod tests {
 			id: RecordId::from(("user", "test123")),
 			username: "testuser".to_string(),
 			email: "test@example.com".to_string(),
+			timezone: None,
 			created_at: Utc::now(),
 			updated_at: Utc::now(),
 		};
@@ -0,0 +1,269 @@
+//! Webhook entity definitions for outbound tournament and match notifications
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::RecordId;
+
+use crate::utils::error::validation::{Validate, ValidationErrors, ValidationResult, validators};
+
+/// Shortest signing secret a caller can provide, in bytes
+pub const MIN_SECRET_LENGTH: usize = 32;
+
+/// Longest signing secret a caller can provide, in bytes
+pub const MAX_SECRET_LENGTH: usize = 256;
+
+/// Registered webhook endpoint for a tournament
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+	pub id: RecordId,
+	pub tournament: RecordId,
+	pub url: String,
+	/// Shared secret used to sign every payload (never returned to clients)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub secret: Option<String>,
+	/// Events this endpoint is subscribed to (empty means every event)
+	pub events: Vec<WebhookEventKind>,
+	pub active: bool,
+	pub created_by: RecordId,
+	pub created_at: DateTime<Utc>,
+	pub updated_at: DateTime<Utc>,
+}
+
+impl Webhook {
+	/// Check whether this endpoint wants to receive the given event
+	pub fn accepts(&self, event: WebhookEventKind) -> bool {
+		self.active && (self.events.is_empty() || self.events.contains(&event))
+	}
+}
+
+/// Data for registering a new webhook endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateWebhookData {
+	pub url: String,
+	#[serde(default)]
+	pub events: Vec<WebhookEventKind>,
+	/// Optional caller-provided secret; one is generated when omitted
+	pub secret: Option<String>,
+}
+
+impl Validate for CreateWebhookData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		errors.check(validators::public_https_url(&self.url, "url"));
+		if let Some(secret) = &self.secret {
+			errors.check(validators::length(
+				secret,
+				MIN_SECRET_LENGTH,
+				MAX_SECRET_LENGTH,
+				"secret",
+			));
+		}
+		errors.into_result()
+	}
+}
+
+/// Data for updating an existing webhook endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateWebhookData {
+	pub url: Option<String>,
+	pub events: Option<Vec<WebhookEventKind>>,
+	pub active: Option<bool>,
+}
+
+impl Validate for UpdateWebhookData {
+	fn validate(&self) -> ValidationResult<()> {
+		let mut errors = ValidationErrors::new();
+		if let Some(url) = &self.url {
+			errors.check(validators::public_https_url(url, "url"));
+		}
+		errors.into_result()
+	}
+}
+
+/// Public webhook information (without the signing secret)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicWebhook {
+	pub id: RecordId,
+	pub tournament: RecordId,
+	pub url: String,
+	pub events: Vec<WebhookEventKind>,
+	pub active: bool,
+	pub created_at: DateTime<Utc>,
+}
+
+impl From<Webhook> for PublicWebhook {
+	fn from(webhook: Webhook) -> Self {
+		Self {
+			id: webhook.id,
+			tournament: webhook.tournament,
+			url: webhook.url,
+			events: webhook.events,
+			active: webhook.active,
+			created_at: webhook.created_at,
+		}
+	}
+}
+
+/// Webhook returned once on creation, including the generated secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedWebhook {
+	#[serde(flatten)]
+	pub webhook: PublicWebhook,
+	pub secret: String,
+}
+
+/// Events that can be delivered to webhook endpoints
+///
+/// Endpoints belong to a tournament, so there is no `tournament.created`:
+/// nothing could be subscribed to it yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WebhookEventKind {
+	#[serde(rename = "tournament.updated")]
+	TournamentUpdated,
+	#[serde(rename = "tournament.published")]
+	TournamentPublished,
+	#[serde(rename = "participant.joined")]
+	ParticipantJoined,
+	#[serde(rename = "participant.left")]
+	ParticipantLeft,
+	#[serde(rename = "match.scheduled")]
+	MatchScheduled,
+	#[serde(rename = "match.started")]
+	MatchStarted,
+	#[serde(rename = "match.reported")]
+	MatchReported,
+	#[serde(rename = "match.confirmed")]
+	MatchConfirmed,
+	#[serde(rename = "match.cancelled")]
+	MatchCancelled,
+}
+
+impl WebhookEventKind {
+	/// Wire name of the event, as sent in the `X-Liga-Event` header
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			WebhookEventKind::TournamentUpdated => "tournament.updated",
+			WebhookEventKind::TournamentPublished => "tournament.published",
+			WebhookEventKind::ParticipantJoined => "participant.joined",
+			WebhookEventKind::ParticipantLeft => "participant.left",
+			WebhookEventKind::MatchScheduled => "match.scheduled",
+			WebhookEventKind::MatchStarted => "match.started",
+			WebhookEventKind::MatchReported => "match.reported",
+			WebhookEventKind::MatchConfirmed => "match.confirmed",
+			WebhookEventKind::MatchCancelled => "match.cancelled",
+		}
+	}
+}
+
+/// Delivery status enumeration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+	#[default]
+	Pending,
+	Delivered,
+	Failed,
+}
+
+/// A single event delivery to a webhook endpoint, with its attempt history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+	pub id: RecordId,
+	pub webhook: RecordId,
+	pub event: WebhookEventKind,
+	pub payload: serde_json::Value,
+	pub status: DeliveryStatus,
+	pub attempts: u32,
+	pub last_status_code: Option<u16>,
+	pub last_error: Option<String>,
+	/// Log of every delivery attempt, oldest first
+	#[serde(default)]
+	pub history: Vec<DeliveryAttempt>,
+	pub created_at: DateTime<Utc>,
+	pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of one HTTP attempt for a delivery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryAttempt {
+	pub attempt: u32,
+	pub status_code: Option<u16>,
+	pub error: Option<String>,
+	pub attempted_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::Utc;
+	use surrealdb::RecordId;
+
+	fn webhook(events: Vec<WebhookEventKind>, active: bool) -> Webhook {
+		Webhook {
+			id: RecordId::from(("webhook", "hook123")),
+			tournament: RecordId::from(("tournament", "tourney123")),
+			url: "https://example.com/hook".to_string(),
+			secret: Some("secret".to_string()),
+			events,
+			active,
+			created_by: RecordId::from(("user", "user123")),
+			created_at: Utc::now(),
+			updated_at: Utc::now(),
+		}
+	}
+
+	#[test]
+	fn test_webhook_event_filter() {
+		let all = webhook(vec![], true);
+		assert!(all.accepts(WebhookEventKind::MatchReported));
+
+		let filtered = webhook(vec![WebhookEventKind::ParticipantJoined], true);
+		assert!(filtered.accepts(WebhookEventKind::ParticipantJoined));
+		assert!(!filtered.accepts(WebhookEventKind::MatchReported));
+
+		let inactive = webhook(vec![], false);
+		assert!(!inactive.accepts(WebhookEventKind::ParticipantJoined));
+	}
+
+	#[test]
+	fn test_webhook_event_kind_serialization() {
+		let json = serde_json::to_string(&WebhookEventKind::MatchReported).unwrap();
+		assert_eq!(json, "\"match.reported\"");
+		assert_eq!(WebhookEventKind::MatchReported.as_str(), "match.reported");
+
+		let deserialized: WebhookEventKind = serde_json::from_str("\"participant.joined\"").unwrap();
+		assert_eq!(deserialized, WebhookEventKind::ParticipantJoined);
+		assert_eq!(
+			WebhookEventKind::TournamentPublished.as_str(),
+			"tournament.published"
+		);
+		assert!(serde_json::from_str::<WebhookEventKind>("\"tournament.created\"").is_err());
+		assert!(serde_json::from_str::<WebhookEventKind>("\"tournament.deleted\"").is_err());
+	}
+
+	#[test]
+	fn test_create_webhook_data_secret_length() {
+		let data = |secret: Option<String>| CreateWebhookData {
+			url: "https://example.com/hook".to_string(),
+			events: vec![],
+			secret,
+		};
+		assert!(data(None).validate().is_ok());
+		assert!(data(Some("x".repeat(MIN_SECRET_LENGTH))).validate().is_ok());
+
+		let errors = data(Some("top-secret".to_string())).validate().unwrap_err();
+		assert_eq!(errors.first().unwrap().field.as_deref(), Some("secret"));
+		assert!(
+			data(Some("x".repeat(MAX_SECRET_LENGTH + 1)))
+				.validate()
+				.is_err()
+		);
+	}
+
+	#[test]
+	fn test_public_webhook_hides_secret() {
+		let public: PublicWebhook = webhook(vec![], true).into();
+		let json = serde_json::to_value(&public).unwrap();
+		assert!(json.get("secret").is_none());
+	}
+}
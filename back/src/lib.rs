@@ -1,111 +1,131 @@
 // Lib file to help with testing and module organization
 
-use std::env;
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock};
 
 use surrealdb::Surreal;
 use surrealdb::engine::any::Any;
-use surrealdb::opt::auth::Root;
 
+pub mod config;
+pub mod db;
 pub mod entities;
+pub mod middleware;
+pub mod migrations;
+pub mod repositories;
 pub mod routes;
+pub mod services;
+pub mod supervisor;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 pub mod utils;
 
+use crate::config::Config;
+use crate::db::Database;
+use crate::middleware::rate_limit::RateLimiter;
+#[cfg(any(test, feature = "test-utils"))]
+use crate::repositories::{InMemoryParticipantRepository, InMemoryTournamentRepository};
+use crate::repositories::{
+	ParticipantRepository, SurrealParticipantRepository, SurrealTournamentRepository,
+	TournamentRepository,
+};
+use crate::services::jobs::JobQueue;
+use crate::services::live::LiveHub;
+use crate::services::twitch::TwitchClient;
+use crate::services::webhooks::{RetryPolicy, WebhookDispatcher};
+use crate::supervisor::Supervisor;
 use crate::utils::error::ApiResult;
-use crate::utils::logging;
 
 // Global database client using Any engine for multi-protocol support
 pub static DB: LazyLock<Surreal<Any>> = LazyLock::new(Surreal::init);
 
-#[derive(Clone, Default)]
-pub struct AppState {}
+/// Shared application state handed to every handler
+///
+/// Cloned into every worker; all clones share the same hub, clients and queue.
+#[derive(Clone)]
+pub struct AppState {
+	/// Handle the services run their queries through
+	pub db: Database,
+	/// Connection supervisor for `db`
+	pub connection: Arc<Supervisor>,
+	pub config: Arc<Config>,
+	pub tournaments: Arc<dyn TournamentRepository>,
+	pub participants: Arc<dyn ParticipantRepository>,
+	/// Broadcast hub behind the live WebSocket endpoints
+	pub live: Arc<LiveHub>,
+	/// HTTP client pushing outbound webhooks
+	pub webhooks: Arc<WebhookDispatcher>,
+	/// Helix client, when Twitch credentials are configured
+	pub twitch: Option<Arc<TwitchClient>>,
+	pub jobs: JobQueue,
+	/// Request limits enforced by `middleware::rate_limit`
+	pub rate_limiter: Arc<RateLimiter>,
+}
 
 impl AppState {
-	pub fn new() -> Self {
-		Self::default()
+	/// State backed by the global SurrealDB connection
+	pub fn new(config: Config) -> Self {
+		let twitch = config
+			.twitch
+			.credentials()
+			.map(|credentials| Arc::new(TwitchClient::new(credentials)));
+
+		let db = Database::new(DB.clone());
+		Self {
+			connection: Arc::new(Supervisor::new(DB.clone(), config.database.clone())),
+			rate_limiter: Arc::new(RateLimiter::new(&config.rate_limit)),
+			config: Arc::new(config),
+			tournaments: Arc::new(SurrealTournamentRepository::new(db.clone())),
+			participants: Arc::new(SurrealParticipantRepository::new(db.clone())),
+			db,
+			live: Arc::new(LiveHub::new()),
+			webhooks: Arc::new(
+				WebhookDispatcher::new(RetryPolicy::default())
+					.expect("Failed to build the webhook HTTP client"),
+			),
+			twitch,
+			jobs: JobQueue::spawning(),
+		}
 	}
 
+	/// State backed by in-memory fakes and default settings
+	///
+	/// Repositories start empty, Twitch is not configured, webhooks are tried
+	/// once and background jobs wait in a deferred queue until the test runs
+	/// them with [`JobQueue::run_pending`]. Tests reaching the `mem://` database
+	/// through the repositories use `test_utils::state` instead.
+	#[cfg(any(test, feature = "test-utils"))]
 	pub fn new_test() -> Self {
-		Self {}
+		let config = Config::default();
+		Self {
+			db: Database::new(DB.clone()),
+			connection: Arc::new(Supervisor::new(DB.clone(), config.database.clone())),
+			rate_limiter: Arc::new(RateLimiter::new(&config.rate_limit)),
+			config: Arc::new(config),
+			tournaments: Arc::new(InMemoryTournamentRepository::new()),
+			participants: Arc::new(InMemoryParticipantRepository::new()),
+			live: Arc::new(LiveHub::new()),
+			webhooks: Arc::new(
+				WebhookDispatcher::new(RetryPolicy {
+					max_attempts: 1,
+					..RetryPolicy::default()
+				})
+				.expect("Failed to build the webhook HTTP client"),
+			),
+			twitch: None,
+			jobs: JobQueue::deferred(),
+		}
 	}
 }
 
-/// Initialize the database connection with SurrealDB
-pub async fn init_db() -> ApiResult<()> {
-	// Get database connection details from environment
-	let db_url = env::var("SURREAL_URL").unwrap_or("wss://localhost:8000".to_owned());
-	let namespace = env::var("SURREAL_NAMESPACE").unwrap_or("liga".to_owned());
-	let database = env::var("SURREAL_DATABASE").unwrap_or("muertos".to_owned());
-	let username = env::var("SURREAL_USER").unwrap_or("root".to_owned());
-	let password = env::var("SURREAL_PASS").unwrap_or("root".to_owned());
-
-	// Connect using Any engine which auto-detects protocol
-	DB.connect(&db_url).await?;
-
-	// Sign in as root user
-	DB.signin(Root {
-		username: &username,
-		password: &password,
-	})
-	.await?;
-
-	// Use the specified namespace and database
-	DB.use_ns(&namespace).use_db(&database).await?;
+/// Connect to SurrealDB and bring the schema up to date
+///
+/// The first connection is retried with backoff; call [`Supervisor::watch`]
+/// afterwards to keep the connection supervised.
+pub async fn init_db(connection: &Supervisor) -> ApiResult<()> {
+	connection.connect().await?;
 
-	logging::database_info(&db_url, &namespace, &database);
+	// Bring the schema up to date
+	migrations::run().await?;
 
-	// Initialize database schema
-	init_schema().await?;
-
-	Ok(())
-}
-
-/// Initialize database schema and tables
-async fn init_schema() -> ApiResult<()> {
-	logging::schema_init();
-
-	// Define basic schema for the application
-	let schema_query = r#"
-        -- Define users table for authentication
-        DEFINE TABLE IF NOT EXISTS user SCHEMALESS
-            PERMISSIONS FOR
-                SELECT, UPDATE WHERE id = $auth,
-                FOR CREATE, DELETE NONE;
-
-        DEFINE FIELD IF NOT EXISTS username ON TABLE user TYPE string;
-        DEFINE FIELD IF NOT EXISTS email ON TABLE user TYPE string;
-        DEFINE FIELD IF NOT EXISTS created_at ON TABLE user TYPE datetime VALUE time::now() READONLY;
-        DEFINE FIELD IF NOT EXISTS updated_at ON TABLE user TYPE datetime VALUE time::now();
-
-        -- Define tournaments table
-        DEFINE TABLE IF NOT EXISTS tournament SCHEMALESS
-            PERMISSIONS FOR
-                SELECT WHERE published = true OR created_by = $auth,
-                FOR CREATE, UPDATE, DELETE WHERE created_by = $auth;
-
-        DEFINE FIELD IF NOT EXISTS name ON TABLE tournament TYPE string;
-        DEFINE FIELD IF NOT EXISTS description ON TABLE tournament TYPE string;
-        DEFINE FIELD IF NOT EXISTS published ON TABLE tournament TYPE bool VALUE false;
-        DEFINE FIELD IF NOT EXISTS created_by ON TABLE tournament VALUE $auth READONLY;
-        DEFINE FIELD IF NOT EXISTS created_at ON TABLE tournament TYPE datetime VALUE time::now() READONLY;
-        DEFINE FIELD IF NOT EXISTS updated_at ON TABLE tournament TYPE datetime VALUE time::now();
-
-        -- Define participants table
-        DEFINE TABLE IF NOT EXISTS participant SCHEMALESS
-            PERMISSIONS FOR
-                SELECT WHERE tournament IN (SELECT id FROM tournament WHERE published = true OR created_by = $auth),
-                FOR CREATE WHERE tournament IN (SELECT id FROM tournament WHERE published = true),
-                FOR UPDATE, DELETE WHERE user_id = $auth OR tournament IN (SELECT id FROM tournament WHERE created_by = $auth);
-
-        DEFINE FIELD IF NOT EXISTS tournament ON TABLE participant TYPE record<tournament>;
-        DEFINE FIELD IF NOT EXISTS user_id ON TABLE participant TYPE record<user>;
-        DEFINE FIELD IF NOT EXISTS joined_at ON TABLE participant TYPE datetime VALUE time::now() READONLY;
-  "#;
-
-	// Execute schema definition using the documentation pattern
-	DB.query(schema_query).await?;
-
-	logging::schema_success();
 	Ok(())
 }
 
@@ -116,7 +136,7 @@ pub mod database {
 	/// Execute a database query with proper error handling
 	///
 	/// # Example
-	/// ```rust
+	/// ```rust,no_run
 	/// use liga_muertos_back::database;
 	///
 	/// #[actix_web::main]
@@ -165,51 +185,46 @@ pub mod database {
 #[cfg(test)]
 mod tests {
 	use super::*;
-	// use surrealdb::engine::local::Mem;
 
 	#[test]
 	fn test_app_state_creation() {
-		let _state = AppState::new();
-		// AppState is just an empty struct now
+		let state = AppState::new(Config::default());
+		assert_eq!(state.config.server.port, utils::constants::DEFAULT_PORT);
 	}
 
 	#[test]
 	fn test_app_state_test_creation() {
-		let _state = AppState::new_test();
-		// AppState is just an empty struct now
+		let state = AppState::new_test();
+		assert!(state.twitch.is_none());
+		assert_eq!(state.webhooks.policy().max_attempts, 1);
+		assert_eq!(state.jobs.pending(), 0);
 	}
 
 	#[test]
 	fn test_surreal_env_vars_parsing() {
-		// Test that our environment variable names are correctly used
-		use std::env;
-
-		unsafe {
-			// Set test environment variables with SURREAL_ prefix
-			env::set_var("SURREAL_URL", "ws://test.example.com:8000/rpc");
-			env::set_var("SURREAL_NAMESPACE", "test_namespace");
-			env::set_var("SURREAL_DATABASE", "test_database");
-			env::set_var("SURREAL_USER", "test_user");
-			env::set_var("SURREAL_PASS", "test_pass");
-		}
-
-		// Verify that the variables can be read with the new names
-		assert_eq!(
-			env::var("SURREAL_URL").unwrap(),
-			"ws://test.example.com:8000/rpc"
-		);
-		assert_eq!(env::var("SURREAL_NAMESPACE").unwrap(), "test_namespace");
-		assert_eq!(env::var("SURREAL_DATABASE").unwrap(), "test_database");
-		assert_eq!(env::var("SURREAL_USER").unwrap(), "test_user");
-		assert_eq!(env::var("SURREAL_PASS").unwrap(), "test_pass");
-
-		unsafe {
-			// Clean up test environment variables
-			env::remove_var("SURREAL_URL");
-			env::remove_var("SURREAL_NAMESPACE");
-			env::remove_var("SURREAL_DATABASE");
-			env::remove_var("SURREAL_USER");
-			env::remove_var("SURREAL_PASS");
-		}
+		// The SURREAL_ variables feed the database section of the config
+		let vars = [
+			("SURREAL_URL", "ws://test.example.com:8000/rpc"),
+			("SURREAL_NAMESPACE", "test_namespace"),
+			("SURREAL_DATABASE", "test_database"),
+			("SURREAL_USER", "test_user"),
+			("SURREAL_PASS", "test_pass"),
+		];
+		let mut config = Config::default();
+		config
+			.apply_env(|key| {
+				vars
+					.iter()
+					.find(|(name, _)| *name == key)
+					.map(|(_, value)| value.to_string())
+			})
+			.unwrap();
+
+		let database = &config.database;
+		assert_eq!(database.url, "ws://test.example.com:8000/rpc");
+		assert_eq!(database.namespace, "test_namespace");
+		assert_eq!(database.database, "test_database");
+		assert_eq!(database.username, "test_user");
+		assert_eq!(database.password.expose(), "test_pass");
 	}
 }
@@ -1,41 +1,116 @@
 use actix_web::{
 	App, HttpServer,
-	middleware::{Logger, NormalizePath},
+	middleware::{Logger, NormalizePath, from_fn},
 	web,
 };
 use dotenvy::dotenv;
 use std::env;
 
-use liga_muertos_back::{AppState, init_db, routes, utils::logging};
+use liga_muertos_back::{
+	AppState,
+	config::Config,
+	init_db,
+	middleware::{auth, locale, metrics, rate_limit, request_id},
+	routes,
+	services::polls,
+	utils::logging,
+};
+
+/// Access log format: the default one plus the request id
+const LOG_FORMAT: &str = r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T req=%{x-request-id}o"#;
 
 #[actix_web::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
 	// Load environment variables from .env file
 	dotenv().ok();
 
-	// Get port from environment or use 4000 as default
-	let port = env::var("PORT").unwrap_or("4000".to_owned());
-	let port = port.parse::<u16>().expect("Invalid PORT value");
+	// Load and validate settings - fail fast on a bad configuration
+	let config = Config::load()?;
+
+	// Configure enhanced logging in the configured format
+	logging::init(&config.logging);
+	let port = config.server.port;
+	let host = config.server.host.clone();
+
+	logging::startup_info(&host, port);
+	log::debug!("🔧 Configuration: {config:?}");
 
-	// Configure enhanced logging
-	logging::init();
+	// Clerk session validation - without a secret key every request is anonymous
+	let authorizer = auth::authorizer(&config.auth).map(web::Data::new);
+	let state = AppState::new(config);
+
+	// Initialize database connection - give up after a few attempts
+	init_db(&state.connection).await?;
+
+	// `--migrate-only` applies pending migrations (done by init_db) and exits,
+	// e.g. as a release step before rolling out new instances
+	if env::args().any(|arg| arg == "--migrate-only") {
+		log::info!("✅ Migrations applied, exiting (--migrate-only)");
+		return Ok(());
+	}
 
-	logging::startup_info(port);
+	// Reconnect in the background if the database goes away
+	state.connection.clone().watch();
 
-	// Initialize database connection - fail fast if connection fails
-	init_db().await?;
+	// Feed poll changes to live subscribers, restarted after every reconnection
+	polls::watch(&state.connection, state.live.clone()).await?;
+
+	// Forget rate limit buckets of clients that went quiet
+	let limiter = state.rate_limiter.clone();
+	state
+		.jobs
+		.every("sweep_rate_limits", rate_limit::SWEEP_INTERVAL, move || {
+			let limiter = limiter.clone();
+			async move {
+				let dropped = limiter.sweep().await;
+				log::debug!("Dropped {dropped} idle rate limit buckets");
+			}
+		});
+
+	// Archive the results of polls once their close time has passed
+	let db = state.db.clone();
+	state
+		.jobs
+		.every("archive_polls", polls::ARCHIVE_INTERVAL, move || {
+			let db = db.clone();
+			async move {
+				if let Err(e) = polls::archive_due(&db).await {
+					log::error!("Failed to archive closed polls: {e}");
+				}
+			}
+		});
+
+	if authorizer.is_none() {
+		log::warn!("⚠️  CLERK_SECRET_KEY not set, authenticated endpoints will reject all requests");
+	}
+
+	if !state.config.twitch.is_configured() {
+		log::warn!("⚠️  Twitch credentials not set, Twitch endpoints are disabled");
+	}
 
 	// Start HTTP server
 	logging::server_ready(port);
 
-	HttpServer::new(|| {
+	HttpServer::new(move || {
+		let authorizer = authorizer.clone();
+		let state = state.clone();
 		App::new()
-			.wrap(Logger::default())
+			.wrap(from_fn(rate_limit::enforce))
+			.wrap(from_fn(auth::authenticate))
+			.wrap(Logger::new(LOG_FORMAT))
+			.wrap(from_fn(metrics::track))
+			.wrap(from_fn(locale::negotiate))
+			.wrap(from_fn(request_id::propagate))
 			.wrap(NormalizePath::trim())
-			.app_data(web::Data::new(AppState::new()))
+			.app_data(web::Data::new(state))
+			.configure(|cfg| {
+				if let Some(authorizer) = authorizer {
+					cfg.app_data(authorizer);
+				}
+			})
 			.configure(routes::entry)
 	})
-	.bind(("0.0.0.0", port))?
+	.bind((host, port))?
 	.run()
 	.await?;
 
@@ -0,0 +1,170 @@
+//! Clerk session authentication
+//!
+//! [`authenticate`] validates the Clerk session token (when present) and stores
+//! the resulting [`AuthUser`] in the request extensions. Handlers that require a
+//! signed-in user simply take an [`AuthUser`] argument; anonymous requests are
+//! rejected with [`ApiError::Authentication`].
+
+use std::future::{Ready, ready};
+
+use actix_web::{
+	FromRequest, HttpMessage, HttpRequest,
+	body::MessageBody,
+	dev::{Payload, ServiceRequest, ServiceResponse},
+	middleware::Next,
+	web,
+};
+use clerk_rs::{
+	ClerkConfiguration,
+	clerk::Clerk,
+	validators::{
+		authorizer::{ClerkAuthorizer, ClerkError, ClerkJwt, ClerkRequest},
+		jwks::MemoryCacheJwksProvider,
+	},
+};
+use surrealdb::RecordId;
+
+use crate::config::AuthConfig;
+use crate::middleware::request_id;
+use crate::utils::error::ApiError;
+use crate::utils::logging;
+
+/// Clerk authorizer shared through `app_data`
+pub type Authorizer = ClerkAuthorizer<MemoryCacheJwksProvider>;
+
+/// Build the Clerk authorizer, if a secret key is configured
+pub fn authorizer(auth: &AuthConfig) -> Option<Authorizer> {
+	let secret_key = auth.clerk_secret_key.as_ref()?.expose().to_string();
+	let config = ClerkConfiguration::new(None, None, Some(secret_key), None);
+	let jwks = MemoryCacheJwksProvider::new(Clerk::new(config));
+
+	Some(ClerkAuthorizer::new(jwks, true))
+}
+
+/// Authenticated user attached to the request by [`authenticate`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthUser {
+	/// User record (`user:<clerk user id>`)
+	pub id: RecordId,
+	/// Clerk session id, when the token carries one
+	pub session_id: Option<String>,
+}
+
+impl AuthUser {
+	/// Create an authenticated user from a Clerk user id
+	pub fn new(clerk_user_id: &str) -> Self {
+		Self {
+			id: RecordId::from(("user", clerk_user_id)),
+			session_id: None,
+		}
+	}
+}
+
+impl From<&ClerkJwt> for AuthUser {
+	fn from(jwt: &ClerkJwt) -> Self {
+		Self {
+			id: RecordId::from(("user", jwt.sub.as_str())),
+			session_id: jwt.sid.clone(),
+		}
+	}
+}
+
+impl FromRequest for AuthUser {
+	type Error = ApiError;
+	type Future = Ready<Result<Self, Self::Error>>;
+
+	fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+		ready(
+			req
+				.extensions()
+				.get::<AuthUser>()
+				.cloned()
+				.ok_or_else(|| ApiError::authentication("Authentication required")),
+		)
+	}
+}
+
+/// Adapter so the Clerk authorizer can read headers and cookies from actix requests
+struct ClerkServiceRequest<'a>(&'a ServiceRequest);
+
+impl ClerkRequest for ClerkServiceRequest<'_> {
+	fn get_header(&self, key: &str) -> Option<String> {
+		self
+			.0
+			.headers()
+			.get(key)
+			.and_then(|value| value.to_str().ok())
+			.map(str::to_string)
+	}
+
+	fn get_cookie(&self, key: &str) -> Option<String> {
+		self.0.cookie(key).map(|cookie| cookie.value().to_string())
+	}
+}
+
+/// Authenticate the request when it carries a Clerk session token
+///
+/// Requests without a token pass through anonymously; requests with an invalid
+/// token are rejected. When no [`Authorizer`] is registered (e.g. Clerk is not
+/// configured) every request is treated as anonymous.
+pub async fn authenticate(
+	authorizer: Option<web::Data<Authorizer>>,
+	req: ServiceRequest,
+	next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+	let has_token = req.headers().contains_key("Authorization") || req.cookie("__session").is_some();
+
+	if let Some(authorizer) = authorizer
+		&& has_token
+	{
+		match authorizer.authorize(&ClerkServiceRequest(&req)).await {
+			Ok(jwt) => {
+				let user = AuthUser::from(&jwt);
+				request_id::set_user(&jwt.sub);
+				logging::auth_event("session_validated", Some(&jwt.sub));
+				req.extensions_mut().insert(user);
+			}
+			Err(ClerkError::Unauthorized(message)) => {
+				logging::auth_event("session_rejected", None);
+				return Err(ApiError::authentication(&message).into());
+			}
+			Err(ClerkError::InternalServerError(message)) => {
+				return Err(
+					ApiError::ExternalService {
+						service: "clerk".to_string(),
+						message,
+					}
+					.into(),
+				);
+			}
+		}
+	}
+
+	next.call(req).await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use actix_web::{App, HttpResponse, test};
+
+	async fn whoami(user: AuthUser) -> HttpResponse {
+		HttpResponse::Ok().body(user.id.to_string())
+	}
+
+	#[actix_web::test]
+	async fn test_auth_user_extractor() {
+		let app = test::init_service(App::new().route("/whoami", web::get().to(whoami))).await;
+
+		// Anonymous requests are rejected
+		let req = test::TestRequest::get().uri("/whoami").to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.status(), actix_web::http::StatusCode::UNAUTHORIZED);
+
+		// Requests carrying an authenticated user are accepted
+		let req = test::TestRequest::get().uri("/whoami").to_request();
+		req.extensions_mut().insert(AuthUser::new("user_abc"));
+		let resp = test::call_service(&app, req).await;
+		assert!(resp.status().is_success());
+	}
+}
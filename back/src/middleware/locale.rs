@@ -0,0 +1,127 @@
+//! Language of error messages
+//!
+//! [`negotiate`] picks the best supported [`Locale`] from the caller's
+//! `Accept-Language` header (English when nothing matches) and runs the rest
+//! of the request inside a scope holding it, so error bodies can be rendered
+//! in that language wherever they are built. The chosen locale is sent back
+//! in `Content-Language`.
+
+use actix_web::{
+	body::MessageBody,
+	dev::{ServiceRequest, ServiceResponse},
+	error::InternalError,
+	http::header::{ACCEPT_LANGUAGE, CONTENT_LANGUAGE, HeaderValue},
+	middleware::Next,
+};
+
+use crate::utils::i18n::Locale;
+
+tokio::task_local! {
+	static CURRENT: Locale;
+}
+
+/// Locale of the request the current task is working for (English outside
+/// of a request)
+pub fn current() -> Locale {
+	CURRENT.try_with(|locale| *locale).unwrap_or_default()
+}
+
+/// Middleware selecting the locale of every request
+pub async fn negotiate(
+	req: ServiceRequest,
+	next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+	let locale = req
+		.headers()
+		.get(ACCEPT_LANGUAGE)
+		.and_then(|value| value.to_str().ok())
+		.and_then(Locale::negotiate)
+		.unwrap_or_default();
+	let header = HeaderValue::from_static(locale.code());
+
+	match CURRENT.scope(locale, next.call(req)).await {
+		Ok(mut response) => {
+			response.headers_mut().insert(CONTENT_LANGUAGE, header);
+			Ok(response)
+		}
+		// Errors from inner middleware are rendered here, in the caller's language
+		Err(error) => {
+			let mut response = CURRENT.sync_scope(locale, || error.as_response_error().error_response());
+			response.headers_mut().insert(CONTENT_LANGUAGE, header);
+			Err(InternalError::from_response(error.to_string(), response).into())
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::utils::error::{ApiError, ApiErrorResponse};
+	use actix_web::body::BoxBody;
+	use actix_web::middleware::from_fn;
+	use actix_web::{App, HttpResponse, test, web};
+
+	async fn missing() -> Result<HttpResponse, ApiError> {
+		Err(ApiError::not_found("tournament", "copa"))
+	}
+
+	#[actix_web::test]
+	async fn test_errors_follow_accept_language() {
+		let app = test::init_service(
+			App::new()
+				.wrap(from_fn(negotiate))
+				.route("/missing", web::get().to(missing)),
+		)
+		.await;
+
+		let req = test::TestRequest::get()
+			.uri("/missing")
+			.insert_header((ACCEPT_LANGUAGE, "es-MX,es;q=0.9,en;q=0.8"))
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.headers().get(CONTENT_LANGUAGE).unwrap(), "es");
+		let body: ApiErrorResponse = test::read_body_json(resp).await;
+		assert_eq!(
+			body.message,
+			"Recurso no encontrado: tournament con id copa"
+		);
+		let details = body.details.unwrap();
+		assert_eq!(details["message_key"], "error.not_found");
+		assert_eq!(details["message_params"]["id"], "copa");
+
+		let req = test::TestRequest::get()
+			.uri("/missing")
+			.insert_header((ACCEPT_LANGUAGE, "fr"))
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.headers().get(CONTENT_LANGUAGE).unwrap(), "en");
+		let body: ApiErrorResponse = test::read_body_json(resp).await;
+		assert_eq!(body.message, "Resource not found: tournament with id copa");
+	}
+
+	#[actix_web::test]
+	async fn test_middleware_errors_are_translated() {
+		let app = test::init_service(
+			App::new()
+				.wrap(from_fn(
+					|_req: ServiceRequest, _next: Next<BoxBody>| async {
+						Err::<ServiceResponse<BoxBody>, _>(ApiError::rate_limit("Too many requests", 5).into())
+					},
+				))
+				.wrap(from_fn(negotiate))
+				.route("/missing", web::get().to(missing)),
+		)
+		.await;
+
+		let req = test::TestRequest::get()
+			.uri("/missing")
+			.insert_header((ACCEPT_LANGUAGE, "es"))
+			.to_request();
+		let error = test::try_call_service(&app, req).await.err().unwrap();
+		let resp = error.error_response();
+		assert_eq!(resp.headers().get(CONTENT_LANGUAGE).unwrap(), "es");
+		let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap();
+		let body: ApiErrorResponse = serde_json::from_slice(&body).unwrap();
+		assert!(body.message.starts_with("Límite de peticiones excedido"));
+	}
+}
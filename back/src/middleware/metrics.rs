@@ -0,0 +1,68 @@
+//! Request counts and latency for `/metrics`
+//!
+//! [`track`] times every request and records it under the pattern of the
+//! route that handled it. Requests rejected before routing (e.g. by the
+//! authentication middleware) or not matching any route are recorded as
+//! [`metrics::UNMATCHED_ROUTE`].
+
+use std::time::Instant;
+
+use actix_web::{
+	body::MessageBody,
+	dev::{ServiceRequest, ServiceResponse},
+	middleware::Next,
+};
+
+use crate::utils::metrics;
+
+/// Record the request in the HTTP metrics
+pub async fn track(
+	req: ServiceRequest,
+	next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+	let started = Instant::now();
+	let method = req.method().to_string();
+
+	let result = next.call(req).await;
+	let (route, status) = match &result {
+		Ok(response) => (
+			response.request().match_pattern(),
+			response.status().as_u16(),
+		),
+		Err(error) => (None, error.as_response_error().status_code().as_u16()),
+	};
+	metrics::http_request(
+		&method,
+		route.as_deref().unwrap_or(metrics::UNMATCHED_ROUTE),
+		status,
+		started.elapsed(),
+	);
+	result
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use actix_web::middleware::from_fn;
+	use actix_web::{App, HttpResponse, test, web};
+
+	#[actix_web::test]
+	async fn test_requests_are_recorded_by_route_pattern() {
+		let app = test::init_service(App::new().wrap(from_fn(track)).route(
+			"/tracked/{id}",
+			web::get().to(|| async { HttpResponse::Ok().finish() }),
+		))
+		.await;
+
+		for uri in ["/tracked/1", "/tracked/2", "/untracked"] {
+			test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+		}
+
+		let text = metrics::global().render();
+		assert!(
+			text.contains(r#"http_requests_total{method="GET",route="/tracked/{id}",status="200"} 2"#)
+		);
+		assert!(text.contains(r#"route="unmatched",status="404"}"#));
+		assert!(!text.contains("/tracked/1"));
+	}
+}
@@ -0,0 +1,10 @@
+//! Request/response middleware for the Liga de los Muertos backend
+//!
+//! Middleware here runs around every route registered in `routes::entry`
+//! and is wired up in `main.rs`.
+
+pub mod auth;
+pub mod locale;
+pub mod metrics;
+pub mod rate_limit;
+pub mod request_id;
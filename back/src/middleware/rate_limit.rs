@@ -0,0 +1,558 @@
+//! Token-bucket rate limiting
+//!
+//! Every client gets a bucket per policy: `capacity` requests can be made at
+//! once, and the bucket refills at `capacity` tokens per `period_secs`.
+//! Clients are told apart by user id when authenticated, otherwise by a
+//! configured `X-Api-Key`, otherwise by IP address, so [`enforce`] runs inside
+//! [`super::auth::authenticate`]. The address is the peer's, unless the peer
+//! is a trusted proxy: then it is the last `X-Forwarded-For` hop that isn't
+//! one, so clients can't pick their own bucket with made-up headers.
+//!
+//! Requests use the first route rule matching their method and path, or the
+//! default policy. Responses carry `RateLimit-Limit`, `RateLimit-Remaining`,
+//! `RateLimit-Reset` and `RateLimit-Policy`; rejected requests get an
+//! [`ApiError::RateLimit`] (429) with `Retry-After`.
+//!
+//! Buckets are kept in a [`RateLimitStore`]; [`InMemoryStore`] only limits a
+//! single instance, and can be replaced by a shared store when the backend
+//! runs on several. Idle buckets are dropped by [`RateLimiter::sweep`], run
+//! every [`SWEEP_INTERVAL`] on the job queue rather than by requests.
+
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::{
+	HttpMessage, ResponseError,
+	body::{EitherBody, MessageBody},
+	dev::{ServiceRequest, ServiceResponse},
+	http::header::{HeaderMap, HeaderName, HeaderValue},
+	middleware::Next,
+	web,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::AppState;
+use crate::config::RateLimitConfig;
+use crate::middleware::auth::AuthUser;
+use crate::utils::error::ApiError;
+
+/// Header identifying API clients that aren't signed in
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Paths never limited, so probes and scrapers aren't turned away
+const EXEMPT_PREFIXES: &[&str] = &["/v1/health", "/metrics"];
+
+/// Header listing the addresses a request was forwarded for
+const FORWARDED_FOR_HEADER: &str = "X-Forwarded-For";
+
+/// How often idle (full) buckets are dropped
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How many requests a client can make, and how fast that allowance refills
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitPolicy {
+	/// Requests allowed in a burst
+	pub capacity: u32,
+	/// Seconds for an empty bucket to refill completely
+	pub period_secs: u64,
+}
+
+impl RateLimitPolicy {
+	pub const fn new(capacity: u32, period_secs: u64) -> Self {
+		Self {
+			capacity,
+			period_secs,
+		}
+	}
+
+	/// Tokens added per second
+	fn rate(&self) -> f64 {
+		f64::from(self.capacity) / self.period_secs.max(1) as f64
+	}
+}
+
+/// Policy for the requests matching a method and path pattern
+///
+/// `{name}` segments in `path` match any single segment.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteRule {
+	/// Name used in the bucket key, so rules don't share buckets
+	pub name: String,
+	pub method: String,
+	pub path: String,
+	pub capacity: u32,
+	pub period_secs: u64,
+}
+
+impl RouteRule {
+	pub fn new(name: &str, method: &str, path: &str, policy: RateLimitPolicy) -> Self {
+		Self {
+			name: name.to_string(),
+			method: method.to_string(),
+			path: path.to_string(),
+			capacity: policy.capacity,
+			period_secs: policy.period_secs,
+		}
+	}
+
+	pub fn policy(&self) -> RateLimitPolicy {
+		RateLimitPolicy::new(self.capacity, self.period_secs)
+	}
+
+	/// Whether a request is covered by this rule
+	pub fn matches(&self, method: &str, path: &str) -> bool {
+		if !self.method.eq_ignore_ascii_case(method) {
+			return false;
+		}
+		let mut pattern = self.path.trim_matches('/').split('/');
+		let mut segments = path.trim_matches('/').split('/');
+		loop {
+			match (pattern.next(), segments.next()) {
+				(None, None) => return true,
+				(Some(expected), Some(segment)) => {
+					let wildcard = expected.starts_with('{') && expected.ends_with('}');
+					if !wildcard && expected != segment {
+						return false;
+					}
+				}
+				_ => return false,
+			}
+		}
+	}
+}
+
+/// Outcome of taking a token from a bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+	pub allowed: bool,
+	pub limit: u32,
+	/// Requests left in the bucket
+	pub remaining: u32,
+	/// Until the bucket is full again
+	pub reset_after: Duration,
+	/// Until the next request is allowed (zero when allowed)
+	pub retry_after: Duration,
+}
+
+/// A client's token bucket
+#[derive(Debug, Clone, Copy)]
+pub struct Bucket {
+	tokens: f64,
+	updated: Instant,
+}
+
+impl Bucket {
+	/// A full bucket
+	pub fn new(policy: &RateLimitPolicy, now: Instant) -> Self {
+		Self {
+			tokens: f64::from(policy.capacity),
+			updated: now,
+		}
+	}
+
+	/// Refill for the time since the last request and try to take a token
+	pub fn take(&mut self, policy: &RateLimitPolicy, now: Instant) -> Decision {
+		let capacity = f64::from(policy.capacity);
+		let rate = policy.rate();
+		let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+		self.tokens = (self.tokens + elapsed * rate).min(capacity);
+		self.updated = now;
+
+		let allowed = self.tokens >= 1.0;
+		if allowed {
+			self.tokens -= 1.0;
+		}
+		let retry_after = if allowed {
+			Duration::ZERO
+		} else {
+			Duration::from_secs_f64((1.0 - self.tokens) / rate)
+		};
+
+		Decision {
+			allowed,
+			limit: policy.capacity,
+			remaining: self.tokens.floor() as u32,
+			reset_after: Duration::from_secs_f64((capacity - self.tokens) / rate),
+			retry_after,
+		}
+	}
+
+	/// Whether the bucket would be full by `now`, so it can be forgotten
+	fn is_idle(&self, policy: &RateLimitPolicy, now: Instant) -> bool {
+		let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+		self.tokens + elapsed * policy.rate() >= f64::from(policy.capacity)
+	}
+}
+
+/// Where buckets are kept
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+	/// Take a token from the bucket stored under `key`
+	async fn take(&self, key: &str, policy: &RateLimitPolicy) -> Decision;
+
+	/// Forget buckets that have refilled, returning how many; stores that
+	/// expire buckets on their own have nothing to do
+	async fn sweep(&self) -> usize {
+		0
+	}
+}
+
+/// Buckets held in process memory
+#[derive(Default)]
+pub struct InMemoryStore {
+	buckets: Mutex<HashMap<String, (RateLimitPolicy, Bucket)>>,
+}
+
+impl InMemoryStore {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+#[async_trait]
+impl RateLimitStore for InMemoryStore {
+	async fn take(&self, key: &str, policy: &RateLimitPolicy) -> Decision {
+		let now = Instant::now();
+		let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+		let (_, bucket) = buckets
+			.entry(key.to_string())
+			.or_insert_with(|| (*policy, Bucket::new(policy, now)));
+		bucket.take(policy, now)
+	}
+
+	async fn sweep(&self) -> usize {
+		let now = Instant::now();
+		let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+		let before = buckets.len();
+		buckets.retain(|_, (policy, bucket)| !bucket.is_idle(policy, now));
+		before - buckets.len()
+	}
+}
+
+/// Policies and the store they're enforced with
+pub struct RateLimiter {
+	enabled: bool,
+	default: RateLimitPolicy,
+	routes: Vec<RouteRule>,
+	/// Digests of the configured API keys
+	api_keys: HashSet<String>,
+	trusted_proxies: Vec<IpAddr>,
+	store: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimiter {
+	/// Limiter keeping its buckets in memory
+	pub fn new(config: &RateLimitConfig) -> Self {
+		Self::with_store(config, Arc::new(InMemoryStore::new()))
+	}
+
+	pub fn with_store(config: &RateLimitConfig, store: Arc<dyn RateLimitStore>) -> Self {
+		Self {
+			enabled: config.enabled,
+			default: config.default,
+			routes: config.routes.clone(),
+			api_keys: config
+				.api_keys
+				.iter()
+				.map(|key| digest(key.expose().as_bytes()))
+				.collect(),
+			trusted_proxies: config.trusted_proxies.clone(),
+			store,
+		}
+	}
+
+	/// Name and policy applying to a request
+	pub fn policy_for(&self, method: &str, path: &str) -> (&str, RateLimitPolicy) {
+		self
+			.routes
+			.iter()
+			.find(|rule| rule.matches(method, path))
+			.map_or(("default", self.default), |rule| {
+				(rule.name.as_str(), rule.policy())
+			})
+	}
+
+	/// Count a request against its policy, or `None` when it isn't limited
+	pub async fn check(
+		&self,
+		method: &str,
+		path: &str,
+		client: &str,
+	) -> Option<(Decision, RateLimitPolicy)> {
+		if !self.enabled
+			|| EXEMPT_PREFIXES
+				.iter()
+				.any(|prefix| path.starts_with(prefix))
+		{
+			return None;
+		}
+		let (name, policy) = self.policy_for(method, path);
+		let decision = self.store.take(&format!("{name}:{client}"), &policy).await;
+		Some((decision, policy))
+	}
+
+	/// Drop idle buckets from the store
+	pub async fn sweep(&self) -> usize {
+		self.store.sweep().await
+	}
+
+	/// Who is making the request: user id, API key digest or IP address
+	pub fn client_key(&self, req: &ServiceRequest) -> String {
+		if let Some(user) = req.extensions().get::<AuthUser>() {
+			return format!("user:{}", user.id.key());
+		}
+		if let Some(key) = req.headers().get(API_KEY_HEADER) {
+			let digest = digest(key.as_bytes());
+			if self.api_keys.contains(&digest) {
+				return format!("key:{}", &digest[..16]);
+			}
+		}
+		match req.peer_addr() {
+			Some(peer) => format!("ip:{}", self.client_ip(peer.ip(), req.headers())),
+			None => "ip:unknown".to_string(),
+		}
+	}
+
+	/// The client's address, seen through the trusted proxies in front of us
+	///
+	/// Hops are read from the right: each trusted proxy appended the address
+	/// it got the request from, so the first untrusted one is the client.
+	fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+		let mut client = peer;
+		let hops = headers
+			.get_all(FORWARDED_FOR_HEADER)
+			.filter_map(|value| value.to_str().ok())
+			.flat_map(|value| value.split(','))
+			.collect::<Vec<_>>();
+		for hop in hops.iter().rev() {
+			if !self.trusted_proxies.contains(&client) {
+				break;
+			}
+			match hop.trim().parse() {
+				Ok(ip) => client = ip,
+				Err(_) => break,
+			}
+		}
+		client
+	}
+}
+
+/// Keys are secrets; only their digest is kept in memory
+fn digest(key: &[u8]) -> String {
+	hex::encode(Sha256::digest(key))
+}
+
+fn seconds(duration: Duration) -> u64 {
+	duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+fn insert_headers(headers: &mut HeaderMap, decision: &Decision, policy: &RateLimitPolicy) {
+	let values = [
+		("ratelimit-limit", decision.limit.to_string()),
+		("ratelimit-remaining", decision.remaining.to_string()),
+		("ratelimit-reset", seconds(decision.reset_after).to_string()),
+		(
+			"ratelimit-policy",
+			format!("{};w={}", policy.capacity, policy.period_secs),
+		),
+	];
+	for (name, value) in values {
+		if let Ok(value) = HeaderValue::from_str(&value) {
+			headers.insert(HeaderName::from_static(name), value);
+		}
+	}
+}
+
+/// Reject requests over their policy's limit
+pub async fn enforce<B: MessageBody + 'static>(
+	req: ServiceRequest,
+	next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, actix_web::Error> {
+	let Some(state) = req.app_data::<web::Data<AppState>>().cloned() else {
+		return Ok(next.call(req).await?.map_into_left_body());
+	};
+	let method = req.method().to_string();
+	let path = req.path().to_string();
+	let client = state.rate_limiter.client_key(&req);
+
+	let Some((decision, policy)) = state.rate_limiter.check(&method, &path, &client).await else {
+		return Ok(next.call(req).await?.map_into_left_body());
+	};
+
+	if !decision.allowed {
+		let retry_after = seconds(decision.retry_after).max(1);
+		let error = ApiError::rate_limit(
+			&format!("Too many requests, retry in {retry_after} second(s)"),
+			retry_after,
+		);
+		let mut response = req.into_response(error.error_response());
+		insert_headers(response.headers_mut(), &decision, &policy);
+		return Ok(response.map_into_right_body());
+	}
+
+	let mut response = next.call(req).await?;
+	insert_headers(response.headers_mut(), &decision, &policy);
+	Ok(response.map_into_left_body())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::Secret;
+	use crate::utils::error::ApiErrorResponse;
+	use actix_web::middleware::from_fn;
+	use actix_web::{App, HttpResponse, test};
+
+	fn config(default: RateLimitPolicy) -> RateLimitConfig {
+		RateLimitConfig {
+			default,
+			..RateLimitConfig::default()
+		}
+	}
+
+	#[actix_web::test]
+	async fn test_bucket_refills_over_time() {
+		let policy = RateLimitPolicy::new(2, 10);
+		let start = Instant::now();
+		let mut bucket = Bucket::new(&policy, start);
+
+		assert_eq!(bucket.take(&policy, start).remaining, 1);
+		assert!(bucket.take(&policy, start).allowed);
+		let rejected = bucket.take(&policy, start);
+		assert!(!rejected.allowed);
+		assert_eq!(seconds(rejected.retry_after), 5);
+		assert_eq!(seconds(rejected.reset_after), 10);
+
+		// One token every five seconds
+		assert!(bucket.take(&policy, start + Duration::from_secs(5)).allowed);
+		assert!(!bucket.take(&policy, start + Duration::from_secs(6)).allowed);
+	}
+
+	#[actix_web::test]
+	async fn test_route_rules_match_patterns() {
+		let rule = RouteRule::new(
+			"score_reports",
+			"POST",
+			"/v1/matches/{match_id}/report",
+			RateLimitPolicy::new(5, 60),
+		);
+		assert!(rule.matches("POST", "/v1/matches/abc/report"));
+		assert!(rule.matches("post", "/v1/matches/abc/report/"));
+		assert!(!rule.matches("GET", "/v1/matches/abc/report"));
+		assert!(!rule.matches("POST", "/v1/matches/abc"));
+		assert!(!rule.matches("POST", "/v1/matches/abc/report/extra"));
+
+		let limiter = RateLimiter::new(&RateLimitConfig::default());
+		assert_eq!(
+			limiter.policy_for("POST", "/v1/matches/abc/report").0,
+			"score_reports"
+		);
+		assert_eq!(limiter.policy_for("GET", "/v1/matches/abc").0, "default");
+	}
+
+	#[actix_web::test]
+	async fn test_over_limit_requests_get_429_with_headers() {
+		let mut state = AppState::new_test();
+		state.rate_limiter = Arc::new(RateLimiter::new(&RateLimitConfig {
+			api_keys: vec![Secret::new("key_1"), Secret::new("key_2")],
+			..config(RateLimitPolicy::new(1, 60))
+		}));
+		let app = test::init_service(
+			App::new()
+				.app_data(web::Data::new(state))
+				.wrap(from_fn(enforce))
+				.route("/limited", web::get().to(HttpResponse::Ok))
+				.route("/v1/health", web::get().to(HttpResponse::Ok)),
+		)
+		.await;
+
+		let req = || {
+			test::TestRequest::get()
+				.uri("/limited")
+				.insert_header((API_KEY_HEADER, "key_1"))
+				.to_request()
+		};
+		let resp = test::call_service(&app, req()).await;
+		assert!(resp.status().is_success());
+		assert_eq!(resp.headers().get("ratelimit-limit").unwrap(), "1");
+		assert_eq!(resp.headers().get("ratelimit-remaining").unwrap(), "0");
+		assert_eq!(resp.headers().get("ratelimit-policy").unwrap(), "1;w=60");
+
+		let resp = test::call_service(&app, req()).await;
+		assert_eq!(
+			resp.status(),
+			actix_web::http::StatusCode::TOO_MANY_REQUESTS
+		);
+		assert_eq!(resp.headers().get("retry-after").unwrap(), "60");
+		let body: ApiErrorResponse = test::read_body_json(resp).await;
+		assert_eq!(body.error_code, "RATE_LIMIT_EXCEEDED");
+		assert_eq!(body.details.unwrap()["retry_after"], 60);
+
+		// Other clients have their own bucket, probes are never limited
+		let other = test::TestRequest::get()
+			.uri("/limited")
+			.insert_header((API_KEY_HEADER, "key_2"))
+			.to_request();
+		assert!(test::call_service(&app, other).await.status().is_success());
+		for _ in 0..3 {
+			let probe = test::TestRequest::get().uri("/v1/health").to_request();
+			assert!(test::call_service(&app, probe).await.status().is_success());
+		}
+	}
+
+	#[actix_web::test]
+	async fn test_clients_cannot_pick_their_own_bucket() {
+		let limiter = RateLimiter::new(&RateLimitConfig {
+			api_keys: vec![Secret::new("key_1")],
+			trusted_proxies: vec!["10.0.0.1".parse().unwrap()],
+			..RateLimitConfig::default()
+		});
+		let key = |peer: &str, headers: &[(&str, &str)]| {
+			let mut req = test::TestRequest::get().peer_addr(peer.parse().unwrap());
+			for &header in headers {
+				req = req.insert_header(header);
+			}
+			limiter.client_key(&req.to_srv_request())
+		};
+
+		assert!(key("203.0.113.7:5000", &[(API_KEY_HEADER, "key_1")]).starts_with("key:"));
+		// Unknown keys and forwarded addresses from untrusted peers are ignored
+		assert_eq!(
+			key("203.0.113.7:5000", &[(API_KEY_HEADER, "made_up")]),
+			"ip:203.0.113.7"
+		);
+		assert_eq!(
+			key("203.0.113.7:5000", &[("X-Forwarded-For", "198.51.100.1")]),
+			"ip:203.0.113.7"
+		);
+
+		// Behind a trusted proxy the client is the last hop it saw
+		assert_eq!(
+			key(
+				"10.0.0.1:443",
+				&[("X-Forwarded-For", "198.51.100.1, 203.0.113.7")]
+			),
+			"ip:203.0.113.7"
+		);
+		assert_eq!(key("10.0.0.1:443", &[]), "ip:10.0.0.1");
+	}
+
+	#[actix_web::test]
+	async fn test_sweep_drops_idle_buckets() {
+		let store = InMemoryStore::new();
+		let slow = RateLimitPolicy::new(1, 3600);
+		let fast = RateLimitPolicy::new(1000, 1);
+		store.take("busy", &slow).await;
+		store.take("idle", &fast).await;
+		tokio::time::sleep(Duration::from_millis(10)).await;
+
+		assert_eq!(store.sweep().await, 1);
+		assert!(!store.take("busy", &slow).await.allowed);
+	}
+}
@@ -0,0 +1,267 @@
+//! Request ids for tracing a request across logs and responses
+//!
+//! [`propagate`] takes the caller's `X-Request-Id` (when it looks sane) or
+//! generates one, and runs the rest of the request inside a scope holding it.
+//! Within that scope every log record is tagged with the id (and, once
+//! authentication ran, the user id), error bodies carry it in `request_id`, and
+//! background jobs queued by the request inherit it.
+//! The id is echoed back in the `X-Request-Id` response header.
+
+use std::future::{Future, Ready, ready};
+use std::sync::OnceLock;
+
+use actix_web::{
+	FromRequest, HttpMessage, HttpRequest,
+	body::MessageBody,
+	dev::{Payload, ServiceRequest, ServiceResponse},
+	error::InternalError,
+	http::header::{HeaderName, HeaderValue},
+	middleware::Next,
+};
+
+/// Header carrying the request id in both directions
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest caller-provided id that is accepted as is
+const MAX_LENGTH: usize = 128;
+
+/// What the task knows about the request it works for
+struct Context {
+	id: String,
+	user_id: OnceLock<String>,
+}
+
+impl Context {
+	fn new(id: String) -> Self {
+		Self {
+			id,
+			user_id: OnceLock::new(),
+		}
+	}
+}
+
+tokio::task_local! {
+	static CURRENT: Context;
+}
+
+/// Request id and user captured to carry into another task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestScope {
+	pub id: String,
+	pub user_id: Option<String>,
+}
+
+impl RequestScope {
+	pub fn new(id: impl Into<String>) -> Self {
+		Self {
+			id: id.into(),
+			user_id: None,
+		}
+	}
+
+	/// Run `future` on behalf of this request
+	pub async fn run<F: Future>(self, future: F) -> F::Output {
+		let context = Context::new(self.id);
+		if let Some(user_id) = self.user_id {
+			let _ = context.user_id.set(user_id);
+		}
+		CURRENT.scope(context, future).await
+	}
+}
+
+/// Id of the request being handled, available as an extractor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl FromRequest for RequestId {
+	type Error = actix_web::Error;
+	type Future = Ready<Result<Self, Self::Error>>;
+
+	fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+		let id = req
+			.extensions()
+			.get::<RequestId>()
+			.cloned()
+			.unwrap_or_else(|| RequestId(generate()));
+		ready(Ok(id))
+	}
+}
+
+/// Id of the request the current task is working for, if any
+pub fn current() -> Option<String> {
+	CURRENT.try_with(|context| context.id.clone()).ok()
+}
+
+/// Signed-in user of the current request, once authentication recorded it
+pub fn current_user() -> Option<String> {
+	CURRENT
+		.try_with(|context| context.user_id.get().cloned())
+		.ok()
+		.flatten()
+}
+
+/// Record the signed-in user of the current request
+pub fn set_user(user_id: &str) {
+	let _ = CURRENT.try_with(|context| context.user_id.set(user_id.to_string()));
+}
+
+/// The current request's id and user, to hand to background work
+pub fn snapshot() -> Option<RequestScope> {
+	CURRENT
+		.try_with(|context| RequestScope {
+			id: context.id.clone(),
+			user_id: context.user_id.get().cloned(),
+		})
+		.ok()
+}
+
+/// Fresh random request id
+pub fn generate() -> String {
+	uuid::Uuid::new_v4().to_string()
+}
+
+/// Caller-provided id, if it is short and made of safe characters
+fn accept(value: &str) -> Option<String> {
+	let valid = !value.is_empty()
+		&& value.len() <= MAX_LENGTH
+		&& value
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+	valid.then(|| value.to_string())
+}
+
+/// Middleware assigning every request an id
+pub async fn propagate(
+	req: ServiceRequest,
+	next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+	let id = req
+		.headers()
+		.get(REQUEST_ID_HEADER)
+		.and_then(|value| value.to_str().ok())
+		.and_then(accept)
+		.unwrap_or_else(generate);
+	req.extensions_mut().insert(RequestId(id.clone()));
+	let header = HeaderValue::from_str(&id).ok();
+
+	match CURRENT
+		.scope(Context::new(id.clone()), next.call(req))
+		.await
+	{
+		Ok(mut response) => {
+			if let Some(header) = header {
+				response.headers_mut().insert(REQUEST_ID, header);
+			}
+			Ok(response)
+		}
+		// Errors from inner middleware are rendered here, while the id is known
+		Err(error) => {
+			let mut response = CURRENT.sync_scope(Context::new(id), || {
+				error.as_response_error().error_response()
+			});
+			if let Some(header) = header {
+				response.headers_mut().insert(REQUEST_ID, header);
+			}
+			Err(InternalError::from_response(error.to_string(), response).into())
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::utils::error::{ApiError, ApiErrorResponse};
+	use actix_web::body::BoxBody;
+	use actix_web::middleware::from_fn;
+	use actix_web::{App, HttpResponse, test, web};
+
+	async fn echo(id: RequestId) -> HttpResponse {
+		assert_eq!(current(), Some(id.0.clone()));
+		HttpResponse::Ok().body(id.0)
+	}
+
+	async fn fail() -> Result<HttpResponse, ApiError> {
+		Err(ApiError::not_found("tournament", "missing"))
+	}
+
+	#[actix_web::test]
+	async fn test_request_id_is_accepted_or_generated() {
+		let app = test::init_service(
+			App::new()
+				.wrap(from_fn(propagate))
+				.route("/echo", web::get().to(echo)),
+		)
+		.await;
+
+		let req = test::TestRequest::get()
+			.uri("/echo")
+			.insert_header((REQUEST_ID_HEADER, "trace-123"))
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "trace-123");
+		assert_eq!(test::read_body(resp).await, "trace-123");
+
+		// Unsafe ids are replaced with a generated one
+		let req = test::TestRequest::get()
+			.uri("/echo")
+			.insert_header((REQUEST_ID_HEADER, "bad id\twith spaces"))
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		let header = resp
+			.headers()
+			.get(REQUEST_ID_HEADER)
+			.unwrap()
+			.to_str()
+			.unwrap()
+			.to_string();
+		assert_eq!(header.len(), 36);
+		assert_eq!(test::read_body(resp).await, header);
+	}
+
+	#[actix_web::test]
+	async fn test_error_bodies_carry_the_request_id() {
+		let app = test::init_service(
+			App::new()
+				.wrap(from_fn(propagate))
+				.route("/fail", web::get().to(fail)),
+		)
+		.await;
+
+		let req = test::TestRequest::get()
+			.uri("/fail")
+			.insert_header((REQUEST_ID_HEADER, "trace-404"))
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "trace-404");
+		let body: ApiErrorResponse = test::read_body_json(resp).await;
+		assert_eq!(body.request_id.as_deref(), Some("trace-404"));
+	}
+
+	#[actix_web::test]
+	async fn test_middleware_errors_carry_the_request_id() {
+		let app = test::init_service(
+			App::new()
+				.wrap(from_fn(
+					|_req: ServiceRequest, _next: Next<BoxBody>| async {
+						Err::<ServiceResponse<BoxBody>, _>(ApiError::authentication("No session").into())
+					},
+				))
+				.wrap(from_fn(propagate))
+				.route("/echo", web::get().to(echo)),
+		)
+		.await;
+
+		let req = test::TestRequest::get()
+			.uri("/echo")
+			.insert_header((REQUEST_ID_HEADER, "trace-401"))
+			.to_request();
+		let error = test::try_call_service(&app, req).await.err().unwrap();
+		let resp = error.error_response();
+		assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "trace-401");
+		let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap();
+		let body: ApiErrorResponse = serde_json::from_slice(&body).unwrap();
+		assert_eq!(body.request_id.as_deref(), Some("trace-401"));
+	}
+}
@@ -0,0 +1,392 @@
+//! Versioned schema migrations
+//!
+//! Migrations are `.surql` files embedded at build time and applied in
+//! version order at startup. Each applied migration is recorded in the
+//! `_migration` table with a checksum of its source; a migration whose file
+//! changed after it ran stops startup, since the database no longer matches
+//! what the code expects. Migrations are up-only: to undo a change, add a new
+//! migration.
+//!
+//! A lock record in `_migration_lock` keeps concurrently starting instances
+//! from migrating at the same time. Locks expire so a crashed instance can't
+//! block the others forever.
+//!
+//! To add a migration, create `NNNN_short_name.surql` next to this file and
+//! append it to [`MIGRATIONS`].
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use surrealdb::RecordId;
+
+use crate::DB;
+use crate::utils::error::{ApiError, ApiResult};
+use crate::utils::logging;
+
+/// A schema change
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+	pub version: u32,
+	pub name: &'static str,
+	pub sql: &'static str,
+}
+
+impl Migration {
+	/// Hex SHA-256 of the migration source
+	pub fn checksum(&self) -> String {
+		hex::encode(Sha256::digest(self.sql.as_bytes()))
+	}
+}
+
+/// All migrations, in version order
+pub const MIGRATIONS: &[Migration] = &[
+	Migration {
+		version: 1,
+		name: "baseline",
+		sql: include_str!("0001_baseline.surql"),
+	},
+	Migration {
+		version: 2,
+		name: "tournament_owner_and_unique_participants",
+		sql: include_str!("0002_tournament_owner_and_unique_participants.surql"),
+	},
+];
+
+/// How long a lock is honoured before another instance may take it over
+const LOCK_TTL: &str = "5m";
+
+/// How often a running instance pushes its lock's expiry back
+const LOCK_HEARTBEAT: Duration = Duration::from_secs(60);
+
+/// How long to wait for another instance to finish migrating
+const LOCK_WAIT: Duration = Duration::from_secs(120);
+
+/// Delay between attempts to take the lock
+const LOCK_RETRY: Duration = Duration::from_secs(2);
+
+/// Bookkeeping tables used by the runner itself
+const BOOTSTRAP: &str = "
+	DEFINE TABLE IF NOT EXISTS _migration SCHEMAFULL PERMISSIONS NONE;
+	DEFINE FIELD IF NOT EXISTS version ON TABLE _migration TYPE int;
+	DEFINE FIELD IF NOT EXISTS name ON TABLE _migration TYPE string;
+	DEFINE FIELD IF NOT EXISTS checksum ON TABLE _migration TYPE string;
+	DEFINE FIELD IF NOT EXISTS applied_at ON TABLE _migration TYPE datetime DEFAULT time::now() READONLY;
+
+	DEFINE TABLE IF NOT EXISTS _migration_lock SCHEMAFULL PERMISSIONS NONE;
+	DEFINE FIELD IF NOT EXISTS owner ON TABLE _migration_lock TYPE string;
+	DEFINE FIELD IF NOT EXISTS expires_at ON TABLE _migration_lock TYPE datetime;
+";
+
+/// Row of the `_migration` table
+#[derive(Debug, Clone, Deserialize)]
+pub struct AppliedMigration {
+	pub version: u32,
+	pub name: String,
+	pub checksum: String,
+	pub applied_at: DateTime<Utc>,
+}
+
+/// Why the recorded migrations don't line up with the known ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+	/// An applied migration's source changed since it ran
+	Modified { version: u32, name: String },
+	/// The database has a migration this build doesn't know about
+	Unknown { version: u32, name: String },
+}
+
+impl std::fmt::Display for MigrationError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			MigrationError::Modified { version, name } => write!(
+				f,
+				"Migration {version:04} ({name}) was modified after it was applied"
+			),
+			MigrationError::Unknown { version, name } => write!(
+				f,
+				"Database has migration {version:04} ({name}), which this build doesn't know; \
+				 is it running an older version?"
+			),
+		}
+	}
+}
+
+impl From<MigrationError> for ApiError {
+	fn from(error: MigrationError) -> Self {
+		ApiError::internal(&error.to_string())
+	}
+}
+
+/// Migrations still to apply, after checking the applied ones are intact
+pub fn pending<'a>(
+	known: &'a [Migration],
+	applied: &[AppliedMigration],
+) -> Result<Vec<&'a Migration>, MigrationError> {
+	for record in applied {
+		let Some(migration) = known.iter().find(|m| m.version == record.version) else {
+			return Err(MigrationError::Unknown {
+				version: record.version,
+				name: record.name.clone(),
+			});
+		};
+		if migration.checksum() != record.checksum {
+			return Err(MigrationError::Modified {
+				version: migration.version,
+				name: migration.name.to_string(),
+			});
+		}
+	}
+
+	Ok(
+		known
+			.iter()
+			.filter(|m| !applied.iter().any(|record| record.version == m.version))
+			.collect(),
+	)
+}
+
+/// Apply pending migrations, holding the migration lock while doing so
+pub async fn run() -> ApiResult<()> {
+	logging::schema_init();
+	DB.query(BOOTSTRAP).await?.check()?;
+
+	let owner = format!(
+		"pid-{}-{}",
+		std::process::id(),
+		Utc::now().timestamp_millis()
+	);
+	acquire_lock(&owner).await?;
+	let outcome = tokio::select! {
+		outcome = apply_pending() => outcome,
+		never = heartbeat(&owner) => match never {},
+	};
+	// The lock expires on its own, so failing to release it must not hide
+	// how the migrations went
+	if let Err(e) = release_lock(&owner).await {
+		log::warn!("Failed to release the migration lock: {e}");
+	}
+	outcome?;
+
+	logging::schema_success();
+	Ok(())
+}
+
+/// Migrations recorded as applied in the database
+pub async fn applied() -> ApiResult<Vec<AppliedMigration>> {
+	Ok(
+		DB.query("SELECT version, name, checksum, applied_at FROM _migration ORDER BY version")
+			.await?
+			.take(0)?,
+	)
+}
+
+/// Migrations this build knows about that the database hasn't applied yet
+pub async fn outstanding() -> ApiResult<Vec<&'static Migration>> {
+	Ok(pending(MIGRATIONS, &applied().await?)?)
+}
+
+async fn apply_pending() -> ApiResult<()> {
+	for migration in outstanding().await? {
+		DB.query(format!(
+			"BEGIN TRANSACTION;
+			{}
+			CREATE $id CONTENT {{ version: $version, name: $name, checksum: $checksum }};
+			COMMIT TRANSACTION;",
+			migration.sql
+		))
+		.bind((
+			"id",
+			RecordId::from(("_migration", migration.version as i64)),
+		))
+		.bind(("version", migration.version))
+		.bind(("name", migration.name))
+		.bind(("checksum", migration.checksum()))
+		.await?
+		.check()?;
+
+		logging::migration_applied(migration.version, migration.name);
+	}
+	Ok(())
+}
+
+/// Take the lock, waiting for other instances (or stale locks) to clear
+async fn acquire_lock(owner: &str) -> ApiResult<()> {
+	let started = std::time::Instant::now();
+	loop {
+		let taken = DB
+			.query(format!(
+				"DELETE _migration_lock:lock WHERE expires_at < time::now();
+				CREATE _migration_lock:lock CONTENT {{
+					owner: $owner,
+					expires_at: time::now() + {LOCK_TTL},
+				}};"
+			))
+			.bind(("owner", owner.to_string()))
+			.await?
+			.check();
+		if lock_taken(taken.map(|_| ()))? {
+			return Ok(());
+		}
+
+		if started.elapsed() >= LOCK_WAIT {
+			return Err(ApiError::internal(
+				"Timed out waiting for another instance to finish migrating",
+			));
+		}
+		log::info!("⏳ Another instance is migrating the database, waiting...");
+		tokio::time::sleep(LOCK_RETRY).await;
+	}
+}
+
+/// Whether the attempt to create the lock got it
+///
+/// Only the lock record already existing means another instance holds it;
+/// any other error is returned.
+fn lock_taken(attempt: surrealdb::Result<()>) -> ApiResult<bool> {
+	match attempt.map_err(ApiError::from) {
+		Ok(()) => Ok(true),
+		Err(ApiError::Conflict { .. }) => Ok(false),
+		Err(e) => Err(e),
+	}
+}
+
+/// Keep renewing the lock so long migrations don't outlive [`LOCK_TTL`]
+async fn heartbeat(owner: &str) -> std::convert::Infallible {
+	loop {
+		tokio::time::sleep(LOCK_HEARTBEAT).await;
+		if let Err(e) = renew_lock(owner).await {
+			log::warn!("Failed to renew the migration lock: {e}");
+		}
+	}
+}
+
+async fn renew_lock(owner: &str) -> ApiResult<()> {
+	DB.query(format!(
+		"UPDATE _migration_lock:lock SET expires_at = time::now() + {LOCK_TTL} WHERE owner = $owner"
+	))
+	.bind(("owner", owner.to_string()))
+	.await?
+	.check()?;
+	Ok(())
+}
+
+async fn release_lock(owner: &str) -> ApiResult<()> {
+	DB.query("DELETE _migration_lock:lock WHERE owner = $owner")
+		.bind(("owner", owner.to_string()))
+		.await?
+		.check()?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn applied(migration: &Migration) -> AppliedMigration {
+		AppliedMigration {
+			version: migration.version,
+			name: migration.name.to_string(),
+			checksum: migration.checksum(),
+			applied_at: Utc::now(),
+		}
+	}
+
+	#[test]
+	fn test_migrations_are_ordered_from_one() {
+		for (index, migration) in MIGRATIONS.iter().enumerate() {
+			assert_eq!(migration.version as usize, index + 1);
+			assert!(!migration.sql.trim().is_empty());
+		}
+	}
+
+	#[test]
+	fn test_pending_skips_applied_migrations() {
+		let known = [
+			MIGRATIONS[0],
+			Migration {
+				version: 2,
+				name: "next",
+				sql: "DEFINE TABLE IF NOT EXISTS next;",
+			},
+		];
+		assert_eq!(pending(&known, &[]).unwrap().len(), 2);
+
+		let done = vec![applied(&known[0])];
+		let todo = pending(&known, &done).unwrap();
+		assert_eq!(todo.len(), 1);
+		assert_eq!(todo[0].version, 2);
+
+		let all: Vec<_> = known.iter().map(applied).collect();
+		assert!(pending(&known, &all).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_pending_rejects_modified_and_unknown_migrations() {
+		let mut modified = applied(&MIGRATIONS[0]);
+		modified.checksum = "0".repeat(64);
+		assert_eq!(
+			pending(MIGRATIONS, &[modified]).unwrap_err(),
+			MigrationError::Modified {
+				version: 1,
+				name: "baseline".to_string()
+			}
+		);
+
+		let unknown = AppliedMigration {
+			version: 999,
+			name: "from_the_future".to_string(),
+			checksum: String::new(),
+			applied_at: Utc::now(),
+		};
+		assert!(matches!(
+			pending(MIGRATIONS, &[unknown]),
+			Err(MigrationError::Unknown { version: 999, .. })
+		));
+	}
+
+	#[test]
+	fn test_only_an_existing_lock_means_it_is_held() {
+		use surrealdb::error::{Api, Db};
+
+		assert!(lock_taken(Ok(())).unwrap());
+
+		let held = Db::RecordExists {
+			thing: surrealdb::sql::Thing::from(("_migration_lock", "lock")),
+		};
+		assert!(!lock_taken(Err(surrealdb::Error::Db(held))).unwrap());
+
+		let lost = surrealdb::Error::Api(Api::Ws("connection reset".to_string()));
+		assert!(lock_taken(Err(lost)).is_err());
+	}
+
+	#[actix_web::test]
+	async fn test_only_the_owner_renews_and_releases_the_lock() {
+		crate::test_utils::setup();
+		let expires_at = || async {
+			let mut response = DB
+				.query("RETURN _migration_lock:lock.expires_at")
+				.await
+				.unwrap();
+			let expires_at: Option<DateTime<Utc>> = response.take(0).unwrap();
+			expires_at
+		};
+
+		acquire_lock("owner").await.unwrap();
+		DB.query("UPDATE _migration_lock:lock SET expires_at = time::now() + 1m")
+			.await
+			.unwrap();
+		let before = expires_at().await.unwrap();
+
+		renew_lock("someone-else").await.unwrap();
+		assert_eq!(expires_at().await, Some(before));
+		renew_lock("owner").await.unwrap();
+		assert!(expires_at().await.unwrap() > before);
+
+		release_lock("someone-else").await.unwrap();
+		assert!(expires_at().await.is_some());
+		release_lock("owner").await.unwrap();
+		assert!(expires_at().await.is_none());
+	}
+}
@@ -0,0 +1,266 @@
+//! In-memory repositories for tests
+//!
+//! Records live in a `Vec` behind a mutex. Created records get random UUID
+//! keys, which can't collide with the ids of records stored as is. The
+//! repositories follow the same rules as the SurrealDB implementations where
+//! those rules matter to callers, such as one participation per user.
+
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use surrealdb::RecordId;
+use uuid::Uuid;
+
+use super::{ParticipantRepository, TournamentRepository};
+use crate::entities::{CreateTournamentData, Participant, Tournament, UpdateTournamentData};
+use crate::utils::error::{ApiError, ApiResult};
+
+fn new_key() -> String {
+	Uuid::new_v4().simple().to_string()
+}
+
+/// Tournaments kept in memory
+#[derive(Default)]
+pub struct InMemoryTournamentRepository {
+	tournaments: Mutex<Vec<Tournament>>,
+}
+
+impl InMemoryTournamentRepository {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Store a tournament as is, replacing one with the same id
+	pub fn insert(&self, tournament: Tournament) {
+		let mut tournaments = self.tournaments.lock().unwrap();
+		tournaments.retain(|existing| existing.id != tournament.id);
+		tournaments.push(tournament);
+	}
+}
+
+#[async_trait]
+impl TournamentRepository for InMemoryTournamentRepository {
+	async fn find(&self, id: &RecordId) -> ApiResult<Option<Tournament>> {
+		let tournaments = self.tournaments.lock().unwrap();
+		Ok(tournaments.iter().find(|t| &t.id == id).cloned())
+	}
+
+	async fn list(&self, published_only: bool) -> ApiResult<Vec<Tournament>> {
+		let tournaments = self.tournaments.lock().unwrap();
+		let mut listed: Vec<Tournament> = tournaments
+			.iter()
+			.filter(|t| !published_only || t.published)
+			.cloned()
+			.collect();
+		listed.sort_by_key(|t| std::cmp::Reverse(t.created_at));
+		Ok(listed)
+	}
+
+	async fn create(
+		&self,
+		data: CreateTournamentData,
+		created_by: &RecordId,
+	) -> ApiResult<Tournament> {
+		let mut tournaments = self.tournaments.lock().unwrap();
+		let now = Utc::now();
+		let tournament = Tournament {
+			id: RecordId::from(("tournament", new_key())),
+			name: data.name,
+			description: data.description,
+			published: data.published.unwrap_or(false),
+			season: data.season,
+			created_by: created_by.clone(),
+			created_at: now,
+			updated_at: now,
+		};
+		tournaments.push(tournament.clone());
+		Ok(tournament)
+	}
+
+	async fn update(
+		&self,
+		id: &RecordId,
+		data: UpdateTournamentData,
+	) -> ApiResult<Option<Tournament>> {
+		let mut tournaments = self.tournaments.lock().unwrap();
+		let Some(tournament) = tournaments.iter_mut().find(|t| &t.id == id) else {
+			return Ok(None);
+		};
+
+		if let Some(name) = data.name {
+			tournament.name = name;
+		}
+		if let Some(description) = data.description {
+			tournament.description = description;
+		}
+		if let Some(published) = data.published {
+			tournament.published = published;
+		}
+		if let Some(season) = data.season {
+			tournament.season = season;
+		}
+		tournament.updated_at = Utc::now();
+		Ok(Some(tournament.clone()))
+	}
+}
+
+/// Participants kept in memory
+#[derive(Default)]
+pub struct InMemoryParticipantRepository {
+	participants: Mutex<Vec<Participant>>,
+}
+
+impl InMemoryParticipantRepository {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+#[async_trait]
+impl ParticipantRepository for InMemoryParticipantRepository {
+	async fn find(&self, id: &RecordId) -> ApiResult<Option<Participant>> {
+		let participants = self.participants.lock().unwrap();
+		Ok(participants.iter().find(|p| &p.id == id).cloned())
+	}
+
+	async fn list_by_tournament(&self, tournament: &RecordId) -> ApiResult<Vec<Participant>> {
+		let participants = self.participants.lock().unwrap();
+		Ok(
+			participants
+				.iter()
+				.filter(|p| &p.tournament == tournament)
+				.cloned()
+				.collect(),
+		)
+	}
+
+	async fn find_by_user(
+		&self,
+		tournament: &RecordId,
+		user: &RecordId,
+	) -> ApiResult<Option<Participant>> {
+		let participants = self.participants.lock().unwrap();
+		Ok(
+			participants
+				.iter()
+				.find(|p| &p.tournament == tournament && &p.user_id == user)
+				.cloned(),
+		)
+	}
+
+	async fn add(&self, tournament: &RecordId, user: &RecordId) -> ApiResult<Participant> {
+		let mut participants = self.participants.lock().unwrap();
+		if participants
+			.iter()
+			.any(|p| &p.tournament == tournament && &p.user_id == user)
+		{
+			return Err(ApiError::conflict(
+				"User already takes part in this tournament",
+			));
+		}
+
+		let participant = Participant {
+			id: RecordId::from(("participant", new_key())),
+			tournament: tournament.clone(),
+			user_id: user.clone(),
+			joined_at: Utc::now(),
+		};
+		participants.push(participant.clone());
+		Ok(participant)
+	}
+
+	async fn remove(&self, id: &RecordId) -> ApiResult<bool> {
+		let mut participants = self.participants.lock().unwrap();
+		let before = participants.len();
+		participants.retain(|p| &p.id != id);
+		Ok(participants.len() < before)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn data(name: &str, published: bool) -> CreateTournamentData {
+		CreateTournamentData {
+			name: name.to_string(),
+			description: String::new(),
+			published: Some(published),
+			season: None,
+		}
+	}
+
+	#[actix_web::test]
+	async fn test_tournament_repository_roundtrip() {
+		let repo = InMemoryTournamentRepository::new();
+		let organizer = RecordId::from(("user", "catrina"));
+
+		let draft = repo.create(data("Copa", false), &organizer).await.unwrap();
+		repo.create(data("Liga", true), &organizer).await.unwrap();
+		assert_eq!(repo.list(false).await.unwrap().len(), 2);
+		assert_eq!(repo.list(true).await.unwrap().len(), 1);
+
+		let changes = UpdateTournamentData {
+			name: None,
+			description: None,
+			published: Some(true),
+			season: None,
+		};
+		let updated = repo.update(&draft.id, changes).await.unwrap().unwrap();
+		assert!(updated.published);
+		assert_eq!(updated.name, "Copa");
+		assert!(
+			repo
+				.update(
+					&RecordId::from(("tournament", "missing")),
+					UpdateTournamentData {
+						name: None,
+						description: None,
+						published: None,
+						season: None,
+					}
+				)
+				.await
+				.unwrap()
+				.is_none()
+		);
+	}
+
+	#[actix_web::test]
+	async fn test_created_tournaments_never_replace_inserted_ones() {
+		let repo = InMemoryTournamentRepository::new();
+		let organizer = RecordId::from(("user", "catrina"));
+		let first = repo.create(data("Copa", false), &organizer).await.unwrap();
+		repo.insert(Tournament {
+			id: RecordId::from(("tournament", "t2")),
+			..first.clone()
+		});
+
+		let second = repo.create(data("Liga", true), &organizer).await.unwrap();
+		assert_ne!(second.id, first.id);
+		assert_eq!(repo.list(false).await.unwrap().len(), 3);
+	}
+
+	#[actix_web::test]
+	async fn test_participant_repository_rejects_duplicates() {
+		let repo = InMemoryParticipantRepository::new();
+		let tournament = RecordId::from(("tournament", "copa"));
+		let user = RecordId::from(("user", "calaca"));
+
+		let participant = repo.add(&tournament, &user).await.unwrap();
+		let err = repo.add(&tournament, &user).await.unwrap_err();
+		assert!(matches!(err, ApiError::Conflict { .. }));
+
+		let found = repo.find_by_user(&tournament, &user).await.unwrap();
+		assert_eq!(found.map(|p| p.id), Some(participant.id.clone()));
+		assert!(repo.remove(&participant.id).await.unwrap());
+		assert!(
+			repo
+				.list_by_tournament(&tournament)
+				.await
+				.unwrap()
+				.is_empty()
+		);
+	}
+}
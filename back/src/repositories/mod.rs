@@ -0,0 +1,64 @@
+//! Typed data access behind traits
+//!
+//! Handlers reach tournaments and participants through the repositories held
+//! in [`crate::AppState`] instead of the global `DB`, so they can run against
+//! in-memory implementations in tests (`memory`, built for tests and the
+//! `test-utils` feature only). [`surreal`] holds the implementations used in
+//! production.
+
+use async_trait::async_trait;
+use surrealdb::RecordId;
+
+use crate::entities::{CreateTournamentData, Participant, Tournament, UpdateTournamentData};
+use crate::utils::error::ApiResult;
+
+#[cfg(any(test, feature = "test-utils"))]
+pub mod memory;
+pub mod surreal;
+
+#[cfg(any(test, feature = "test-utils"))]
+pub use memory::{InMemoryParticipantRepository, InMemoryTournamentRepository};
+pub use surreal::{SurrealParticipantRepository, SurrealTournamentRepository};
+
+/// Storage of tournaments
+#[async_trait]
+pub trait TournamentRepository: Send + Sync {
+	async fn find(&self, id: &RecordId) -> ApiResult<Option<Tournament>>;
+
+	/// All tournaments, or only the published ones, newest first
+	async fn list(&self, published_only: bool) -> ApiResult<Vec<Tournament>>;
+
+	async fn create(
+		&self,
+		data: CreateTournamentData,
+		created_by: &RecordId,
+	) -> ApiResult<Tournament>;
+
+	/// Apply the given changes; `None` when the tournament doesn't exist
+	async fn update(
+		&self,
+		id: &RecordId,
+		data: UpdateTournamentData,
+	) -> ApiResult<Option<Tournament>>;
+}
+
+/// Storage of tournament participants
+#[async_trait]
+pub trait ParticipantRepository: Send + Sync {
+	async fn find(&self, id: &RecordId) -> ApiResult<Option<Participant>>;
+
+	/// Participants of a tournament in joining order
+	async fn list_by_tournament(&self, tournament: &RecordId) -> ApiResult<Vec<Participant>>;
+
+	async fn find_by_user(
+		&self,
+		tournament: &RecordId,
+		user: &RecordId,
+	) -> ApiResult<Option<Participant>>;
+
+	/// Add a user to a tournament, failing with `Conflict` if already in it
+	async fn add(&self, tournament: &RecordId, user: &RecordId) -> ApiResult<Participant>;
+
+	/// Remove a participant; `false` when it didn't exist
+	async fn remove(&self, id: &RecordId) -> ApiResult<bool>;
+}
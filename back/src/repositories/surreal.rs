@@ -0,0 +1,161 @@
+//! SurrealDB-backed repositories
+
+use async_trait::async_trait;
+use surrealdb::RecordId;
+
+use super::{ParticipantRepository, TournamentRepository};
+use crate::db::Database;
+use crate::entities::{CreateTournamentData, Participant, Tournament, UpdateTournamentData};
+use crate::utils::error::{ApiError, ApiResult};
+
+/// Tournaments stored in the `tournament` table
+#[derive(Clone)]
+pub struct SurrealTournamentRepository {
+	db: Database,
+}
+
+impl SurrealTournamentRepository {
+	pub fn new(db: Database) -> Self {
+		Self { db }
+	}
+}
+
+#[async_trait]
+impl TournamentRepository for SurrealTournamentRepository {
+	async fn find(&self, id: &RecordId) -> ApiResult<Option<Tournament>> {
+		Ok(self.db.select(id).await?)
+	}
+
+	async fn list(&self, published_only: bool) -> ApiResult<Vec<Tournament>> {
+		let mut response = self
+			.db
+			.query(
+				"SELECT * FROM tournament
+				WHERE !$published_only OR published = true
+				ORDER BY created_at DESC",
+			)
+			.bind(("published_only", published_only))
+			.await?;
+		Ok(response.take(0)?)
+	}
+
+	async fn create(
+		&self,
+		data: CreateTournamentData,
+		created_by: &RecordId,
+	) -> ApiResult<Tournament> {
+		let mut response = self
+			.db
+			.query(
+				"CREATE tournament CONTENT {
+					name: $name,
+					description: $description,
+					published: $published,
+					season: $season,
+					created_by: $created_by,
+				}",
+			)
+			.bind(("name", data.name))
+			.bind(("description", data.description))
+			.bind(("published", data.published.unwrap_or(false)))
+			.bind(("season", data.season))
+			.bind(("created_by", created_by.clone()))
+			.await?;
+
+		let tournament: Option<Tournament> = response.take(0)?;
+		tournament.ok_or_else(|| ApiError::internal("Failed to create tournament"))
+	}
+
+	async fn update(
+		&self,
+		id: &RecordId,
+		data: UpdateTournamentData,
+	) -> ApiResult<Option<Tournament>> {
+		let mut response = self
+			.db
+			.query(
+				"UPDATE $id SET
+					name = $name ?? name,
+					description = $description ?? description,
+					published = $published ?? published,
+					season = IF $season_changed THEN $season ELSE season END",
+			)
+			.bind(("id", id.clone()))
+			.bind(("name", data.name))
+			.bind(("description", data.description))
+			.bind(("published", data.published))
+			.bind(("season_changed", data.season.is_some()))
+			.bind(("season", data.season.flatten()))
+			.await?;
+		Ok(response.take(0)?)
+	}
+}
+
+/// Participants stored in the `participant` table
+#[derive(Clone)]
+pub struct SurrealParticipantRepository {
+	db: Database,
+}
+
+impl SurrealParticipantRepository {
+	pub fn new(db: Database) -> Self {
+		Self { db }
+	}
+}
+
+#[async_trait]
+impl ParticipantRepository for SurrealParticipantRepository {
+	async fn find(&self, id: &RecordId) -> ApiResult<Option<Participant>> {
+		Ok(self.db.select(id).await?)
+	}
+
+	async fn list_by_tournament(&self, tournament: &RecordId) -> ApiResult<Vec<Participant>> {
+		let mut response = self
+			.db
+			.query("SELECT * FROM participant WHERE tournament = $tournament ORDER BY joined_at")
+			.bind(("tournament", tournament.clone()))
+			.await?;
+		Ok(response.take(0)?)
+	}
+
+	async fn find_by_user(
+		&self,
+		tournament: &RecordId,
+		user: &RecordId,
+	) -> ApiResult<Option<Participant>> {
+		let mut response = self
+			.db
+			.query(
+				"SELECT * FROM participant
+				WHERE tournament = $tournament AND user_id = $user
+				LIMIT 1",
+			)
+			.bind(("tournament", tournament.clone()))
+			.bind(("user", user.clone()))
+			.await?;
+		Ok(response.take(0)?)
+	}
+
+	async fn add(&self, tournament: &RecordId, user: &RecordId) -> ApiResult<Participant> {
+		if self.find_by_user(tournament, user).await?.is_some() {
+			return Err(ApiError::conflict(
+				"User already takes part in this tournament",
+			));
+		}
+
+		let mut response = self
+			.db
+			.query("CREATE participant CONTENT { tournament: $tournament, user_id: $user }")
+			.bind(("tournament", tournament.clone()))
+			.bind(("user", user.clone()))
+			.await?;
+
+		let participant: Option<Participant> = response.take(0)?;
+		participant.ok_or_else(|| ApiError::internal("Failed to create participant"))
+	}
+
+	async fn remove(&self, id: &RecordId) -> ApiResult<bool> {
+		let removed: Option<Participant> = self.db.delete(id).await?;
+		Ok(removed.is_some())
+	}
+}
@@ -0,0 +1,116 @@
+use crate::AppState;
+use crate::entities::{ApiResponse, ImportSource, TournamentImportReport};
+use crate::middleware::auth::AuthUser;
+use crate::services::{archive, importers, tournaments};
+use crate::utils::error::{ApiError, ApiResult};
+use actix_web::{HttpResponse, get, post, web};
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Deserialize)]
+struct ImportQuery {
+	/// Format of the uploaded document
+	#[serde(default)]
+	source: ImportSource,
+	/// Convert and report without creating anything
+	#[serde(default)]
+	dry_run: bool,
+	/// Import anyway, dropping participants with no matching local user
+	#[serde(default)]
+	skip_unmatched: bool,
+}
+
+#[get("/tournaments/{tournament_id}/export")]
+async fn export(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_organizer(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		&user,
+	)
+	.await?;
+	let archive = archive::export(&state.db, &tournament).await?;
+
+	Ok(
+		HttpResponse::Ok()
+			.insert_header((
+				"Content-Disposition",
+				format!(
+					"attachment; filename=\"tournament-{}.json\"",
+					tournament.id.key()
+				),
+			))
+			.json(archive),
+	)
+}
+
+#[post("/tournaments/import")]
+async fn import(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	query: web::Query<ImportQuery>,
+	body: web::Json<Value>,
+) -> ApiResult<HttpResponse> {
+	let converted = importers::convert(query.source, body.into_inner())
+		.map_err(|e| ApiError::bad_request(&e.to_string()))?;
+
+	let mut report = TournamentImportReport {
+		source: query.source,
+		dry_run: query.dry_run,
+		tournament: None,
+		archive: None,
+		unmapped: converted.unmapped,
+	};
+	if query.dry_run {
+		report.archive = Some(converted.archive);
+		return Ok(HttpResponse::Ok().json(ApiResponse::success(report)));
+	}
+
+	let tournament = archive::import(
+		&state.db,
+		state.tournaments.as_ref(),
+		&converted.archive,
+		&user.id,
+		query.skip_unmatched,
+	)
+	.await?;
+	report.tournament = Some(tournament);
+	Ok(HttpResponse::Created().json(ApiResponse::success(report)))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+	cfg.service(export).service(import);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::AppState;
+	use actix_web::{App, http::StatusCode, test};
+
+	#[actix_web::test]
+	async fn test_archive_routes_require_authentication() {
+		let app = test::init_service(
+			App::new()
+				.app_data(web::Data::new(AppState::new_test()))
+				.configure(config),
+		)
+		.await;
+
+		let req = test::TestRequest::get()
+			.uri("/tournaments/t1/export")
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+		let req = test::TestRequest::post()
+			.uri("/tournaments/import?source=challonge&dry_run=true")
+			.set_payload("{}")
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+	}
+}
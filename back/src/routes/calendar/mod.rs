@@ -0,0 +1,79 @@
+use crate::AppState;
+use crate::middleware::auth::AuthUser;
+use crate::services::calendar::{self, FeedScope};
+use crate::services::{participants, tournaments, users};
+use crate::utils::constants;
+use crate::utils::error::ApiResult;
+use actix_web::{HttpResponse, get, web};
+
+/// Serve an `.ics` document
+fn ics(body: String, filename: &str) -> HttpResponse {
+	HttpResponse::Ok()
+		.content_type("text/calendar; charset=utf-8")
+		.insert_header((
+			"Content-Disposition",
+			format!("inline; filename=\"{filename}.ics\""),
+		))
+		.body(body)
+}
+
+#[get("/tournaments/{tournament_id}/calendar.ics")]
+async fn tournament_feed(
+	state: web::Data<AppState>,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_visible(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		user.as_ref(),
+	)
+	.await?;
+
+	let body = calendar::feed(
+		&state.db,
+		&tournament.name,
+		&FeedScope::Tournament(tournament.id),
+	)
+	.await?;
+	Ok(ics(body, &path))
+}
+
+#[get("/participants/{participant_id}/calendar.ics")]
+async fn participant_feed(
+	state: web::Data<AppState>,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let participant =
+		participants::find(state.participants.as_ref(), &participants::record_id(&path)).await?;
+	let tournament = tournaments::ensure_visible(
+		state.tournaments.as_ref(),
+		&participant.tournament,
+		user.as_ref(),
+	)
+	.await?;
+	let name = match users::find(&state.db, &participant.user_id).await {
+		Ok(player) => format!("{} · {}", player.username, tournament.name),
+		Err(_) => tournament.name,
+	};
+
+	let body = calendar::feed(&state.db, &name, &FeedScope::Participant(participant.id)).await?;
+	Ok(ics(body, &path))
+}
+
+#[get("/users/{user_id}/calendar.ics")]
+async fn user_feed(state: web::Data<AppState>, path: web::Path<String>) -> ApiResult<HttpResponse> {
+	let player = users::find(&state.db, &users::record_id(&path)).await?;
+	let name = format!("{} · {}", player.username, constants::APP_NAME);
+
+	let body = calendar::feed(&state.db, &name, &FeedScope::User(player.id)).await?;
+	Ok(ics(body, &path))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+	cfg
+		.service(tournament_feed)
+		.service(participant_feed)
+		.service(user_feed);
+}
@@ -1,4 +1,18 @@
-use crate::DB;
+//! Health probes
+//!
+//! `/health/live` only says the process is up and serving requests, so an
+//! orchestrator restarts it when it stops answering. `/health/ready` checks
+//! the dependencies a request needs and answers 503 until they are all
+//! available, so traffic is held back (not the process restarted) while the
+//! database reconnects or migrations are outstanding.
+
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use crate::AppState;
+use crate::migrations;
+use crate::supervisor::{ConnectionState, ConnectionStatus};
+use crate::utils::constants;
 use crate::utils::error::ApiResult;
 use actix_web::{HttpResponse, get, web};
 use serde::{Deserialize, Serialize};
@@ -9,32 +23,192 @@ pub struct HealthStatus {
 	pub status: String,
 	pub version: String,
 	pub database: Option<String>,
+	/// Details from the connection supervisor
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub connection: Option<ConnectionStatus>,
 }
 
-#[get("")]
-async fn status() -> ApiResult<HttpResponse> {
-	// Check database connection by trying a simple query
-	let db_status = match DB.query("RETURN 'connected'").await {
-		Ok(_) => Some("Connected".to_string()),
-		Err(e) => {
-			// Log database connection issue but don't fail health check
-			log::warn!("Health check database query failed: {e}");
-			Some("Disconnected".to_string())
+/// Outcome of a single dependency check
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+	Up,
+	Down,
+	/// Optional integration that isn't configured
+	Disabled,
+}
+
+/// Status of one dependency in the readiness report
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DependencyCheck {
+	pub status: CheckStatus,
+	/// Whether the service is not ready while this check is down
+	pub required: bool,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub latency_ms: Option<u64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub message: Option<String>,
+}
+
+impl DependencyCheck {
+	fn up(required: bool, latency_ms: Option<u64>) -> Self {
+		Self {
+			status: CheckStatus::Up,
+			required,
+			latency_ms,
+			message: None,
 		}
+	}
+
+	fn down(required: bool, message: impl Into<String>) -> Self {
+		Self {
+			status: CheckStatus::Down,
+			required,
+			latency_ms: None,
+			message: Some(message.into()),
+		}
+	}
+
+	fn disabled(message: impl Into<String>) -> Self {
+		Self {
+			status: CheckStatus::Disabled,
+			required: false,
+			latency_ms: None,
+			message: Some(message.into()),
+		}
+	}
+
+	/// Whether this check keeps the service from being ready
+	pub fn is_blocking(&self) -> bool {
+		self.required && self.status == CheckStatus::Down
+	}
+}
+
+/// Body of `/health/live`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Liveness {
+	pub status: String,
+	pub version: String,
+}
+
+/// Body of `/health/ready`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Readiness {
+	/// `ready` or `not_ready`
+	pub status: String,
+	pub version: String,
+	pub checks: BTreeMap<String, DependencyCheck>,
+}
+
+impl Readiness {
+	pub fn new(checks: BTreeMap<String, DependencyCheck>) -> Self {
+		let blocked = checks.values().any(DependencyCheck::is_blocking);
+		Self {
+			status: if blocked { "not_ready" } else { "ready" }.to_string(),
+			version: constants::APP_VERSION.to_string(),
+			checks,
+		}
+	}
+
+	pub fn is_ready(&self) -> bool {
+		self.status == "ready"
+	}
+}
+
+fn elapsed_ms(started: Instant) -> Option<u64> {
+	Some(started.elapsed().as_millis() as u64)
+}
+
+#[get("")]
+async fn status(state: web::Data<AppState>) -> ApiResult<HttpResponse> {
+	// The supervisor pings the database in the background; report what it saw
+	let connection = state.connection.status();
+	let db_status = match connection.state {
+		ConnectionState::Connected => "Connected",
+		ConnectionState::Connecting => "Connecting",
+		ConnectionState::Reconnecting => "Disconnected",
 	};
 
 	let health = HealthStatus {
-		name: "La Liga de los Muertos".to_string(),
+		name: constants::APP_NAME.to_string(),
 		status: "OK".to_string(),
-		version: "v0.1.0".to_string(),
-		database: db_status,
+		version: constants::APP_VERSION.to_string(),
+		database: Some(db_status.to_string()),
+		connection: Some(connection),
 	};
 
 	Ok(HttpResponse::Ok().json(health))
 }
 
+/// Liveness probe: answers as long as the server can handle requests
+#[get("/live")]
+async fn live() -> ApiResult<HttpResponse> {
+	Ok(HttpResponse::Ok().json(Liveness {
+		status: "alive".to_string(),
+		version: constants::APP_VERSION.to_string(),
+	}))
+}
+
+/// Readiness probe: 503 while a required dependency is unavailable
+#[get("/ready")]
+async fn ready(state: web::Data<AppState>) -> ApiResult<HttpResponse> {
+	let mut checks = BTreeMap::new();
+
+	let database = match state.connection.ping().await {
+		Ok(latency) => DependencyCheck::up(true, Some(latency.as_millis() as u64)),
+		Err(e) => DependencyCheck::down(true, e.to_string()),
+	};
+
+	// Without a database there is nothing to compare the migrations with
+	let migrations = if database.status == CheckStatus::Up {
+		let started = Instant::now();
+		match migrations::outstanding().await {
+			Ok(outstanding) if outstanding.is_empty() => DependencyCheck::up(true, elapsed_ms(started)),
+			Ok(outstanding) => DependencyCheck {
+				latency_ms: elapsed_ms(started),
+				..DependencyCheck::down(
+					true,
+					format!("{} migration(s) not applied yet", outstanding.len()),
+				)
+			},
+			Err(e) => DependencyCheck::down(true, e.to_string()),
+		}
+	} else {
+		DependencyCheck::down(true, "Database unavailable")
+	};
+	checks.insert("database".to_string(), database);
+	checks.insert("migrations".to_string(), migrations);
+
+	// Without Clerk every authenticated endpoint rejects its requests
+	let auth = if state.config.auth.clerk_secret_key.is_some() {
+		DependencyCheck::up(true, None)
+	} else {
+		DependencyCheck::down(true, "CLERK_SECRET_KEY is not set")
+	};
+	checks.insert("auth".to_string(), auth);
+
+	let twitch = if state.twitch.is_some() {
+		DependencyCheck::up(false, None)
+	} else {
+		DependencyCheck::disabled("Twitch credentials are not set")
+	};
+	checks.insert("twitch".to_string(), twitch);
+
+	let readiness = Readiness::new(checks);
+	if readiness.is_ready() {
+		Ok(HttpResponse::Ok().json(readiness))
+	} else {
+		Ok(HttpResponse::ServiceUnavailable().json(readiness))
+	}
+}
+
 pub fn config(cfg: &mut web::ServiceConfig) {
-	cfg.service(web::scope("/health").service(status));
+	cfg.service(
+		web::scope("/health")
+			.service(status)
+			.service(live)
+			.service(ready),
+	);
 }
 
 #[cfg(test)]
@@ -61,6 +235,9 @@ mod tests {
 		let body: HealthStatus = test::read_body_json(resp).await;
 		assert_eq!(body.name, "La Liga de los Muertos");
 		assert_eq!(body.status, "OK");
+		// The test state's supervisor never connects
+		assert_eq!(body.database.as_deref(), Some("Connecting"));
+		assert_eq!(body.connection.unwrap().state, ConnectionState::Connecting);
 	}
 
 	// Use actix_web test macro for consistency
@@ -71,6 +248,7 @@ mod tests {
 			status: "OK".to_string(),
 			version: "v1.0.0".to_string(),
 			database: Some("Connected".to_string()),
+			connection: None,
 		};
 
 		assert_eq!(health.name, "Test");
@@ -78,4 +256,49 @@ mod tests {
 		assert_eq!(health.version, "v1.0.0");
 		assert_eq!(health.database, Some("Connected".to_string()));
 	}
+
+	#[actix_web::test]
+	async fn test_liveness_and_readiness_probes() {
+		let app = test::init_service(
+			App::new()
+				.app_data(web::Data::new(AppState::new_test()))
+				.configure(config),
+		)
+		.await;
+
+		let req = test::TestRequest::get().uri("/health/live").to_request();
+		let body: Liveness = test::call_and_read_body_json(&app, req).await;
+		assert_eq!(body.version, constants::APP_VERSION);
+
+		// The test state has no Clerk key, so it's never ready
+		let req = test::TestRequest::get().uri("/health/ready").to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(
+			resp.status(),
+			actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+		);
+		let body: Readiness = test::read_body_json(resp).await;
+		assert_eq!(body.status, "not_ready");
+		assert!(body.checks["auth"].is_blocking());
+		assert_eq!(body.checks["twitch"].status, CheckStatus::Disabled);
+		assert!(body.checks.contains_key("database"));
+		assert!(body.checks.contains_key("migrations"));
+	}
+
+	#[actix_web::test]
+	async fn test_optional_checks_dont_block_readiness() {
+		let mut checks = BTreeMap::new();
+		checks.insert("database".to_string(), DependencyCheck::up(true, Some(3)));
+		checks.insert(
+			"twitch".to_string(),
+			DependencyCheck::down(false, "Helix unreachable"),
+		);
+		assert!(Readiness::new(checks.clone()).is_ready());
+
+		checks.insert(
+			"auth".to_string(),
+			DependencyCheck::down(true, "missing key"),
+		);
+		assert!(!Readiness::new(checks).is_ready());
+	}
 }
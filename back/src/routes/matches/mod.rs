@@ -0,0 +1,145 @@
+use crate::AppState;
+use crate::entities::{ApiResponse, CreateMatchData, ReportMatchData};
+use crate::middleware::auth::AuthUser;
+use crate::services::{matches, tournaments};
+use crate::utils::error::ApiResult;
+use crate::utils::error::validation::ValidatedJson;
+use actix_web::{HttpResponse, get, post, web};
+
+#[get("/tournaments/{tournament_id}/matches")]
+async fn list(
+	state: web::Data<AppState>,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_visible(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		user.as_ref(),
+	)
+	.await?;
+
+	let games = matches::list(&state.db, &tournament.id).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(games)))
+}
+
+#[post("/tournaments/{tournament_id}/matches")]
+async fn create(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+	body: ValidatedJson<CreateMatchData>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_organizer(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		&user,
+	)
+	.await?;
+
+	let game = matches::create(&state, &tournament, body.into_inner()).await?;
+	Ok(HttpResponse::Created().json(ApiResponse::success(game)))
+}
+
+#[get("/matches/{match_id}")]
+async fn show(
+	state: web::Data<AppState>,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let game = matches::find(&state.db, &matches::record_id(&path)).await?;
+	tournaments::ensure_visible(state.tournaments.as_ref(), &game.tournament, user.as_ref()).await?;
+
+	Ok(HttpResponse::Ok().json(ApiResponse::success(game)))
+}
+
+#[post("/matches/{match_id}/start")]
+async fn start(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let game = matches::find(&state.db, &matches::record_id(&path)).await?;
+	tournaments::ensure_organizer(state.tournaments.as_ref(), &game.tournament, &user).await?;
+
+	let game = matches::start(&state, &game).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(game)))
+}
+
+#[post("/matches/{match_id}/report")]
+async fn report(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+	body: ValidatedJson<ReportMatchData>,
+) -> ApiResult<HttpResponse> {
+	let game = matches::find(&state.db, &matches::record_id(&path)).await?;
+	matches::ensure_involved(&state, &game, &user).await?;
+
+	let game = matches::report(&state, &game, body.into_inner(), &user.id).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(game)))
+}
+
+#[post("/matches/{match_id}/confirm")]
+async fn confirm(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let game = matches::find(&state.db, &matches::record_id(&path)).await?;
+	let tournament = matches::ensure_involved(&state, &game, &user).await?;
+
+	let game = matches::confirm(&state, &game, &user, &tournament).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(game)))
+}
+
+#[post("/matches/{match_id}/cancel")]
+async fn cancel(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let game = matches::find(&state.db, &matches::record_id(&path)).await?;
+	tournaments::ensure_organizer(state.tournaments.as_ref(), &game.tournament, &user).await?;
+
+	let game = matches::cancel(&state, &game).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(game)))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+	cfg
+		.service(list)
+		.service(create)
+		.service(show)
+		.service(start)
+		.service(report)
+		.service(confirm)
+		.service(cancel);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::AppState;
+	use actix_web::{App, http::StatusCode, test};
+
+	#[actix_web::test]
+	async fn test_match_actions_require_authentication() {
+		let app = test::init_service(
+			App::new()
+				.app_data(web::Data::new(AppState::new_test()))
+				.configure(config),
+		)
+		.await;
+
+		for uri in [
+			"/matches/m1/start",
+			"/matches/m1/confirm",
+			"/matches/m1/cancel",
+		] {
+			let req = test::TestRequest::post().uri(uri).to_request();
+			let resp = test::call_service(&app, req).await;
+			assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+		}
+	}
+}
@@ -0,0 +1,70 @@
+//! Prometheus scrape endpoint
+//!
+//! Served at `/metrics`, outside the versioned API, where scrapers look for
+//! it by default.
+
+use crate::AppState;
+use crate::supervisor::ConnectionState;
+use crate::utils::error::ApiResult;
+use crate::utils::metrics;
+use actix_web::{HttpResponse, get, web};
+
+#[get("/metrics")]
+async fn scrape(state: web::Data<AppState>) -> ApiResult<HttpResponse> {
+	// Gauges of in-memory state are sampled on scrape rather than tracked
+	let collectors = metrics::global();
+	collectors
+		.live_subscribers
+		.set(state.live.total_subscribers() as i64);
+	collectors.live_topics.set(state.live.topic_count() as i64);
+
+	let connection = state.connection.status();
+	for candidate in ConnectionState::ALL {
+		collectors
+			.db_connection_state
+			.with_label_values(&[candidate.as_str()])
+			.set((candidate == connection.state) as i64);
+	}
+	collectors.db_reconnects.set(connection.reconnects as i64);
+
+	Ok(
+		HttpResponse::Ok()
+			.content_type(metrics::CONTENT_TYPE)
+			.body(collectors.render()),
+	)
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+	cfg.service(scrape);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use actix_web::{App, test};
+
+	#[actix_web::test]
+	async fn test_metrics_are_exposed_as_prometheus_text() {
+		let state = AppState::new_test();
+		let _subscriber = state.live.subscribe("poll:metrics");
+		let app =
+			test::init_service(App::new().app_data(web::Data::new(state)).configure(config)).await;
+
+		let resp =
+			test::call_service(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+		assert!(resp.status().is_success());
+		assert_eq!(
+			resp.headers().get("content-type").unwrap(),
+			metrics::CONTENT_TYPE
+		);
+
+		let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+		assert!(body.contains("# TYPE matches_reported_total counter"));
+		assert!(body.contains("live_subscribers 1"));
+		assert!(body.contains("live_topics 1"));
+		// The test state never connects
+		assert!(body.contains(r#"db_connection_state{state="connecting"} 1"#));
+		assert!(body.contains(r#"db_connection_state{state="connected"} 0"#));
+		assert!(body.contains("db_reconnects 0"));
+	}
+}
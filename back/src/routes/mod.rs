@@ -1,7 +1,36 @@
 use actix_web::web;
 
+use crate::utils::error::extractors;
+
+pub mod archive;
+pub mod calendar;
 pub mod health;
+pub mod matches;
+pub mod metrics;
+pub mod participants;
+pub mod polls;
+pub mod predictions;
+pub mod schedule;
+pub mod tournaments;
+pub mod twitch;
+pub mod webhooks;
 
 pub fn entry(cfg: &mut web::ServiceConfig) {
-	cfg.service(web::scope("/v1").configure(health::config));
+	cfg
+		.configure(extractors::config)
+		.configure(metrics::config)
+		.service(
+			web::scope("/v1")
+				.configure(health::config)
+				.configure(archive::config)
+				.configure(calendar::config)
+				.configure(matches::config)
+				.configure(participants::config)
+				.configure(polls::config)
+				.configure(predictions::config)
+				.configure(schedule::config)
+				.configure(tournaments::config)
+				.configure(twitch::config)
+				.configure(webhooks::config),
+		);
 }
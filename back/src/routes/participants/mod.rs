@@ -0,0 +1,141 @@
+use crate::AppState;
+use crate::entities::ApiResponse;
+use crate::middleware::auth::AuthUser;
+use crate::services::participants::{self, ImportFormat};
+use crate::services::tournaments;
+use crate::utils::error::{ApiError, ApiResult, extractors::Body};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, delete, get, post, web};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct ImportQuery {
+	/// Validate and report without creating anything
+	#[serde(default)]
+	dry_run: bool,
+}
+
+#[get("/tournaments/{tournament_id}/participants")]
+async fn list(
+	state: web::Data<AppState>,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_visible(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		user.as_ref(),
+	)
+	.await?;
+
+	let listed = participants::list(state.participants.as_ref(), &tournament.id).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(listed)))
+}
+
+#[post("/tournaments/{tournament_id}/participants")]
+async fn join(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_visible(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		Some(&user),
+	)
+	.await?;
+
+	let participant = participants::join(&state, &tournament, &user.id).await?;
+	Ok(HttpResponse::Created().json(ApiResponse::success(participant)))
+}
+
+#[delete("/tournaments/{tournament_id}/participants/me")]
+async fn leave(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_visible(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		Some(&user),
+	)
+	.await?;
+
+	participants::leave(&state, &tournament, &user.id).await?;
+	Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/tournaments/{tournament_id}/participants/import")]
+async fn import(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	req: HttpRequest,
+	path: web::Path<String>,
+	query: web::Query<ImportQuery>,
+	body: Body,
+) -> ApiResult<HttpResponse> {
+	let format = ImportFormat::from_content_type(req.content_type()).ok_or_else(|| {
+		ApiError::UnsupportedMediaType {
+			message: "Send participants as text/csv or application/json".to_string(),
+		}
+	})?;
+	let tournament = tournaments::ensure_organizer(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		&user,
+	)
+	.await?;
+
+	let rows = participants::parse_rows(format, &body)?;
+	let report = participants::import(&state, &tournament, &rows, query.dry_run).await?;
+
+	if report.has_errors() {
+		return Ok(HttpResponse::UnprocessableEntity().json(ApiResponse {
+			success: false,
+			data: Some(report),
+			message: Some("Some rows are invalid; nothing was imported".to_string()),
+			errors: None,
+		}));
+	}
+
+	let response = if report.committed {
+		HttpResponse::Created()
+	} else {
+		HttpResponse::Ok()
+	}
+	.json(ApiResponse::success(report));
+	Ok(response)
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+	cfg
+		.service(list)
+		.service(join)
+		.service(leave)
+		.service(import);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::AppState;
+	use actix_web::{App, http::StatusCode, test};
+
+	#[actix_web::test]
+	async fn test_import_requires_authentication() {
+		let app = test::init_service(
+			App::new()
+				.app_data(web::Data::new(AppState::new_test()))
+				.configure(config),
+		)
+		.await;
+
+		let req = test::TestRequest::post()
+			.uri("/tournaments/t1/participants/import?dry_run=true")
+			.insert_header(("Content-Type", "text/csv"))
+			.set_payload("username\nana\n")
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+	}
+}
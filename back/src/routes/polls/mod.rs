@@ -0,0 +1,148 @@
+use crate::AppState;
+use crate::entities::{ApiResponse, CreatePollData, VoteData};
+use crate::middleware::auth::AuthUser;
+use crate::services::{live, polls, tournaments};
+use crate::utils::error::ApiResult;
+use crate::utils::error::validation::ValidatedJson;
+use actix_web::{HttpRequest, HttpResponse, get, post, web};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct ListPollsQuery {
+	/// Only return closed polls with their archived results
+	#[serde(default)]
+	archived: bool,
+}
+
+#[get("/tournaments/{tournament_id}/polls")]
+async fn list(
+	state: web::Data<AppState>,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+	query: web::Query<ListPollsQuery>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_visible(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		user.as_ref(),
+	)
+	.await?;
+
+	let polls = polls::list(&state.db, &tournament.id, query.archived).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(polls)))
+}
+
+#[post("/tournaments/{tournament_id}/polls")]
+async fn create(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+	body: ValidatedJson<CreatePollData>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_organizer(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		&user,
+	)
+	.await?;
+
+	let poll = polls::create(&state.db, &tournament, &user.id, body.into_inner()).await?;
+	Ok(HttpResponse::Created().json(ApiResponse::success(poll)))
+}
+
+#[get("/polls/{poll_id}")]
+async fn show(
+	state: web::Data<AppState>,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let poll = polls::find(&state.db, &polls::record_id(&path)).await?;
+	tournaments::ensure_visible(state.tournaments.as_ref(), &poll.tournament, user.as_ref()).await?;
+
+	let view = polls::view(poll);
+	Ok(HttpResponse::Ok().json(ApiResponse::success(view)))
+}
+
+#[post("/polls/{poll_id}/votes")]
+async fn vote(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+	body: ValidatedJson<VoteData>,
+) -> ApiResult<HttpResponse> {
+	let poll = polls::find(&state.db, &polls::record_id(&path)).await?;
+	tournaments::ensure_visible(state.tournaments.as_ref(), &poll.tournament, Some(&user)).await?;
+
+	let view = polls::vote(&state.db, &poll, &user.id, body.into_inner()).await?;
+	Ok(HttpResponse::Created().json(ApiResponse::success(view)))
+}
+
+#[post("/polls/{poll_id}/close")]
+async fn close(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let poll = polls::find(&state.db, &polls::record_id(&path)).await?;
+	tournaments::ensure_organizer(state.tournaments.as_ref(), &poll.tournament, &user).await?;
+
+	let view = polls::close(&state.db, &poll).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(view)))
+}
+
+/// WebSocket streaming the live tally, meant for stream overlays
+#[get("/polls/{poll_id}/live")]
+async fn live_tally(
+	state: web::Data<AppState>,
+	req: HttpRequest,
+	body: web::Payload,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let poll = polls::find(&state.db, &polls::record_id(&path)).await?;
+	tournaments::ensure_visible(state.tournaments.as_ref(), &poll.tournament, user.as_ref()).await?;
+
+	let topic = live::poll_topic(&poll.id);
+	let view = polls::view(poll);
+	live::serve(
+		&state.live,
+		&req,
+		body,
+		&topic,
+		live::LiveEvent::new("poll.tally", view)?,
+	)
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+	cfg
+		.service(list)
+		.service(create)
+		.service(show)
+		.service(vote)
+		.service(close)
+		.service(live_tally);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::AppState;
+	use actix_web::{App, http::StatusCode, test};
+
+	#[actix_web::test]
+	async fn test_voting_requires_authentication() {
+		let app = test::init_service(
+			App::new()
+				.app_data(web::Data::new(AppState::new_test()))
+				.configure(config),
+		)
+		.await;
+
+		let req = test::TestRequest::post()
+			.uri("/polls/p1/votes")
+			.set_json(serde_json::json!({ "choices": [0] }))
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+	}
+}
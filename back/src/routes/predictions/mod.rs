@@ -0,0 +1,110 @@
+use crate::AppState;
+use crate::entities::{ApiResponse, PredictMatchData};
+use crate::middleware::auth::AuthUser;
+use crate::services::predictions::{self, LeaderboardScope};
+use crate::services::{matches, tournaments};
+use crate::utils::error::validation::ValidatedJson;
+use crate::utils::error::{ApiError, ApiResult};
+use actix_web::{HttpResponse, get, put, web};
+
+#[put("/matches/{match_id}/predictions/me")]
+async fn predict(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+	body: ValidatedJson<PredictMatchData>,
+) -> ApiResult<HttpResponse> {
+	let game = matches::find(&state.db, &matches::record_id(&path)).await?;
+	tournaments::ensure_visible(state.tournaments.as_ref(), &game.tournament, Some(&user)).await?;
+
+	let prediction = predictions::predict(&state.db, &game, &user.id, body.into_inner()).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(prediction)))
+}
+
+#[get("/matches/{match_id}/predictions/me")]
+async fn mine(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let game = matches::record_id(&path);
+	let prediction = predictions::find_for_user(&state.db, &game, &user.id)
+		.await?
+		.ok_or_else(|| ApiError::not_found("prediction", &path))?;
+
+	Ok(HttpResponse::Ok().json(ApiResponse::success(prediction)))
+}
+
+#[get("/matches/{match_id}/predictions")]
+async fn distribution(
+	state: web::Data<AppState>,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let game = matches::find(&state.db, &matches::record_id(&path)).await?;
+	tournaments::ensure_visible(state.tournaments.as_ref(), &game.tournament, user.as_ref()).await?;
+
+	let split = predictions::distribution(&state.db, &game.id).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(split)))
+}
+
+#[get("/tournaments/{tournament_id}/predictions/leaderboard")]
+async fn tournament_leaderboard(
+	state: web::Data<AppState>,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_visible(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		user.as_ref(),
+	)
+	.await?;
+
+	let standings =
+		predictions::leaderboard(&state.db, LeaderboardScope::Tournament(tournament.id)).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(standings)))
+}
+
+#[get("/seasons/{season}/predictions/leaderboard")]
+async fn season_leaderboard(
+	state: web::Data<AppState>,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let standings =
+		predictions::leaderboard(&state.db, LeaderboardScope::Season(path.into_inner())).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(standings)))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+	cfg
+		.service(predict)
+		.service(mine)
+		.service(distribution)
+		.service(tournament_leaderboard)
+		.service(season_leaderboard);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::AppState;
+	use actix_web::{App, http::StatusCode, test};
+
+	#[actix_web::test]
+	async fn test_predicting_requires_authentication() {
+		let app = test::init_service(
+			App::new()
+				.app_data(web::Data::new(AppState::new_test()))
+				.configure(config),
+		)
+		.await;
+
+		let req = test::TestRequest::put()
+			.uri("/matches/m1/predictions/me")
+			.set_json(serde_json::json!({ "outcome": "home" }))
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+	}
+}
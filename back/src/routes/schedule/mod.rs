@@ -0,0 +1,236 @@
+use crate::AppState;
+use crate::entities::{
+	ApiResponse, AutoScheduleData, CreateAvailabilityData, ProposeRescheduleData, ScheduleMatchData,
+	UpdateTimezoneData,
+};
+use crate::middleware::auth::AuthUser;
+use crate::services::{matches, schedule, tournaments, users};
+use crate::utils::error::ApiResult;
+use crate::utils::error::validation::ValidatedJson;
+use actix_web::{HttpResponse, delete, get, post, put, web};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct TimezoneQuery {
+	/// IANA timezone to render times in (defaults to the user's, then UTC)
+	tz: Option<String>,
+}
+
+#[get("/tournaments/{tournament_id}/availability")]
+async fn list_windows(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_organizer(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		&user,
+	)
+	.await?;
+
+	let windows = schedule::list_windows(&state.db, &tournament.id).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(windows)))
+}
+
+#[post("/tournaments/{tournament_id}/availability")]
+async fn add_window(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+	body: ValidatedJson<CreateAvailabilityData>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_organizer(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		&user,
+	)
+	.await?;
+
+	let window = schedule::add_window(&state.db, &tournament, body.into_inner()).await?;
+	Ok(HttpResponse::Created().json(ApiResponse::success(window)))
+}
+
+#[delete("/tournaments/{tournament_id}/availability/{window_id}")]
+async fn remove_window(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<(String, String)>,
+) -> ApiResult<HttpResponse> {
+	let (tournament_id, window_id) = path.into_inner();
+	let tournament = tournaments::ensure_organizer(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&tournament_id),
+		&user,
+	)
+	.await?;
+
+	schedule::remove_window(
+		&state.db,
+		&tournament.id,
+		&schedule::window_record_id(&window_id),
+	)
+	.await?;
+	Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/tournaments/{tournament_id}/schedule")]
+async fn timetable(
+	state: web::Data<AppState>,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+	query: web::Query<TimezoneQuery>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_visible(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		user.as_ref(),
+	)
+	.await?;
+	let timezone = users::display_timezone(
+		&state.db,
+		query.tz.as_deref(),
+		user.as_ref().map(|user| &user.id),
+	)
+	.await?;
+
+	let timetable = schedule::timetable(&state.db, &tournament.id, timezone).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(timetable)))
+}
+
+#[post("/tournaments/{tournament_id}/schedule")]
+async fn auto_schedule(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+	body: ValidatedJson<AutoScheduleData>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_organizer(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		&user,
+	)
+	.await?;
+
+	let plan = schedule::auto_schedule(&state, &tournament, body.into_inner()).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(plan)))
+}
+
+#[put("/matches/{match_id}/schedule")]
+async fn schedule_match(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+	body: ValidatedJson<ScheduleMatchData>,
+) -> ApiResult<HttpResponse> {
+	let game = matches::find(&state.db, &matches::record_id(&path)).await?;
+	tournaments::ensure_organizer(state.tournaments.as_ref(), &game.tournament, &user).await?;
+
+	let game = schedule::schedule_match(&state, &game, body.into_inner()).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(game)))
+}
+
+#[get("/matches/{match_id}/reschedules")]
+async fn list_proposals(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let game = matches::find(&state.db, &matches::record_id(&path)).await?;
+	matches::ensure_involved(&state, &game, &user).await?;
+
+	let proposals = schedule::list_proposals(&state.db, &game.id).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(proposals)))
+}
+
+#[post("/matches/{match_id}/reschedules")]
+async fn propose(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+	body: ValidatedJson<ProposeRescheduleData>,
+) -> ApiResult<HttpResponse> {
+	let game = matches::find(&state.db, &matches::record_id(&path)).await?;
+
+	let proposal = schedule::propose(&state.db, &game, &user.id, body.into_inner()).await?;
+	Ok(HttpResponse::Created().json(ApiResponse::success(proposal)))
+}
+
+#[post("/reschedules/{proposal_id}/accept")]
+async fn accept(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let proposal = schedule::find_proposal(&state.db, &schedule::proposal_record_id(&path)).await?;
+
+	let proposal = schedule::respond(&state, &proposal, &user.id, true).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(proposal)))
+}
+
+#[post("/reschedules/{proposal_id}/decline")]
+async fn decline(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let proposal = schedule::find_proposal(&state.db, &schedule::proposal_record_id(&path)).await?;
+
+	let proposal = schedule::respond(&state, &proposal, &user.id, false).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(proposal)))
+}
+
+#[put("/users/me/timezone")]
+async fn set_timezone(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	body: ValidatedJson<UpdateTimezoneData>,
+) -> ApiResult<HttpResponse> {
+	let updated = users::set_timezone(&state.db, &user.id, &body.timezone).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(updated)))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+	cfg
+		.service(list_windows)
+		.service(add_window)
+		.service(remove_window)
+		.service(timetable)
+		.service(auto_schedule)
+		.service(schedule_match)
+		.service(list_proposals)
+		.service(propose)
+		.service(accept)
+		.service(decline)
+		.service(set_timezone);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::AppState;
+	use actix_web::{App, http::StatusCode, test};
+
+	#[actix_web::test]
+	async fn test_scheduling_requires_authentication() {
+		let app = test::init_service(
+			App::new()
+				.app_data(web::Data::new(AppState::new_test()))
+				.configure(config),
+		)
+		.await;
+
+		let req = test::TestRequest::post()
+			.uri("/reschedules/r1/accept")
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+		let req = test::TestRequest::put()
+			.uri("/users/me/timezone")
+			.set_json(serde_json::json!({ "timezone": "America/Mexico_City" }))
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+	}
+}
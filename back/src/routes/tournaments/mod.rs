@@ -0,0 +1,122 @@
+use crate::AppState;
+use crate::entities::{ApiResponse, CreateTournamentData, UpdateTournamentData};
+use crate::middleware::auth::AuthUser;
+use crate::services::tournaments;
+use crate::utils::error::ApiResult;
+use crate::utils::error::validation::ValidatedJson;
+use actix_web::{HttpResponse, get, patch, post, web};
+
+#[get("/tournaments")]
+async fn list(state: web::Data<AppState>, user: Option<AuthUser>) -> ApiResult<HttpResponse> {
+	let listed = tournaments::list(state.tournaments.as_ref(), user.as_ref()).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(listed)))
+}
+
+#[post("/tournaments")]
+async fn create(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	body: ValidatedJson<CreateTournamentData>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::create(&state, body.into_inner(), &user).await?;
+	Ok(HttpResponse::Created().json(ApiResponse::success(tournament)))
+}
+
+#[get("/tournaments/{tournament_id}")]
+async fn show(
+	state: web::Data<AppState>,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_visible(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		user.as_ref(),
+	)
+	.await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(tournament)))
+}
+
+#[patch("/tournaments/{tournament_id}")]
+async fn update(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+	body: ValidatedJson<UpdateTournamentData>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_organizer(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		&user,
+	)
+	.await?;
+
+	let tournament = tournaments::update(&state, &tournament, body.into_inner()).await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(tournament)))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+	cfg
+		.service(list)
+		.service(create)
+		.service(show)
+		.service(update);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::AppState;
+	use crate::test_utils::{self, TEST_USER_HEADER};
+	use actix_web::middleware::from_fn;
+	use actix_web::{App, http::StatusCode, test};
+
+	#[actix_web::test]
+	async fn test_organizing_requires_authentication() {
+		let app = test::init_service(
+			App::new()
+				.app_data(web::Data::new(AppState::new_test()))
+				.configure(config),
+		)
+		.await;
+
+		let req = test::TestRequest::post()
+			.uri("/tournaments")
+			.set_json(serde_json::json!({ "name": "Copa Catrina", "description": "" }))
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+	}
+
+	#[actix_web::test]
+	async fn test_drafts_are_only_shown_to_their_organizer() {
+		let state = AppState::new_test();
+		let organizer = AuthUser::new("catrina");
+		let data = CreateTournamentData {
+			name: "Copa Catrina".to_string(),
+			description: String::new(),
+			published: None,
+			season: None,
+		};
+		let draft = state.tournaments.create(data, &organizer.id).await.unwrap();
+		let app = test::init_service(
+			App::new()
+				.wrap(from_fn(test_utils::fake_auth))
+				.app_data(web::Data::new(state))
+				.configure(config),
+		)
+		.await;
+		let uri = format!("/tournaments/{}", draft.id.key());
+
+		let req = test::TestRequest::get().uri(&uri).to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+		let req = test::TestRequest::get()
+			.uri(&uri)
+			.insert_header((TEST_USER_HEADER, "catrina"))
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.status(), StatusCode::OK);
+	}
+}
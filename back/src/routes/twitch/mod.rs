@@ -0,0 +1,90 @@
+use crate::AppState;
+use crate::entities::{ApiResponse, LinkTwitchAccountData};
+use crate::middleware::auth::AuthUser;
+use crate::services::{tournaments, twitch};
+use crate::utils::error::validation::ValidatedJson;
+use crate::utils::error::{ApiError, ApiResult};
+use actix_web::{HttpResponse, delete, get, post, web};
+
+#[post("/link")]
+async fn link(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	body: ValidatedJson<LinkTwitchAccountData>,
+) -> ApiResult<HttpResponse> {
+	let account = twitch::link(
+		&state.db,
+		twitch::client(state.twitch.as_deref())?,
+		&user.id,
+		body.access_token.trim(),
+	)
+	.await?;
+	Ok(HttpResponse::Created().json(ApiResponse::success(account)))
+}
+
+#[delete("/link")]
+async fn unlink(state: web::Data<AppState>, user: AuthUser) -> ApiResult<HttpResponse> {
+	twitch::unlink(&state.db, &user.id).await?;
+	Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/me")]
+async fn me(state: web::Data<AppState>, user: AuthUser) -> ApiResult<HttpResponse> {
+	let account = twitch::find_by_user(&state.db, &user.id)
+		.await?
+		.ok_or_else(|| ApiError::not_found("twitch_account", &user.id.key().to_string()))?;
+
+	Ok(HttpResponse::Ok().json(ApiResponse::success(account)))
+}
+
+#[post("/me/refresh")]
+async fn refresh(state: web::Data<AppState>, user: AuthUser) -> ApiResult<HttpResponse> {
+	let account = twitch::find_by_user(&state.db, &user.id)
+		.await?
+		.ok_or_else(|| ApiError::not_found("twitch_account", &user.id.key().to_string()))?;
+
+	let account = twitch::refresh(
+		&state.db,
+		twitch::client(state.twitch.as_deref())?,
+		&account,
+	)
+	.await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(account)))
+}
+
+/// Live status of the organizer's channel for a tournament
+#[get("/tournaments/{tournament_id}/channel")]
+async fn tournament_channel(
+	state: web::Data<AppState>,
+	user: Option<AuthUser>,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::ensure_visible(
+		state.tournaments.as_ref(),
+		&tournaments::record_id(&path),
+		user.as_ref(),
+	)
+	.await?;
+
+	let status = twitch::channel_status(
+		&state.db,
+		twitch::client(state.twitch.as_deref())?,
+		&tournament.created_by,
+	)
+	.await?
+	.ok_or_else(|| ApiError::not_found("twitch_account", &tournament.created_by.key().to_string()))?;
+
+	Ok(HttpResponse::Ok().json(ApiResponse::success(status)))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+	cfg
+		.service(
+			web::scope("/twitch")
+				.service(link)
+				.service(unlink)
+				.service(me)
+				.service(refresh),
+		)
+		.service(tournament_channel);
+}
@@ -0,0 +1,138 @@
+use crate::AppState;
+use crate::entities::{
+	ApiResponse, CreateWebhookData, CreatedWebhook, DeliveryStatus, PublicWebhook, UpdateWebhookData,
+};
+use crate::middleware::auth::AuthUser;
+use crate::services::{tournaments, webhooks};
+use crate::utils::error::ApiResult;
+use crate::utils::error::validation::ValidatedJson;
+use actix_web::{HttpResponse, delete, get, patch, post, web};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct DeliveryQuery {
+	pub status: Option<DeliveryStatus>,
+}
+
+#[post("")]
+async fn create(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+	body: ValidatedJson<CreateWebhookData>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::record_id(&path);
+	tournaments::ensure_organizer(state.tournaments.as_ref(), &tournament, &user).await?;
+
+	let webhook = webhooks::create(&state.db, &tournament, body.into_inner(), &user.id).await?;
+	let secret = webhook.secret.clone().unwrap_or_default();
+	let created = CreatedWebhook {
+		webhook: webhook.into(),
+		secret,
+	};
+
+	Ok(HttpResponse::Created().json(ApiResponse::success(created)))
+}
+
+#[get("")]
+async fn list(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<String>,
+) -> ApiResult<HttpResponse> {
+	let tournament = tournaments::record_id(&path);
+	tournaments::ensure_organizer(state.tournaments.as_ref(), &tournament, &user).await?;
+
+	let hooks: Vec<PublicWebhook> = webhooks::list(&state.db, &tournament)
+		.await?
+		.into_iter()
+		.map(PublicWebhook::from)
+		.collect();
+
+	Ok(HttpResponse::Ok().json(ApiResponse::success(hooks)))
+}
+
+#[patch("/{webhook_id}")]
+async fn update(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<(String, String)>,
+	body: ValidatedJson<UpdateWebhookData>,
+) -> ApiResult<HttpResponse> {
+	let (tournament_id, webhook_id) = path.into_inner();
+	let tournament = tournaments::record_id(&tournament_id);
+	tournaments::ensure_organizer(state.tournaments.as_ref(), &tournament, &user).await?;
+
+	let webhook = webhooks::update(
+		&state.db,
+		&tournament,
+		&webhooks::record_id(&webhook_id),
+		body.into_inner(),
+	)
+	.await?;
+	Ok(HttpResponse::Ok().json(ApiResponse::success(PublicWebhook::from(webhook))))
+}
+
+#[delete("/{webhook_id}")]
+async fn remove(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<(String, String)>,
+) -> ApiResult<HttpResponse> {
+	let (tournament_id, webhook_id) = path.into_inner();
+	let tournament = tournaments::record_id(&tournament_id);
+	tournaments::ensure_organizer(state.tournaments.as_ref(), &tournament, &user).await?;
+
+	webhooks::delete(&state.db, &tournament, &webhooks::record_id(&webhook_id)).await?;
+	Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/{webhook_id}/deliveries")]
+async fn deliveries(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<(String, String)>,
+	query: web::Query<DeliveryQuery>,
+) -> ApiResult<HttpResponse> {
+	let (tournament_id, webhook_id) = path.into_inner();
+	let tournament = tournaments::record_id(&tournament_id);
+	tournaments::ensure_organizer(state.tournaments.as_ref(), &tournament, &user).await?;
+
+	let webhook = webhooks::find(&state.db, &tournament, &webhooks::record_id(&webhook_id)).await?;
+	let log = webhooks::deliveries(&state.db, &webhook.id, query.status).await?;
+
+	Ok(HttpResponse::Ok().json(ApiResponse::success(log)))
+}
+
+#[post("/{webhook_id}/deliveries/{delivery_id}/replay")]
+async fn replay(
+	state: web::Data<AppState>,
+	user: AuthUser,
+	path: web::Path<(String, String, String)>,
+) -> ApiResult<HttpResponse> {
+	let (tournament_id, webhook_id, delivery_id) = path.into_inner();
+	let tournament = tournaments::record_id(&tournament_id);
+	tournaments::ensure_organizer(state.tournaments.as_ref(), &tournament, &user).await?;
+
+	let webhook = webhooks::find(&state.db, &tournament, &webhooks::record_id(&webhook_id)).await?;
+	let delivery = webhooks::replay(
+		&state,
+		&webhook,
+		&webhooks::delivery_record_id(&delivery_id),
+	)
+	.await?;
+
+	Ok(HttpResponse::Accepted().json(ApiResponse::success(delivery)))
+}
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+	cfg.service(
+		web::scope("/tournaments/{tournament_id}/webhooks")
+			.service(create)
+			.service(list)
+			.service(update)
+			.service(remove)
+			.service(deliveries)
+			.service(replay),
+	);
+}
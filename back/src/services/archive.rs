@@ -0,0 +1,267 @@
+//! Tournament export and import (see [`crate::entities::TournamentArchive`])
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::RecordId;
+
+use crate::db::Database;
+use crate::entities::{
+	ARCHIVE_FORMAT, ARCHIVE_VERSION, ArchivedMatch, ArchivedParticipant, ArchivedResult,
+	ArchivedTournament, MatchStatus, ParticipantImportRow, Tournament, TournamentArchive,
+};
+use crate::repositories::TournamentRepository;
+use crate::services::{matches, participants, tournaments};
+use crate::utils::constants;
+use crate::utils::error::{ApiError, ApiResult};
+use crate::utils::{logging, metrics};
+
+/// Export a tournament with its participants and matches
+pub async fn export(db: &Database, tournament: &Tournament) -> ApiResult<TournamentArchive> {
+	#[derive(Deserialize)]
+	struct ParticipantRow {
+		id: RecordId,
+		user_id: RecordId,
+		username: Option<String>,
+		joined_at: DateTime<Utc>,
+	}
+
+	// Emails stay on this instance
+	let mut response = db
+		.query(
+			"SELECT id, user_id, user_id.username AS username, joined_at
+				FROM participant
+				WHERE tournament = $tournament
+				ORDER BY joined_at",
+		)
+		.bind(("tournament", tournament.id.clone()))
+		.await?;
+	let rows: Vec<ParticipantRow> = response.take(0)?;
+
+	let participants = rows
+		.into_iter()
+		.map(|row| ArchivedParticipant {
+			id: row.id.to_string(),
+			user: Some(row.user_id.to_string()),
+			username: row.username,
+			email: None,
+			joined_at: row.joined_at,
+		})
+		.collect();
+
+	let matches = matches::list(db, &tournament.id)
+		.await?
+		.into_iter()
+		.map(|game| ArchivedMatch {
+			id: game.id.to_string(),
+			round: game.round,
+			position: game.position,
+			home: game.home.map(|id| id.to_string()),
+			away: game.away.map(|id| id.to_string()),
+			status: game.status,
+			scheduled_at: game.scheduled_at,
+			duration_minutes: game.duration_minutes,
+			started_at: game.started_at,
+			result: game.result.map(|result| ArchivedResult {
+				home_score: result.home_score,
+				away_score: result.away_score,
+				reported_at: result.reported_at,
+				confirmed_at: result.confirmed_at,
+			}),
+		})
+		.collect();
+
+	Ok(TournamentArchive {
+		format: ARCHIVE_FORMAT.to_string(),
+		version: ARCHIVE_VERSION,
+		generator: constants::APP_VERSION.to_string(),
+		exported_at: Utc::now(),
+		tournament: ArchivedTournament {
+			id: tournament.id.to_string(),
+			name: tournament.name.clone(),
+			description: tournament.description.clone(),
+			published: tournament.published,
+			season: tournament.season.clone(),
+			created_at: tournament.created_at,
+		},
+		participants,
+		matches,
+	})
+}
+
+/// Match to create on import, with references already remapped
+#[derive(Debug, Serialize)]
+struct NewMatch {
+	id: RecordId,
+	round: u32,
+	position: u32,
+	home: Option<RecordId>,
+	away: Option<RecordId>,
+	status: MatchStatus,
+	scheduled_at: Option<String>,
+	duration_minutes: u32,
+	started_at: Option<String>,
+}
+
+/// Result to attach to an imported match
+#[derive(Debug, Serialize)]
+struct NewResult {
+	r#match: RecordId,
+	home_score: u32,
+	away_score: u32,
+	reported_at: String,
+	confirmed_at: Option<String>,
+}
+
+/// Participant to create on import
+#[derive(Debug, Serialize)]
+struct NewParticipant {
+	id: RecordId,
+	user: RecordId,
+}
+
+/// Import an archive as a new, unpublished tournament owned by `organizer`
+///
+/// Participants are matched to local users by username, or by email for
+/// converted third-party exports that carry one. Unless
+/// `skip_unmatched` is set, any participant without a local user aborts the
+/// import; skipped participants leave their match slots empty. Everything is
+/// created in a single transaction.
+pub async fn import(
+	db: &Database,
+	repo: &dyn TournamentRepository,
+	archive: &TournamentArchive,
+	organizer: &RecordId,
+	skip_unmatched: bool,
+) -> ApiResult<Tournament> {
+	let rows: Vec<ParticipantImportRow> = archive
+		.participants
+		.iter()
+		.map(|participant| ParticipantImportRow {
+			username: participant.username.clone(),
+			email: participant.email.clone(),
+		})
+		.collect();
+	let users = participants::lookup_users(db, &rows).await?;
+	let resolved = participants::resolve_rows(&rows, &users, &[]);
+
+	// Named by username or archive id only, so no email is echoed back
+	let unmatched: Vec<String> = resolved
+		.iter()
+		.zip(&archive.participants)
+		.filter(|(row, _)| !row.is_valid())
+		.map(|(row, participant)| {
+			let who = row.username.as_deref().unwrap_or(&participant.id);
+			format!("row {} ({who})", row.row)
+		})
+		.collect();
+	if !unmatched.is_empty() && !skip_unmatched {
+		return Err(ApiError::validation_with_field(
+			&format!(
+				"Participants without a matching user: {}",
+				unmatched.join(", ")
+			),
+			"participants",
+		));
+	}
+
+	let mut key: Option<String> = db.query("RETURN rand::ulid()").await?.take(0)?;
+	let key = key
+		.take()
+		.ok_or_else(|| ApiError::internal("Failed to generate tournament id"))?;
+	let tournament_id = tournaments::record_id(&key);
+
+	let mut remapped: HashMap<&str, RecordId> = HashMap::new();
+	let mut new_participants = Vec::new();
+	for (participant, row) in archive.participants.iter().zip(&resolved) {
+		let Some(user) = row.user.clone().filter(|_| row.is_valid()) else {
+			continue;
+		};
+		let id = participants::record_id(&format!("{key}_p{}", row.row));
+		remapped.insert(participant.id.as_str(), id.clone());
+		new_participants.push(NewParticipant { id, user });
+	}
+
+	let slot = |reference: &Option<String>| {
+		reference
+			.as_deref()
+			.and_then(|reference| remapped.get(reference).cloned())
+	};
+	let mut new_matches = Vec::new();
+	let mut results = Vec::new();
+	for (index, game) in archive.matches.iter().enumerate() {
+		let id = matches::record_id(&format!("{key}_m{}", index + 1));
+		if let Some(result) = &game.result {
+			results.push(NewResult {
+				r#match: id.clone(),
+				home_score: result.home_score,
+				away_score: result.away_score,
+				reported_at: result.reported_at.to_rfc3339(),
+				confirmed_at: result.confirmed_at.map(|t| t.to_rfc3339()),
+			});
+		}
+		new_matches.push(NewMatch {
+			id,
+			round: game.round,
+			position: game.position,
+			home: slot(&game.home),
+			away: slot(&game.away),
+			status: game.status,
+			scheduled_at: game.scheduled_at.map(|t| t.to_rfc3339()),
+			duration_minutes: game.duration_minutes,
+			started_at: game.started_at.map(|t| t.to_rfc3339()),
+		});
+	}
+
+	db.query(
+		"BEGIN TRANSACTION;
+		CREATE $tournament CONTENT {
+			name: $name,
+			description: $description,
+			season: $season,
+			created_by: $organizer,
+		};
+		FOR $p IN $participants {
+			CREATE $p.id CONTENT { tournament: $tournament, user_id: $p.user };
+		};
+		FOR $m IN $matches {
+			CREATE $m.id CONTENT {
+				tournament: $tournament,
+				round: $m.round,
+				position: $m.position,
+				home: $m.home,
+				away: $m.away,
+				status: $m.status,
+				scheduled_at: <option<datetime>> $m.scheduled_at,
+				duration_minutes: $m.duration_minutes,
+				started_at: <option<datetime>> $m.started_at,
+			};
+		};
+		FOR $r IN $results {
+			UPDATE $r.match SET result = {
+				home_score: $r.home_score,
+				away_score: $r.away_score,
+				reported_by: $organizer,
+				reported_at: <datetime> $r.reported_at,
+				confirmed_by: IF $r.confirmed_at != NONE THEN $organizer ELSE NONE END,
+				confirmed_at: <option<datetime>> $r.confirmed_at,
+			};
+		};
+		COMMIT TRANSACTION;",
+	)
+	.bind(("tournament", tournament_id.clone()))
+	.bind(("name", archive.tournament.name.clone()))
+	.bind(("description", archive.tournament.description.clone()))
+	.bind(("season", archive.tournament.season.clone()))
+	.bind(("organizer", organizer.clone()))
+	.bind(("participants", new_participants))
+	.bind(("matches", new_matches))
+	.bind(("results", results))
+	.await?
+	.check()?;
+
+	logging::tournament_event("imported", &key, Some(&organizer.key().to_string()));
+	metrics::tournament_created("import");
+	tournaments::find(repo, &tournament_id).await
+}
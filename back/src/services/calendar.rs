@@ -0,0 +1,164 @@
+//! iCalendar feeds of scheduled matches
+//!
+//! Feeds exist per tournament, per participant (team) and per user. Every
+//! match maps to one event whose UID derives from the match id, and whose
+//! sequence is the match's `schedule_revision`, so subscribed calendars move
+//! or cancel the event in place instead of duplicating it.
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use surrealdb::RecordId;
+
+use crate::db::Database;
+use crate::entities::MatchStatus;
+use crate::utils::error::ApiResult;
+use crate::utils::ical::{Calendar, CalendarEvent, EventStatus};
+use crate::utils::time::TimeSlot;
+
+/// Domain part of event UIDs; never change it or calendars will duplicate events
+const UID_DOMAIN: &str = "liga-muertos";
+
+/// Whose matches a feed contains
+#[derive(Debug, Clone)]
+pub enum FeedScope {
+	Tournament(RecordId),
+	Participant(RecordId),
+	User(RecordId),
+}
+
+/// Scheduled match with the names needed to describe it
+#[derive(Debug, Clone, Deserialize)]
+pub struct CalendarMatch {
+	pub id: RecordId,
+	pub round: u32,
+	pub position: u32,
+	pub status: MatchStatus,
+	pub scheduled_at: DateTime<Utc>,
+	pub duration_minutes: u32,
+	#[serde(default)]
+	pub schedule_revision: u32,
+	pub updated_at: DateTime<Utc>,
+	pub tournament_name: String,
+	pub home_name: Option<String>,
+	pub away_name: Option<String>,
+}
+
+/// Stable UID of the event for a match
+pub fn event_uid(game: &RecordId) -> String {
+	format!("match-{}@{UID_DOMAIN}", game.key())
+}
+
+impl CalendarMatch {
+	/// Calendar event for this match
+	pub fn to_event(&self) -> CalendarEvent {
+		let slot = TimeSlot::starting_at(self.scheduled_at, self.duration_minutes);
+		let home = self.home_name.as_deref().unwrap_or("TBD");
+		let away = self.away_name.as_deref().unwrap_or("TBD");
+
+		CalendarEvent {
+			uid: event_uid(&self.id),
+			sequence: self.schedule_revision,
+			updated_at: self.updated_at,
+			starts_at: slot.starts_at,
+			ends_at: slot.ends_at,
+			summary: format!("{home} vs {away} · {}", self.tournament_name),
+			description: Some(format!(
+				"{}, round {} (match {})",
+				self.tournament_name, self.round, self.position
+			)),
+			status: match self.status {
+				MatchStatus::Cancelled => EventStatus::Cancelled,
+				MatchStatus::Pending => EventStatus::Tentative,
+				_ => EventStatus::Confirmed,
+			},
+		}
+	}
+}
+
+/// Scheduled matches of published tournaments in the given scope
+pub async fn matches(db: &Database, scope: &FeedScope) -> ApiResult<Vec<CalendarMatch>> {
+	let (filter, value) = match scope {
+		FeedScope::Tournament(id) => ("tournament = $scope", id),
+		FeedScope::Participant(id) => ("(home = $scope OR away = $scope)", id),
+		FeedScope::User(id) => ("(home.user_id = $scope OR away.user_id = $scope)", id),
+	};
+
+	let mut response = db
+		.query(format!(
+			"SELECT
+				id, round, position, status, scheduled_at, duration_minutes,
+				schedule_revision, updated_at,
+				tournament.name AS tournament_name,
+				home.user_id.username AS home_name,
+				away.user_id.username AS away_name
+			FROM match
+			WHERE scheduled_at != NONE AND tournament.published = true AND {filter}
+			ORDER BY scheduled_at"
+		))
+		.bind(("scope", value.clone()))
+		.await?;
+
+	Ok(response.take(0)?)
+}
+
+/// Build the `.ics` feed for a scope
+pub async fn feed(db: &Database, name: &str, scope: &FeedScope) -> ApiResult<String> {
+	let events = matches(db, scope)
+		.await?
+		.iter()
+		.map(CalendarMatch::to_event)
+		.collect();
+
+	let calendar = Calendar {
+		name: name.to_string(),
+		events,
+	};
+	Ok(calendar.render())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn scheduled(status: MatchStatus, revision: u32) -> CalendarMatch {
+		CalendarMatch {
+			id: RecordId::from(("match", "m1")),
+			round: 2,
+			position: 1,
+			status,
+			scheduled_at: Utc::now(),
+			duration_minutes: 90,
+			schedule_revision: revision,
+			updated_at: Utc::now(),
+			tournament_name: "Copa Catrina".to_string(),
+			home_name: Some("catrina".to_string()),
+			away_name: None,
+		}
+	}
+
+	#[test]
+	fn test_event_uid_is_stable_across_reschedules() {
+		let original = scheduled(MatchStatus::Scheduled, 1).to_event();
+		let mut moved = scheduled(MatchStatus::Scheduled, 2);
+		moved.scheduled_at += chrono::Duration::days(1);
+		let moved = moved.to_event();
+
+		assert_eq!(original.uid, "match-m1@liga-muertos");
+		assert_eq!(original.uid, moved.uid);
+		assert!(moved.sequence > original.sequence);
+		assert_eq!(
+			moved.ends_at - moved.starts_at,
+			chrono::Duration::minutes(90)
+		);
+	}
+
+	#[test]
+	fn test_event_reflects_match_status() {
+		let event = scheduled(MatchStatus::Cancelled, 3).to_event();
+		assert_eq!(event.status, EventStatus::Cancelled);
+		assert_eq!(event.summary, "catrina vs TBD · Copa Catrina");
+
+		let event = scheduled(MatchStatus::Completed, 1).to_event();
+		assert_eq!(event.status, EventStatus::Confirmed);
+	}
+}
@@ -0,0 +1,682 @@
+//! Converters from third-party bracket exports to tournament archives
+//!
+//! Challonge (API v1 tournament JSON with participants and matches included)
+//! and start.gg (GraphQL `event` query results with entrants and sets) exports
+//! become a [`TournamentArchive`], which then goes through the regular archive
+//! import. Anything the archive has no place for is reported as unmapped
+//! instead of being dropped silently.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+
+use crate::entities::{
+	ARCHIVE_FORMAT, ARCHIVE_VERSION, ArchiveError, ArchivedMatch, ArchivedParticipant,
+	ArchivedResult, ArchivedTournament, ConvertedArchive, DEFAULT_MATCH_MINUTES, ImportSource,
+	MatchStatus, TournamentArchive, UnmappedField,
+};
+
+/// Unmapped fields by path, with how often they occurred and why
+#[derive(Debug, Default)]
+struct Unmapped(BTreeMap<String, (usize, String)>);
+
+impl Unmapped {
+	fn note(&mut self, field: &str, reason: &str) {
+		self
+			.0
+			.entry(field.to_string())
+			.or_insert_with(|| (0, reason.to_string()))
+			.0 += 1;
+	}
+
+	/// Note every key of `object` carrying data that isn't in `mapped`
+	fn rest(&mut self, prefix: &str, object: &Map<String, Value>, mapped: &[&str]) {
+		for (key, value) in object {
+			if !mapped.contains(&key.as_str()) && has_data(value) {
+				self.note(&format!("{prefix}{key}"), "no matching field");
+			}
+		}
+	}
+
+	fn into_fields(self) -> Vec<UnmappedField> {
+		self
+			.0
+			.into_iter()
+			.map(|(field, (occurrences, reason))| UnmappedField {
+				field,
+				occurrences,
+				reason,
+			})
+			.collect()
+	}
+}
+
+/// Whether a value holds anything worth reporting
+fn has_data(value: &Value) -> bool {
+	match value {
+		Value::Null => false,
+		Value::Bool(flag) => *flag,
+		Value::String(text) => !text.is_empty(),
+		Value::Array(items) => !items.is_empty(),
+		Value::Object(fields) => !fields.is_empty(),
+		Value::Number(_) => true,
+	}
+}
+
+/// Non-empty string or number as text
+fn text(object: &Map<String, Value>, key: &str) -> Option<String> {
+	match object.get(key)? {
+		Value::String(text) if !text.trim().is_empty() => Some(text.trim().to_string()),
+		Value::Number(number) => Some(number.to_string()),
+		_ => None,
+	}
+}
+
+fn integer(object: &Map<String, Value>, key: &str) -> Option<i64> {
+	object.get(key)?.as_i64()
+}
+
+/// RFC 3339 string or Unix timestamp in seconds
+fn timestamp(object: &Map<String, Value>, key: &str) -> Option<DateTime<Utc>> {
+	match object.get(key)? {
+		Value::String(text) => DateTime::parse_from_rfc3339(text)
+			.ok()
+			.map(|time| time.with_timezone(&Utc)),
+		Value::Number(number) => DateTime::from_timestamp(number.as_i64()?, 0),
+		_ => None,
+	}
+}
+
+/// Challonge wraps list items as `{"participant": {..}}`; accept both forms
+fn unwrap_item<'a>(value: &'a Value, key: &str) -> Option<&'a Map<String, Value>> {
+	value.get(key).unwrap_or(value).as_object()
+}
+
+/// GraphQL connections list items under `nodes`; accept bare arrays too
+fn nodes<'a>(object: &'a Map<String, Value>, key: &str) -> &'a [Value] {
+	let list = object.get(key);
+	list
+		.and_then(|list| list.get("nodes"))
+		.or(list)
+		.and_then(Value::as_array)
+		.map(Vec::as_slice)
+		.unwrap_or_default()
+}
+
+/// Match read from an export, before positions are assigned
+struct Draft {
+	order: i64,
+	game: ArchivedMatch,
+}
+
+/// Number matches within each round in play order
+fn number_positions(mut drafts: Vec<Draft>) -> Vec<ArchivedMatch> {
+	drafts.sort_by_key(|draft| (draft.game.round, draft.order));
+
+	let mut matches: Vec<ArchivedMatch> = Vec::with_capacity(drafts.len());
+	for Draft { mut game, .. } in drafts {
+		game.position = match matches.last() {
+			Some(previous) if previous.round == game.round => previous.position + 1,
+			_ => 1,
+		};
+		matches.push(game);
+	}
+	matches
+}
+
+/// Round number, or `None` (noted) for losers bracket rounds
+fn winners_round(round: Option<i64>, field: &str, unmapped: &mut Unmapped) -> Option<u32> {
+	match round {
+		Some(round) if round > 0 => u32::try_from(round).ok(),
+		_ => {
+			unmapped.note(
+				field,
+				"losers bracket matches have no counterpart and were skipped",
+			);
+			None
+		}
+	}
+}
+
+/// Result for a finished match, falling back to a 1-0 for the winner
+fn finished_result(
+	scores: Option<(u32, u32)>,
+	home_won: Option<bool>,
+	reported_at: DateTime<Utc>,
+	unmapped: &mut Unmapped,
+	winner_field: &str,
+) -> Option<ArchivedResult> {
+	let (home_score, away_score) = match (scores, home_won) {
+		(Some(scores), _) => scores,
+		(None, Some(home_won)) => {
+			unmapped.note(winner_field, "results without a score were imported as 1-0");
+			if home_won { (1, 0) } else { (0, 1) }
+		}
+		(None, None) => return None,
+	};
+	Some(ArchivedResult {
+		home_score,
+		away_score,
+		reported_at,
+		confirmed_at: Some(reported_at),
+	})
+}
+
+/// Parse Challonge `scores_csv` ("3-1", or per set "2-1,1-2,3-0")
+///
+/// Several sets are reduced to sets won by each side. Returns the score and
+/// whether it was reduced.
+fn parse_scores_csv(scores: &str) -> Option<((u32, u32), bool)> {
+	let sets: Vec<(u32, u32)> = scores
+		.split(',')
+		.map(|set| {
+			let (home, away) = set.trim().split_once('-')?;
+			Some((home.trim().parse().ok()?, away.trim().parse().ok()?))
+		})
+		.collect::<Option<_>>()?;
+
+	match sets.as_slice() {
+		[] => None,
+		[single] => Some((*single, false)),
+		sets => {
+			let home = sets.iter().filter(|(home, away)| home > away).count() as u32;
+			let away = sets.iter().filter(|(home, away)| away > home).count() as u32;
+			Some(((home, away), true))
+		}
+	}
+}
+
+fn archive(
+	generator: &str,
+	tournament: ArchivedTournament,
+	participants: Vec<ArchivedParticipant>,
+	drafts: Vec<Draft>,
+	unmapped: Unmapped,
+) -> ConvertedArchive {
+	ConvertedArchive {
+		archive: TournamentArchive {
+			format: ARCHIVE_FORMAT.to_string(),
+			version: ARCHIVE_VERSION,
+			generator: generator.to_string(),
+			exported_at: Utc::now(),
+			tournament,
+			participants,
+			matches: number_positions(drafts),
+		},
+		unmapped: unmapped.into_fields(),
+	}
+}
+
+/// Read a document from any supported source
+pub fn convert(source: ImportSource, value: Value) -> Result<ConvertedArchive, ArchiveError> {
+	match source {
+		ImportSource::Archive => Ok(ConvertedArchive {
+			archive: TournamentArchive::from_json(value)?,
+			unmapped: Vec::new(),
+		}),
+		ImportSource::Challonge => from_challonge(&value),
+		ImportSource::StartGg => from_startgg(&value),
+	}
+}
+
+/// Convert a Challonge tournament export
+pub fn from_challonge(value: &Value) -> Result<ConvertedArchive, ArchiveError> {
+	let tournament = unwrap_item(value, "tournament")
+		.filter(|tournament| tournament.contains_key("tournament_type"))
+		.ok_or_else(|| ArchiveError::Invalid("not a Challonge tournament export".to_string()))?;
+	let name = text(tournament, "name")
+		.ok_or_else(|| ArchiveError::Invalid("tournament name is missing".to_string()))?;
+	let created_at = timestamp(tournament, "created_at").unwrap_or_else(Utc::now);
+
+	let mut unmapped = Unmapped::default();
+	unmapped.rest(
+		"tournament.",
+		tournament,
+		&[
+			"id",
+			"name",
+			"description",
+			"created_at",
+			"updated_at",
+			"participants",
+			"matches",
+		],
+	);
+
+	let list = |key: &str| -> &[Value] {
+		tournament
+			.get(key)
+			.or_else(|| value.get(key))
+			.and_then(Value::as_array)
+			.map(Vec::as_slice)
+			.unwrap_or_default()
+	};
+	let participant_id = |id: String| format!("participant:{id}");
+
+	let mut participants = Vec::new();
+	for participant in list("participants") {
+		let Some(participant) = unwrap_item(participant, "participant") else {
+			continue;
+		};
+		let Some(id) = text(participant, "id") else {
+			unmapped.note("participants[]", "participants without an id were skipped");
+			continue;
+		};
+		unmapped.rest(
+			"participants[].",
+			participant,
+			&[
+				"id",
+				"tournament_id",
+				"name",
+				"display_name",
+				"challonge_username",
+				"username",
+				"email",
+				"active",
+				"created_at",
+				"updated_at",
+			],
+		);
+		participants.push(ArchivedParticipant {
+			id: participant_id(id),
+			user: None,
+			username: text(participant, "challonge_username")
+				.or_else(|| text(participant, "username"))
+				.or_else(|| text(participant, "name")),
+			email: text(participant, "email"),
+			joined_at: timestamp(participant, "created_at").unwrap_or(created_at),
+		});
+	}
+
+	let mut drafts = Vec::new();
+	for (index, game) in list("matches").iter().enumerate() {
+		let Some(game) = unwrap_item(game, "match") else {
+			continue;
+		};
+		let Some(round) = winners_round(integer(game, "round"), "matches[].round", &mut unmapped)
+		else {
+			continue;
+		};
+		unmapped.rest(
+			"matches[].",
+			game,
+			&[
+				"id",
+				"tournament_id",
+				"identifier",
+				"round",
+				"suggested_play_order",
+				"state",
+				"player1_id",
+				"player2_id",
+				"winner_id",
+				"loser_id",
+				"scores_csv",
+				"scheduled_time",
+				"underway_at",
+				"completed_at",
+				"updated_at",
+			],
+		);
+
+		let home = text(game, "player1_id");
+		let scheduled_at = timestamp(game, "scheduled_time");
+		let underway_at = timestamp(game, "underway_at");
+		let scores = text(game, "scores_csv").and_then(|scores| parse_scores_csv(&scores));
+		if scores.is_some_and(|(_, reduced)| reduced) {
+			unmapped.note(
+				"matches[].scores_csv",
+				"per-set scores were reduced to sets won",
+			);
+		}
+
+		let state = text(game, "state").unwrap_or_default();
+		let (status, result) = if state == "complete" {
+			let reported_at = timestamp(game, "completed_at")
+				.or_else(|| timestamp(game, "updated_at"))
+				.unwrap_or_else(Utc::now);
+			let home_won = text(game, "winner_id").map(|winner| Some(winner) == home);
+			let result = finished_result(
+				scores.map(|(scores, _)| scores),
+				home_won,
+				reported_at,
+				&mut unmapped,
+				"matches[].winner_id",
+			);
+			(MatchStatus::Completed, result)
+		} else if state == "open" && underway_at.is_some() {
+			(MatchStatus::InProgress, None)
+		} else if scheduled_at.is_some() {
+			(MatchStatus::Scheduled, None)
+		} else {
+			(MatchStatus::Pending, None)
+		};
+
+		drafts.push(Draft {
+			order: integer(game, "suggested_play_order").unwrap_or(index as i64),
+			game: ArchivedMatch {
+				id: format!(
+					"match:{}",
+					text(game, "id").unwrap_or_else(|| index.to_string())
+				),
+				round,
+				position: 0,
+				home: home.map(participant_id),
+				away: text(game, "player2_id").map(participant_id),
+				status,
+				scheduled_at,
+				duration_minutes: DEFAULT_MATCH_MINUTES,
+				started_at: underway_at,
+				result,
+			},
+		});
+	}
+
+	let summary = ArchivedTournament {
+		id: format!("challonge:{}", text(tournament, "id").unwrap_or_default()),
+		name,
+		description: text(tournament, "description").unwrap_or_default(),
+		published: false,
+		season: None,
+		created_at,
+	};
+	Ok(archive(
+		"challonge",
+		summary,
+		participants,
+		drafts,
+		unmapped,
+	))
+}
+
+/// Convert a start.gg event export
+pub fn from_startgg(value: &Value) -> Result<ConvertedArchive, ArchiveError> {
+	let event = value
+		.pointer("/data/event")
+		.or_else(|| value.get("event"))
+		.and_then(Value::as_object)
+		.ok_or_else(|| ArchiveError::Invalid("not a start.gg event export".to_string()))?;
+	let event_name = text(event, "name");
+	let tournament = event.get("tournament").and_then(Value::as_object);
+	let name = match (tournament.and_then(|t| text(t, "name")), event_name) {
+		(Some(tournament), Some(event)) => format!("{tournament} · {event}"),
+		(Some(name), None) | (None, Some(name)) => name,
+		(None, None) => {
+			return Err(ArchiveError::Invalid("event name is missing".to_string()));
+		}
+	};
+	let created_at = timestamp(event, "createdAt").unwrap_or_else(Utc::now);
+
+	let mut unmapped = Unmapped::default();
+	unmapped.rest(
+		"event.",
+		event,
+		&["id", "name", "createdAt", "tournament", "entrants", "sets"],
+	);
+	if let Some(tournament) = tournament {
+		unmapped.rest("event.tournament.", tournament, &["id", "name"]);
+	}
+
+	let participant_id = |id: String| format!("participant:{id}");
+
+	let mut participants = Vec::new();
+	for entrant in nodes(event, "entrants") {
+		let Some(entrant) = entrant.as_object() else {
+			continue;
+		};
+		let Some(id) = text(entrant, "id") else {
+			unmapped.note("entrants[]", "entrants without an id were skipped");
+			continue;
+		};
+		unmapped.rest("entrants[].", entrant, &["id", "name", "participants"]);
+
+		let members = entrant
+			.get("participants")
+			.and_then(Value::as_array)
+			.map(Vec::as_slice)
+			.unwrap_or_default();
+		if members.len() > 1 {
+			unmapped.note(
+				"entrants[].participants",
+				"teams were imported as their first member",
+			);
+		}
+		let player = members.first().and_then(Value::as_object);
+		if let Some(player) = player {
+			unmapped.rest(
+				"entrants[].participants[].",
+				player,
+				&["id", "gamerTag", "email"],
+			);
+		}
+
+		participants.push(ArchivedParticipant {
+			id: participant_id(id),
+			user: None,
+			username: player
+				.and_then(|player| text(player, "gamerTag"))
+				.or_else(|| text(entrant, "name")),
+			email: player.and_then(|player| text(player, "email")),
+			joined_at: created_at,
+		});
+	}
+
+	let mut drafts = Vec::new();
+	for (index, set) in nodes(event, "sets").iter().enumerate() {
+		let Some(set) = set.as_object() else {
+			continue;
+		};
+		let Some(round) = winners_round(integer(set, "round"), "sets[].round", &mut unmapped) else {
+			continue;
+		};
+		unmapped.rest(
+			"sets[].",
+			set,
+			&[
+				"id",
+				"identifier",
+				"round",
+				"fullRoundText",
+				"state",
+				"winnerId",
+				"startAt",
+				"startedAt",
+				"completedAt",
+				"slots",
+			],
+		);
+
+		let slots = set
+			.get("slots")
+			.and_then(Value::as_array)
+			.map(Vec::as_slice)
+			.unwrap_or_default();
+		let entrant = |slot: usize| {
+			slots
+				.get(slot)
+				.and_then(|slot| slot.pointer("/entrant/id"))
+				.and_then(|id| match id {
+					Value::Number(id) => Some(id.to_string()),
+					Value::String(id) => Some(id.clone()),
+					_ => None,
+				})
+		};
+		let score = |slot: usize| {
+			slots
+				.get(slot)
+				.and_then(|slot| slot.pointer("/standing/stats/score/value"))
+				.and_then(Value::as_i64)
+		};
+		let home = entrant(0);
+
+		// start.gg marks a disqualification with a score of -1
+		let scores = match (score(0), score(1)) {
+			(Some(home), Some(away)) if home < 0 || away < 0 => {
+				unmapped.note(
+					"sets[].slots[].standing",
+					"disqualifications were imported as a win for the other side",
+				);
+				None
+			}
+			(Some(home), Some(away)) => Some((home as u32, away as u32)),
+			_ => None,
+		};
+
+		let scheduled_at = timestamp(set, "startAt");
+		let started_at = timestamp(set, "startedAt");
+		let (status, result) = match integer(set, "state") {
+			Some(3) => {
+				let reported_at = timestamp(set, "completedAt").unwrap_or_else(Utc::now);
+				let home_won = text(set, "winnerId").map(|winner| Some(winner) == home);
+				let result = finished_result(
+					scores,
+					home_won,
+					reported_at,
+					&mut unmapped,
+					"sets[].winnerId",
+				);
+				(MatchStatus::Completed, result)
+			}
+			Some(2) => (MatchStatus::InProgress, None),
+			_ if scheduled_at.is_some() => (MatchStatus::Scheduled, None),
+			_ => (MatchStatus::Pending, None),
+		};
+
+		drafts.push(Draft {
+			order: index as i64,
+			game: ArchivedMatch {
+				id: format!(
+					"match:{}",
+					text(set, "id").unwrap_or_else(|| index.to_string())
+				),
+				round,
+				position: 0,
+				home: home.map(participant_id),
+				away: entrant(1).map(participant_id),
+				status,
+				scheduled_at,
+				duration_minutes: DEFAULT_MATCH_MINUTES,
+				started_at,
+				result,
+			},
+		});
+	}
+
+	let summary = ArchivedTournament {
+		id: format!("startgg:{}", text(event, "id").unwrap_or_default()),
+		name,
+		description: String::new(),
+		published: false,
+		season: None,
+		created_at,
+	};
+	Ok(archive("start.gg", summary, participants, drafts, unmapped))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn fixture(name: &str) -> Value {
+		let path = format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+		serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+	}
+
+	fn unmapped<'a>(converted: &'a ConvertedArchive, field: &str) -> Option<&'a UnmappedField> {
+		converted.unmapped.iter().find(|f| f.field == field)
+	}
+
+	#[test]
+	fn test_challonge_export_maps_to_archive() {
+		let converted = from_challonge(&fixture("challonge.json")).unwrap();
+		let archive = &converted.archive;
+
+		assert_eq!(archive.tournament.name, "Copa Catrina 2024");
+		assert!(!archive.tournament.published);
+		assert_eq!(archive.participants.len(), 4);
+		assert_eq!(archive.participants[0].username.as_deref(), Some("catrina"));
+		assert_eq!(archive.participants[1].username.as_deref(), Some("Calaca"));
+		assert_eq!(
+			archive.participants[1].email.as_deref(),
+			Some("calaca@example.com")
+		);
+
+		// The losers bracket match is skipped
+		let positions: Vec<(u32, u32)> = archive
+			.matches
+			.iter()
+			.map(|m| (m.round, m.position))
+			.collect();
+		assert_eq!(positions, vec![(1, 1), (1, 2), (2, 1)]);
+
+		let first = &archive.matches[0];
+		assert_eq!(first.home.as_deref(), Some("participant:201"));
+		assert_eq!(first.status, MatchStatus::Completed);
+		let result = first.result.as_ref().unwrap();
+		assert_eq!((result.home_score, result.away_score), (3, 1));
+
+		let sets = archive.matches[1].result.as_ref().unwrap();
+		assert_eq!((sets.home_score, sets.away_score), (1, 2));
+		assert_eq!(archive.matches[2].status, MatchStatus::InProgress);
+
+		assert_eq!(
+			unmapped(&converted, "matches[].round").unwrap().occurrences,
+			1
+		);
+		assert!(unmapped(&converted, "matches[].scores_csv").is_some());
+		assert_eq!(
+			unmapped(&converted, "participants[].seed")
+				.unwrap()
+				.occurrences,
+			4
+		);
+		assert!(unmapped(&converted, "tournament.tournament_type").is_some());
+		// Empty values aren't worth reporting
+		assert!(unmapped(&converted, "participants[].final_rank").is_none());
+	}
+
+	#[test]
+	fn test_startgg_export_maps_to_archive() {
+		let converted = from_startgg(&fixture("startgg.json")).unwrap();
+		let archive = &converted.archive;
+
+		assert_eq!(archive.tournament.name, "Noche de Muertos · Singles");
+		assert_eq!(archive.participants.len(), 4);
+		assert_eq!(archive.participants[2].username.as_deref(), Some("xolo"));
+		assert_eq!(archive.matches.len(), 3);
+
+		let first = &archive.matches[0];
+		assert_eq!(first.home.as_deref(), Some("participant:9001"));
+		assert_eq!(first.away.as_deref(), Some("participant:9004"));
+		let result = first.result.as_ref().unwrap();
+		assert_eq!((result.home_score, result.away_score), (2, 0));
+
+		let disqualified = archive.matches[1].result.as_ref().unwrap();
+		assert_eq!((disqualified.home_score, disqualified.away_score), (0, 1));
+		assert_eq!(archive.matches[2].status, MatchStatus::Scheduled);
+
+		assert!(unmapped(&converted, "entrants[].participants").is_some());
+		assert!(unmapped(&converted, "sets[].slots[].standing").is_some());
+		assert!(unmapped(&converted, "sets[].round").is_some());
+		assert!(unmapped(&converted, "event.videogame").is_some());
+	}
+
+	#[test]
+	fn test_rejects_documents_from_other_sources() {
+		let challonge = fixture("challonge.json");
+		let startgg = fixture("startgg.json");
+
+		assert!(from_challonge(&startgg).is_err());
+		assert!(from_startgg(&challonge).is_err());
+	}
+
+	#[test]
+	fn test_parse_scores_csv() {
+		assert_eq!(parse_scores_csv("3-1"), Some(((3, 1), false)));
+		assert_eq!(parse_scores_csv("2-1,1-2,3-0"), Some(((2, 1), true)));
+		assert_eq!(parse_scores_csv(""), None);
+		assert_eq!(parse_scores_csv("abc"), None);
+	}
+}
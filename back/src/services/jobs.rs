@@ -0,0 +1,182 @@
+//! Background work queued by request handlers
+//!
+//! Handlers hand follow-up work (webhook deliveries, for now) to the
+//! [`JobQueue`] in [`crate::AppState`] instead of spawning tasks themselves.
+//! In production jobs start right away on the actix runtime; the deferred
+//! queue used by tests holds them until [`JobQueue::run_pending`] is called,
+//! so a test decides when (and whether) the side effects happen.
+//!
+//! Jobs run under the request id (and user) of the request that queued them,
+//! so their log records can be traced back to it. Periodic upkeep (such as
+//! archiving closed polls) is scheduled with [`JobQueue::every`].
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::middleware::request_id;
+
+/// A queued unit of background work
+pub type Job = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Jobs held by a deferred queue, with their names
+type Deferred = Arc<Mutex<Vec<(&'static str, Job)>>>;
+
+/// Queue for fire-and-forget background jobs
+#[derive(Clone)]
+pub struct JobQueue {
+	deferred: Option<Deferred>,
+}
+
+impl JobQueue {
+	/// Queue that starts every job immediately on the current runtime
+	pub fn spawning() -> Self {
+		Self { deferred: None }
+	}
+
+	/// Queue that holds jobs until [`JobQueue::run_pending`]
+	pub fn deferred() -> Self {
+		Self {
+			deferred: Some(Arc::default()),
+		}
+	}
+
+	/// Queue a job; `name` identifies it in logs
+	pub fn push(&self, name: &'static str, job: impl Future<Output = ()> + Send + 'static) {
+		let job: Job = match request_id::snapshot() {
+			Some(scope) => Box::pin(scope.run(job)),
+			None => Box::pin(job),
+		};
+		match &self.deferred {
+			Some(jobs) => jobs
+				.lock()
+				.unwrap_or_else(|e| e.into_inner())
+				.push((name, job)),
+			None => {
+				log::debug!("Starting background job {name}");
+				actix_web::rt::spawn(job);
+			}
+		}
+	}
+
+	/// Run a job every `period`, starting one period from now
+	///
+	/// A deferred queue holds a single run, so tests trigger the job with
+	/// [`JobQueue::run_pending`] like any other.
+	pub fn every<F, Fut>(&self, name: &'static str, period: Duration, job: F)
+	where
+		F: Fn() -> Fut + Send + 'static,
+		Fut: Future<Output = ()> + Send + 'static,
+	{
+		if self.deferred.is_some() {
+			self.push(name, job());
+			return;
+		}
+
+		log::debug!("Scheduling background job {name} every {period:?}");
+		actix_web::rt::spawn(async move {
+			let mut ticks = actix_web::rt::time::interval(period);
+			ticks.tick().await;
+			loop {
+				ticks.tick().await;
+				job().await;
+			}
+		});
+	}
+
+	/// Number of jobs waiting in a deferred queue
+	pub fn pending(&self) -> usize {
+		self.deferred.as_ref().map_or(0, |jobs| {
+			jobs.lock().unwrap_or_else(|e| e.into_inner()).len()
+		})
+	}
+
+	/// Names of the jobs waiting in a deferred queue, oldest first
+	pub fn pending_names(&self) -> Vec<&'static str> {
+		self.deferred.as_ref().map_or_else(Vec::new, |jobs| {
+			let jobs = jobs.lock().unwrap_or_else(|e| e.into_inner());
+			jobs.iter().map(|(name, _)| *name).collect()
+		})
+	}
+
+	/// Run the jobs waiting in a deferred queue, returning how many ran
+	pub async fn run_pending(&self) -> usize {
+		let Some(jobs) = &self.deferred else {
+			return 0;
+		};
+		let queued = std::mem::take(&mut *jobs.lock().unwrap_or_else(|e| e.into_inner()));
+		let count = queued.len();
+		for (_, job) in queued {
+			job.await;
+		}
+		count
+	}
+}
+
+impl Default for JobQueue {
+	fn default() -> Self {
+		Self::spawning()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	#[actix_web::test]
+	async fn test_deferred_queue_runs_jobs_on_demand() {
+		let queue = JobQueue::deferred();
+		let runs = Arc::new(AtomicUsize::new(0));
+		for _ in 0..2 {
+			let runs = runs.clone();
+			queue.push("count", async move {
+				runs.fetch_add(1, Ordering::SeqCst);
+			});
+		}
+
+		assert_eq!(queue.pending_names(), vec!["count", "count"]);
+		assert_eq!(runs.load(Ordering::SeqCst), 0);
+		assert_eq!(queue.run_pending().await, 2);
+		assert_eq!(runs.load(Ordering::SeqCst), 2);
+		assert_eq!(queue.pending(), 0);
+	}
+
+	#[actix_web::test]
+	async fn test_deferred_queue_holds_one_run_of_a_periodic_job() {
+		let queue = JobQueue::deferred();
+		let runs = Arc::new(AtomicUsize::new(0));
+		let counter = runs.clone();
+		queue.every("tick", Duration::from_millis(1), move || {
+			let runs = counter.clone();
+			async move {
+				runs.fetch_add(1, Ordering::SeqCst);
+			}
+		});
+
+		assert_eq!(queue.pending_names(), vec!["tick"]);
+		assert_eq!(queue.run_pending().await, 1);
+		assert_eq!(runs.load(Ordering::SeqCst), 1);
+	}
+
+	#[actix_web::test]
+	async fn test_jobs_inherit_the_request_id() {
+		let queue = JobQueue::deferred();
+		let seen = Arc::new(Mutex::new(None));
+		let slot = seen.clone();
+		request_id::RequestScope::new("trace-job")
+			.run(async {
+				request_id::set_user("user_1");
+				queue.push("remember", async move {
+					*slot.lock().unwrap() = request_id::snapshot();
+				});
+			})
+			.await;
+
+		queue.run_pending().await;
+		let seen = seen.lock().unwrap().clone().unwrap();
+		assert_eq!(seen.id, "trace-job");
+		assert_eq!(seen.user_id.as_deref(), Some("user_1"));
+	}
+}
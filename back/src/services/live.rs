@@ -0,0 +1,185 @@
+//! Live updates pushed to overlays and viewers over WebSockets
+//!
+//! Producers publish [`LiveEvent`]s to a named topic (e.g. `poll:abc`) on the
+//! [`LiveHub`]; every WebSocket subscribed to that topic receives them as JSON
+//! text frames. Topics are created on first subscription and dropped once
+//! nobody listens anymore. The hub is shared through [`crate::AppState`].
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_ws::Message;
+use serde::{Deserialize, Serialize};
+use surrealdb::RecordId;
+use tokio::sync::broadcast;
+
+use crate::utils::error::{ApiError, ApiResult};
+
+/// Events buffered per topic before slow subscribers start skipping
+const TOPIC_CAPACITY: usize = 64;
+
+/// Message pushed to live subscribers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LiveEvent {
+	/// Event name (e.g. "poll.tally")
+	pub event: String,
+	pub data: serde_json::Value,
+}
+
+impl LiveEvent {
+	pub fn new(event: &str, data: impl Serialize) -> ApiResult<Self> {
+		Ok(Self {
+			event: event.to_string(),
+			data: serde_json::to_value(data)?,
+		})
+	}
+}
+
+/// Topic-based broadcast hub
+#[derive(Default)]
+pub struct LiveHub {
+	topics: Mutex<HashMap<String, broadcast::Sender<LiveEvent>>>,
+}
+
+impl LiveHub {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Subscribe to a topic, creating it if needed
+	pub fn subscribe(&self, topic: &str) -> broadcast::Receiver<LiveEvent> {
+		let mut topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+		topics
+			.entry(topic.to_string())
+			.or_insert_with(|| broadcast::channel(TOPIC_CAPACITY).0)
+			.subscribe()
+	}
+
+	/// Publish an event, returning how many subscribers received it
+	pub fn publish(&self, topic: &str, event: LiveEvent) -> usize {
+		let mut topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+		let Some(sender) = topics.get(topic) else {
+			return 0;
+		};
+
+		match sender.send(event) {
+			Ok(receivers) => receivers,
+			Err(_) => {
+				topics.remove(topic);
+				0
+			}
+		}
+	}
+
+	/// Number of live subscribers of a topic
+	pub fn subscribers(&self, topic: &str) -> usize {
+		let topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+		topics
+			.get(topic)
+			.map_or(0, |sender| sender.receiver_count())
+	}
+
+	/// Number of live subscribers across all topics
+	pub fn total_subscribers(&self) -> usize {
+		let topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+		topics.values().map(|sender| sender.receiver_count()).sum()
+	}
+
+	/// Number of topics with a channel open
+	pub fn topic_count(&self) -> usize {
+		self.topics.lock().unwrap_or_else(|e| e.into_inner()).len()
+	}
+}
+
+/// Topic carrying the live tally of a poll
+pub fn poll_topic(poll: &RecordId) -> String {
+	format!("poll:{}", poll.key())
+}
+
+/// Upgrade the request to a WebSocket streaming a topic
+///
+/// `initial` is sent right after the handshake so overlays render without
+/// waiting for the next update.
+pub fn serve(
+	hub: &LiveHub,
+	req: &HttpRequest,
+	body: web::Payload,
+	topic: &str,
+	initial: LiveEvent,
+) -> ApiResult<HttpResponse> {
+	let (response, mut session, mut messages) = actix_ws::handle(req, body)
+		.map_err(|_| ApiError::bad_request("Expected a WebSocket upgrade request"))?;
+	let mut events = hub.subscribe(topic);
+	let topic = topic.to_string();
+
+	actix_web::rt::spawn(async move {
+		let mut next = Some(initial);
+		loop {
+			if let Some(event) = next.take() {
+				let Ok(text) = serde_json::to_string(&event) else {
+					continue;
+				};
+				if session.text(text).await.is_err() {
+					break;
+				}
+			}
+
+			tokio::select! {
+				received = events.recv() => match received {
+					Ok(event) => next = Some(event),
+					Err(broadcast::error::RecvError::Lagged(skipped)) => {
+						log::debug!("Live subscriber of {topic} skipped {skipped} event(s)");
+					}
+					Err(broadcast::error::RecvError::Closed) => break,
+				},
+				message = messages.recv() => match message {
+					Some(Ok(Message::Ping(bytes))) => {
+						if session.pong(&bytes).await.is_err() {
+							break;
+						}
+					}
+					Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+					Some(Ok(_)) => {}
+				},
+			}
+		}
+
+		let _ = session.close(None).await;
+	});
+
+	Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[actix_web::test]
+	async fn test_hub_fans_out_to_topic_subscribers() {
+		let hub = LiveHub::new();
+		let mut first = hub.subscribe("poll:a");
+		let mut second = hub.subscribe("poll:a");
+		let mut other = hub.subscribe("poll:b");
+
+		let event = LiveEvent::new("poll.tally", serde_json::json!({ "voters": 1 })).unwrap();
+		assert_eq!(hub.publish("poll:a", event.clone()), 2);
+
+		assert_eq!(first.recv().await.unwrap(), event);
+		assert_eq!(second.recv().await.unwrap(), event);
+		assert!(other.try_recv().is_err());
+	}
+
+	#[test]
+	fn test_hub_drops_abandoned_topics() {
+		let hub = LiveHub::new();
+		let subscriber = hub.subscribe("poll:a");
+		assert_eq!(hub.subscribers("poll:a"), 1);
+
+		drop(subscriber);
+		let event = LiveEvent::new("poll.tally", ()).unwrap();
+		assert_eq!(hub.publish("poll:a", event.clone()), 0);
+		assert_eq!(hub.subscribers("poll:a"), 0);
+		assert_eq!(hub.publish("poll:unknown", event), 0);
+	}
+}
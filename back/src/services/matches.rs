@@ -0,0 +1,474 @@
+//! Match lifecycle: creation, kickoff, score reporting and confirmation
+//!
+//! A match moves `pending`/`scheduled` → `in_progress` → `reported` →
+//! `completed`. Either player (or the organizer) reports the score; the
+//! organizer or the other player confirms it, which makes the result final
+//! and triggers prediction scoring.
+//!
+//! Every transition is guarded on the current status inside its query, so of
+//! two concurrent requests only one succeeds; the other gets a `Conflict`.
+
+use serde::Deserialize;
+use surrealdb::RecordId;
+
+use crate::AppState;
+use crate::db::Database;
+use crate::entities::{
+	CreateMatchData, DEFAULT_MATCH_MINUTES, Match, MatchStatus, ReportMatchData, Tournament,
+	WebhookEventKind,
+};
+use crate::middleware::auth::AuthUser;
+use crate::services::{predictions, tournaments, webhooks};
+use crate::utils::error::validation::ValidationErrors;
+use crate::utils::error::{ApiError, ApiResult};
+use crate::utils::metrics;
+
+/// Build a match record id from its key
+pub fn record_id(key: &str) -> RecordId {
+	RecordId::from(("match", key))
+}
+
+/// Fetch a match, failing with `NotFound` when it doesn't exist
+pub async fn find(db: &Database, id: &RecordId) -> ApiResult<Match> {
+	let game: Option<Match> = db.select(id).await?;
+	game.ok_or_else(|| ApiError::not_found("match", &id.key().to_string()))
+}
+
+/// List the matches of a tournament in bracket order
+pub async fn list(db: &Database, tournament: &RecordId) -> ApiResult<Vec<Match>> {
+	let mut response = db
+		.query("SELECT * FROM match WHERE tournament = $tournament ORDER BY round, position")
+		.bind(("tournament", tournament.clone()))
+		.await?;
+
+	Ok(response.take(0)?)
+}
+
+/// Create a match in a tournament
+///
+/// Both sides, when given, must be participants of the tournament: players
+/// of a match get to report and confirm its score.
+pub async fn create(
+	state: &AppState,
+	tournament: &Tournament,
+	data: CreateMatchData,
+) -> ApiResult<Match> {
+	ensure_participants(
+		&state.db,
+		tournament,
+		[("home", &data.home), ("away", &data.away)],
+	)
+	.await?;
+
+	let status = if data.scheduled_at.is_some() {
+		MatchStatus::Scheduled
+	} else {
+		MatchStatus::Pending
+	};
+
+	let mut response = state
+		.db
+		.query(
+			"CREATE match CONTENT {
+				tournament: $tournament,
+				round: $round,
+				position: $position,
+				home: $home,
+				away: $away,
+				status: $status,
+				scheduled_at: <option<datetime>> $scheduled_at,
+				duration_minutes: $duration_minutes,
+			}",
+		)
+		.bind(("tournament", tournament.id.clone()))
+		.bind(("round", data.round))
+		.bind(("position", data.position))
+		.bind(("home", data.home))
+		.bind(("away", data.away))
+		.bind(("status", status))
+		.bind(("scheduled_at", data.scheduled_at.map(|t| t.to_rfc3339())))
+		.bind((
+			"duration_minutes",
+			data.duration_minutes.unwrap_or(DEFAULT_MATCH_MINUTES),
+		))
+		.await?;
+
+	let game: Option<Match> = response.take(0)?;
+	let game = game.ok_or_else(|| ApiError::internal("Failed to create match"))?;
+
+	if game.scheduled_at.is_some() {
+		notify(state, WebhookEventKind::MatchScheduled, &game).await;
+	}
+	Ok(game)
+}
+
+/// Fail with a validation error for every side that isn't in the tournament
+async fn ensure_participants(
+	db: &Database,
+	tournament: &Tournament,
+	sides: [(&str, &Option<RecordId>); 2],
+) -> ApiResult<()> {
+	let given: Vec<RecordId> = sides
+		.iter()
+		.filter_map(|(_, participant)| (*participant).clone())
+		.collect();
+	if given.is_empty() {
+		return Ok(());
+	}
+
+	let mut response = db
+		.query("SELECT VALUE id FROM participant WHERE id IN $ids AND tournament = $tournament")
+		.bind(("ids", given))
+		.bind(("tournament", tournament.id.clone()))
+		.await?;
+	let entered: Vec<RecordId> = response.take(0)?;
+
+	let mut errors = ValidationErrors::new();
+	for (field, participant) in sides {
+		if let Some(participant) = participant
+			&& !entered.contains(participant)
+		{
+			errors.add_error(
+				"Not a participant of this tournament",
+				field,
+				"NOT_A_PARTICIPANT",
+			);
+		}
+	}
+	Ok(errors.into_result()?)
+}
+
+/// User ids of the home and away players
+pub async fn player_users(
+	db: &Database,
+	game: &Match,
+) -> ApiResult<(Option<RecordId>, Option<RecordId>)> {
+	#[derive(Deserialize)]
+	struct Players {
+		home: Option<RecordId>,
+		away: Option<RecordId>,
+	}
+
+	let mut response = db
+		.query("RETURN { home: $home.user_id, away: $away.user_id }")
+		.bind(("home", game.home.clone()))
+		.bind(("away", game.away.clone()))
+		.await?;
+
+	let players: Option<Players> = response.take(0)?;
+	Ok(players.map_or((None, None), |players| (players.home, players.away)))
+}
+
+/// Ensure the user is the organizer or one of the players of the match
+pub async fn ensure_involved(
+	state: &AppState,
+	game: &Match,
+	user: &AuthUser,
+) -> ApiResult<Tournament> {
+	let tournament = tournaments::find(state.tournaments.as_ref(), &game.tournament).await?;
+	if tournament.created_by == user.id {
+		return Ok(tournament);
+	}
+
+	let (home, away) = player_users(&state.db, game).await?;
+	if home.as_ref() == Some(&user.id) || away.as_ref() == Some(&user.id) {
+		return Ok(tournament);
+	}
+
+	Err(ApiError::authorization(
+		"Only the organizer or the players of this match can do this",
+	))
+}
+
+/// Kick off a match
+pub async fn start(state: &AppState, game: &Match) -> ApiResult<Match> {
+	if !matches!(game.status, MatchStatus::Pending | MatchStatus::Scheduled) {
+		return Err(ApiError::conflict("Match has already started"));
+	}
+	if !game.is_ready() {
+		return Err(ApiError::bad_request(
+			"Both participants must be set before the match can start",
+		));
+	}
+
+	let mut response = state
+		.db
+		.query(
+			"UPDATE $id SET status = 'in_progress', started_at = time::now()
+				WHERE status IN ['pending', 'scheduled']",
+		)
+		.bind(("id", game.id.clone()))
+		.await?;
+
+	let game: Option<Match> = response.take(0)?;
+	let game = game.ok_or_else(|| ApiError::conflict("Match has already started"))?;
+
+	notify(state, WebhookEventKind::MatchStarted, &game).await;
+	Ok(game)
+}
+
+/// Report (or correct) the score of a match in progress
+pub async fn report(
+	state: &AppState,
+	game: &Match,
+	data: ReportMatchData,
+	reporter: &RecordId,
+) -> ApiResult<Match> {
+	if !matches!(game.status, MatchStatus::InProgress | MatchStatus::Reported) {
+		return Err(ApiError::conflict(
+			"Scores can only be reported for matches in progress",
+		));
+	}
+
+	let mut response = state
+		.db
+		.query(
+			"UPDATE $id SET
+				status = 'reported',
+				result = {
+					home_score: $home_score,
+					away_score: $away_score,
+					reported_by: $reporter,
+					reported_at: time::now(),
+					confirmed_by: NONE,
+					confirmed_at: NONE,
+				}
+				WHERE status IN ['in_progress', 'reported']",
+		)
+		.bind(("id", game.id.clone()))
+		.bind(("home_score", data.home_score))
+		.bind(("away_score", data.away_score))
+		.bind(("reporter", reporter.clone()))
+		.await?;
+
+	let game: Option<Match> = response.take(0)?;
+	let game = game
+		.ok_or_else(|| ApiError::conflict("Scores can only be reported for matches in progress"))?;
+
+	metrics::match_reported();
+	notify(state, WebhookEventKind::MatchReported, &game).await;
+	Ok(game)
+}
+
+/// Confirm a reported score, making the result final and scoring predictions
+///
+/// Both happen in one transaction: if scoring fails the match stays
+/// `reported` and the confirmation can simply be retried.
+///
+/// The organizer can always confirm; players can only confirm a score that
+/// somebody else reported.
+pub async fn confirm(
+	state: &AppState,
+	game: &Match,
+	user: &AuthUser,
+	tournament: &Tournament,
+) -> ApiResult<Match> {
+	let Some(result) = game
+		.result
+		.as_ref()
+		.filter(|_| game.status == MatchStatus::Reported)
+	else {
+		return Err(ApiError::conflict("Match has no reported score to confirm"));
+	};
+
+	if result.reported_by == user.id && tournament.created_by != user.id {
+		return Err(ApiError::authorization(
+			"The score must be confirmed by the opponent or the organizer",
+		));
+	}
+
+	let scores = predictions::scores(&state.db, &game.id, result).await?;
+	let scored = scores.len();
+
+	// The result is only final together with its prediction scores. Guarded on
+	// the reported score, so a correction reported meanwhile isn't confirmed
+	// with scores computed for the previous one.
+	let mut response = state
+		.db
+		.query(
+			"BEGIN TRANSACTION;
+			LET $confirmed = (UPDATE $id SET
+					status = 'completed',
+					result.confirmed_by = $user,
+					result.confirmed_at = time::now()
+				WHERE status = 'reported'
+					AND result.reported_by = $reporter
+					AND result.home_score = $home_score
+					AND result.away_score = $away_score);
+			IF $confirmed {
+				FOR $score IN $scores {
+					UPDATE $score.id SET points = $score.points;
+				};
+			};
+			RETURN $confirmed[0];
+			COMMIT TRANSACTION;",
+		)
+		.bind(("id", game.id.clone()))
+		.bind(("user", user.id.clone()))
+		.bind(("reporter", result.reported_by.clone()))
+		.bind(("home_score", result.home_score))
+		.bind(("away_score", result.away_score))
+		.bind(("scores", scores))
+		.await?;
+
+	// RETURN ends the transaction with a single result
+	let game: Option<Match> = response.take(0)?;
+	let game = game.ok_or_else(|| ApiError::conflict("Match has no reported score to confirm"))?;
+	log::info!("🎯 Scored {scored} prediction(s) for {}", game.id);
+
+	notify(state, WebhookEventKind::MatchConfirmed, &game).await;
+	Ok(game)
+}
+
+/// Cancel a match that hasn't finished
+///
+/// The match keeps its slot so calendar feeds can mark the event cancelled;
+/// pending reschedule proposals are withdrawn.
+pub async fn cancel(state: &AppState, game: &Match) -> ApiResult<Match> {
+	if matches!(game.status, MatchStatus::Completed | MatchStatus::Cancelled) {
+		return Err(ApiError::conflict("Match is already finished"));
+	}
+
+	let mut response = state
+		.db
+		.query(
+			"BEGIN TRANSACTION;
+			LET $cancelled = (UPDATE $id SET status = 'cancelled', schedule_revision += 1
+				WHERE status NOT IN ['completed', 'cancelled']);
+			IF $cancelled {
+				UPDATE reschedule_proposal SET status = 'withdrawn'
+					WHERE match = $id AND status = 'pending';
+			};
+			RETURN $cancelled[0];
+			COMMIT TRANSACTION;",
+		)
+		.bind(("id", game.id.clone()))
+		.await?;
+
+	let game: Option<Match> = response.take(0)?;
+	let game = game.ok_or_else(|| ApiError::conflict("Match is already finished"))?;
+
+	notify(state, WebhookEventKind::MatchCancelled, &game).await;
+	Ok(game)
+}
+
+/// Emit a match webhook; delivery problems never fail the caller
+pub(crate) async fn notify(state: &AppState, event: WebhookEventKind, game: &Match) {
+	let data = match serde_json::to_value(game) {
+		Ok(data) => data,
+		Err(e) => {
+			log::error!("Failed to serialize {} for webhooks: {e}", game.id);
+			return;
+		}
+	};
+
+	if let Err(e) = webhooks::emit(state, event, &game.tournament, data).await {
+		log::warn!("Failed to emit {} for {}: {e}", event.as_str(), game.id);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::entities::PredictMatchData;
+	use crate::test_utils::{self, factories};
+
+	/// A ready match between two fresh participants
+	async fn ready_match(state: &AppState) -> Match {
+		let organizer = factories::user("organizer").await;
+		let tournament = factories::tournament(&organizer, true).await;
+		let home = factories::participant(&tournament, &factories::user("home").await).await;
+		let away = factories::participant(&tournament, &factories::user("away").await).await;
+		let data = CreateMatchData {
+			round: 1,
+			position: 1,
+			home: Some(home.id),
+			away: Some(away.id),
+			scheduled_at: None,
+			duration_minutes: None,
+		};
+		create(state, &tournament, data).await.unwrap()
+	}
+
+	#[actix_web::test]
+	async fn test_players_must_be_participants_of_the_tournament() {
+		test_utils::setup();
+		let state = test_utils::state();
+		let organizer = factories::user("organizer").await;
+		let tournament = factories::tournament(&organizer, true).await;
+		let other = factories::tournament(&organizer, true).await;
+		let home = factories::participant(&tournament, &factories::user("home").await).await;
+		let outsider = factories::participant(&other, &factories::user("away").await).await;
+		let data = CreateMatchData {
+			round: 1,
+			position: 1,
+			home: Some(home.id),
+			away: Some(outsider.id),
+			scheduled_at: None,
+			duration_minutes: None,
+		};
+
+		let err = create(&state, &tournament, data).await.unwrap_err();
+		let ApiError::ValidationErrors { errors } = err else {
+			panic!("expected validation errors, got {err:?}");
+		};
+		assert_eq!(errors.errors.len(), 1);
+		assert_eq!(errors.errors[0].field.as_deref(), Some("away"));
+	}
+
+	// Each test acts on a stale copy of the match, as the loser of a race
+	// between two requests would
+
+	#[actix_web::test]
+	async fn test_a_match_starts_once() {
+		test_utils::setup();
+		let state = test_utils::state();
+		let stale = ready_match(&state).await;
+
+		start(&state, &stale).await.unwrap();
+		let err = start(&state, &stale).await.unwrap_err();
+		assert!(matches!(err, ApiError::Conflict { .. }));
+	}
+
+	#[actix_web::test]
+	async fn test_a_completed_match_cannot_be_cancelled() {
+		test_utils::setup();
+		let state = test_utils::state();
+		let stale = ready_match(&state).await;
+		state
+			.db
+			.query("UPDATE $id SET status = 'completed'")
+			.bind(("id", stale.id.clone()))
+			.await
+			.unwrap();
+
+		let err = cancel(&state, &stale).await.unwrap_err();
+		assert!(matches!(err, ApiError::Conflict { .. }));
+		assert_eq!(
+			find(&state.db, &stale.id).await.unwrap().status,
+			MatchStatus::Completed
+		);
+	}
+
+	#[actix_web::test]
+	async fn test_predictions_lock_at_kickoff() {
+		test_utils::setup();
+		let state = test_utils::state();
+		let stale = ready_match(&state).await;
+		let viewer = factories::user("viewer").await;
+		let pick = || PredictMatchData {
+			outcome: crate::entities::MatchOutcome::Home,
+			home_score: None,
+			away_score: None,
+		};
+
+		predictions::predict(&state.db, &stale, &viewer.id, pick())
+			.await
+			.unwrap();
+		start(&state, &stale).await.unwrap();
+		let err = predictions::predict(&state.db, &stale, &viewer.id, pick())
+			.await
+			.unwrap_err();
+		assert!(matches!(err, ApiError::Conflict { .. }));
+	}
+}
@@ -0,0 +1,19 @@
+//! Business logic layer for the Liga de los Muertos backend
+//!
+//! Services sit between the HTTP handlers in `routes` and the database,
+//! and hold the rules that should not live in request handlers.
+
+pub mod archive;
+pub mod calendar;
+pub mod importers;
+pub mod jobs;
+pub mod live;
+pub mod matches;
+pub mod participants;
+pub mod polls;
+pub mod predictions;
+pub mod schedule;
+pub mod tournaments;
+pub mod twitch;
+pub mod users;
+pub mod webhooks;
@@ -0,0 +1,509 @@
+//! Tournament participants: registration, lookups and bulk imports
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use surrealdb::RecordId;
+
+use crate::AppState;
+use crate::db::Database;
+use crate::entities::{
+	ImportRowResult, Participant, ParticipantImportReport, ParticipantImportRow, Tournament, User,
+	WebhookEventKind,
+};
+use crate::middleware::locale;
+use crate::repositories::ParticipantRepository;
+use crate::services::webhooks;
+use crate::utils::error::validation::{ValidationError, ValidationErrors, validators};
+use crate::utils::error::{ApiError, ApiResult};
+use crate::utils::logging;
+
+/// Build a participant record id from its key
+pub fn record_id(key: &str) -> RecordId {
+	RecordId::from(("participant", key))
+}
+
+/// Fetch a participant, failing with `NotFound` when it doesn't exist
+pub async fn find(repo: &dyn ParticipantRepository, id: &RecordId) -> ApiResult<Participant> {
+	let participant = repo.find(id).await?;
+	participant.ok_or_else(|| ApiError::not_found("participant", &id.key().to_string()))
+}
+
+/// Participants of a tournament in joining order
+pub async fn list(
+	repo: &dyn ParticipantRepository,
+	tournament: &RecordId,
+) -> ApiResult<Vec<Participant>> {
+	repo.list_by_tournament(tournament).await
+}
+
+/// Register the user in a published tournament
+pub async fn join(
+	state: &AppState,
+	tournament: &Tournament,
+	user: &RecordId,
+) -> ApiResult<Participant> {
+	if !tournament.published {
+		return Err(ApiError::conflict(
+			"This tournament is not open for registration",
+		));
+	}
+
+	let participant = state.participants.add(&tournament.id, user).await?;
+	logging::tournament_event(
+		"participant_joined",
+		&tournament.id.key().to_string(),
+		Some(&user.key().to_string()),
+	);
+	notify(
+		state,
+		WebhookEventKind::ParticipantJoined,
+		&tournament.id,
+		std::slice::from_ref(&participant),
+	)
+	.await;
+	Ok(participant)
+}
+
+/// Withdraw the user from a tournament
+///
+/// Participants already drawn into a match stay, so no match loses a side.
+pub async fn leave(
+	state: &AppState,
+	tournament: &Tournament,
+	user: &RecordId,
+) -> ApiResult<Participant> {
+	let participant = state
+		.participants
+		.find_by_user(&tournament.id, user)
+		.await?
+		.ok_or_else(|| ApiError::not_found("participant", &user.key().to_string()))?;
+
+	let mut response = state
+		.db
+		.query("SELECT VALUE id FROM match WHERE home = $participant OR away = $participant LIMIT 1")
+		.bind(("participant", participant.id.clone()))
+		.await?;
+	let drawn: Vec<RecordId> = response.take(0)?;
+	if !drawn.is_empty() {
+		return Err(ApiError::conflict(
+			"Participants already drawn into a match can't leave the tournament",
+		));
+	}
+
+	state.participants.remove(&participant.id).await?;
+	logging::tournament_event(
+		"participant_left",
+		&tournament.id.key().to_string(),
+		Some(&user.key().to_string()),
+	);
+	notify(
+		state,
+		WebhookEventKind::ParticipantLeft,
+		&tournament.id,
+		std::slice::from_ref(&participant),
+	)
+	.await;
+	Ok(participant)
+}
+
+/// Largest number of rows accepted by a single import
+pub const MAX_IMPORT_ROWS: usize = 1000;
+
+/// Format of a participant import payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+	Csv,
+	Json,
+}
+
+impl ImportFormat {
+	/// Pick the format from a request's content type
+	pub fn from_content_type(content_type: &str) -> Option<Self> {
+		let essence = content_type.split(';').next()?.trim();
+		match essence.to_ascii_lowercase().as_str() {
+			"text/csv" | "application/csv" => Some(ImportFormat::Csv),
+			"application/json" => Some(ImportFormat::Json),
+			_ => None,
+		}
+	}
+}
+
+/// Parse an import payload into rows
+///
+/// CSV needs a header row with a `username` and/or `email` column; other
+/// columns are ignored. JSON is either an array of rows or an object with a
+/// `participants` array.
+pub fn parse_rows(format: ImportFormat, body: &[u8]) -> ApiResult<Vec<ParticipantImportRow>> {
+	let rows = match format {
+		ImportFormat::Csv => parse_csv(body)?,
+		ImportFormat::Json => {
+			#[derive(Deserialize)]
+			#[serde(untagged)]
+			enum Payload {
+				Rows(Vec<ParticipantImportRow>),
+				Wrapped {
+					participants: Vec<ParticipantImportRow>,
+				},
+			}
+
+			match serde_json::from_slice(body)? {
+				Payload::Rows(rows) | Payload::Wrapped { participants: rows } => rows,
+			}
+		}
+	};
+
+	if rows.is_empty() {
+		return Err(ApiError::bad_request("The import contains no rows"));
+	}
+	if rows.len() > MAX_IMPORT_ROWS {
+		return Err(ApiError::bad_request(&format!(
+			"Imports are limited to {MAX_IMPORT_ROWS} rows"
+		)));
+	}
+	Ok(rows)
+}
+
+fn parse_csv(body: &[u8]) -> ApiResult<Vec<ParticipantImportRow>> {
+	let mut reader = csv::ReaderBuilder::new()
+		.trim(csv::Trim::All)
+		.flexible(true)
+		.from_reader(body);
+
+	let headers = reader
+		.headers()
+		.map_err(|e| ApiError::bad_request(&format!("Invalid CSV header: {e}")))?
+		.clone();
+	let column = |name: &str| {
+		headers
+			.iter()
+			.position(|header| header.eq_ignore_ascii_case(name))
+	};
+	let (username, email) = (column("username"), column("email"));
+	if username.is_none() && email.is_none() {
+		return Err(ApiError::bad_request(
+			"The CSV header must have a username or an email column",
+		));
+	}
+
+	let mut rows = Vec::new();
+	for (index, record) in reader.records().enumerate() {
+		let record = record
+			.map_err(|e| ApiError::bad_request(&format!("Invalid CSV at data row {}: {e}", index + 1)))?;
+		let cell = |position: Option<usize>| {
+			position
+				.and_then(|position| record.get(position))
+				.filter(|value| !value.is_empty())
+				.map(str::to_string)
+		};
+		rows.push(ParticipantImportRow {
+			username: cell(username),
+			email: cell(email),
+		});
+	}
+	Ok(rows)
+}
+
+/// Validate rows and match them to users
+///
+/// `registered` holds the users already taking part in the tournament.
+pub fn resolve_rows(
+	rows: &[ParticipantImportRow],
+	users: &[User],
+	registered: &[RecordId],
+) -> Vec<ImportRowResult> {
+	let mut seen: HashMap<String, usize> = HashMap::new();
+
+	rows
+		.iter()
+		.enumerate()
+		.map(|(index, row)| {
+			let number = index + 1;
+			let username = row
+				.username
+				.as_deref()
+				.map(str::trim)
+				.filter(|v| !v.is_empty());
+			let email = row
+				.email
+				.as_deref()
+				.map(str::trim)
+				.filter(|v| !v.is_empty());
+			let mut errors = ValidationErrors::new();
+
+			if username.is_none() && email.is_none() {
+				errors.add_error(
+					"Each row needs a username or an email",
+					"username",
+					"USERNAME_OR_EMAIL",
+				);
+			}
+			if let Some(username) = username
+				&& let Err(error) = validators::username(username, "username")
+			{
+				errors.add(error);
+			}
+			if let Some(email) = email
+				&& let Err(error) = validators::email(email, "email")
+			{
+				errors.add(error);
+			}
+
+			let mut user = None;
+			if !errors.has_errors() {
+				let by_username = username.and_then(|username| {
+					users
+						.iter()
+						.find(|user| user.username.eq_ignore_ascii_case(username))
+				});
+				let by_email = email.and_then(|email| {
+					users
+						.iter()
+						.find(|user| user.email.eq_ignore_ascii_case(email))
+				});
+
+				match (by_username, by_email) {
+					(Some(a), Some(b)) if a.id != b.id => errors.add_error(
+						"The username and the email belong to different users",
+						"email",
+						"USER_MISMATCH",
+					),
+					(Some(found), _) | (None, Some(found)) => user = Some(found.id.clone()),
+					(None, None) => errors.add_error(
+						"No user matches this row",
+						if username.is_some() {
+							"username"
+						} else {
+							"email"
+						},
+						"USER_NOT_FOUND",
+					),
+				}
+			}
+
+			if let Some(id) = &user {
+				if registered.contains(id) {
+					errors.add_error(
+						"This user is already registered in the tournament",
+						"user",
+						"ALREADY_PARTICIPANT",
+					);
+				} else if let Some(first) = seen.get(&id.to_string()) {
+					errors.add(
+						ValidationError::with_field(
+							&format!("Same user as row {first}"),
+							"user",
+							"DUPLICATE_ROW",
+						)
+						.with_param("row", *first),
+					);
+				} else {
+					seen.insert(id.to_string(), number);
+				}
+			}
+
+			ImportRowResult {
+				row: number,
+				username: username.map(str::to_string),
+				email: email.map(str::to_string),
+				user,
+				errors: errors.localized_field_map(locale::current()),
+			}
+		})
+		.collect()
+}
+
+/// Users whose username or email appears in the rows
+pub async fn lookup_users(db: &Database, rows: &[ParticipantImportRow]) -> ApiResult<Vec<User>> {
+	let lowercase = |values: Vec<Option<&String>>| -> Vec<String> {
+		values
+			.into_iter()
+			.flatten()
+			.map(|value| value.trim().to_lowercase())
+			.collect()
+	};
+	let usernames = lowercase(rows.iter().map(|row| row.username.as_ref()).collect());
+	let emails = lowercase(rows.iter().map(|row| row.email.as_ref()).collect());
+
+	let mut response = db
+		.query(
+			"SELECT * FROM user
+				WHERE string::lowercase(username) IN $usernames
+					OR string::lowercase(email) IN $emails",
+		)
+		.bind(("usernames", usernames))
+		.bind(("emails", emails))
+		.await?;
+
+	Ok(response.take(0)?)
+}
+
+/// Import participants into a tournament
+///
+/// Nothing is written on dry runs or when any row is invalid; otherwise all
+/// participants are created by a single statement, then announced to the
+/// tournament's webhooks as `participant.joined`.
+pub async fn import(
+	state: &AppState,
+	tournament: &Tournament,
+	rows: &[ParticipantImportRow],
+	dry_run: bool,
+) -> ApiResult<ParticipantImportReport> {
+	let users = lookup_users(&state.db, rows).await?;
+	let registered: Vec<RecordId> = list(state.participants.as_ref(), &tournament.id)
+		.await?
+		.into_iter()
+		.map(|participant| participant.user_id)
+		.collect();
+	let mut report = ParticipantImportReport::new(resolve_rows(rows, &users, &registered), dry_run);
+
+	if dry_run || report.has_errors() {
+		return Ok(report);
+	}
+
+	#[derive(Serialize)]
+	struct NewParticipant {
+		tournament: RecordId,
+		user_id: RecordId,
+	}
+
+	let participants: Vec<NewParticipant> = report
+		.rows
+		.iter()
+		.filter_map(|row| row.user.clone())
+		.map(|user_id| NewParticipant {
+			tournament: tournament.id.clone(),
+			user_id,
+		})
+		.collect();
+	let mut response = state
+		.db
+		.query("INSERT INTO participant $participants")
+		.bind(("participants", participants))
+		.await?;
+	let created: Vec<Participant> = response.take(0)?;
+
+	report.committed = true;
+	logging::tournament_event(
+		"participants_imported",
+		&tournament.id.key().to_string(),
+		None,
+	);
+	notify(
+		state,
+		WebhookEventKind::ParticipantJoined,
+		&tournament.id,
+		&created,
+	)
+	.await;
+	Ok(report)
+}
+
+/// Emit a participant webhook for each participant in one batch
+///
+/// Delivery problems never fail the caller.
+async fn notify(
+	state: &AppState,
+	event: WebhookEventKind,
+	tournament: &RecordId,
+	participants: &[Participant],
+) {
+	let data = match participants
+		.iter()
+		.map(serde_json::to_value)
+		.collect::<Result<Vec<_>, _>>()
+	{
+		Ok(data) => data,
+		Err(e) => {
+			log::error!("Failed to serialize participants of {tournament} for webhooks: {e}");
+			return;
+		}
+	};
+
+	if let Err(e) = webhooks::emit_all(state, event, tournament, data).await {
+		log::warn!("Failed to emit {} for {tournament}: {e}", event.as_str());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::Utc;
+
+	fn user(key: &str, email: &str) -> User {
+		User {
+			id: RecordId::from(("user", key)),
+			username: key.to_string(),
+			email: email.to_string(),
+			timezone: None,
+			created_at: Utc::now(),
+			updated_at: Utc::now(),
+		}
+	}
+
+	fn row(username: Option<&str>, email: Option<&str>) -> ParticipantImportRow {
+		ParticipantImportRow {
+			username: username.map(str::to_string),
+			email: email.map(str::to_string),
+		}
+	}
+
+	#[test]
+	fn test_parse_csv_and_json() {
+		let csv = b"Email, Username, team\nana@example.com, ana, Calacas\n, beto,\n";
+		let rows = parse_rows(ImportFormat::Csv, csv).unwrap();
+		assert_eq!(
+			rows,
+			vec![
+				row(Some("ana"), Some("ana@example.com")),
+				row(Some("beto"), None)
+			]
+		);
+
+		let json = br#"{"participants": [{"email": "caro@example.com"}]}"#;
+		let rows = parse_rows(ImportFormat::Json, json).unwrap();
+		assert_eq!(rows, vec![row(None, Some("caro@example.com"))]);
+
+		assert!(parse_rows(ImportFormat::Csv, b"team\nCalacas\n").is_err());
+		assert!(parse_rows(ImportFormat::Json, b"[]").is_err());
+		assert_eq!(
+			ImportFormat::from_content_type("text/csv; charset=utf-8"),
+			Some(ImportFormat::Csv)
+		);
+	}
+
+	#[test]
+	fn test_resolve_rows_reports_errors_per_field() {
+		let users = [
+			user("ana", "ana@example.com"),
+			user("beto", "beto@example.com"),
+			user("caro", "caro@example.com"),
+		];
+		let registered = [RecordId::from(("user", "caro"))];
+		let rows = [
+			row(Some("ANA"), None),
+			row(None, Some("beto@example.com")),
+			row(Some("ana"), Some("beto@example.com")),
+			row(Some("ana"), None),
+			row(Some("caro"), None),
+			row(Some("nadie"), None),
+			row(None, Some("not-an-email")),
+			row(None, None),
+		];
+
+		let results = resolve_rows(&rows, &users, &registered);
+		assert!(results[0].is_valid());
+		assert_eq!(results[0].user, Some(RecordId::from(("user", "ana"))));
+		assert!(results[1].is_valid());
+		assert!(results[2].errors.contains_key("email"));
+		assert_eq!(results[3].errors["user"], vec!["Same user as row 1"]);
+		assert!(results[4].errors.contains_key("user"));
+		assert!(results[5].errors.contains_key("username"));
+		assert!(results[6].errors.contains_key("email"));
+		assert!(results[7].errors.contains_key("username"));
+
+		let report = ParticipantImportReport::new(results, true);
+		assert_eq!((report.valid, report.invalid), (2, 6));
+		assert!(report.has_errors());
+	}
+}
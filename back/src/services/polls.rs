@@ -0,0 +1,442 @@
+//! Live polls run by organizers during a broadcast
+//!
+//! Polls belong to a tournament and may be about a specific match. Each user
+//! casts a single ballot per poll (enforced by a unique index on
+//! `poll_vote`); the ballot bumps the poll's per-option counters in the same
+//! transaction. [`watch`] follows the `poll` table with a LIVE query and
+//! pushes every change to the poll's live topic, so viewers connected to any
+//! instance see each vote. When a poll closes its final tally is archived on
+//! the poll record, so the tournament keeps a history of its polls. Polls
+//! closed by their close time are archived by [`archive_due`], run
+//! periodically on the job queue.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use futures_util::StreamExt;
+use surrealdb::{Action, RecordId};
+
+use crate::db::{self, Database};
+use crate::entities::{CreatePollData, Poll, PollStatus, PollView, PollVote, Tournament, VoteData};
+use crate::services::live::{self, LiveHub};
+use crate::services::matches;
+use crate::supervisor::Supervisor;
+use crate::utils::error::{ApiError, ApiResult};
+
+/// How often polls past their close time are archived
+pub const ARCHIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Build a poll record id from its key
+pub fn record_id(key: &str) -> RecordId {
+	RecordId::from(("poll", key))
+}
+
+/// Fetch a poll, failing with `NotFound` when it doesn't exist
+pub async fn find(db: &Database, id: &RecordId) -> ApiResult<Poll> {
+	let poll: Option<Poll> = db.select(id).await?;
+	poll.ok_or_else(|| ApiError::not_found("poll", &id.key().to_string()))
+}
+
+/// List the polls of a tournament, newest first
+///
+/// With `archived_only` only closed polls (with their final results) are
+/// returned.
+pub async fn list(
+	db: &Database,
+	tournament: &RecordId,
+	archived_only: bool,
+) -> ApiResult<Vec<Poll>> {
+	let filter = if archived_only {
+		"AND results != NONE"
+	} else {
+		""
+	};
+	let mut response = db
+		.query(format!(
+			"SELECT * FROM poll WHERE tournament = $tournament {filter} ORDER BY created_at DESC"
+		))
+		.bind(("tournament", tournament.clone()))
+		.await?;
+
+	Ok(response.take(0)?)
+}
+
+/// Create a poll in a tournament
+pub async fn create(
+	db: &Database,
+	tournament: &Tournament,
+	created_by: &RecordId,
+	data: CreatePollData,
+) -> ApiResult<Poll> {
+	let question = data.question.trim();
+	let options: Vec<String> = data
+		.options
+		.iter()
+		.map(|option| option.trim().to_string())
+		.collect();
+
+	let game = match data.r#match.as_deref() {
+		Some(key) => {
+			let game = matches::find(db, &matches::record_id(key)).await?;
+			if game.tournament != tournament.id {
+				return Err(ApiError::validation_with_field(
+					"The match belongs to another tournament",
+					"match",
+				));
+			}
+			Some(game.id)
+		}
+		None => None,
+	};
+
+	let mut response = db
+		.query(
+			"CREATE poll CONTENT {
+				tournament: $tournament,
+				match: $match,
+				question: $question,
+				kind: $kind,
+				options: $options,
+				opens_at: <option<datetime>> $opens_at,
+				closes_at: <option<datetime>> $closes_at,
+				created_by: $created_by,
+				votes: $votes,
+				voters: 0,
+			}",
+		)
+		.bind(("tournament", tournament.id.clone()))
+		.bind(("match", game))
+		.bind(("question", question.to_string()))
+		.bind(("kind", data.kind))
+		.bind(("votes", vec![0u32; options.len()]))
+		.bind(("options", options))
+		.bind(("opens_at", data.opens_at.map(|t| t.to_rfc3339())))
+		.bind(("closes_at", data.closes_at.map(|t| t.to_rfc3339())))
+		.bind(("created_by", created_by.clone()))
+		.await?;
+
+	let poll: Option<Poll> = response.take(0)?;
+	poll.ok_or_else(|| ApiError::internal("Failed to create poll"))
+}
+
+/// Poll with its current status and tally
+pub fn view(poll: Poll) -> PollView {
+	PollView {
+		status: poll.status(Utc::now()),
+		tally: poll.tally(),
+		poll,
+	}
+}
+
+/// The user's ballot in a poll, if any
+pub async fn find_vote(
+	db: &Database,
+	poll: &RecordId,
+	user: &RecordId,
+) -> ApiResult<Option<PollVote>> {
+	let mut response = db
+		.query("SELECT * FROM poll_vote WHERE poll = $poll AND user = $user LIMIT 1")
+		.bind(("poll", poll.clone()))
+		.bind(("user", user.clone()))
+		.await?;
+
+	Ok(response.take(0)?)
+}
+
+/// Cast the user's ballot, returning the new tally
+pub async fn vote(
+	db: &Database,
+	poll: &Poll,
+	user: &RecordId,
+	data: VoteData,
+) -> ApiResult<PollView> {
+	match poll.status(Utc::now()) {
+		PollStatus::Scheduled => return Err(ApiError::conflict("This poll is not open yet")),
+		PollStatus::Closed => return Err(ApiError::conflict("This poll is closed")),
+		PollStatus::Open => {}
+	}
+	poll
+		.check_choices(&data.choices)
+		.map_err(|message| ApiError::validation_with_field(message, "choices"))?;
+
+	if find_vote(db, &poll.id, user).await?.is_some() {
+		return Err(ApiError::conflict("You have already voted in this poll"));
+	}
+
+	// All counters move in one UPDATE, so live subscribers never see a ballot
+	// half counted; the choices are option indexes checked above
+	let counters: String = data
+		.choices
+		.iter()
+		.map(|choice| format!("votes[{choice}] += 1, "))
+		.collect();
+
+	// The unique index on poll_vote rolls the counters back on a second
+	// ballot, and the ballot is rolled back if the poll closed meanwhile
+	let response = db
+		.query(format!(
+			"BEGIN TRANSACTION;
+			CREATE poll_vote CONTENT {{ poll: $poll, user: $user, choices: $choices }};
+			LET $counted = (UPDATE $poll SET {counters}voters += 1
+				WHERE results = NONE AND (closes_at = NONE OR closes_at > time::now()));
+			IF !$counted {{ THROW 'This poll is closed' }};
+			RETURN $counted[0];
+			COMMIT TRANSACTION;"
+		))
+		.bind(("poll", poll.id.clone()))
+		.bind(("user", user.clone()))
+		.bind(("choices", data.choices))
+		.await?;
+
+	let poll: Option<Poll> = db::check_transaction(response)?.take(0)?;
+	let poll = poll.ok_or_else(|| ApiError::internal("Failed to record vote"))?;
+
+	Ok(view(poll))
+}
+
+/// Close a poll now and archive its final tally
+pub async fn close(db: &Database, poll: &Poll) -> ApiResult<PollView> {
+	let closed = || ApiError::conflict("This poll is already closed");
+	if poll.results.is_some() {
+		return Err(closed());
+	}
+
+	let poll = archive(db, poll).await?.ok_or_else(closed)?;
+	Ok(view(poll))
+}
+
+/// Archive every poll whose close time has passed, returning how many
+pub async fn archive_due(db: &Database) -> ApiResult<usize> {
+	let mut response = db
+		.query(
+			"SELECT * FROM poll WHERE results = NONE AND closes_at != NONE AND closes_at <= time::now()",
+		)
+		.await?;
+	let due: Vec<Poll> = response.take(0)?;
+
+	let mut archived = 0;
+	for poll in &due {
+		if archive(db, poll).await?.is_some() {
+			archived += 1;
+		}
+	}
+	Ok(archived)
+}
+
+/// Store the final tally on the poll
+///
+/// `None` when the poll was archived meanwhile (e.g. closed by hand while
+/// the periodic archiving ran).
+async fn archive(db: &Database, poll: &Poll) -> ApiResult<Option<Poll>> {
+	let mut response = db
+		.query(
+			"UPDATE $id SET
+				results = $results,
+				closes_at = IF closes_at = NONE OR closes_at > time::now() {
+					time::now()
+				} ELSE {
+					closes_at
+				}
+			WHERE results = NONE",
+		)
+		.bind(("id", poll.id.clone()))
+		.bind(("results", poll.tally()))
+		.await?;
+
+	Ok(response.take(0)?)
+}
+
+/// Push every poll change to its live topic
+///
+/// The LIVE query is registered with the connection supervisor, which
+/// restarts it after the database comes back from an outage.
+pub async fn watch(supervisor: &Supervisor, hub: Arc<LiveHub>) -> ApiResult<()> {
+	supervisor
+		.register_live("poll_updates", move |db| {
+			let hub = hub.clone();
+			Box::pin(async move {
+				let mut updates = db.select::<Vec<Poll>>("poll").live().await?;
+				actix_web::rt::spawn(async move {
+					while let Some(update) = updates.next().await {
+						match update {
+							Ok(update) if matches!(update.action, Action::Update) => announce(&hub, update.data),
+							Ok(_) => {}
+							Err(e) => log::error!("Unreadable poll update: {e}"),
+						}
+					}
+				});
+				Ok(())
+			})
+		})
+		.await
+}
+
+/// Publish a changed poll: `poll.closed` once archived, `poll.tally` before
+fn announce(hub: &LiveHub, poll: Poll) {
+	let event = if poll.results.is_some() {
+		"poll.closed"
+	} else {
+		"poll.tally"
+	};
+	let view = view(poll);
+	match live::LiveEvent::new(event, &view) {
+		Ok(message) => {
+			hub.publish(&live::poll_topic(&view.poll.id), message);
+		}
+		Err(e) => log::error!("Failed to serialize {} for live update: {e}", view.poll.id),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::DB;
+	use crate::config::DatabaseConfig;
+	use crate::entities::PollKind;
+	use crate::test_utils::{self, factories};
+	use tokio::sync::broadcast;
+
+	async fn poll(db: &Database, closes_at: Option<chrono::DateTime<Utc>>) -> Poll {
+		let organizer = factories::user("organizer").await;
+		let tournament = factories::tournament(&organizer, true).await;
+		let data = CreatePollData {
+			question: "Who takes the series?".to_string(),
+			kind: PollKind::Multiple,
+			options: vec!["Catrina".to_string(), "Calaca".to_string()],
+			r#match: None,
+			opens_at: None,
+			closes_at,
+		};
+		create(db, &tournament, &organizer.id, data).await.unwrap()
+	}
+
+	#[actix_web::test]
+	async fn test_votes_update_the_counters() {
+		test_utils::setup();
+		let state = test_utils::state();
+		let db = &state.db;
+		let poll = poll(db, None).await;
+		let catrina = factories::user("catrina").await;
+		let calaca = factories::user("calaca").await;
+
+		vote(
+			db,
+			&poll,
+			&catrina.id,
+			VoteData {
+				choices: vec![0, 1],
+			},
+		)
+		.await
+		.unwrap();
+		let view = vote(db, &poll, &calaca.id, VoteData { choices: vec![1] })
+			.await
+			.unwrap();
+		assert_eq!(view.tally.voters, 2);
+		let votes: Vec<u32> = view
+			.tally
+			.options
+			.iter()
+			.map(|option| option.votes)
+			.collect();
+		assert_eq!(votes, vec![1, 2]);
+
+		// A second ballot is rejected and leaves the counters alone
+		let err = vote(db, &poll, &calaca.id, VoteData { choices: vec![0] })
+			.await
+			.unwrap_err();
+		assert!(matches!(err, ApiError::Conflict { .. }), "got {err:?}");
+		assert_eq!(find(db, &poll.id).await.unwrap().votes, vec![1, 2]);
+	}
+
+	#[actix_web::test]
+	async fn test_a_ballot_cast_on_a_closed_poll_is_rolled_back() {
+		test_utils::setup();
+		let state = test_utils::state();
+		let db = &state.db;
+		// Still open in this copy, as in a vote racing the organizer closing it
+		let stale = poll(db, None).await;
+		close(db, &stale).await.unwrap();
+		let late = factories::user("late").await;
+
+		let err = vote(db, &stale, &late.id, VoteData { choices: vec![0] })
+			.await
+			.unwrap_err();
+		assert!(matches!(err, ApiError::Conflict { .. }), "got {err:?}");
+		assert!(find_vote(db, &stale.id, &late.id).await.unwrap().is_none());
+		assert_eq!(find(db, &stale.id).await.unwrap().voters, 0);
+	}
+
+	#[actix_web::test]
+	async fn test_archive_due_archives_polls_past_their_close_time() {
+		test_utils::setup();
+		let state = test_utils::state();
+		let db = &state.db;
+		let open = poll(db, None).await;
+		let due = poll(db, Some(Utc::now() + chrono::Duration::milliseconds(200))).await;
+		vote(
+			db,
+			&due,
+			&factories::user("catrina").await.id,
+			VoteData { choices: vec![1] },
+		)
+		.await
+		.unwrap();
+		tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+
+		// Reading a closed poll does not archive it
+		let shown = view(find(db, &due.id).await.unwrap());
+		assert_eq!(shown.status, PollStatus::Closed);
+		assert!(shown.poll.results.is_none());
+
+		assert!(archive_due(db).await.unwrap() >= 1);
+		let archived = find(db, &due.id).await.unwrap().results.unwrap();
+		assert_eq!(archived.voters, 1);
+		assert_eq!(archived.options[1].votes, 1);
+		assert!(find(db, &open.id).await.unwrap().results.is_none());
+
+		let err = close(db, &find(db, &due.id).await.unwrap())
+			.await
+			.unwrap_err();
+		assert!(matches!(err, ApiError::Conflict { .. }), "got {err:?}");
+	}
+
+	async fn next_event(updates: &mut broadcast::Receiver<live::LiveEvent>) -> live::LiveEvent {
+		tokio::time::timeout(std::time::Duration::from_secs(5), updates.recv())
+			.await
+			.expect("no live update")
+			.unwrap()
+	}
+
+	#[actix_web::test]
+	async fn test_watch_pushes_votes_and_closing_to_the_poll_topic() {
+		test_utils::setup();
+		let state = test_utils::state();
+		let db = &state.db;
+		let supervisor = Supervisor::new(
+			DB.clone(),
+			DatabaseConfig {
+				url: "mem://".to_string(),
+				namespace: "test".to_string(),
+				database: "test".to_string(),
+				..DatabaseConfig::default()
+			},
+		);
+		supervisor.connect().await.unwrap();
+		watch(&supervisor, state.live.clone()).await.unwrap();
+
+		let poll = poll(db, None).await;
+		let mut updates = state.live.subscribe(&live::poll_topic(&poll.id));
+		let catrina = factories::user("catrina").await;
+		vote(db, &poll, &catrina.id, VoteData { choices: vec![1] })
+			.await
+			.unwrap();
+		let tally = next_event(&mut updates).await;
+		assert_eq!(tally.event, "poll.tally");
+		assert_eq!(tally.data["tally"]["voters"], 1);
+
+		close(db, &find(db, &poll.id).await.unwrap()).await.unwrap();
+		assert_eq!(next_event(&mut updates).await.event, "poll.closed");
+	}
+}
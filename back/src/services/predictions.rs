@@ -0,0 +1,224 @@
+//! Viewer match predictions and prediction leaderboards
+//!
+//! Viewers pick a winner (optionally an exact score) until kickoff. Each user
+//! has one prediction per match, stored under the id `prediction:[match, user]`
+//! so re-submitting simply replaces it. When a result is confirmed every
+//! prediction of the match is scored (see [`crate::entities::score_prediction`])
+//! in the same transaction.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use surrealdb::RecordId;
+
+use crate::db::Database;
+use crate::entities::{
+	EXACT_SCORE_BONUS, Match, MatchOutcome, MatchResult, OUTCOME_POINTS, PredictMatchData,
+	Prediction, PredictionStanding, rank_standings,
+};
+use crate::utils::error::{ApiError, ApiResult};
+
+/// Which predictions a leaderboard aggregates
+#[derive(Debug, Clone)]
+pub enum LeaderboardScope {
+	Tournament(RecordId),
+	Season(String),
+}
+
+/// How the viewers' picks for a match are split
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PredictionDistribution {
+	pub home: u32,
+	pub away: u32,
+	pub draw: u32,
+	pub total: u32,
+}
+
+/// Create or replace the user's prediction for a match
+pub async fn predict(
+	db: &Database,
+	game: &Match,
+	user: &RecordId,
+	data: PredictMatchData,
+) -> ApiResult<Prediction> {
+	if game.has_started(Utc::now()) {
+		return Err(ApiError::conflict(
+			"Predictions are locked once the match has started",
+		));
+	}
+	if !game.is_ready() {
+		return Err(ApiError::bad_request(
+			"Predictions open once both participants are known",
+		));
+	}
+
+	// Checked again against the stored match, so a prediction can't slip in
+	// while the match is being started
+	let mut response = db
+		.query(
+			"BEGIN TRANSACTION;
+			LET $open = $match.status IN ['pending', 'scheduled']
+				AND ($match.scheduled_at = NONE OR $match.scheduled_at > time::now());
+			RETURN IF $open THEN (
+				UPSERT ONLY type::thing('prediction', [$match, $user]) MERGE {
+					match: $match,
+					tournament: $tournament,
+					user: $user,
+					outcome: $outcome,
+					home_score: $home_score,
+					away_score: $away_score,
+				}
+			) END;
+			COMMIT TRANSACTION;",
+		)
+		.bind(("match", game.id.clone()))
+		.bind(("tournament", game.tournament.clone()))
+		.bind(("user", user.clone()))
+		.bind(("outcome", data.outcome))
+		.bind(("home_score", data.home_score))
+		.bind(("away_score", data.away_score))
+		.await?;
+
+	let prediction: Option<Prediction> = response.take(0)?;
+	prediction.ok_or_else(|| ApiError::conflict("Predictions are locked once the match has started"))
+}
+
+/// The user's prediction for a match, if any
+pub async fn find_for_user(
+	db: &Database,
+	game: &RecordId,
+	user: &RecordId,
+) -> ApiResult<Option<Prediction>> {
+	let mut response = db
+		.query("SELECT * FROM prediction WHERE match = $match AND user = $user LIMIT 1")
+		.bind(("match", game.clone()))
+		.bind(("user", user.clone()))
+		.await?;
+
+	let prediction: Option<Prediction> = response.take(0)?;
+	Ok(prediction)
+}
+
+/// Split of the viewers' picks for a match
+pub async fn distribution(db: &Database, game: &RecordId) -> ApiResult<PredictionDistribution> {
+	#[derive(Deserialize)]
+	struct OutcomeCount {
+		outcome: MatchOutcome,
+		count: u32,
+	}
+
+	let mut response = db
+		.query(
+			"SELECT outcome, count() AS count FROM prediction
+				WHERE match = $match
+				GROUP BY outcome",
+		)
+		.bind(("match", game.clone()))
+		.await?;
+
+	let counts: Vec<OutcomeCount> = response.take(0)?;
+	let mut split = PredictionDistribution::default();
+	for row in counts {
+		match row.outcome {
+			MatchOutcome::Home => split.home = row.count,
+			MatchOutcome::Away => split.away = row.count,
+			MatchOutcome::Draw => split.draw = row.count,
+		}
+		split.total += row.count;
+	}
+
+	Ok(split)
+}
+
+/// Points earned by a prediction
+#[derive(Debug, Clone, Serialize)]
+pub struct PredictionScore {
+	pub id: RecordId,
+	pub points: u32,
+}
+
+/// Score every prediction of a match against a result
+///
+/// Nothing is written; the scores are stored by [`crate::services::matches::confirm`]
+/// in the same transaction that makes the result final. Predictions are
+/// locked by then, so the set scored here is the set that counts.
+pub async fn scores(
+	db: &Database,
+	game: &RecordId,
+	result: &MatchResult,
+) -> ApiResult<Vec<PredictionScore>> {
+	let mut response = db
+		.query("SELECT * FROM prediction WHERE match = $match")
+		.bind(("match", game.clone()))
+		.await?;
+	let predictions: Vec<Prediction> = response.take(0)?;
+
+	Ok(
+		predictions
+			.iter()
+			.map(|prediction| PredictionScore {
+				id: prediction.id.clone(),
+				points: prediction.score(result),
+			})
+			.collect(),
+	)
+}
+
+/// Ranked prediction leaderboard for a tournament or a season
+pub async fn leaderboard(
+	db: &Database,
+	scope: LeaderboardScope,
+) -> ApiResult<Vec<PredictionStanding>> {
+	#[derive(Deserialize)]
+	struct Row {
+		user: RecordId,
+		username: Option<String>,
+		points: u32,
+		predictions: u32,
+		correct: u32,
+		exact: u32,
+	}
+
+	let sql = |filter: &str| {
+		format!(
+			"SELECT
+				user,
+				user.username AS username,
+				math::sum(points) AS points,
+				count() AS predictions,
+				count(points > 0) AS correct,
+				count(points = $exact_points) AS exact
+			FROM prediction
+			WHERE points != NONE AND {filter}
+			GROUP BY user, username"
+		)
+	};
+
+	let query = match scope {
+		LeaderboardScope::Tournament(tournament) => db
+			.query(sql("tournament = $scope"))
+			.bind(("scope", tournament)),
+		LeaderboardScope::Season(season) => db
+			.query(sql("tournament.season = $scope"))
+			.bind(("scope", season)),
+	};
+
+	let mut response = query
+		.bind(("exact_points", OUTCOME_POINTS + EXACT_SCORE_BONUS))
+		.await?;
+	let rows: Vec<Row> = response.take(0)?;
+
+	let standings = rows
+		.into_iter()
+		.map(|row| PredictionStanding {
+			rank: 0,
+			user: row.user,
+			username: row.username,
+			points: row.points,
+			predictions: row.predictions,
+			correct: row.correct,
+			exact: row.exact,
+		})
+		.collect();
+
+	Ok(rank_standings(standings))
+}
@@ -0,0 +1,590 @@
+//! Match scheduling: availability windows, slot planning, conflict detection
+//! and reschedule proposals
+//!
+//! Times are stored in UTC. A player can't be booked in two overlapping
+//! matches, across every tournament they play in. Players may propose a new
+//! kickoff time, which takes effect once their opponent accepts it.
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use surrealdb::RecordId;
+
+use crate::AppState;
+use crate::db::{self, Database};
+use crate::entities::{
+	AutoScheduleData, AvailabilityWindow, BookedSlot, CreateAvailabilityData, MAX_SLOT_MINUTES,
+	MIN_SLOT_MINUTES, Match, MatchStatus, ProposeRescheduleData, RescheduleProposal,
+	RescheduleStatus, ScheduleConflict, ScheduleMatchData, SchedulePlan, ScheduledMatch,
+	SchedulingItem, Tournament, WebhookEventKind, find_conflicts, plan_schedule,
+};
+use crate::services::matches;
+use crate::utils::error::{ApiError, ApiResult, validation::validators};
+use crate::utils::time::{self, TimeSlot};
+
+/// Build an availability window record id from its key
+pub fn window_record_id(key: &str) -> RecordId {
+	RecordId::from(("availability", key))
+}
+
+/// Build a reschedule proposal record id from its key
+pub fn proposal_record_id(key: &str) -> RecordId {
+	RecordId::from(("reschedule_proposal", key))
+}
+
+/// List the organizer's availability windows, earliest first
+pub async fn list_windows(
+	db: &Database,
+	tournament: &RecordId,
+) -> ApiResult<Vec<AvailabilityWindow>> {
+	let mut response = db
+		.query("SELECT * FROM availability WHERE tournament = $tournament ORDER BY starts_at")
+		.bind(("tournament", tournament.clone()))
+		.await?;
+
+	Ok(response.take(0)?)
+}
+
+/// Add an availability window to a tournament
+pub async fn add_window(
+	db: &Database,
+	tournament: &Tournament,
+	data: CreateAvailabilityData,
+) -> ApiResult<AvailabilityWindow> {
+	let mut response = db
+		.query(
+			"CREATE availability CONTENT {
+				tournament: $tournament,
+				starts_at: <datetime> $starts_at,
+				ends_at: <datetime> $ends_at,
+			}",
+		)
+		.bind(("tournament", tournament.id.clone()))
+		.bind(("starts_at", data.starts_at.to_rfc3339()))
+		.bind(("ends_at", data.ends_at.to_rfc3339()))
+		.await?;
+
+	let window: Option<AvailabilityWindow> = response.take(0)?;
+	window.ok_or_else(|| ApiError::internal("Failed to create availability window"))
+}
+
+/// Remove an availability window (already scheduled matches stay put)
+pub async fn remove_window(db: &Database, tournament: &RecordId, id: &RecordId) -> ApiResult<()> {
+	let mut response = db
+		.query("DELETE $id WHERE tournament = $tournament RETURN BEFORE")
+		.bind(("id", id.clone()))
+		.bind(("tournament", tournament.clone()))
+		.await?;
+
+	let removed: Option<AvailabilityWindow> = response.take(0)?;
+	removed
+		.map(|_| ())
+		.ok_or_else(|| ApiError::not_found("availability", &id.key().to_string()))
+}
+
+/// Slots already booked by any of the given players
+async fn booked_slots(db: &Database, players: &[RecordId]) -> ApiResult<Vec<BookedSlot>> {
+	#[derive(Deserialize)]
+	struct Row {
+		id: RecordId,
+		scheduled_at: DateTime<Utc>,
+		duration_minutes: u32,
+		players: Vec<Option<RecordId>>,
+	}
+
+	if players.is_empty() {
+		return Ok(Vec::new());
+	}
+
+	let mut response = db
+		.query(
+			"SELECT id, scheduled_at, duration_minutes, [home.user_id, away.user_id] AS players
+				FROM match
+				WHERE scheduled_at != NONE
+					AND status IN ['pending', 'scheduled', 'in_progress']
+					AND (home.user_id IN $players OR away.user_id IN $players)",
+		)
+		.bind(("players", players.to_vec()))
+		.await?;
+	let rows: Vec<Row> = response.take(0)?;
+
+	Ok(
+		rows
+			.into_iter()
+			.map(|row| BookedSlot {
+				r#match: row.id,
+				players: row.players.into_iter().flatten().collect(),
+				slot: TimeSlot::starting_at(row.scheduled_at, row.duration_minutes),
+			})
+			.collect(),
+	)
+}
+
+/// Users playing a match
+async fn players_of(db: &Database, game: &Match) -> ApiResult<Vec<RecordId>> {
+	let (home, away) = matches::player_users(db, game).await?;
+	Ok(home.into_iter().chain(away).collect())
+}
+
+/// Conflicts the match would have if played in `slot`
+pub async fn conflicts_for(
+	db: &Database,
+	game: &Match,
+	slot: &TimeSlot,
+) -> ApiResult<Vec<ScheduleConflict>> {
+	let players = players_of(db, game).await?;
+	let booked = booked_slots(db, &players).await?;
+	Ok(find_conflicts(&game.id, &players, slot, &booked))
+}
+
+/// Fail with `Conflict` when a player is already busy during `slot`
+pub async fn ensure_no_conflicts(db: &Database, game: &Match, slot: &TimeSlot) -> ApiResult<()> {
+	let conflicts = conflicts_for(db, game, slot).await?;
+	match conflicts.first() {
+		Some(conflict) => Err(ApiError::conflict(&format!(
+			"Player {} is already playing {} at that time",
+			conflict.player, conflict.conflicting_match
+		))),
+		None => Ok(()),
+	}
+}
+
+/// Check that a match can still be (re)scheduled
+fn ensure_reschedulable(game: &Match) -> ApiResult<()> {
+	if !matches!(game.status, MatchStatus::Pending | MatchStatus::Scheduled) {
+		return Err(ApiError::conflict(
+			"Only matches that haven't started can be scheduled",
+		));
+	}
+	Ok(())
+}
+
+/// Book `$id` at `$starts_at` and withdraw its other pending proposals
+///
+/// Throws when one of its players is already busy during the slot, or when
+/// the match started or was moved since `$revision` was read, so the
+/// enclosing transaction rolls back.
+const BOOK_MATCH: &str = "
+	LET $ends_at = <datetime> $starts_at + duration::from::mins($minutes);
+	LET $players = [$id.home.user_id, $id.away.user_id][WHERE $this != NONE];
+	LET $busy = (SELECT id, [home.user_id, away.user_id] AS players FROM match
+		WHERE id != $id
+			AND scheduled_at != NONE
+			AND status IN ['pending', 'scheduled', 'in_progress']
+			AND scheduled_at < $ends_at
+			AND scheduled_at + duration::from::mins(duration_minutes) > <datetime> $starts_at
+			AND (home.user_id IN $players OR away.user_id IN $players));
+	IF $busy {
+		THROW 'Player ' + <string> array::intersect($busy[0].players, $players)[0]
+			+ ' is already playing ' + <string> $busy[0].id + ' at that time'
+	};
+	LET $booked = (UPDATE $id SET
+		scheduled_at = <datetime> $starts_at,
+		duration_minutes = $minutes,
+		schedule_revision += 1,
+		status = 'scheduled'
+		WHERE status IN ['pending', 'scheduled'] AND schedule_revision = $revision);
+	IF !$booked { THROW 'This match has changed meanwhile; reload it and try again' };
+	UPDATE reschedule_proposal SET status = 'withdrawn'
+		WHERE match = $id AND status = 'pending' AND scheduled_at != <datetime> $starts_at;";
+
+/// Move a match to a new slot, withdrawing pending reschedule proposals
+async fn book(state: &AppState, game: &Match, slot: &TimeSlot) -> ApiResult<Match> {
+	let response = state
+		.db
+		.query(format!(
+			"BEGIN TRANSACTION;
+			{BOOK_MATCH}
+			RETURN $booked[0];
+			COMMIT TRANSACTION;"
+		))
+		.bind(("id", game.id.clone()))
+		.bind(("revision", game.schedule_revision))
+		.bind(("starts_at", slot.starts_at.to_rfc3339()))
+		.bind(("minutes", slot_minutes(slot)))
+		.await?;
+
+	let game: Option<Match> = db::check_transaction(response)?.take(0)?;
+	let game = game.ok_or_else(|| ApiError::internal("Failed to schedule match"))?;
+
+	matches::notify(state, WebhookEventKind::MatchScheduled, &game).await;
+	Ok(game)
+}
+
+fn slot_minutes(slot: &TimeSlot) -> i64 {
+	(slot.ends_at - slot.starts_at).num_minutes()
+}
+
+/// Schedule (or move) a single match at a given time
+pub async fn schedule_match(
+	state: &AppState,
+	game: &Match,
+	data: ScheduleMatchData,
+) -> ApiResult<Match> {
+	ensure_reschedulable(game)?;
+
+	let minutes = data.duration_minutes.unwrap_or(game.duration_minutes);
+	validators::range(
+		minutes as i32,
+		MIN_SLOT_MINUTES as i32,
+		MAX_SLOT_MINUTES as i32,
+		"duration_minutes",
+	)?;
+
+	let slot = TimeSlot::starting_at(data.scheduled_at, minutes);
+	book(state, game, &slot).await
+}
+
+/// Place the tournament's unscheduled matches in the organizer's windows
+///
+/// Only future time is used. Matches that don't fit, or that can no longer
+/// be booked in their planned slot, are reported back in
+/// [`SchedulePlan::unscheduled`].
+pub async fn auto_schedule(
+	state: &AppState,
+	tournament: &Tournament,
+	data: AutoScheduleData,
+) -> ApiResult<SchedulePlan> {
+	#[derive(Deserialize)]
+	struct Row {
+		id: RecordId,
+		players: Vec<Option<RecordId>>,
+	}
+
+	let now = Utc::now();
+	let windows: Vec<TimeSlot> = list_windows(&state.db, &tournament.id)
+		.await?
+		.iter()
+		.map(AvailabilityWindow::slot)
+		.filter(|window| window.ends_at > now)
+		.map(|window| TimeSlot::new(window.starts_at.max(now), window.ends_at))
+		.collect();
+
+	let mut response = state
+		.db
+		.query(
+			"SELECT id, round, position, [home.user_id, away.user_id] AS players
+				FROM match
+				WHERE tournament = $tournament
+					AND status = 'pending'
+					AND scheduled_at = NONE
+					AND ($round = NONE OR round = $round)
+				ORDER BY round, position",
+		)
+		.bind(("tournament", tournament.id.clone()))
+		.bind(("round", data.round))
+		.await?;
+	let rows: Vec<Row> = response.take(0)?;
+
+	let items: Vec<SchedulingItem> = rows
+		.into_iter()
+		.map(|row| SchedulingItem {
+			r#match: row.id,
+			players: row.players.into_iter().flatten().collect(),
+		})
+		.collect();
+
+	let mut players: Vec<RecordId> = items.iter().flat_map(|item| item.players.clone()).collect();
+	players.sort_by_key(|player| player.to_string());
+	players.dedup();
+	let booked = booked_slots(&state.db, &players).await?;
+
+	let plan = plan_schedule(&items, &windows, data.slot_minutes, data.parallel, &booked);
+
+	// Each booking re-checks its slot, so a match booked elsewhere since the
+	// plan was made is reported as unscheduled rather than failing the rest
+	let mut scheduled = SchedulePlan {
+		assignments: Vec::with_capacity(plan.assignments.len()),
+		unscheduled: plan.unscheduled,
+	};
+	for assignment in plan.assignments {
+		let game = matches::find(&state.db, &assignment.r#match).await?;
+		match book(state, &game, &assignment.slot).await {
+			Ok(_) => scheduled.assignments.push(assignment),
+			Err(ApiError::Conflict { .. }) => scheduled.unscheduled.push(assignment.r#match),
+			Err(err) => return Err(err),
+		}
+	}
+
+	Ok(scheduled)
+}
+
+/// Scheduled matches of a tournament with times rendered in `timezone`
+pub async fn timetable(
+	db: &Database,
+	tournament: &RecordId,
+	timezone: Tz,
+) -> ApiResult<Vec<ScheduledMatch>> {
+	let games = matches::list(db, tournament).await?;
+
+	let mut timetable: Vec<ScheduledMatch> = games
+		.into_iter()
+		.filter(|game| game.status != MatchStatus::Cancelled)
+		.filter_map(|game| {
+			let slot = game.slot()?;
+			Some(ScheduledMatch {
+				r#match: game.id,
+				round: game.round,
+				position: game.position,
+				home: game.home,
+				away: game.away,
+				timezone: timezone.name().to_string(),
+				local_starts_at: time::format_in_timezone(&slot.starts_at, timezone),
+				local_ends_at: time::format_in_timezone(&slot.ends_at, timezone),
+				slot,
+			})
+		})
+		.collect();
+
+	timetable.sort_by_key(|entry| entry.slot.starts_at);
+	Ok(timetable)
+}
+
+/// Fetch a reschedule proposal, failing with `NotFound` when it doesn't exist
+pub async fn find_proposal(db: &Database, id: &RecordId) -> ApiResult<RescheduleProposal> {
+	let proposal: Option<RescheduleProposal> = db.select(id).await?;
+	proposal.ok_or_else(|| ApiError::not_found("reschedule_proposal", &id.key().to_string()))
+}
+
+/// Reschedule proposals of a match, newest first
+pub async fn list_proposals(db: &Database, game: &RecordId) -> ApiResult<Vec<RescheduleProposal>> {
+	let mut response = db
+		.query("SELECT * FROM reschedule_proposal WHERE match = $match ORDER BY created_at DESC")
+		.bind(("match", game.clone()))
+		.await?;
+
+	Ok(response.take(0)?)
+}
+
+/// Propose a new kickoff time; replaces the match's pending proposal
+pub async fn propose(
+	db: &Database,
+	game: &Match,
+	user: &RecordId,
+	data: ProposeRescheduleData,
+) -> ApiResult<RescheduleProposal> {
+	ensure_reschedulable(game)?;
+
+	let players = players_of(db, game).await?;
+	if !players.contains(user) {
+		return Err(ApiError::authorization(
+			"Only the players of this match can propose a new time",
+		));
+	}
+	if players.len() < 2 {
+		return Err(ApiError::bad_request(
+			"A new time can only be proposed once the opponent is known",
+		));
+	}
+
+	let slot = TimeSlot::starting_at(data.scheduled_at, game.duration_minutes);
+	ensure_no_conflicts(db, game, &slot).await?;
+
+	let mut response = db
+		.query(
+			"BEGIN TRANSACTION;
+			UPDATE reschedule_proposal SET status = 'withdrawn'
+				WHERE match = $match AND status = 'pending';
+			CREATE reschedule_proposal CONTENT {
+				match: $match,
+				proposed_by: $user,
+				scheduled_at: <datetime> $scheduled_at,
+				reason: $reason,
+				status: 'pending',
+			};
+			COMMIT TRANSACTION;",
+		)
+		.bind(("match", game.id.clone()))
+		.bind(("user", user.clone()))
+		.bind(("scheduled_at", data.scheduled_at.to_rfc3339()))
+		.bind(("reason", data.reason))
+		.await?;
+
+	let proposal: Option<RescheduleProposal> = response.take(1)?;
+	proposal.ok_or_else(|| ApiError::internal("Failed to create reschedule proposal"))
+}
+
+/// Accept or decline a pending proposal as the proposer's opponent
+///
+/// Accepting moves the match once the slot is re-checked for conflicts. The
+/// answer and the booking happen in one transaction, so a proposal is never
+/// accepted for a match that couldn't be moved.
+pub async fn respond(
+	state: &AppState,
+	proposal: &RescheduleProposal,
+	user: &RecordId,
+	accept: bool,
+) -> ApiResult<RescheduleProposal> {
+	let answered = || ApiError::conflict("This proposal has already been answered");
+	if proposal.status != RescheduleStatus::Pending {
+		return Err(answered());
+	}
+
+	let game = matches::find(&state.db, &proposal.r#match).await?;
+	let players = players_of(&state.db, &game).await?;
+	if !players.contains(user) || &proposal.proposed_by == user {
+		return Err(ApiError::authorization(
+			"Only the opponent can answer a reschedule proposal",
+		));
+	}
+
+	let slot = TimeSlot::starting_at(proposal.scheduled_at, game.duration_minutes);
+	if accept {
+		ensure_reschedulable(&game)?;
+	}
+
+	let response = state
+		.db
+		.query(format!(
+			"BEGIN TRANSACTION;
+			LET $answered = (UPDATE $proposal SET
+				status = $status,
+				responded_by = $user,
+				responded_at = time::now()
+				WHERE status = 'pending');
+			IF $answered AND $accept {{ {BOOK_MATCH} }};
+			RETURN $answered[0];
+			COMMIT TRANSACTION;"
+		))
+		.bind(("proposal", proposal.id.clone()))
+		.bind((
+			"status",
+			if accept {
+				RescheduleStatus::Accepted
+			} else {
+				RescheduleStatus::Declined
+			},
+		))
+		.bind(("user", user.clone()))
+		.bind(("accept", accept))
+		.bind(("id", game.id.clone()))
+		.bind(("revision", game.schedule_revision))
+		.bind(("starts_at", slot.starts_at.to_rfc3339()))
+		.bind(("minutes", slot_minutes(&slot)))
+		.await?;
+
+	let proposal: Option<RescheduleProposal> = db::check_transaction(response)?.take(0)?;
+	let proposal = proposal.ok_or_else(answered)?;
+
+	if accept {
+		let game = matches::find(&state.db, &game.id).await?;
+		matches::notify(state, WebhookEventKind::MatchScheduled, &game).await;
+	}
+	Ok(proposal)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::entities::{CreateMatchData, User};
+	use crate::test_utils::{self, factories};
+	use chrono::Duration;
+
+	/// A ready match and the users playing it
+	async fn ready_match(state: &AppState) -> (Match, User, User) {
+		let organizer = factories::user("organizer").await;
+		let tournament = factories::tournament(&organizer, true).await;
+		let home = factories::user("home").await;
+		let away = factories::user("away").await;
+		let data = CreateMatchData {
+			round: 1,
+			position: 1,
+			home: Some(factories::participant(&tournament, &home).await.id),
+			away: Some(factories::participant(&tournament, &away).await.id),
+			scheduled_at: None,
+			duration_minutes: None,
+		};
+		let game = matches::create(state, &tournament, data).await.unwrap();
+		(game, home, away)
+	}
+
+	fn at(hours: i64) -> ScheduleMatchData {
+		ScheduleMatchData {
+			scheduled_at: Utc::now() + Duration::hours(hours),
+			duration_minutes: None,
+		}
+	}
+
+	// Each test acts on a stale copy of the match or proposal, as the loser of
+	// a race between two requests would
+
+	#[actix_web::test]
+	async fn test_a_stale_match_is_not_booked() {
+		test_utils::setup();
+		let state = test_utils::state();
+		let (stale, _, _) = ready_match(&state).await;
+
+		schedule_match(&state, &stale, at(24)).await.unwrap();
+		let err = schedule_match(&state, &stale, at(48)).await.unwrap_err();
+		assert!(matches!(err, ApiError::Conflict { .. }));
+
+		let game = matches::find(&state.db, &stale.id).await.unwrap();
+		assert_eq!(game.schedule_revision, stale.schedule_revision + 1);
+	}
+
+	#[actix_web::test]
+	async fn test_a_started_match_is_not_booked() {
+		test_utils::setup();
+		let state = test_utils::state();
+		let (stale, _, _) = ready_match(&state).await;
+		matches::start(&state, &stale).await.unwrap();
+
+		let err = schedule_match(&state, &stale, at(24)).await.unwrap_err();
+		assert!(matches!(err, ApiError::Conflict { .. }));
+		assert_eq!(
+			matches::find(&state.db, &stale.id).await.unwrap().status,
+			MatchStatus::InProgress
+		);
+	}
+
+	#[actix_web::test]
+	async fn test_a_player_is_not_booked_in_overlapping_matches() {
+		test_utils::setup();
+		let state = test_utils::state();
+		let (first, home, _) = ready_match(&state).await;
+		let organizer = factories::user("organizer").await;
+		let tournament = factories::tournament(&organizer, true).await;
+		let rival = factories::user("rival").await;
+		let data = CreateMatchData {
+			round: 1,
+			position: 1,
+			home: Some(factories::participant(&tournament, &home).await.id),
+			away: Some(factories::participant(&tournament, &rival).await.id),
+			scheduled_at: None,
+			duration_minutes: None,
+		};
+		let second = matches::create(&state, &tournament, data).await.unwrap();
+
+		schedule_match(&state, &first, at(24)).await.unwrap();
+		let err = schedule_match(&state, &second, at(24)).await.unwrap_err();
+		assert!(
+			matches!(&err, ApiError::Conflict { message, .. } if message.contains("is already playing")),
+			"{err:?}"
+		);
+
+		let second = matches::find(&state.db, &second.id).await.unwrap();
+		assert_eq!(second.scheduled_at, None);
+		schedule_match(&state, &second, at(48)).await.unwrap();
+	}
+
+	#[actix_web::test]
+	async fn test_a_proposal_is_answered_once() {
+		test_utils::setup();
+		let state = test_utils::state();
+		let (game, home, away) = ready_match(&state).await;
+		let data = ProposeRescheduleData {
+			scheduled_at: Utc::now() + Duration::hours(24),
+			reason: None,
+		};
+		let stale = propose(&state.db, &game, &home.id, data).await.unwrap();
+
+		respond(&state, &stale, &away.id, false).await.unwrap();
+		let err = respond(&state, &stale, &away.id, true).await.unwrap_err();
+		assert!(matches!(err, ApiError::Conflict { .. }));
+
+		let proposal = find_proposal(&state.db, &stale.id).await.unwrap();
+		assert_eq!(proposal.status, RescheduleStatus::Declined);
+		let game = matches::find(&state.db, &game.id).await.unwrap();
+		assert_eq!(game.scheduled_at, None);
+	}
+}
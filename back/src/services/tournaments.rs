@@ -0,0 +1,217 @@
+//! Tournament management, plus the lookups and ownership checks shared by
+//! other services
+
+use surrealdb::RecordId;
+
+use crate::AppState;
+use crate::entities::{CreateTournamentData, Tournament, UpdateTournamentData, WebhookEventKind};
+use crate::middleware::auth::AuthUser;
+use crate::repositories::TournamentRepository;
+use crate::services::webhooks;
+use crate::utils::error::{ApiError, ApiResult};
+use crate::utils::{logging, metrics};
+
+/// Build a tournament record id from its key
+pub fn record_id(key: &str) -> RecordId {
+	RecordId::from(("tournament", key))
+}
+
+/// Fetch a tournament, failing with `NotFound` when it doesn't exist
+pub async fn find(repo: &dyn TournamentRepository, id: &RecordId) -> ApiResult<Tournament> {
+	let tournament = repo.find(id).await?;
+	tournament.ok_or_else(|| ApiError::not_found("tournament", &id.key().to_string()))
+}
+
+/// Fetch a tournament the user is allowed to see
+///
+/// Unpublished tournaments are only visible to their organizer; everyone else
+/// gets `NotFound` so drafts don't leak.
+pub async fn ensure_visible(
+	repo: &dyn TournamentRepository,
+	id: &RecordId,
+	user: Option<&AuthUser>,
+) -> ApiResult<Tournament> {
+	let tournament = find(repo, id).await?;
+	let is_organizer = user.is_some_and(|user| user.id == tournament.created_by);
+	if !tournament.published && !is_organizer {
+		return Err(ApiError::not_found("tournament", &id.key().to_string()));
+	}
+	Ok(tournament)
+}
+
+/// Fetch a tournament and ensure the user is the organizer who created it
+pub async fn ensure_organizer(
+	repo: &dyn TournamentRepository,
+	id: &RecordId,
+	user: &AuthUser,
+) -> ApiResult<Tournament> {
+	let tournament = find(repo, id).await?;
+	if tournament.created_by != user.id {
+		return Err(ApiError::authorization(
+			"Only the tournament organizer can perform this action",
+		));
+	}
+	Ok(tournament)
+}
+
+/// Tournaments the user can see, newest first
+///
+/// Everyone sees the published tournaments; organizers also see their drafts.
+pub async fn list(
+	repo: &dyn TournamentRepository,
+	user: Option<&AuthUser>,
+) -> ApiResult<Vec<Tournament>> {
+	let Some(user) = user else {
+		return repo.list(true).await;
+	};
+
+	let mut tournaments = repo.list(false).await?;
+	tournaments.retain(|tournament| tournament.published || tournament.created_by == user.id);
+	Ok(tournaments)
+}
+
+/// Create a tournament organized by the user
+pub async fn create(
+	state: &AppState,
+	data: CreateTournamentData,
+	organizer: &AuthUser,
+) -> ApiResult<Tournament> {
+	let tournament = state.tournaments.create(data, &organizer.id).await?;
+
+	metrics::tournament_created("api");
+	logging::tournament_event(
+		"created",
+		&tournament.id.key().to_string(),
+		Some(&organizer.id.key().to_string()),
+	);
+	Ok(tournament)
+}
+
+/// Apply the organizer's changes to a tournament
+///
+/// Announced as `tournament.published` when the changes publish a draft, and
+/// as `tournament.updated` otherwise.
+pub async fn update(
+	state: &AppState,
+	tournament: &Tournament,
+	data: UpdateTournamentData,
+) -> ApiResult<Tournament> {
+	let key = tournament.id.key().to_string();
+	let updated = state
+		.tournaments
+		.update(&tournament.id, data)
+		.await?
+		.ok_or_else(|| ApiError::not_found("tournament", &key))?;
+
+	let event = if updated.published && !tournament.published {
+		WebhookEventKind::TournamentPublished
+	} else {
+		WebhookEventKind::TournamentUpdated
+	};
+	notify(state, event, &updated).await;
+	Ok(updated)
+}
+
+/// Emit a tournament webhook; delivery problems never fail the caller
+async fn notify(state: &AppState, event: WebhookEventKind, tournament: &Tournament) {
+	let data = match serde_json::to_value(tournament) {
+		Ok(data) => data,
+		Err(e) => {
+			log::error!("Failed to serialize {} for webhooks: {e}", tournament.id);
+			return;
+		}
+	};
+
+	if let Err(e) = webhooks::emit(state, event, &tournament.id, data).await {
+		log::warn!(
+			"Failed to emit {} for {}: {e}",
+			event.as_str(),
+			tournament.id
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::repositories::InMemoryTournamentRepository;
+	use chrono::Utc;
+
+	fn repo_with_draft() -> InMemoryTournamentRepository {
+		let repo = InMemoryTournamentRepository::new();
+		repo.insert(Tournament {
+			id: record_id("draft"),
+			name: "Copa Catrina".to_string(),
+			description: String::new(),
+			published: false,
+			season: None,
+			created_by: RecordId::from(("user", "organizer")),
+			created_at: Utc::now(),
+			updated_at: Utc::now(),
+		});
+		repo
+	}
+
+	#[actix_web::test]
+	async fn test_drafts_are_hidden_from_everyone_but_the_organizer() {
+		let repo = repo_with_draft();
+		let organizer = AuthUser::new("organizer");
+		let viewer = AuthUser::new("viewer");
+
+		assert!(
+			ensure_visible(&repo, &record_id("draft"), Some(&organizer))
+				.await
+				.is_ok()
+		);
+		let err = ensure_visible(&repo, &record_id("draft"), Some(&viewer))
+			.await
+			.unwrap_err();
+		assert!(matches!(err, ApiError::NotFound { .. }));
+		assert!(
+			ensure_visible(&repo, &record_id("draft"), None)
+				.await
+				.is_err()
+		);
+	}
+
+	#[actix_web::test]
+	async fn test_only_the_organizer_passes_ensure_organizer() {
+		let repo = repo_with_draft();
+
+		assert!(
+			ensure_organizer(&repo, &record_id("draft"), &AuthUser::new("organizer"))
+				.await
+				.is_ok()
+		);
+		let err = ensure_organizer(&repo, &record_id("draft"), &AuthUser::new("viewer"))
+			.await
+			.unwrap_err();
+		assert!(matches!(err, ApiError::Authorization { .. }));
+	}
+
+	#[actix_web::test]
+	async fn test_list_shows_drafts_to_their_organizer_only() {
+		let repo = repo_with_draft();
+		let organizer = AuthUser::new("organizer");
+		let data = CreateTournamentData {
+			name: "Liga Calaca".to_string(),
+			description: String::new(),
+			published: Some(true),
+			season: None,
+		};
+		repo
+			.create(data, &RecordId::from(("user", "another")))
+			.await
+			.unwrap();
+
+		assert_eq!(list(&repo, Some(&organizer)).await.unwrap().len(), 2);
+		assert_eq!(
+			list(&repo, Some(&AuthUser::new("viewer")))
+				.await
+				.unwrap()
+				.len(),
+			1
+		);
+		assert_eq!(list(&repo, None).await.unwrap().len(), 1);
+	}
+}
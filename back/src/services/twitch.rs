@@ -0,0 +1,569 @@
+//! Twitch integration through the Helix API
+//!
+//! Users link their Twitch account by handing over their own OAuth token once
+//! (we only use it to ask Helix who they are). Profile data and live status are
+//! then refreshed with an app access token obtained through the client
+//! credentials flow.
+//!
+//! Both the Helix and the OAuth base URLs come from [`TwitchConfig`], so tests
+//! and local development can point the client at a stand-in server.
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use surrealdb::RecordId;
+use tokio::sync::Mutex;
+
+use crate::config::Secret;
+use crate::db::Database;
+use crate::entities::{ChannelStatus, TwitchAccount};
+use crate::utils::error::{ApiError, ApiResult};
+use crate::utils::logging;
+
+/// Default Helix API base URL
+pub const DEFAULT_API_URL: &str = "https://api.twitch.tv/helix";
+
+/// Default Twitch OAuth base URL
+pub const DEFAULT_AUTH_URL: &str = "https://id.twitch.tv/oauth2";
+
+/// Cached profiles and live status older than this are refreshed on read
+const STALE_AFTER_SECONDS: i64 = 60;
+
+/// Request timeout for Helix calls
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
+/// Twitch application credentials and endpoints
+#[derive(Debug, Clone)]
+pub struct TwitchConfig {
+	pub client_id: String,
+	pub client_secret: Secret,
+	/// Helix API base URL (`TWITCH_API_URL`)
+	pub api_url: String,
+	/// OAuth base URL (`TWITCH_AUTH_URL`)
+	pub auth_url: String,
+}
+
+/// Helix user object (subset)
+#[derive(Debug, Clone, Deserialize)]
+pub struct HelixUser {
+	pub id: String,
+	pub login: String,
+	pub display_name: String,
+	#[serde(default)]
+	pub profile_image_url: Option<String>,
+}
+
+/// Helix stream object (subset); only present while the channel is live
+#[derive(Debug, Clone, Deserialize)]
+pub struct HelixStream {
+	pub user_id: String,
+	#[serde(default)]
+	pub title: Option<String>,
+	#[serde(default)]
+	pub game_name: Option<String>,
+	#[serde(default)]
+	pub viewer_count: Option<u32>,
+	#[serde(default)]
+	pub started_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct HelixData<T> {
+	data: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+	access_token: String,
+	expires_in: u64,
+}
+
+struct AppToken {
+	value: String,
+	expires_at: Instant,
+}
+
+/// Minimal Helix API client
+pub struct TwitchClient {
+	http: reqwest::Client,
+	config: TwitchConfig,
+	app_token: Mutex<Option<AppToken>>,
+}
+
+fn twitch_error(message: impl Into<String>) -> ApiError {
+	ApiError::ExternalService {
+		service: "twitch".to_string(),
+		message: message.into(),
+	}
+}
+
+impl TwitchClient {
+	/// Create a client for the given configuration
+	pub fn new(config: TwitchConfig) -> Self {
+		let http = reqwest::Client::builder()
+			.timeout(Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+			.build()
+			.unwrap_or_default();
+
+		Self {
+			http,
+			config,
+			app_token: Mutex::new(None),
+		}
+	}
+
+	/// Get a valid app access token, requesting a new one when needed
+	async fn app_token(&self) -> ApiResult<String> {
+		let mut cached = self.app_token.lock().await;
+		if let Some(token) = cached.as_ref()
+			&& token.expires_at > Instant::now()
+		{
+			return Ok(token.value.clone());
+		}
+
+		let response = self
+			.http
+			.post(format!("{}/token", self.config.auth_url))
+			.form(&[
+				("client_id", self.config.client_id.as_str()),
+				("client_secret", self.config.client_secret.expose()),
+				("grant_type", "client_credentials"),
+			])
+			.send()
+			.await
+			.map_err(|e| twitch_error(e.to_string()))?;
+
+		if !response.status().is_success() {
+			return Err(twitch_error(format!(
+				"Token request failed with status {}",
+				response.status()
+			)));
+		}
+
+		let token: TokenResponse = response
+			.json()
+			.await
+			.map_err(|e| twitch_error(e.to_string()))?;
+
+		// Renew a minute early so in-flight requests never use an expired token
+		let lifetime = Duration::from_secs(token.expires_in.saturating_sub(60));
+		*cached = Some(AppToken {
+			value: token.access_token.clone(),
+			expires_at: Instant::now() + lifetime,
+		});
+
+		Ok(token.access_token)
+	}
+
+	/// Perform a Helix GET with the given bearer token
+	async fn get<T: for<'de> Deserialize<'de>>(
+		&self,
+		path: &str,
+		query: &[(&str, &str)],
+		token: &str,
+	) -> ApiResult<(reqwest::StatusCode, Vec<T>)> {
+		let response = self
+			.http
+			.get(format!("{}{path}", self.config.api_url))
+			.query(query)
+			.bearer_auth(token)
+			.header("Client-Id", &self.config.client_id)
+			.send()
+			.await
+			.map_err(|e| twitch_error(e.to_string()))?;
+
+		let status = response.status();
+		if !status.is_success() {
+			return Ok((status, Vec::new()));
+		}
+
+		let body: HelixData<T> = response
+			.json()
+			.await
+			.map_err(|e| twitch_error(e.to_string()))?;
+
+		Ok((status, body.data))
+	}
+
+	/// Perform a Helix GET with the app token, renewing it once if rejected
+	async fn get_as_app<T: for<'de> Deserialize<'de>>(
+		&self,
+		path: &str,
+		query: &[(&str, &str)],
+	) -> ApiResult<Vec<T>> {
+		let token = self.app_token().await?;
+		let (status, data) = self.get(path, query, &token).await?;
+
+		let (status, data) = if status == reqwest::StatusCode::UNAUTHORIZED {
+			self.app_token.lock().await.take();
+			let token = self.app_token().await?;
+			self.get(path, query, &token).await?
+		} else {
+			(status, data)
+		};
+
+		if !status.is_success() {
+			return Err(twitch_error(format!(
+				"Helix {path} failed with status {status}"
+			)));
+		}
+
+		Ok(data)
+	}
+
+	/// Identify the owner of a user access token
+	pub async fn authenticated_user(&self, user_token: &str) -> ApiResult<HelixUser> {
+		let (status, users) = self.get::<HelixUser>("/users", &[], user_token).await?;
+
+		if status == reqwest::StatusCode::UNAUTHORIZED {
+			return Err(ApiError::authentication("Invalid or expired Twitch token"));
+		}
+		if !status.is_success() {
+			return Err(twitch_error(format!(
+				"Helix /users failed with status {status}"
+			)));
+		}
+
+		users
+			.into_iter()
+			.next()
+			.ok_or_else(|| twitch_error("Helix returned no user for the token"))
+	}
+
+	/// Fetch a user profile by Twitch id
+	pub async fn user_by_id(&self, twitch_id: &str) -> ApiResult<Option<HelixUser>> {
+		let users = self
+			.get_as_app::<HelixUser>("/users", &[("id", twitch_id)])
+			.await?;
+		Ok(users.into_iter().next())
+	}
+
+	/// Fetch the live stream of a channel; `None` when offline
+	pub async fn stream_by_user_id(&self, twitch_id: &str) -> ApiResult<Option<HelixStream>> {
+		let streams = self
+			.get_as_app::<HelixStream>("/streams", &[("user_id", twitch_id)])
+			.await?;
+		Ok(streams.into_iter().next())
+	}
+}
+
+/// The Twitch client from the app state, if the integration is configured
+pub fn client(configured: Option<&TwitchClient>) -> ApiResult<&TwitchClient> {
+	configured.ok_or_else(|| twitch_error("Twitch integration is not configured"))
+}
+
+/// Find the Twitch account linked to a user
+pub async fn find_by_user(db: &Database, user: &RecordId) -> ApiResult<Option<TwitchAccount>> {
+	let mut response = db
+		.query("SELECT * FROM twitch_account WHERE user = $user LIMIT 1")
+		.bind(("user", user.clone()))
+		.await?;
+
+	let account: Option<TwitchAccount> = response.take(0)?;
+	Ok(account)
+}
+
+/// Link the Twitch account owning `access_token` to the user
+pub async fn link(
+	db: &Database,
+	client: &TwitchClient,
+	user: &RecordId,
+	access_token: &str,
+) -> ApiResult<TwitchAccount> {
+	let profile = client.authenticated_user(access_token).await?;
+
+	let mut response = db
+		.query("SELECT * FROM twitch_account WHERE twitch_id = $twitch_id AND user != $user")
+		.bind(("twitch_id", profile.id.clone()))
+		.bind(("user", user.clone()))
+		.await?;
+	let taken: Vec<TwitchAccount> = response.take(0)?;
+	if !taken.is_empty() {
+		return Err(ApiError::conflict(
+			"This Twitch account is already linked to another user",
+		));
+	}
+
+	let stream = client.stream_by_user_id(&profile.id).await?;
+
+	let mut response = db
+		.query(
+			"BEGIN TRANSACTION;
+			DELETE twitch_account WHERE user = $user;
+			CREATE twitch_account CONTENT {
+				user: $user,
+				twitch_id: $twitch_id,
+				login: $login,
+				display_name: $display_name,
+				profile_image_url: $profile_image_url,
+			};
+			COMMIT TRANSACTION;",
+		)
+		.bind(("user", user.clone()))
+		.bind(("twitch_id", profile.id))
+		.bind(("login", profile.login))
+		.bind(("display_name", profile.display_name))
+		.bind(("profile_image_url", profile.profile_image_url))
+		.await?;
+
+	let account: Option<TwitchAccount> = response.take(1)?;
+	let account = account.ok_or_else(|| ApiError::internal("Failed to link Twitch account"))?;
+	let account = store_stream(db, &account.id, stream.as_ref()).await?;
+
+	logging::auth_event("twitch_linked", Some(&user.key().to_string()));
+	Ok(account)
+}
+
+/// Remove the Twitch link of a user
+pub async fn unlink(db: &Database, user: &RecordId) -> ApiResult<()> {
+	db.query("DELETE twitch_account WHERE user = $user")
+		.bind(("user", user.clone()))
+		.await?
+		.check()?;
+
+	logging::auth_event("twitch_unlinked", Some(&user.key().to_string()));
+	Ok(())
+}
+
+/// Refresh profile and live status of a linked account from Helix
+pub async fn refresh(
+	db: &Database,
+	client: &TwitchClient,
+	account: &TwitchAccount,
+) -> ApiResult<TwitchAccount> {
+	let profile = client.user_by_id(&account.twitch_id).await?;
+	let stream = client.stream_by_user_id(&account.twitch_id).await?;
+
+	if let Some(profile) = profile {
+		db.query(
+			"UPDATE $id SET
+				login = $login,
+				display_name = $display_name,
+				profile_image_url = $profile_image_url",
+		)
+		.bind(("id", account.id.clone()))
+		.bind(("login", profile.login))
+		.bind(("display_name", profile.display_name))
+		.bind(("profile_image_url", profile.profile_image_url))
+		.await?
+		.check()?;
+	}
+
+	store_stream(db, &account.id, stream.as_ref()).await
+}
+
+/// Persist the current stream (or lack of one) on the account
+async fn store_stream(
+	db: &Database,
+	id: &RecordId,
+	stream: Option<&HelixStream>,
+) -> ApiResult<TwitchAccount> {
+	let mut response = db
+		.query(
+			"UPDATE $id SET
+				is_live = $is_live,
+				stream_title = $title,
+				game_name = $game_name,
+				viewer_count = $viewer_count,
+				live_started_at = <option<datetime>> $started_at,
+				refreshed_at = time::now()",
+		)
+		.bind(("id", id.clone()))
+		.bind(("is_live", stream.is_some()))
+		.bind(("title", stream.and_then(|s| s.title.clone())))
+		.bind(("game_name", stream.and_then(|s| s.game_name.clone())))
+		.bind(("viewer_count", stream.and_then(|s| s.viewer_count)))
+		.bind((
+			"started_at",
+			stream.and_then(|s| s.started_at).map(|t| t.to_rfc3339()),
+		))
+		.await?;
+
+	let account: Option<TwitchAccount> = response.take(0)?;
+	account.ok_or_else(|| ApiError::not_found("twitch_account", &id.key().to_string()))
+}
+
+/// Live status of a user's channel, refreshed from Helix when stale
+pub async fn channel_status(
+	db: &Database,
+	client: &TwitchClient,
+	user: &RecordId,
+) -> ApiResult<Option<ChannelStatus>> {
+	let Some(account) = find_by_user(db, user).await? else {
+		return Ok(None);
+	};
+
+	let age = Utc::now() - account.refreshed_at;
+	let account = if age.num_seconds() > STALE_AFTER_SECONDS {
+		match refresh(db, client, &account).await {
+			Ok(account) => account,
+			Err(e) => {
+				// Serve the last known status rather than failing the request
+				log::warn!("Twitch refresh failed for {user}: {e}");
+				account
+			}
+		}
+	} else {
+		account
+	};
+
+	Ok(Some(account.into()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use wiremock::matchers::{header, method, path, query_param};
+	use wiremock::{Mock, MockServer, ResponseTemplate};
+
+	fn client_for(server: &MockServer) -> TwitchClient {
+		TwitchClient::new(TwitchConfig {
+			client_id: "client-id".to_string(),
+			client_secret: Secret::new("client-secret"),
+			api_url: format!("{}/helix", server.uri()),
+			auth_url: format!("{}/oauth2", server.uri()),
+		})
+	}
+
+	async fn mount_token(server: &MockServer, expected_calls: u64) {
+		Mock::given(method("POST"))
+			.and(path("/oauth2/token"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+				"access_token": "app-token",
+				"expires_in": 3600,
+				"token_type": "bearer"
+			})))
+			.expect(expected_calls)
+			.mount(server)
+			.await;
+	}
+
+	#[actix_web::test]
+	async fn test_authenticated_user_uses_user_token() {
+		let server = MockServer::start().await;
+		Mock::given(method("GET"))
+			.and(path("/helix/users"))
+			.and(header("Authorization", "Bearer user-token"))
+			.and(header("Client-Id", "client-id"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+				"data": [{
+					"id": "141981764",
+					"login": "twitchdev",
+					"display_name": "TwitchDev",
+					"profile_image_url": "https://static-cdn.jtvnw.net/twitchdev.png"
+				}]
+			})))
+			.mount(&server)
+			.await;
+
+		let client = client_for(&server);
+		let user = client.authenticated_user("user-token").await.unwrap();
+
+		assert_eq!(user.id, "141981764");
+		assert_eq!(user.login, "twitchdev");
+		assert_eq!(user.display_name, "TwitchDev");
+	}
+
+	#[actix_web::test]
+	async fn test_authenticated_user_rejects_invalid_token() {
+		let server = MockServer::start().await;
+		Mock::given(method("GET"))
+			.and(path("/helix/users"))
+			.respond_with(ResponseTemplate::new(401))
+			.mount(&server)
+			.await;
+
+		let client = client_for(&server);
+		let error = client.authenticated_user("expired").await.unwrap_err();
+
+		assert_eq!(error.error_code(), "AUTHENTICATION_ERROR");
+	}
+
+	#[actix_web::test]
+	async fn test_stream_lookup_caches_app_token() {
+		let server = MockServer::start().await;
+		mount_token(&server, 1).await;
+		Mock::given(method("GET"))
+			.and(path("/helix/streams"))
+			.and(query_param("user_id", "141981764"))
+			.and(header("Authorization", "Bearer app-token"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+				"data": [{
+					"user_id": "141981764",
+					"type": "live",
+					"title": "Liga de los Muertos - Jornada 3",
+					"game_name": "EA Sports FC 25",
+					"viewer_count": 321,
+					"started_at": "2025-06-01T18:00:00Z"
+				}]
+			})))
+			.mount(&server)
+			.await;
+
+		let client = client_for(&server);
+		let stream = client
+			.stream_by_user_id("141981764")
+			.await
+			.unwrap()
+			.unwrap();
+		assert_eq!(stream.viewer_count, Some(321));
+		assert_eq!(stream.game_name.as_deref(), Some("EA Sports FC 25"));
+
+		// Second call reuses the cached app token (token endpoint expects 1 call)
+		let stream = client.stream_by_user_id("141981764").await.unwrap();
+		assert!(stream.is_some());
+	}
+
+	#[actix_web::test]
+	async fn test_offline_channel_has_no_stream() {
+		let server = MockServer::start().await;
+		mount_token(&server, 1).await;
+		Mock::given(method("GET"))
+			.and(path("/helix/streams"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "data": [] })))
+			.mount(&server)
+			.await;
+
+		let client = client_for(&server);
+		assert!(client.stream_by_user_id("1").await.unwrap().is_none());
+	}
+
+	#[actix_web::test]
+	async fn test_rejected_app_token_is_renewed_once() {
+		let server = MockServer::start().await;
+		mount_token(&server, 2).await;
+		Mock::given(method("GET"))
+			.and(path("/helix/users"))
+			.respond_with(ResponseTemplate::new(401))
+			.up_to_n_times(1)
+			.mount(&server)
+			.await;
+		Mock::given(method("GET"))
+			.and(path("/helix/users"))
+			.respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+				"data": [{ "id": "1", "login": "organizer", "display_name": "Organizer" }]
+			})))
+			.mount(&server)
+			.await;
+
+		let client = client_for(&server);
+		let user = client.user_by_id("1").await.unwrap().unwrap();
+		assert_eq!(user.login, "organizer");
+	}
+
+	#[actix_web::test]
+	async fn test_helix_errors_map_to_external_service() {
+		let server = MockServer::start().await;
+		mount_token(&server, 1).await;
+		Mock::given(method("GET"))
+			.and(path("/helix/streams"))
+			.respond_with(ResponseTemplate::new(503))
+			.mount(&server)
+			.await;
+
+		let client = client_for(&server);
+		let error = client.stream_by_user_id("1").await.unwrap_err();
+		assert_eq!(error.error_code(), "EXTERNAL_SERVICE_ERROR");
+	}
+}
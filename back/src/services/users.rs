@@ -0,0 +1,62 @@
+//! User profile lookups and preferences
+
+use chrono_tz::Tz;
+use surrealdb::RecordId;
+
+use crate::db::Database;
+use crate::entities::User;
+use crate::utils::error::{ApiError, ApiResult};
+use crate::utils::time;
+
+/// Build a user record id from its key
+pub fn record_id(key: &str) -> RecordId {
+	RecordId::from(("user", key))
+}
+
+/// Fetch a user, failing with `NotFound` when it doesn't exist
+pub async fn find(db: &Database, id: &RecordId) -> ApiResult<User> {
+	let user: Option<User> = db.select(id).await?;
+	user.ok_or_else(|| ApiError::not_found("user", &id.key().to_string()))
+}
+
+/// Store the user's display timezone
+pub async fn set_timezone(db: &Database, id: &RecordId, timezone: &str) -> ApiResult<User> {
+	let Some(timezone) = time::parse_timezone(timezone) else {
+		return Err(ApiError::validation_with_field(
+			"Unknown IANA timezone",
+			"timezone",
+		));
+	};
+
+	let mut response = db
+		.query("UPDATE $id SET timezone = $timezone")
+		.bind(("id", id.clone()))
+		.bind(("timezone", timezone.name().to_string()))
+		.await?;
+
+	let user: Option<User> = response.take(0)?;
+	user.ok_or_else(|| ApiError::not_found("user", &id.key().to_string()))
+}
+
+/// Timezone to render times in: an explicit one, else the user's, else UTC
+pub async fn display_timezone(
+	db: &Database,
+	requested: Option<&str>,
+	user: Option<&RecordId>,
+) -> ApiResult<Tz> {
+	if let Some(name) = requested {
+		return time::parse_timezone(name)
+			.ok_or_else(|| ApiError::validation_with_field("Unknown IANA timezone", "tz"));
+	}
+
+	let Some(user) = user else {
+		return Ok(Tz::UTC);
+	};
+	let stored: Option<User> = db.select(user).await?;
+	Ok(
+		stored
+			.and_then(|user| user.timezone)
+			.and_then(|name| time::parse_timezone(&name))
+			.unwrap_or(Tz::UTC),
+	)
+}
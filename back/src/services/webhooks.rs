@@ -0,0 +1,865 @@
+//! Outbound webhooks for tournament and match events
+//!
+//! Every emitted event is stored as a `webhook_delivery` record for each
+//! active endpoint subscribed to it, then pushed in the background. Payloads
+//! are signed with HMAC-SHA256 and failed attempts are retried with
+//! exponential backoff. Deliveries that run out of retries stay in the log
+//! as `failed` and can be replayed by the organizer, as can deliveries left
+//! `pending` by a run that never finished (e.g. the server restarted).
+//!
+//! Endpoints must be https URLs on public hosts: registration rejects
+//! loopback, private and link-local addresses, and the dispatcher refuses to
+//! connect to hostnames that resolve to them. Redirects are never followed, so
+//! a public endpoint cannot bounce a delivery to an internal address; a `3xx`
+//! answer counts as a failed attempt.
+//!
+//! # Signature
+//! Receivers verify a request by computing
+//! `HMAC-SHA256(secret, "{X-Liga-Timestamp}.{raw body}")` and comparing it
+//! with the hex digest in `X-Liga-Signature` (`sha256=<hex>`).
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use surrealdb::RecordId;
+
+use crate::AppState;
+use crate::db::Database;
+use crate::entities::{
+	CreateWebhookData, DeliveryAttempt, DeliveryStatus, UpdateWebhookData, Webhook, WebhookDelivery,
+	WebhookEventKind,
+};
+use crate::utils::constants;
+use crate::utils::error::{ApiError, ApiResult};
+use crate::utils::{logging, metrics, validation};
+
+/// Header carrying the `sha256=<hex>` payload signature
+pub const SIGNATURE_HEADER: &str = "X-Liga-Signature";
+/// Header carrying the unix timestamp included in the signature
+pub const TIMESTAMP_HEADER: &str = "X-Liga-Timestamp";
+/// Header carrying the event name (e.g. `match.reported`)
+pub const EVENT_HEADER: &str = "X-Liga-Event";
+/// Header carrying the delivery id, stable across retries and replays
+pub const DELIVERY_HEADER: &str = "X-Liga-Delivery";
+
+/// Timeout for a single delivery attempt
+const ATTEMPT_TIMEOUT_SECONDS: u64 = 10;
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn mac(secret: &str, timestamp: i64, body: &[u8]) -> HmacSha256 {
+	let mut mac =
+		HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+	mac.update(timestamp.to_string().as_bytes());
+	mac.update(b".");
+	mac.update(body);
+	mac
+}
+
+/// Sign a payload body, returning the `X-Liga-Signature` header value
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+	format!(
+		"sha256={}",
+		hex::encode(mac(secret, timestamp, body).finalize().into_bytes())
+	)
+}
+
+/// Verify an `X-Liga-Signature` header value in constant time
+pub fn verify(secret: &str, timestamp: i64, body: &[u8], signature: &str) -> bool {
+	let Some(digest) = signature
+		.strip_prefix("sha256=")
+		.and_then(|hex_digest| hex::decode(hex_digest).ok())
+	else {
+		return false;
+	};
+
+	mac(secret, timestamp, body).verify_slice(&digest).is_ok()
+}
+
+/// Exponential backoff policy for delivery retries
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+	/// Maximum number of attempts per delivery run (including the first)
+	pub max_attempts: u32,
+	/// Delay before the first retry
+	pub base_delay: Duration,
+	/// Upper bound for any single delay
+	pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+	/// Delay to wait after the given (1-based) failed attempt
+	pub fn delay_for(&self, attempt: u32) -> Duration {
+		let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+		self.base_delay.saturating_mul(factor).min(self.max_delay)
+	}
+
+	/// Longest a delivery run can go without recording an attempt
+	///
+	/// A `pending` delivery quiet for longer than this has no run left
+	/// working on it.
+	pub fn window(&self) -> Duration {
+		let attempt = Duration::from_secs(ATTEMPT_TIMEOUT_SECONDS);
+		(1..self.max_attempts)
+			.map(|attempt| self.delay_for(attempt))
+			.sum::<Duration>()
+			+ attempt.saturating_mul(self.max_attempts)
+	}
+
+	/// Whether another attempt should follow the given result
+	pub fn should_retry(&self, result: &AttemptResult) -> bool {
+		!result.is_success() && result.is_retryable() && result.attempt < self.max_attempts
+	}
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			max_attempts: 6,
+			base_delay: Duration::from_secs(5),
+			max_delay: Duration::from_secs(600),
+		}
+	}
+}
+
+/// Body sent to webhook endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEnvelope {
+	pub event: WebhookEventKind,
+	pub tournament: String,
+	pub occurred_at: DateTime<Utc>,
+	pub data: serde_json::Value,
+}
+
+/// Everything needed to push one delivery over HTTP
+#[derive(Debug, Clone)]
+pub struct DeliveryRequest {
+	pub delivery_id: String,
+	pub url: String,
+	pub secret: String,
+	pub event: WebhookEventKind,
+	pub payload: serde_json::Value,
+}
+
+impl DeliveryRequest {
+	/// Build the request for a stored delivery
+	pub fn new(webhook: &Webhook, delivery: &WebhookDelivery) -> ApiResult<Self> {
+		if !validation::is_public_https_url(&webhook.url) {
+			return Err(ApiError::bad_request(
+				"Webhook URL must be an https URL on a public host",
+			));
+		}
+		let secret = webhook
+			.secret
+			.clone()
+			.ok_or_else(|| ApiError::internal("Webhook has no signing secret"))?;
+
+		Ok(Self {
+			delivery_id: delivery.id.key().to_string(),
+			url: webhook.url.clone(),
+			secret,
+			event: delivery.event,
+			payload: delivery.payload.clone(),
+		})
+	}
+}
+
+/// Outcome of a single HTTP attempt
+#[derive(Debug, Clone)]
+pub struct AttemptResult {
+	/// 1-based attempt number within the current delivery run
+	pub attempt: u32,
+	pub status_code: Option<u16>,
+	pub error: Option<String>,
+}
+
+impl AttemptResult {
+	/// The endpoint acknowledged the delivery with a 2xx status
+	pub fn is_success(&self) -> bool {
+		matches!(self.status_code, Some(code) if (200..300).contains(&code))
+	}
+
+	/// Network failures, timeouts, throttling and server errors are worth retrying
+	pub fn is_retryable(&self) -> bool {
+		match self.status_code {
+			None => true,
+			Some(code) => code >= 500 || code == 408 || code == 429,
+		}
+	}
+}
+
+/// DNS resolver that only hands out public addresses
+///
+/// Keeps a hostname that points at the internal network (or is later changed
+/// to) from turning webhooks into requests against internal services.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+	fn resolve(&self, name: Name) -> Resolving {
+		Box::pin(async move {
+			let public: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+				.await?
+				.filter(|addr| validation::is_public_ip(addr.ip()))
+				.collect();
+			if public.is_empty() {
+				return Err(format!("{} does not resolve to a public address", name.as_str()).into());
+			}
+			Ok(Box::new(public.into_iter()) as Addrs)
+		})
+	}
+}
+
+/// HTTP client that signs and pushes deliveries
+pub struct WebhookDispatcher {
+	client: reqwest::Client,
+	policy: RetryPolicy,
+}
+
+impl WebhookDispatcher {
+	/// Create a dispatcher with the given retry policy
+	///
+	/// Fails when the HTTP client can't be built (e.g. no TLS backend), rather
+	/// than falling back to a client without the public-host resolver.
+	pub fn new(policy: RetryPolicy) -> reqwest::Result<Self> {
+		let client = reqwest::Client::builder()
+			.timeout(Duration::from_secs(ATTEMPT_TIMEOUT_SECONDS))
+			.dns_resolver(Arc::new(PublicResolver))
+			.redirect(reqwest::redirect::Policy::none())
+			.user_agent(format!("liga-muertos-webhooks/{}", constants::APP_VERSION))
+			.build()?;
+
+		Ok(Self { client, policy })
+	}
+
+	/// Retry policy used by [`WebhookDispatcher::deliver`]
+	pub fn policy(&self) -> &RetryPolicy {
+		&self.policy
+	}
+
+	/// Perform a single signed HTTP attempt
+	pub async fn attempt(&self, request: &DeliveryRequest, attempt: u32) -> AttemptResult {
+		let body = request.payload.to_string().into_bytes();
+		let timestamp = Utc::now().timestamp();
+		let signature = sign(&request.secret, timestamp, &body);
+
+		let response = self
+			.client
+			.post(&request.url)
+			.header(reqwest::header::CONTENT_TYPE, "application/json")
+			.header(EVENT_HEADER, request.event.as_str())
+			.header(DELIVERY_HEADER, &request.delivery_id)
+			.header(TIMESTAMP_HEADER, timestamp.to_string())
+			.header(SIGNATURE_HEADER, signature)
+			.body(body)
+			.send()
+			.await;
+
+		match response {
+			Ok(response) => {
+				let code = response.status().as_u16();
+				let error = (!response.status().is_success())
+					.then(|| format!("Endpoint responded with status {code}"));
+				AttemptResult {
+					attempt,
+					status_code: Some(code),
+					error,
+				}
+			}
+			Err(e) => AttemptResult {
+				attempt,
+				status_code: None,
+				error: Some(e.to_string()),
+			},
+		}
+	}
+
+	/// Deliver with retries, reporting every attempt through `on_attempt`
+	///
+	/// Returns the result of the last attempt.
+	pub async fn deliver<F>(
+		&self,
+		request: &DeliveryRequest,
+		mut on_attempt: impl FnMut(&AttemptResult) -> F,
+	) -> AttemptResult
+	where
+		F: Future<Output = ()>,
+	{
+		let mut attempt = 1;
+		loop {
+			let result = self.attempt(request, attempt).await;
+			on_attempt(&result).await;
+
+			if !self.policy.should_retry(&result) {
+				return result;
+			}
+
+			tokio::time::sleep(self.policy.delay_for(attempt)).await;
+			attempt += 1;
+		}
+	}
+}
+
+/// Build a webhook record id from its key
+pub fn record_id(key: &str) -> RecordId {
+	RecordId::from(("webhook", key))
+}
+
+/// Build a webhook delivery record id from its key
+pub fn delivery_record_id(key: &str) -> RecordId {
+	RecordId::from(("webhook_delivery", key))
+}
+
+/// Register a webhook endpoint for a tournament
+pub async fn create(
+	db: &Database,
+	tournament: &RecordId,
+	data: CreateWebhookData,
+	created_by: &RecordId,
+) -> ApiResult<Webhook> {
+	let mut response = db
+		.query(
+			"CREATE webhook CONTENT {
+				tournament: $tournament,
+				url: $url,
+				events: $events,
+				secret: $secret OR rand::string(40),
+				active: true,
+				created_by: $created_by,
+			}",
+		)
+		.bind(("tournament", tournament.clone()))
+		.bind(("url", data.url))
+		.bind(("events", data.events))
+		.bind(("secret", data.secret))
+		.bind(("created_by", created_by.clone()))
+		.await?;
+
+	let webhook: Option<Webhook> = response.take(0)?;
+	let webhook = webhook.ok_or_else(|| ApiError::internal("Failed to create webhook"))?;
+
+	logging::tournament_event(
+		"webhook_registered",
+		&tournament.key().to_string(),
+		Some(&created_by.key().to_string()),
+	);
+	Ok(webhook)
+}
+
+/// List the webhook endpoints registered for a tournament
+pub async fn list(db: &Database, tournament: &RecordId) -> ApiResult<Vec<Webhook>> {
+	let mut response = db
+		.query("SELECT * FROM webhook WHERE tournament = $tournament ORDER BY created_at")
+		.bind(("tournament", tournament.clone()))
+		.await?;
+
+	Ok(response.take(0)?)
+}
+
+/// Fetch a webhook, making sure it belongs to the tournament
+pub async fn find(db: &Database, tournament: &RecordId, id: &RecordId) -> ApiResult<Webhook> {
+	let webhook: Option<Webhook> = db.select(id).await?;
+
+	webhook
+		.filter(|webhook| &webhook.tournament == tournament)
+		.ok_or_else(|| ApiError::not_found("webhook", &id.key().to_string()))
+}
+
+/// Update a webhook's URL, event filter or active flag
+pub async fn update(
+	db: &Database,
+	tournament: &RecordId,
+	id: &RecordId,
+	data: UpdateWebhookData,
+) -> ApiResult<Webhook> {
+	find(db, tournament, id).await?;
+
+	let mut changes = serde_json::Map::new();
+	if let Some(url) = data.url {
+		changes.insert("url".to_string(), url.into());
+	}
+	if let Some(events) = data.events {
+		changes.insert("events".to_string(), serde_json::to_value(events)?);
+	}
+	if let Some(active) = data.active {
+		changes.insert("active".to_string(), active.into());
+	}
+
+	let mut response = db
+		.query("UPDATE $id MERGE $changes")
+		.bind(("id", id.clone()))
+		.bind(("changes", serde_json::Value::Object(changes)))
+		.await?;
+
+	let webhook: Option<Webhook> = response.take(0)?;
+	webhook.ok_or_else(|| ApiError::not_found("webhook", &id.key().to_string()))
+}
+
+/// Remove a webhook endpoint together with its delivery log
+pub async fn delete(db: &Database, tournament: &RecordId, id: &RecordId) -> ApiResult<()> {
+	find(db, tournament, id).await?;
+
+	db.query(
+		"BEGIN TRANSACTION;
+		DELETE webhook_delivery WHERE webhook = $id;
+		DELETE $id;
+		COMMIT TRANSACTION;",
+	)
+	.bind(("id", id.clone()))
+	.await?
+	.check()?;
+
+	Ok(())
+}
+
+/// List the delivery log of a webhook, newest first
+pub async fn deliveries(
+	db: &Database,
+	webhook: &RecordId,
+	status: Option<DeliveryStatus>,
+) -> ApiResult<Vec<WebhookDelivery>> {
+	let mut response = db
+		.query(
+			"SELECT * FROM webhook_delivery
+				WHERE webhook = $webhook AND ($status = NONE OR status = $status)
+				ORDER BY created_at DESC",
+		)
+		.bind(("webhook", webhook.clone()))
+		.bind(("status", status))
+		.await?;
+
+	Ok(response.take(0)?)
+}
+
+/// Emit an event to every subscribed endpoint of the tournament
+///
+/// Deliveries are recorded before this returns and pushed in the background.
+/// Returns the number of deliveries queued.
+pub async fn emit(
+	state: &AppState,
+	event: WebhookEventKind,
+	tournament: &RecordId,
+	data: serde_json::Value,
+) -> ApiResult<usize> {
+	emit_all(state, event, tournament, vec![data]).await
+}
+
+/// Emit one event per entry of `data`, e.g. for every row of a bulk import
+///
+/// The subscribed endpoints are looked up once and all deliveries are
+/// recorded by a single statement.
+pub async fn emit_all(
+	state: &AppState,
+	event: WebhookEventKind,
+	tournament: &RecordId,
+	data: Vec<serde_json::Value>,
+) -> ApiResult<usize> {
+	if data.is_empty() {
+		return Ok(0);
+	}
+	logging::tournament_event(event.as_str(), &tournament.key().to_string(), None);
+
+	let subscribed: Vec<Webhook> = list(&state.db, tournament)
+		.await?
+		.into_iter()
+		.filter(|webhook| webhook.accepts(event))
+		.collect();
+	if subscribed.is_empty() {
+		return Ok(0);
+	}
+
+	#[derive(Serialize)]
+	struct NewDelivery {
+		webhook: RecordId,
+		event: WebhookEventKind,
+		payload: serde_json::Value,
+		status: DeliveryStatus,
+		attempts: u32,
+		history: Vec<DeliveryAttempt>,
+	}
+
+	let occurred_at = Utc::now();
+	let payloads = data
+		.into_iter()
+		.map(|data| {
+			serde_json::to_value(WebhookEnvelope {
+				event,
+				tournament: tournament.to_string(),
+				occurred_at,
+				data,
+			})
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+	let deliveries: Vec<NewDelivery> = subscribed
+		.iter()
+		.flat_map(|webhook| {
+			payloads.iter().map(|payload| NewDelivery {
+				webhook: webhook.id.clone(),
+				event,
+				payload: payload.clone(),
+				status: DeliveryStatus::Pending,
+				attempts: 0,
+				history: Vec::new(),
+			})
+		})
+		.collect();
+
+	let mut response = state
+		.db
+		.query("INSERT INTO webhook_delivery $deliveries")
+		.bind(("deliveries", deliveries))
+		.await?;
+	let created: Vec<WebhookDelivery> = response.take(0)?;
+
+	for delivery in &created {
+		if let Some(webhook) = subscribed
+			.iter()
+			.find(|webhook| webhook.id == delivery.webhook)
+		{
+			spawn_delivery(state, webhook.clone(), delivery.clone());
+		}
+	}
+	Ok(created.len())
+}
+
+/// Replay a finished delivery with a fresh retry budget
+///
+/// `failed` and `delivered` deliveries can be replayed. A `pending` one is
+/// being worked on, and replaying it would send it twice, unless it has been
+/// quiet for longer than a whole retry run: the run pushing it is gone, e.g.
+/// because the server restarted, and the replay takes over.
+pub async fn replay(
+	state: &AppState,
+	webhook: &Webhook,
+	delivery_id: &RecordId,
+) -> ApiResult<WebhookDelivery> {
+	let not_found = || ApiError::not_found("webhook_delivery", &delivery_id.key().to_string());
+
+	let existing: Option<WebhookDelivery> = state.db.select(delivery_id).await?;
+	existing
+		.filter(|delivery| delivery.webhook == webhook.id)
+		.ok_or_else(not_found)?;
+
+	let window = chrono::Duration::from_std(state.webhooks.policy().window())
+		.map_err(|_| ApiError::internal("Webhook retry window is out of range"))?;
+
+	// Requeuing stamps `queued_at` (`created_at` until the first replay), so
+	// concurrent replays start a single run
+	let mut response = state
+		.db
+		.query(
+			"UPDATE $id SET status = 'pending', last_error = NONE, queued_at = time::now()
+				WHERE webhook = $webhook AND (
+					status IN ['failed', 'delivered']
+					OR time::max(array::append(history.attempted_at, queued_at ?? created_at))
+						< <datetime> $stale_before
+				)",
+		)
+		.bind(("id", delivery_id.clone()))
+		.bind(("webhook", webhook.id.clone()))
+		.bind(("stale_before", (Utc::now() - window).to_rfc3339()))
+		.await?;
+
+	let delivery: Option<WebhookDelivery> = response.take(0)?;
+	let delivery =
+		delivery.ok_or_else(|| ApiError::conflict("This delivery is still in progress"))?;
+
+	spawn_delivery(state, webhook.clone(), delivery.clone());
+	Ok(delivery)
+}
+
+/// Push a delivery in the background, persisting every attempt
+fn spawn_delivery(state: &AppState, webhook: Webhook, delivery: WebhookDelivery) {
+	let (db, dispatcher) = (state.db.clone(), state.webhooks.clone());
+	state.jobs.push("webhook_delivery", async move {
+		let request = match DeliveryRequest::new(&webhook, &delivery) {
+			Ok(request) => request,
+			Err(e) => {
+				log::error!("Cannot deliver webhook {}: {e}", webhook.id);
+				return;
+			}
+		};
+
+		let record = |result: &AttemptResult| {
+			let (db, id, result, policy) = (
+				db.clone(),
+				delivery.id.clone(),
+				result.clone(),
+				dispatcher.policy().clone(),
+			);
+			async move {
+				if let Err(e) = record_attempt(&db, &id, &result, &policy).await {
+					log::error!("Failed to record webhook attempt for {id}: {e}");
+				}
+			}
+		};
+		let result = dispatcher.deliver(&request, record).await;
+
+		metrics::webhook_delivery(if result.is_success() {
+			"delivered"
+		} else {
+			"failed"
+		});
+		if !result.is_success() {
+			log::warn!(
+				"Webhook delivery {} to {} failed after {} attempt(s)",
+				delivery.id,
+				webhook.url,
+				result.attempt
+			);
+		}
+	});
+}
+
+/// Append an attempt to the delivery log and update its status
+async fn record_attempt(
+	db: &Database,
+	delivery: &RecordId,
+	result: &AttemptResult,
+	policy: &RetryPolicy,
+) -> ApiResult<()> {
+	let status = if result.is_success() {
+		DeliveryStatus::Delivered
+	} else if policy.should_retry(result) {
+		DeliveryStatus::Pending
+	} else {
+		DeliveryStatus::Failed
+	};
+
+	db.query(
+		"UPDATE $id SET
+			history += {
+				attempt: attempts + 1,
+				status_code: $status_code,
+				error: $error,
+				attempted_at: time::now(),
+			},
+			attempts += 1,
+			status = $status,
+			last_status_code = $status_code,
+			last_error = $error,
+			delivered_at = IF $status = 'delivered' THEN time::now() ELSE delivered_at END",
+	)
+	.bind(("id", delivery.clone()))
+	.bind(("status_code", result.status_code))
+	.bind(("error", result.error.clone()))
+	.bind(("status", status))
+	.await?
+	.check()?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::{Arc, Mutex};
+	use wiremock::matchers::{header, header_exists, method, path};
+	use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+	fn request(url: String) -> DeliveryRequest {
+		DeliveryRequest {
+			delivery_id: "delivery123".to_string(),
+			url,
+			secret: "top-secret".to_string(),
+			event: WebhookEventKind::MatchReported,
+			payload: serde_json::json!({ "event": "match.reported", "data": { "score": "2-1" } }),
+		}
+	}
+
+	fn fast_policy(max_attempts: u32) -> RetryPolicy {
+		RetryPolicy {
+			max_attempts,
+			base_delay: Duration::from_millis(1),
+			max_delay: Duration::from_millis(5),
+		}
+	}
+
+	#[test]
+	fn test_sign_and_verify() {
+		let body = br#"{"hello":"world"}"#;
+		let signature = sign("secret", 1_700_000_000, body);
+
+		assert!(signature.starts_with("sha256="));
+		assert!(verify("secret", 1_700_000_000, body, &signature));
+		assert!(!verify("other", 1_700_000_000, body, &signature));
+		assert!(!verify("secret", 1_700_000_001, body, &signature));
+		assert!(!verify("secret", 1_700_000_000, b"{}", &signature));
+		assert!(!verify("secret", 1_700_000_000, body, "md5=abc"));
+	}
+
+	#[test]
+	fn test_retry_policy_backoff() {
+		let policy = RetryPolicy {
+			max_attempts: 10,
+			base_delay: Duration::from_secs(2),
+			max_delay: Duration::from_secs(30),
+		};
+
+		assert_eq!(policy.delay_for(1), Duration::from_secs(2));
+		assert_eq!(policy.delay_for(2), Duration::from_secs(4));
+		assert_eq!(policy.delay_for(3), Duration::from_secs(8));
+		assert_eq!(policy.delay_for(5), Duration::from_secs(30));
+		assert_eq!(policy.delay_for(64), Duration::from_secs(30));
+		// 180s of backoff between ten attempts of up to 10s each
+		assert_eq!(policy.window(), Duration::from_secs(280));
+	}
+
+	#[test]
+	fn test_attempt_result_classification() {
+		let result = |status_code| AttemptResult {
+			attempt: 1,
+			status_code,
+			error: None,
+		};
+
+		assert!(result(Some(204)).is_success());
+		assert!(result(None).is_retryable());
+		assert!(result(Some(503)).is_retryable());
+		assert!(result(Some(429)).is_retryable());
+		assert!(!result(Some(404)).is_retryable());
+
+		let policy = fast_policy(3);
+		assert!(!policy.should_retry(&result(Some(200))));
+		assert!(!policy.should_retry(&result(Some(400))));
+		assert!(policy.should_retry(&result(Some(500))));
+		assert!(!policy.should_retry(&AttemptResult {
+			attempt: 3,
+			status_code: Some(500),
+			error: None,
+		}));
+	}
+
+	#[actix_web::test]
+	async fn test_delivery_is_signed() {
+		let server = MockServer::start().await;
+		Mock::given(method("POST"))
+			.and(path("/hook"))
+			.and(header(EVENT_HEADER, "match.reported"))
+			.and(header(DELIVERY_HEADER, "delivery123"))
+			.and(header_exists(TIMESTAMP_HEADER))
+			.and(header_exists(SIGNATURE_HEADER))
+			.respond_with(ResponseTemplate::new(200))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let dispatcher = WebhookDispatcher::new(fast_policy(3)).unwrap();
+		let result = dispatcher
+			.attempt(&request(format!("{}/hook", server.uri())), 1)
+			.await;
+		assert!(result.is_success());
+
+		let received: Vec<Request> = server.received_requests().await.unwrap();
+		let req = &received[0];
+		let timestamp: i64 = req.headers[TIMESTAMP_HEADER]
+			.to_str()
+			.unwrap()
+			.parse()
+			.unwrap();
+		let signature = req.headers[SIGNATURE_HEADER].to_str().unwrap();
+		assert!(verify("top-secret", timestamp, &req.body, signature));
+	}
+
+	#[actix_web::test]
+	async fn test_delivery_retries_until_success() {
+		let server = MockServer::start().await;
+		Mock::given(method("POST"))
+			.respond_with(ResponseTemplate::new(503))
+			.up_to_n_times(2)
+			.mount(&server)
+			.await;
+		Mock::given(method("POST"))
+			.respond_with(ResponseTemplate::new(200))
+			.mount(&server)
+			.await;
+
+		let dispatcher = WebhookDispatcher::new(fast_policy(5)).unwrap();
+		let attempts = Arc::new(Mutex::new(Vec::new()));
+		let result = dispatcher
+			.deliver(&request(server.uri()), |result: &AttemptResult| {
+				attempts.lock().unwrap().push(result.status_code);
+				async {}
+			})
+			.await;
+
+		assert!(result.is_success());
+		assert_eq!(result.attempt, 3);
+		assert_eq!(
+			*attempts.lock().unwrap(),
+			vec![Some(503), Some(503), Some(200)]
+		);
+	}
+
+	#[actix_web::test]
+	async fn test_delivery_gives_up_after_max_attempts() {
+		let server = MockServer::start().await;
+		Mock::given(method("POST"))
+			.respond_with(ResponseTemplate::new(500))
+			.expect(3)
+			.mount(&server)
+			.await;
+
+		let dispatcher = WebhookDispatcher::new(fast_policy(3)).unwrap();
+		let result = dispatcher
+			.deliver(&request(server.uri()), |_| async {})
+			.await;
+
+		assert!(!result.is_success());
+		assert_eq!(result.attempt, 3);
+		assert_eq!(result.status_code, Some(500));
+	}
+
+	#[actix_web::test]
+	async fn test_delivery_does_not_follow_redirects() {
+		// Stands in for an internal service the endpoint points us at
+		let internal = MockServer::start().await;
+		Mock::given(method("POST"))
+			.respond_with(ResponseTemplate::new(200))
+			.expect(0)
+			.mount(&internal)
+			.await;
+
+		let server = MockServer::start().await;
+		Mock::given(method("POST"))
+			.respond_with(
+				ResponseTemplate::new(302)
+					.insert_header("Location", format!("{}/latest/meta-data", internal.uri())),
+			)
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let dispatcher = WebhookDispatcher::new(fast_policy(3)).unwrap();
+		let result = dispatcher
+			.deliver(&request(server.uri()), |_| async {})
+			.await;
+
+		assert!(!result.is_success());
+		assert_eq!(result.attempt, 1);
+		assert_eq!(result.status_code, Some(302));
+	}
+
+	#[actix_web::test]
+	async fn test_delivery_does_not_retry_client_errors() {
+		let server = MockServer::start().await;
+		Mock::given(method("POST"))
+			.respond_with(ResponseTemplate::new(410))
+			.expect(1)
+			.mount(&server)
+			.await;
+
+		let dispatcher = WebhookDispatcher::new(fast_policy(5)).unwrap();
+		let result = dispatcher
+			.deliver(&request(server.uri()), |_| async {})
+			.await;
+
+		assert_eq!(result.attempt, 1);
+		assert_eq!(result.status_code, Some(410));
+	}
+}
@@ -0,0 +1,459 @@
+//! Database connection supervision
+//!
+//! [`Supervisor`] owns the SurrealDB session: it makes the first connection
+//! (retrying with backoff while the database comes up), then pings it in the
+//! background. When pings start failing the connection is marked as
+//! reconnecting; the WebSocket engine re-dials on its own, and once the
+//! database answers again the supervisor signs in, re-selects the namespace and
+//! database and restarts every registered LIVE query, since the server drops
+//! them with the old socket.
+//!
+//! The current [`ConnectionStatus`] is read by the health endpoint and
+//! `/metrics`, and every change to it is mirrored in the `db_connection_up`
+//! gauge.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use surrealdb::Surreal;
+use surrealdb::engine::any::Any;
+use surrealdb::opt::auth::Root;
+
+use crate::config::DatabaseConfig;
+use crate::utils::error::{ApiError, ApiResult};
+use crate::utils::{logging, metrics};
+
+/// Attempts at the first connection before startup gives up
+const CONNECT_ATTEMPTS: u32 = 5;
+
+/// Delay between pings while the connection is healthy
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A ping slower than this counts as a failure
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Future returned by a LIVE query starter
+pub type LiveStart = std::pin::Pin<Box<dyn Future<Output = ApiResult<()>> + Send + 'static>>;
+
+/// Starts (or restarts) a LIVE query on the given connection
+type LiveStarter = Arc<dyn Fn(Surreal<Any>) -> LiveStart + Send + Sync>;
+
+/// Exponential backoff between connection attempts
+#[derive(Debug, Clone)]
+pub struct Backoff {
+	/// Delay after the first failure
+	pub base: Duration,
+	/// Upper bound for any single delay
+	pub max: Duration,
+}
+
+impl Backoff {
+	/// Delay to wait after the given (1-based) consecutive failure
+	pub fn delay_for(&self, failure: u32) -> Duration {
+		let factor = 2u32.saturating_pow(failure.saturating_sub(1));
+		self.base.saturating_mul(factor).min(self.max)
+	}
+}
+
+impl Default for Backoff {
+	fn default() -> Self {
+		Self {
+			base: Duration::from_secs(1),
+			max: Duration::from_secs(30),
+		}
+	}
+}
+
+/// Where the database connection stands
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+	/// Not connected yet
+	Connecting,
+	Connected,
+	/// Was connected, currently unreachable
+	Reconnecting,
+}
+
+impl ConnectionState {
+	pub const ALL: [ConnectionState; 3] = [
+		ConnectionState::Connecting,
+		ConnectionState::Connected,
+		ConnectionState::Reconnecting,
+	];
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ConnectionState::Connecting => "connecting",
+			ConnectionState::Connected => "connected",
+			ConnectionState::Reconnecting => "reconnecting",
+		}
+	}
+}
+
+/// Snapshot of the connection, as reported by health and metrics
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConnectionStatus {
+	pub state: ConnectionState,
+	/// When the connection entered its current state
+	pub since: DateTime<Utc>,
+	/// Successful recoveries since startup
+	pub reconnects: u64,
+	/// Consecutive failed pings or attempts
+	pub failures: u32,
+	pub last_error: Option<String>,
+	/// Round trip of the last successful ping
+	pub latency_ms: Option<u64>,
+}
+
+impl ConnectionStatus {
+	fn new() -> Self {
+		Self {
+			state: ConnectionState::Connecting,
+			since: Utc::now(),
+			reconnects: 0,
+			failures: 0,
+			last_error: None,
+			latency_ms: None,
+		}
+	}
+}
+
+/// A LIVE query to restart after every reconnection
+struct LiveQuery {
+	name: &'static str,
+	start: LiveStarter,
+}
+
+/// Keeps the database session alive and reports on it
+pub struct Supervisor {
+	db: Surreal<Any>,
+	config: DatabaseConfig,
+	backoff: Backoff,
+	status: Mutex<ConnectionStatus>,
+	live_queries: Mutex<Vec<LiveQuery>>,
+}
+
+impl Supervisor {
+	pub fn new(db: Surreal<Any>, config: DatabaseConfig) -> Self {
+		Self::with_backoff(db, config, Backoff::default())
+	}
+
+	pub fn with_backoff(db: Surreal<Any>, config: DatabaseConfig, backoff: Backoff) -> Self {
+		Self {
+			db,
+			config,
+			backoff,
+			status: Mutex::new(ConnectionStatus::new()),
+			live_queries: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// Current connection status
+	pub fn status(&self) -> ConnectionStatus {
+		self
+			.status
+			.lock()
+			.unwrap_or_else(|e| e.into_inner())
+			.clone()
+	}
+
+	pub fn is_connected(&self) -> bool {
+		self.status().state == ConnectionState::Connected
+	}
+
+	/// Make the first connection, retrying with backoff
+	pub async fn connect(&self) -> ApiResult<()> {
+		let mut failure = 0;
+		loop {
+			let outcome = match self.db.connect(&self.config.url).await {
+				// A retry after a failed sign-in finds the engine already set up
+				Ok(()) | Err(surrealdb::Error::Api(surrealdb::error::Api::AlreadyConnected)) => {
+					self.authenticate().await
+				}
+				Err(e) => Err(e.into()),
+			};
+
+			match outcome {
+				Ok(()) => {
+					self.update(|status| {
+						status.state = ConnectionState::Connected;
+						status.since = Utc::now();
+						status.failures = 0;
+						status.last_error = None;
+					});
+					logging::database_info(
+						&self.config.url,
+						&self.config.namespace,
+						&self.config.database,
+					);
+					return Ok(());
+				}
+				Err(e) => {
+					failure += 1;
+					self.update(|status| {
+						status.failures = failure;
+						status.last_error = Some(e.to_string());
+					});
+					if failure >= CONNECT_ATTEMPTS {
+						return Err(e);
+					}
+					let delay = self.backoff.delay_for(failure);
+					log::warn!(
+						"⏳ Database not reachable ({e}), retrying in {}s ({failure}/{CONNECT_ATTEMPTS})",
+						delay.as_secs()
+					);
+					tokio::time::sleep(delay).await;
+				}
+			}
+		}
+	}
+
+	/// Sign in and select the namespace and database
+	async fn authenticate(&self) -> ApiResult<()> {
+		// Embedded engines have no users to sign in as
+		if !self.config.url.starts_with("mem:") {
+			self
+				.db
+				.signin(Root {
+					username: &self.config.username,
+					password: self.config.password.expose(),
+				})
+				.await?;
+		}
+		self
+			.db
+			.use_ns(&self.config.namespace)
+			.use_db(&self.config.database)
+			.await?;
+		Ok(())
+	}
+
+	/// Round trip a trivial query
+	pub async fn ping(&self) -> ApiResult<Duration> {
+		let started = Instant::now();
+		match tokio::time::timeout(PING_TIMEOUT, self.db.query("RETURN 1")).await {
+			Ok(response) => {
+				response?.check()?;
+				Ok(started.elapsed())
+			}
+			Err(_) => Err(ApiError::ExternalService {
+				service: "SurrealDB".to_string(),
+				message: format!("No answer within {}s", PING_TIMEOUT.as_secs()),
+			}),
+		}
+	}
+
+	/// One supervision round: ping, and recover the session after an outage
+	///
+	/// Returns how long to wait before the next round.
+	pub async fn check(&self) -> Duration {
+		let ping = self.ping().await;
+		let previous = self.status();
+
+		match ping {
+			Ok(latency) if previous.state == ConnectionState::Connected => {
+				self.update(|status| status.latency_ms = Some(latency.as_millis() as u64));
+				PING_INTERVAL
+			}
+			Ok(latency) => match self.recover().await {
+				Ok(()) => {
+					// Only a connection that was lost counts as a reconnect
+					let reconnected = previous.state == ConnectionState::Reconnecting;
+					let outage = (Utc::now() - previous.since).to_std().unwrap_or_default();
+					self.update(|status| {
+						status.state = ConnectionState::Connected;
+						status.since = Utc::now();
+						if reconnected {
+							status.reconnects += 1;
+						}
+						status.failures = 0;
+						status.last_error = None;
+						status.latency_ms = Some(latency.as_millis() as u64);
+					});
+					if reconnected {
+						logging::database_reconnected(outage, self.status().reconnects);
+					} else {
+						logging::database_info(
+							&self.config.url,
+							&self.config.namespace,
+							&self.config.database,
+						);
+					}
+					PING_INTERVAL
+				}
+				Err(e) => self.failed(&e),
+			},
+			Err(e) => self.failed(&e),
+		}
+	}
+
+	/// Record a failed round, returning the backoff before the next one
+	fn failed(&self, error: &ApiError) -> Duration {
+		let mut lost = false;
+		self.update(|status| {
+			if status.state == ConnectionState::Connected {
+				status.state = ConnectionState::Reconnecting;
+				status.since = Utc::now();
+				lost = true;
+			}
+			status.failures += 1;
+			status.last_error = Some(error.to_string());
+		});
+		if lost {
+			logging::database_connection_lost(&error.to_string());
+		}
+		self.backoff.delay_for(self.status().failures)
+	}
+
+	/// Restore the session and LIVE queries on a connection that answers again
+	async fn recover(&self) -> ApiResult<()> {
+		self.authenticate().await?;
+
+		let starters: Vec<(&'static str, LiveStarter)> = self
+			.live_queries
+			.lock()
+			.unwrap_or_else(|e| e.into_inner())
+			.iter()
+			.map(|query| (query.name, query.start.clone()))
+			.collect();
+		for (name, start) in starters {
+			if let Err(e) = start(self.db.clone()).await {
+				log::error!("Failed to restart LIVE query {name}: {e}");
+			}
+		}
+		Ok(())
+	}
+
+	/// Start a LIVE query now (when connected) and again after every reconnection
+	///
+	/// `start` issues the `LIVE SELECT` and spawns whatever consumes its
+	/// notifications; the previous consumer simply ends when its stream closes.
+	pub async fn register_live<F>(&self, name: &'static str, start: F) -> ApiResult<()>
+	where
+		F: Fn(Surreal<Any>) -> LiveStart + Send + Sync + 'static,
+	{
+		let start: LiveStarter = Arc::new(start);
+		self
+			.live_queries
+			.lock()
+			.unwrap_or_else(|e| e.into_inner())
+			.push(LiveQuery {
+				name,
+				start: start.clone(),
+			});
+
+		if self.is_connected() {
+			start(self.db.clone()).await?;
+		}
+		Ok(())
+	}
+
+	/// Supervise the connection until the process exits
+	pub fn watch(self: Arc<Self>) {
+		actix_web::rt::spawn(async move {
+			loop {
+				let wait = self.check().await;
+				tokio::time::sleep(wait).await;
+			}
+		});
+	}
+
+	fn update(&self, change: impl FnOnce(&mut ConnectionStatus)) {
+		let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+		change(&mut status);
+		metrics::db_connection_up(status.state == ConnectionState::Connected);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	fn mem_config() -> DatabaseConfig {
+		DatabaseConfig {
+			url: "mem://".to_string(),
+			namespace: "supervisor".to_string(),
+			database: "test".to_string(),
+			..DatabaseConfig::default()
+		}
+	}
+
+	#[test]
+	fn test_backoff_doubles_up_to_the_cap() {
+		let backoff = Backoff::default();
+		assert_eq!(backoff.delay_for(1), Duration::from_secs(1));
+		assert_eq!(backoff.delay_for(3), Duration::from_secs(4));
+		assert_eq!(backoff.delay_for(10), Duration::from_secs(30));
+	}
+
+	#[actix_web::test]
+	async fn test_recovers_session_and_live_queries_after_an_outage() {
+		let db: Surreal<Any> = Surreal::init();
+		let supervisor = Supervisor::new(db.clone(), mem_config());
+		let starts = Arc::new(AtomicUsize::new(0));
+		let counter = starts.clone();
+		supervisor
+			.register_live("test", move |_| {
+				counter.fetch_add(1, Ordering::SeqCst);
+				Box::pin(async { Ok(()) })
+			})
+			.await
+			.unwrap();
+		// Not connected yet, so nothing was started
+		assert_eq!(starts.load(Ordering::SeqCst), 0);
+
+		supervisor.connect().await.unwrap();
+		assert!(supervisor.is_connected());
+
+		// A failed round marks the connection as lost
+		supervisor.failed(&ApiError::internal("socket closed"));
+		let status = supervisor.status();
+		assert_eq!(status.state, ConnectionState::Reconnecting);
+		assert_eq!(status.failures, 1);
+
+		// The next successful ping restores the session and the LIVE query
+		assert_eq!(supervisor.check().await, PING_INTERVAL);
+		let status = supervisor.status();
+		assert_eq!(status.state, ConnectionState::Connected);
+		assert_eq!(status.reconnects, 1);
+		assert_eq!(status.failures, 0);
+		assert!(status.latency_ms.is_some());
+		assert_eq!(starts.load(Ordering::SeqCst), 1);
+	}
+
+	#[actix_web::test]
+	async fn test_first_connection_is_not_a_reconnect() {
+		let db: Surreal<Any> = Surreal::init();
+		db.connect("mem://").await.unwrap();
+		let supervisor = Supervisor::new(db, mem_config());
+
+		// The engine came up on its own; the first good round only connects
+		assert_eq!(supervisor.check().await, PING_INTERVAL);
+		let status = supervisor.status();
+		assert_eq!(status.state, ConnectionState::Connected);
+		assert_eq!(status.reconnects, 0);
+	}
+
+	#[actix_web::test]
+	async fn test_unreachable_database_is_reported() {
+		let supervisor = Supervisor::with_backoff(
+			Surreal::init(),
+			mem_config(),
+			Backoff {
+				base: Duration::from_millis(1),
+				max: Duration::from_millis(1),
+			},
+		);
+
+		// Never connected: pings fail without leaving the connecting state
+		supervisor.check().await;
+		let status = supervisor.status();
+		assert_eq!(status.state, ConnectionState::Connecting);
+		assert_eq!(status.failures, 1);
+		assert!(status.last_error.is_some());
+	}
+}
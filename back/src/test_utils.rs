@@ -0,0 +1,177 @@
+//! Test harness backed by an in-memory SurrealDB (`test-utils` feature)
+//!
+//! [`setup`] connects the global [`DB`] to a `mem://` instance and applies
+//! every migration, so services and routes run against the real schema
+//! without a server. The database lives for the whole test binary and is
+//! shared by all its tests; factories give every record a unique key, so tests
+//! stay independent as long as they only look at the records they created.
+//!
+//! Routes see a signed-in user through [`fake_auth`], which trusts the
+//! [`TEST_USER_HEADER`] instead of validating a Clerk session:
+//!
+//! ```rust,ignore
+//! test_utils::setup();
+//! let organizer = factories::user("catrina").await;
+//! let app = test::init_service(
+//!     App::new()
+//!         .wrap(from_fn(test_utils::fake_auth))
+//!         .app_data(web::Data::new(AppState::new(Config::default())))
+//!         .configure(routes::entry),
+//! )
+//! .await;
+//! let req = test::TestRequest::get()
+//!     .uri("/v1/tournaments/t1/matches")
+//!     .insert_header(test_utils::signed_in(&organizer))
+//!     .to_request();
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use actix_web::{
+	HttpMessage,
+	body::MessageBody,
+	dev::{ServiceRequest, ServiceResponse},
+	middleware::Next,
+};
+
+use crate::middleware::auth::AuthUser;
+use crate::middleware::request_id;
+use crate::repositories::{SurrealParticipantRepository, SurrealTournamentRepository};
+use crate::{AppState, DB, migrations};
+
+/// Header carrying the key of the user a test request acts as
+pub const TEST_USER_HEADER: &str = "X-Test-User";
+
+static READY: OnceLock<()> = OnceLock::new();
+
+/// Connect the global database to an in-memory instance with the schema applied
+///
+/// Safe to call from every test; only the first call does the work. The
+/// embedded engine runs on a dedicated thread, because each `#[actix_web::test]`
+/// has its own runtime and background tasks die with it.
+pub fn setup() {
+	READY.get_or_init(|| {
+		let (ready, started) = std::sync::mpsc::channel();
+		std::thread::spawn(move || {
+			let runtime = tokio::runtime::Builder::new_current_thread()
+				.enable_all()
+				.build()
+				.expect("failed to build the test database runtime");
+			runtime.block_on(async move {
+				let outcome = async {
+					DB.connect("mem://").await?;
+					DB.use_ns("test").use_db("test").await?;
+					migrations::run().await
+				}
+				.await;
+				let _ = ready.send(outcome.map_err(|e| e.to_string()));
+				std::future::pending::<()>().await
+			});
+		});
+
+		started
+			.recv()
+			.expect("test database thread stopped")
+			.expect("failed to set up the test database");
+	});
+}
+
+/// State for tests that read and write the database connected by [`setup`]
+///
+/// Same as [`AppState::new_test`], except that the repositories are the
+/// SurrealDB ones, which see the records the [`factories`] create.
+pub fn state() -> AppState {
+	let state = AppState::new_test();
+	AppState {
+		tournaments: Arc::new(SurrealTournamentRepository::new(state.db.clone())),
+		participants: Arc::new(SurrealParticipantRepository::new(state.db.clone())),
+		..state
+	}
+}
+
+/// Header that makes [`fake_auth`] sign the request in as `user`
+pub fn signed_in(user: &crate::entities::User) -> (&'static str, String) {
+	(TEST_USER_HEADER, user.id.key().to_string())
+}
+
+/// Middleware standing in for Clerk authentication in tests
+pub async fn fake_auth(
+	req: ServiceRequest,
+	next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+	let user = req
+		.headers()
+		.get(TEST_USER_HEADER)
+		.and_then(|value| value.to_str().ok())
+		.map(AuthUser::new);
+	if let Some(user) = user {
+		request_id::set_user(&user.id.key().to_string());
+		req.extensions_mut().insert(user);
+	}
+	next.call(req).await
+}
+
+/// Unique record key with a readable prefix
+pub fn unique_key(prefix: &str) -> String {
+	static NEXT: AtomicU64 = AtomicU64::new(1);
+	format!("{prefix}_{}", NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Builders for records in the test database
+pub mod factories {
+	use surrealdb::RecordId;
+
+	use super::unique_key;
+	use crate::DB;
+	use crate::entities::{Participant, Tournament, User};
+
+	/// Create a user whose username and email derive from `name`
+	///
+	/// The username gets a unique suffix so tests can reuse names.
+	pub async fn user(name: &str) -> User {
+		let key = unique_key(name);
+		let mut response = DB
+			.query("CREATE $id CONTENT { username: $username, email: $email }")
+			.bind(("id", RecordId::from(("user", key.as_str()))))
+			.bind(("username", key.clone()))
+			.bind(("email", format!("{key}@example.com")))
+			.await
+			.expect("failed to create user");
+		let created: Option<User> = response.take(0).expect("failed to read user");
+		created.expect("user was not created")
+	}
+
+	/// Create a tournament organized by `organizer`
+	pub async fn tournament(organizer: &User, published: bool) -> Tournament {
+		let mut response = DB
+			.query(
+				"CREATE $id CONTENT {
+					name: $name,
+					description: 'Created by a test factory',
+					published: $published,
+					created_by: $organizer,
+				}",
+			)
+			.bind(("id", RecordId::from(("tournament", unique_key("copa")))))
+			.bind(("name", unique_key("Copa")))
+			.bind(("published", published))
+			.bind(("organizer", organizer.id.clone()))
+			.await
+			.expect("failed to create tournament");
+		let created: Option<Tournament> = response.take(0).expect("failed to read tournament");
+		created.expect("tournament was not created")
+	}
+
+	/// Register `user` in `tournament`
+	pub async fn participant(tournament: &Tournament, user: &User) -> Participant {
+		let mut response = DB
+			.query("CREATE participant CONTENT { tournament: $tournament, user_id: $user }")
+			.bind(("tournament", tournament.id.clone()))
+			.bind(("user", user.id.clone()))
+			.await
+			.expect("failed to create participant");
+		let created: Option<Participant> = response.take(0).expect("failed to read participant");
+		created.expect("participant was not created")
+	}
+}
@@ -0,0 +1,225 @@
+//! Classification of SurrealDB errors
+//!
+//! Embedded engines (`mem://` in tests) return typed [`Db`] errors, which are
+//! matched directly. Remote engines (`ws://`, `http://`) only send the
+//! server's message back as [`Api::Query`]; those are parsed using the same
+//! message formats the typed errors render to, so both end up as the same
+//! [`ApiError`].
+
+use surrealdb::error::{Api, Db};
+
+use super::ApiError;
+
+/// Fields behind the unique indexes defined in `migrations`
+const UNIQUE_INDEX_FIELDS: &[(&str, &str)] = &[
+	("participant_tournament_user", "user_id"),
+	("prediction_match_user", "user"),
+	("poll_vote_user", "user"),
+	("twitch_account_user", "user"),
+	("twitch_account_twitch_id", "twitch_id"),
+];
+
+/// Map a SurrealDB error to the API error it stands for
+pub fn classify(error: &surrealdb::Error) -> ApiError {
+	match error {
+		surrealdb::Error::Db(error) => classify_db(error),
+		surrealdb::Error::Api(Api::Query(message)) => classify_message(message),
+		surrealdb::Error::Api(
+			Api::Ws(_) | Api::Http(_) | Api::ConnectionUninitialised | Api::AlreadyConnected,
+		) => connection_error(),
+		surrealdb::Error::Api(error) => ApiError::Database {
+			message: error.to_string(),
+		},
+	}
+}
+
+fn classify_db(error: &Db) -> ApiError {
+	match error {
+		Db::IndexExists { thing, index, .. } => duplicate_value(&thing.tb, index),
+		Db::RecordExists { thing } => ApiError::Conflict {
+			message: format!("{} {} already exists", thing.tb, thing.id),
+			field: Some("id".to_string()),
+		},
+		Db::IdNotFound { rid } => record_not_found(rid),
+		Db::TbNotFound { name } => ApiError::not_found(name, ""),
+		Db::TablePermissions { table } => permission_denied(table),
+		Db::Thrown(message) => ApiError::conflict(message),
+		Db::NsNotAllowed { .. }
+		| Db::DbNotAllowed { .. }
+		| Db::ScriptingNotAllowed
+		| Db::FunctionNotAllowed(_)
+		| Db::NetTargetNotAllowed(_) => ApiError::authorization(&error.to_string()),
+		_ => ApiError::Database {
+			message: error.to_string(),
+		},
+	}
+}
+
+/// Classify a server message, as rendered by the matching [`Db`] variant
+fn classify_message(message: &str) -> ApiError {
+	// Database index `{index}` already contains {value}, with record `{thing}`
+	if let Some(index) = between(message, "Database index `", "` already contains") {
+		let table = message
+			.rsplit_once("with record `")
+			.and_then(|(_, thing)| thing.split_once(':'))
+			.map_or("record", |(table, _)| table);
+		return duplicate_value(table, index);
+	}
+	// Database record `{thing}` already exists
+	if let Some(thing) = between(message, "Database record `", "` already exists") {
+		let (table, id) = thing.split_once(':').unwrap_or(("record", thing));
+		return ApiError::Conflict {
+			message: format!("{table} {id} already exists"),
+			field: Some("id".to_string()),
+		};
+	}
+	// The record '{rid}' does not exist
+	if let Some(rid) = between(message, "The record '", "' does not exist") {
+		return record_not_found(rid);
+	}
+	// You don't have permission to run this query on the `{table}` table
+	if let Some(table) = between(
+		message,
+		"You don't have permission to run this query on the `",
+		"` table",
+	) {
+		return permission_denied(table);
+	}
+	// An error occurred: {message}
+	if let Some(thrown) = message.strip_prefix("An error occurred: ") {
+		return ApiError::conflict(thrown);
+	}
+	ApiError::Database {
+		message: message.to_string(),
+	}
+}
+
+fn duplicate_value(table: &str, index: &str) -> ApiError {
+	let field = UNIQUE_INDEX_FIELDS
+		.iter()
+		.find(|(name, _)| *name == index)
+		.map_or(index, |(_, field)| field);
+	ApiError::Conflict {
+		message: format!("A {table} with this {field} already exists"),
+		field: Some(field.to_string()),
+	}
+}
+
+fn record_not_found(rid: &str) -> ApiError {
+	match rid.split_once(':') {
+		Some((table, id)) => ApiError::not_found(table, id.trim_matches(['⟨', '⟩', '`'])),
+		None => ApiError::not_found("record", rid),
+	}
+}
+
+fn permission_denied(table: &str) -> ApiError {
+	ApiError::authorization(&format!("Not allowed to access {table} records"))
+}
+
+fn connection_error() -> ApiError {
+	ApiError::Database {
+		message: "Database connection error".to_string(),
+	}
+}
+
+/// The text between `prefix` and the next `suffix`
+fn between<'a>(message: &'a str, prefix: &str, suffix: &str) -> Option<&'a str> {
+	let start = message.find(prefix)? + prefix.len();
+	let end = message[start..].find(suffix)?;
+	Some(&message[start..start + end])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::DB;
+	use crate::test_utils::{self, factories};
+	use surrealdb::sql::Thing;
+
+	#[test]
+	fn test_typed_errors_keep_their_details() {
+		let thing = Thing::from(("participant", "abc"));
+
+		let err = classify(&surrealdb::Error::Db(Db::IndexExists {
+			thing: thing.clone(),
+			index: "participant_tournament_user".to_string(),
+			value: "['tournament:copa', 'user_1']".to_string(),
+		}));
+		assert!(matches!(&err, ApiError::Conflict { field: Some(field), .. } if field == "user_id"));
+
+		let err = classify(&surrealdb::Error::Db(Db::IdNotFound {
+			rid: "match:m1".to_string(),
+		}));
+		assert!(
+			matches!(&err, ApiError::NotFound { resource, id } if resource == "match" && id == "m1")
+		);
+
+		let err = classify(&surrealdb::Error::Db(Db::TablePermissions {
+			table: "webhook".to_string(),
+		}));
+		assert!(matches!(err, ApiError::Authorization { .. }));
+
+		let err = classify(&surrealdb::Error::Db(Db::RecordExists { thing }));
+		assert!(matches!(err, ApiError::Conflict { .. }));
+
+		let err = classify(&surrealdb::Error::Db(Db::Thrown(
+			"This poll is closed".to_string(),
+		)));
+		assert_eq!(err.to_string(), "Conflict: This poll is closed");
+	}
+
+	#[test]
+	fn test_remote_messages_classify_like_typed_errors() {
+		let remote = |db: Db| surrealdb::Error::Api(Api::Query(db.to_string()));
+
+		let err = classify(&remote(Db::IndexExists {
+			thing: Thing::from(("twitch_account", "t1")),
+			index: "twitch_account_twitch_id".to_string(),
+			value: "'12345'".to_string(),
+		}));
+		assert!(matches!(&err, ApiError::Conflict { field: Some(field), .. } if field == "twitch_id"));
+		assert_eq!(
+			err.to_string(),
+			"Conflict: A twitch_account with this twitch_id already exists"
+		);
+
+		let err = classify(&remote(Db::IdNotFound {
+			rid: "tournament:copa".to_string(),
+		}));
+		assert!(
+			matches!(&err, ApiError::NotFound { resource, id } if resource == "tournament" && id == "copa")
+		);
+
+		let err = classify(&remote(Db::TablePermissions {
+			table: "poll".to_string(),
+		}));
+		assert!(matches!(err, ApiError::Authorization { .. }));
+
+		let err = classify(&remote(Db::Thrown("This poll is closed".to_string())));
+		assert_eq!(err.to_string(), "Conflict: This poll is closed");
+
+		let err = classify(&surrealdb::Error::Api(Api::Ws("closed".to_string())));
+		assert_eq!(err.to_string(), "Database error: Database connection error");
+	}
+
+	#[actix_web::test]
+	async fn test_duplicate_participant_names_the_field() {
+		test_utils::setup();
+		let organizer = factories::user("organizer").await;
+		let player = factories::user("player").await;
+		let tournament = factories::tournament(&organizer, true).await;
+		factories::participant(&tournament, &player).await;
+
+		let err: ApiError = DB
+			.query("CREATE participant CONTENT { tournament: $tournament, user_id: $user }")
+			.bind(("tournament", tournament.id.clone()))
+			.bind(("user", player.id.clone()))
+			.await
+			.unwrap()
+			.check()
+			.unwrap_err()
+			.into();
+		assert!(matches!(&err, ApiError::Conflict { field: Some(field), .. } if field == "user_id"));
+		assert_eq!(err.details().unwrap()["field"], "user_id");
+	}
+}
@@ -0,0 +1,251 @@
+//! Error handlers for actix's built-in extractors
+//!
+//! Without these, a malformed JSON body, path segment or query string is
+//! answered by actix with a plain-text message. [`config`] registers app-wide
+//! [`web::JsonConfig`], [`web::PathConfig`] and [`web::QueryConfig`] handlers
+//! that turn those failures into the usual [`ApiErrorResponse`] body, with the
+//! request path in `details.path` (and, for JSON syntax errors, the `line` and
+//! `column` of the problem). JSON and raw bodies are capped at
+//! [`constants::MAX_REQUEST_SIZE`]; raw bodies are read with [`Body`], as
+//! [`web::PayloadConfig`] takes no error handler.
+//!
+//! [`ApiErrorResponse`]: super::ApiErrorResponse
+
+use std::future::Future;
+use std::ops::Deref;
+use std::pin::Pin;
+
+use actix_web::{
+	FromRequest, HttpRequest,
+	dev::Payload,
+	error::{InternalError, JsonPayloadError, PathError, PayloadError, QueryPayloadError},
+	web,
+};
+use serde_json::{Map, Value};
+
+use super::ApiError;
+use crate::utils::constants;
+
+/// Register the extractor error handlers
+pub fn config(cfg: &mut web::ServiceConfig) {
+	cfg
+		.app_data(json_config())
+		.app_data(web::PayloadConfig::new(constants::MAX_REQUEST_SIZE))
+		.app_data(web::PathConfig::default().error_handler(path_error))
+		.app_data(web::QueryConfig::default().error_handler(query_error));
+}
+
+/// JSON body settings, also picked up by [`super::validation::ValidatedJson`]
+fn json_config() -> web::JsonConfig {
+	web::JsonConfig::default()
+		.limit(constants::MAX_REQUEST_SIZE)
+		.error_handler(json_error)
+}
+
+fn json_error(error: JsonPayloadError, req: &HttpRequest) -> actix_web::Error {
+	let mut extra = Map::new();
+	let api_error = match error {
+		JsonPayloadError::OverflowKnownLength { limit, .. } | JsonPayloadError::Overflow { limit } => {
+			ApiError::PayloadTooLarge { limit }
+		}
+		JsonPayloadError::ContentType => ApiError::UnsupportedMediaType {
+			message: "Expected an application/json body".to_string(),
+		},
+		JsonPayloadError::Deserialize(error) => {
+			extra.insert("line".to_string(), error.line().into());
+			extra.insert("column".to_string(), error.column().into());
+			ApiError::JsonParsing {
+				message: error.to_string(),
+			}
+		}
+		error => ApiError::bad_request(&error.to_string()),
+	};
+	reject(api_error, req, extra)
+}
+
+/// Raw request body, like [`web::Bytes`] but failing with an [`ApiError`]
+pub struct Body(pub web::Bytes);
+
+impl Deref for Body {
+	type Target = web::Bytes;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl FromRequest for Body {
+	type Error = actix_web::Error;
+	type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+	fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+		let bytes = web::Bytes::from_request(req, payload);
+		let req = req.clone();
+		Box::pin(async move {
+			bytes
+				.await
+				.map(Body)
+				.map_err(|error| body_error(error, &req))
+		})
+	}
+}
+
+fn body_error(error: actix_web::Error, req: &HttpRequest) -> actix_web::Error {
+	let api_error = match error.as_error::<PayloadError>() {
+		Some(PayloadError::Overflow) => ApiError::PayloadTooLarge {
+			limit: constants::MAX_REQUEST_SIZE,
+		},
+		_ => ApiError::bad_request(&error.to_string()),
+	};
+	reject(api_error, req, Map::new())
+}
+
+fn path_error(error: PathError, req: &HttpRequest) -> actix_web::Error {
+	let message = match error {
+		PathError::Deserialize(error) => format!("Invalid path parameter: {error}"),
+		error => error.to_string(),
+	};
+	reject(ApiError::bad_request(&message), req, Map::new())
+}
+
+fn query_error(error: QueryPayloadError, req: &HttpRequest) -> actix_web::Error {
+	let message = match error {
+		QueryPayloadError::Deserialize(error) => format!("Invalid query string: {error}"),
+		error => error.to_string(),
+	};
+	reject(ApiError::bad_request(&message), req, Map::new())
+}
+
+/// Render `error` right away, while the request's locale and id are known
+fn reject(error: ApiError, req: &HttpRequest, mut extra: Map<String, Value>) -> actix_web::Error {
+	extra.insert("path".to_string(), req.path().into());
+	let response = error.error_response_with(extra);
+	InternalError::from_response(error, response).into()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::utils::error::ApiErrorResponse;
+	use crate::utils::error::validation::{Validate, ValidatedJson, ValidationResult};
+	use actix_web::http::StatusCode;
+	use actix_web::{App, HttpResponse, test};
+	use serde::Deserialize;
+
+	#[derive(Deserialize)]
+	struct Rename {
+		name: String,
+	}
+
+	impl Validate for Rename {
+		fn validate(&self) -> ValidationResult<()> {
+			Ok(())
+		}
+	}
+
+	#[derive(Deserialize)]
+	struct Page {
+		page: u32,
+	}
+
+	async fn rename(body: ValidatedJson<Rename>) -> HttpResponse {
+		HttpResponse::Ok().body(body.name.clone())
+	}
+
+	async fn upload(body: Body) -> HttpResponse {
+		HttpResponse::Ok().body(format!("{} bytes", body.len()))
+	}
+
+	async fn round(path: web::Path<u32>, query: web::Query<Page>) -> HttpResponse {
+		HttpResponse::Ok().body(format!("round {} page {}", path, query.page))
+	}
+
+	async fn call(req: test::TestRequest) -> (StatusCode, ApiErrorResponse) {
+		let app = test::init_service(
+			App::new()
+				.configure(config)
+				.route("/rename", web::post().to(rename))
+				.route("/upload", web::post().to(upload))
+				.route("/rounds/{round}", web::get().to(round)),
+		)
+		.await;
+		let resp = test::call_service(&app, req.to_request()).await;
+		(resp.status(), test::read_body_json(resp).await)
+	}
+
+	#[actix_web::test]
+	async fn test_json_errors_are_api_errors() {
+		let (status, body) = call(
+			test::TestRequest::post()
+				.uri("/rename")
+				.insert_header(("content-type", "application/json"))
+				.set_payload(r#"{"name": 12}"#),
+		)
+		.await;
+		assert_eq!(status, StatusCode::BAD_REQUEST);
+		assert_eq!(body.error_code, "JSON_PARSING_ERROR");
+		let details = body.details.unwrap();
+		assert_eq!(details["path"], "/rename");
+		assert_eq!(details["line"], 1);
+
+		let (status, body) = call(
+			test::TestRequest::post()
+				.uri("/rename")
+				.insert_header(("content-type", "text/plain"))
+				.set_payload(r#"{"name": "Copa"}"#),
+		)
+		.await;
+		assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+		assert_eq!(body.error_code, "UNSUPPORTED_MEDIA_TYPE");
+
+		let name = "x".repeat(constants::MAX_REQUEST_SIZE);
+		let (status, body) = call(
+			test::TestRequest::post()
+				.uri("/rename")
+				.set_json(serde_json::json!({ "name": name })),
+		)
+		.await;
+		assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+		assert_eq!(body.details.unwrap()["limit"], constants::MAX_REQUEST_SIZE);
+	}
+
+	#[actix_web::test]
+	async fn test_oversized_raw_bodies_are_api_errors() {
+		let app = test::init_service(
+			App::new()
+				.configure(config)
+				.route("/upload", web::post().to(upload)),
+		)
+		.await;
+		let req = test::TestRequest::post()
+			.uri("/upload")
+			.set_payload("x".repeat(constants::MAX_REQUEST_SIZE))
+			.to_request();
+		assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+		let (status, body) = call(
+			test::TestRequest::post()
+				.uri("/upload")
+				.insert_header(("content-type", "text/csv"))
+				.set_payload("x".repeat(constants::MAX_REQUEST_SIZE + 1)),
+		)
+		.await;
+		assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+		assert_eq!(body.error_code, "PAYLOAD_TOO_LARGE");
+		let details = body.details.unwrap();
+		assert_eq!(details["path"], "/upload");
+		assert_eq!(details["limit"], constants::MAX_REQUEST_SIZE);
+	}
+
+	#[actix_web::test]
+	async fn test_path_and_query_errors_are_api_errors() {
+		let (status, body) = call(test::TestRequest::get().uri("/rounds/first?page=1")).await;
+		assert_eq!(status, StatusCode::BAD_REQUEST);
+		assert_eq!(body.error_code, "BAD_REQUEST");
+		assert_eq!(body.details.unwrap()["path"], "/rounds/first");
+
+		let (status, body) = call(test::TestRequest::get().uri("/rounds/1?page=last")).await;
+		assert_eq!(status, StatusCode::BAD_REQUEST);
+		assert!(body.message.contains("Invalid query string"));
+	}
+}
@@ -2,12 +2,21 @@
 //!
 //! This module provides comprehensive error handling for all API endpoints,
 //! with proper HTTP status codes, structured error responses, and logging.
+//!
+//! Messages are rendered in the request's language (see [`crate::utils::i18n`]);
+//! `details.message_key` and `details.message_params` always carry the
+//! untranslated key and params so clients can translate on their own.
 
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{HttpResponse, ResponseError, http::header};
 use serde::{Deserialize, Serialize};
 
 use thiserror::Error;
 
+use crate::middleware::{locale, request_id};
+use crate::utils::i18n::{self, Locale, Params};
+
+pub mod database;
+pub mod extractors;
 pub mod validation;
 
 /// Standard API error response format
@@ -62,6 +71,15 @@ pub enum ApiError {
 	Validation {
 		message: String,
 		field: Option<String>,
+		/// Rule that failed, when the error came from a validator
+		rule: Option<Box<validation::ValidationError>>,
+	},
+
+	/// Several validation errors reported together, e.g. every invalid field
+	/// of a form
+	#[error("Validation error: {}", .errors.summary())]
+	ValidationErrors {
+		errors: validation::ValidationErrors,
 	},
 
 	/// Authentication errors
@@ -78,11 +96,19 @@ pub enum ApiError {
 
 	/// Conflict errors (e.g., duplicate resources)
 	#[error("Conflict: {message}")]
-	Conflict { message: String },
+	Conflict {
+		message: String,
+		/// Field holding the duplicate value, when known
+		field: Option<String>,
+	},
 
 	/// Rate limiting errors
 	#[error("Rate limit exceeded: {message}")]
-	RateLimit { message: String },
+	RateLimit {
+		message: String,
+		/// Seconds until the client may retry, sent as `Retry-After`
+		retry_after: Option<u64>,
+	},
 
 	/// External service errors
 	#[error("External service error: {service} - {message}")]
@@ -100,6 +126,14 @@ pub enum ApiError {
 	#[error("JSON parsing error: {message}")]
 	JsonParsing { message: String },
 
+	/// Request body over the size limit
+	#[error("Payload too large: limit is {limit} bytes")]
+	PayloadTooLarge { limit: usize },
+
+	/// Request body in a format the endpoint doesn't accept
+	#[error("Unsupported media type: {message}")]
+	UnsupportedMediaType { message: String },
+
 	/// Tournament-specific errors
 	#[error("Tournament error: {message}")]
 	Tournament {
@@ -122,16 +156,19 @@ impl ApiError {
 
 		match self {
 			ApiError::Database { .. } => StatusCode::INTERNAL_SERVER_ERROR,
-			ApiError::Validation { .. } => StatusCode::BAD_REQUEST,
+			ApiError::Validation { .. } => StatusCode::UNPROCESSABLE_ENTITY,
 			ApiError::Authentication { .. } => StatusCode::UNAUTHORIZED,
 			ApiError::Authorization { .. } => StatusCode::FORBIDDEN,
 			ApiError::NotFound { .. } => StatusCode::NOT_FOUND,
+			ApiError::ValidationErrors { .. } => StatusCode::UNPROCESSABLE_ENTITY,
 			ApiError::Conflict { .. } => StatusCode::CONFLICT,
 			ApiError::RateLimit { .. } => StatusCode::TOO_MANY_REQUESTS,
 			ApiError::ExternalService { .. } => StatusCode::SERVICE_UNAVAILABLE,
 			ApiError::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
 			ApiError::BadRequest { .. } => StatusCode::BAD_REQUEST,
 			ApiError::JsonParsing { .. } => StatusCode::BAD_REQUEST,
+			ApiError::PayloadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+			ApiError::UnsupportedMediaType { .. } => StatusCode::UNSUPPORTED_MEDIA_TYPE,
 			ApiError::Tournament { .. } => StatusCode::BAD_REQUEST,
 			ApiError::User { .. } => StatusCode::BAD_REQUEST,
 		}
@@ -141,7 +178,9 @@ impl ApiError {
 	pub fn error_code(&self) -> String {
 		match self {
 			ApiError::Database { .. } => "DATABASE_ERROR".to_string(),
-			ApiError::Validation { .. } => "VALIDATION_ERROR".to_string(),
+			ApiError::Validation { .. } | ApiError::ValidationErrors { .. } => {
+				"VALIDATION_ERROR".to_string()
+			}
 			ApiError::Authentication { .. } => "AUTHENTICATION_ERROR".to_string(),
 			ApiError::Authorization { .. } => "AUTHORIZATION_ERROR".to_string(),
 			ApiError::NotFound { .. } => "NOT_FOUND".to_string(),
@@ -151,22 +190,161 @@ impl ApiError {
 			ApiError::Internal { .. } => "INTERNAL_SERVER_ERROR".to_string(),
 			ApiError::BadRequest { .. } => "BAD_REQUEST".to_string(),
 			ApiError::JsonParsing { .. } => "JSON_PARSING_ERROR".to_string(),
+			ApiError::PayloadTooLarge { .. } => "PAYLOAD_TOO_LARGE".to_string(),
+			ApiError::UnsupportedMediaType { .. } => "UNSUPPORTED_MEDIA_TYPE".to_string(),
 			ApiError::Tournament { .. } => "TOURNAMENT_ERROR".to_string(),
 			ApiError::User { .. } => "USER_ERROR".to_string(),
 		}
 	}
 
-	/// Create additional details for the error response
+	/// Stable key of the message, for clients translating on their own
+	pub fn message_key(&self) -> String {
+		match self {
+			ApiError::Database { .. } => "error.database".to_string(),
+			ApiError::Validation {
+				rule: Some(rule), ..
+			} => rule.key(),
+			ApiError::Validation { .. } => "error.validation".to_string(),
+			ApiError::ValidationErrors { errors } if errors.errors.len() > 1 => {
+				"error.validation_errors".to_string()
+			}
+			ApiError::ValidationErrors { .. } => "error.validation".to_string(),
+			ApiError::Authentication { .. } => "error.authentication".to_string(),
+			ApiError::Authorization { .. } => "error.authorization".to_string(),
+			ApiError::NotFound { .. } => "error.not_found".to_string(),
+			ApiError::Conflict { .. } => "error.conflict".to_string(),
+			ApiError::RateLimit { .. } => "error.rate_limit".to_string(),
+			ApiError::ExternalService { .. } => "error.external_service".to_string(),
+			ApiError::Internal { .. } => "error.internal".to_string(),
+			ApiError::BadRequest { .. } => "error.bad_request".to_string(),
+			ApiError::JsonParsing { .. } => "error.json_parsing".to_string(),
+			ApiError::PayloadTooLarge { .. } => "error.payload_too_large".to_string(),
+			ApiError::UnsupportedMediaType { .. } => "error.unsupported_media_type".to_string(),
+			ApiError::Tournament { .. } => "error.tournament".to_string(),
+			ApiError::User { .. } => "error.user".to_string(),
+		}
+	}
+
+	/// Params of the message named by [`Self::message_key`]
+	pub fn message_params(&self) -> Params {
+		let mut params = Params::new();
+		match self {
+			ApiError::Validation {
+				rule: Some(rule), ..
+			} => params = rule.message_params(),
+			ApiError::ValidationErrors { errors } => {
+				if let Some(first) = errors.first() {
+					params.insert("message".to_string(), first.message.clone().into());
+				}
+				if errors.errors.len() > 1 {
+					params.insert("more".to_string(), (errors.errors.len() - 1).into());
+				}
+			}
+			ApiError::NotFound { resource, id } => {
+				params.insert("resource".to_string(), resource.clone().into());
+				params.insert("id".to_string(), id.clone().into());
+			}
+			ApiError::PayloadTooLarge { limit } => {
+				params.insert("limit".to_string(), (*limit).into());
+			}
+			ApiError::ExternalService { service, message } => {
+				params.insert("service".to_string(), service.clone().into());
+				params.insert("message".to_string(), message.clone().into());
+			}
+			ApiError::Database { message }
+			| ApiError::Validation { message, .. }
+			| ApiError::Authentication { message }
+			| ApiError::Authorization { message }
+			| ApiError::Conflict { message, .. }
+			| ApiError::RateLimit { message, .. }
+			| ApiError::Internal { message }
+			| ApiError::BadRequest { message }
+			| ApiError::JsonParsing { message }
+			| ApiError::UnsupportedMediaType { message }
+			| ApiError::Tournament { message, .. }
+			| ApiError::User { message, .. } => {
+				params.insert("message".to_string(), message.clone().into());
+			}
+		}
+		params
+	}
+
+	/// The error message in `locale`
+	///
+	/// Falls back to the English [`Display`](std::fmt::Display) text when the
+	/// catalog can't render it.
+	pub fn localized_message(&self, locale: Locale) -> String {
+		let rendered = match self {
+			// A failed rule is wrapped like any other validation error
+			ApiError::Validation {
+				rule: Some(rule), ..
+			} => {
+				let mut params = Params::new();
+				params.insert("message".to_string(), rule.localized(locale).into());
+				i18n::translate(locale, "error.validation", &params)
+			}
+			ApiError::ValidationErrors { errors } => {
+				let mut params = self.message_params();
+				if let Some(first) = errors.first() {
+					params.insert("message".to_string(), first.localized(locale).into());
+				}
+				i18n::translate(locale, &self.message_key(), &params)
+			}
+			_ => i18n::translate(locale, &self.message_key(), &self.message_params()),
+		};
+		rendered.unwrap_or_else(|| self.to_string())
+	}
+
+	/// Details for the error response, in the current request's language
 	pub fn details(&self) -> Option<serde_json::Value> {
+		self.details_in(locale::current())
+	}
+
+	/// Details for the error response, with messages in `locale`
+	///
+	/// Always holds `message_key` and `message_params`, next to anything
+	/// specific to the error.
+	pub fn details_in(&self, locale: Locale) -> Option<serde_json::Value> {
+		let mut details = match self.specific_details(locale) {
+			Some(serde_json::Value::Object(details)) => details,
+			_ => serde_json::Map::new(),
+		};
+		details.insert("message_key".to_string(), self.message_key().into());
+		details.insert(
+			"message_params".to_string(),
+			serde_json::Value::Object(self.message_params().into_iter().collect()),
+		);
+		Some(serde_json::Value::Object(details))
+	}
+
+	fn specific_details(&self, locale: Locale) -> Option<serde_json::Value> {
 		match self {
-			ApiError::Validation { field, .. } => {
-				field.as_ref().map(|f| serde_json::json!({ "field": f }))
+			// Same `{ "field": ["message"] }` shape as a list of errors
+			ApiError::Validation {
+				message,
+				field,
+				rule,
+			} => {
+				let message = rule
+					.as_ref()
+					.map_or_else(|| message.clone(), |rule| rule.localized(locale));
+				let field = field.as_deref().unwrap_or(validation::GENERAL_FIELD);
+				Some(serde_json::json!({ field: [message] }))
+			}
+			ApiError::Conflict { field, .. } => field.as_ref().map(|f| serde_json::json!({ "field": f })),
+			// `{ "field": ["message", ...] }`
+			ApiError::ValidationErrors { errors } => {
+				serde_json::to_value(errors.localized_field_map(locale)).ok()
 			}
 			ApiError::NotFound { resource, id } => Some(serde_json::json!({
 					"resource": resource,
 					"id": id
 			})),
 			ApiError::ExternalService { service, .. } => Some(serde_json::json!({ "service": service })),
+			ApiError::PayloadTooLarge { limit } => Some(serde_json::json!({ "limit": limit })),
+			ApiError::RateLimit { retry_after, .. } => {
+				retry_after.map(|seconds| serde_json::json!({ "retry_after": seconds }))
+			}
 			ApiError::Tournament { tournament_id, .. } => tournament_id
 				.as_ref()
 				.map(|id| serde_json::json!({ "tournament_id": id })),
@@ -186,8 +364,12 @@ impl ApiError {
 	}
 }
 
-impl ResponseError for ApiError {
-	fn error_response(&self) -> HttpResponse {
+impl ApiError {
+	/// Error response with `extra` entries added to `details`
+	pub fn error_response_with(
+		&self,
+		extra: serde_json::Map<String, serde_json::Value>,
+	) -> HttpResponse {
 		// Log the error with appropriate level
 		if self.should_log_as_error() {
 			log::error!("API Error: {self}");
@@ -195,13 +377,34 @@ impl ResponseError for ApiError {
 			log::warn!("API Warning: {self}");
 		}
 
-		let mut error_response = ApiErrorResponse::new(self.to_string(), self.error_code());
+		let locale = locale::current();
+		let mut error_response =
+			ApiErrorResponse::new(self.localized_message(locale), self.error_code());
+
+		if let Some(serde_json::Value::Object(mut details)) = self.details_in(locale) {
+			details.extend(extra);
+			error_response = error_response.with_details(serde_json::Value::Object(details));
+		}
+
+		if let Some(request_id) = request_id::current() {
+			error_response = error_response.with_request_id(request_id);
+		}
 
-		if let Some(details) = self.details() {
-			error_response = error_response.with_details(details);
+		let mut response = HttpResponse::build(self.status_code());
+		if let ApiError::RateLimit {
+			retry_after: Some(seconds),
+			..
+		} = self
+		{
+			response.insert_header((header::RETRY_AFTER, seconds.to_string()));
 		}
+		response.json(error_response)
+	}
+}
 
-		HttpResponse::build(self.status_code()).json(error_response)
+impl ResponseError for ApiError {
+	fn error_response(&self) -> HttpResponse {
+		self.error_response_with(serde_json::Map::new())
 	}
 
 	fn status_code(&self) -> actix_web::http::StatusCode {
@@ -209,32 +412,11 @@ impl ResponseError for ApiError {
 	}
 }
 
-/// Conversion from SurrealDB errors
+/// Conversion from SurrealDB errors (see [`database::classify`])
 impl From<surrealdb::Error> for ApiError {
 	fn from(error: surrealdb::Error) -> Self {
-		log::error!("SurrealDB error: {error}");
-
-		// Try to categorize SurrealDB errors
-		let error_string = error.to_string();
-
-		if error_string.contains("Connection") || error_string.contains("timeout") {
-			ApiError::Database {
-				message: "Database connection error".to_string(),
-			}
-		} else if error_string.contains("duplicate") || error_string.contains("already exists") {
-			ApiError::Conflict {
-				message: "Resource already exists".to_string(),
-			}
-		} else if error_string.contains("not found") || error_string.contains("No record") {
-			ApiError::NotFound {
-				resource: "record".to_string(),
-				id: "unknown".to_string(),
-			}
-		} else {
-			ApiError::Database {
-				message: error.to_string(),
-			}
-		}
+		log::debug!("SurrealDB error: {error}");
+		database::classify(&error)
 	}
 }
 
@@ -254,10 +436,18 @@ impl From<validation::ValidationError> for ApiError {
 		ApiError::Validation {
 			message: error.message.clone(),
 			field: error.field.clone(),
+			rule: Some(Box::new(error)),
 		}
 	}
 }
 
+/// Conversion from a collection of validation errors
+impl From<validation::ValidationErrors> for ApiError {
+	fn from(errors: validation::ValidationErrors) -> Self {
+		ApiError::ValidationErrors { errors }
+	}
+}
+
 /// Helper type alias for API results
 pub type ApiResult<T> = Result<T, ApiError>;
 
@@ -291,10 +481,7 @@ where
 	}
 
 	fn validation_error(self, field: &str) -> ApiResult<T> {
-		self.map_err(|e| ApiError::Validation {
-			message: e.to_string(),
-			field: Some(field.to_string()),
-		})
+		self.map_err(|e| ApiError::validation_with_field(&e.to_string(), field))
 	}
 }
 
@@ -305,6 +492,7 @@ impl ApiError {
 		ApiError::Validation {
 			message: message.to_string(),
 			field: None,
+			rule: None,
 		}
 	}
 
@@ -313,6 +501,7 @@ impl ApiError {
 		ApiError::Validation {
 			message: message.to_string(),
 			field: Some(field.to_string()),
+			rule: None,
 		}
 	}
 
@@ -342,6 +531,7 @@ impl ApiError {
 	pub fn conflict(message: &str) -> Self {
 		ApiError::Conflict {
 			message: message.to_string(),
+			field: None,
 		}
 	}
 
@@ -352,6 +542,14 @@ impl ApiError {
 		}
 	}
 
+	/// Create a rate limit error telling the client when to retry
+	pub fn rate_limit(message: &str, retry_after: u64) -> Self {
+		ApiError::RateLimit {
+			message: message.to_string(),
+			retry_after: Some(retry_after),
+		}
+	}
+
 	/// Create an internal error
 	pub fn internal(message: &str) -> Self {
 		ApiError::Internal {
@@ -401,7 +599,7 @@ mod tests {
 	fn test_api_error_status_codes() {
 		assert_eq!(
 			ApiError::validation("test").status_code(),
-			StatusCode::BAD_REQUEST
+			StatusCode::UNPROCESSABLE_ENTITY
 		);
 		assert_eq!(
 			ApiError::not_found("user", "123").status_code(),
@@ -453,6 +651,82 @@ mod tests {
 		assert_eq!(details["id"], "123");
 	}
 
+	#[test]
+	fn test_validation_errors_are_reported_together() {
+		let mut errors = validation::ValidationErrors::new();
+		errors.add_error("Question is too short", "question", "LENGTH");
+		errors.add_error("Option is empty", "options", "LENGTH");
+		errors.add_error("Option is too long", "options", "LENGTH");
+		let error = ApiError::from(errors);
+
+		assert_eq!(error.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+		assert_eq!(error.error_code(), "VALIDATION_ERROR");
+		assert_eq!(
+			error.to_string(),
+			"Validation error: Question is too short (and 2 more)"
+		);
+		assert_eq!(
+			error.details().unwrap(),
+			serde_json::json!({
+				"question": ["Question is too short"],
+				"options": ["Option is empty", "Option is too long"],
+				"message_key": "error.validation_errors",
+				"message_params": { "message": "Question is too short", "more": 2 },
+			})
+		);
+	}
+
+	#[test]
+	fn test_single_validation_errors_match_the_list_shape() {
+		let error = ApiError::validation_with_field("Unknown poll option", "choices");
+		assert_eq!(error.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+		assert_eq!(
+			error.details().unwrap()["choices"],
+			serde_json::json!(["Unknown poll option"])
+		);
+
+		let error = ApiError::validation("Pick a side");
+		assert_eq!(
+			error.details().unwrap()[validation::GENERAL_FIELD],
+			serde_json::json!(["Pick a side"])
+		);
+	}
+
+	#[test]
+	fn test_messages_are_localized_with_stable_keys() {
+		let error = ApiError::from(validation::validators::length("ab", 3, 100, "name").unwrap_err());
+		assert_eq!(error.message_key(), "validation.length");
+		assert_eq!(
+			error.localized_message(Locale::Es),
+			"Error de validación: name debe tener entre 3 y 100 caracteres"
+		);
+		assert_eq!(error.localized_message(Locale::En), error.to_string());
+		let details = error.details_in(Locale::Es).unwrap();
+		assert_eq!(
+			details["name"][0],
+			"name debe tener entre 3 y 100 caracteres"
+		);
+		assert_eq!(
+			details["message_params"],
+			serde_json::json!({ "field": "name", "min": 3, "max": 100 })
+		);
+
+		let mut errors = validation::ValidationErrors::new();
+		errors.check(validation::validators::email("nope", "email"));
+		let details = ApiError::from(errors).details_in(Locale::Es).unwrap();
+		assert_eq!(
+			details["email"][0],
+			"Formato de correo electrónico no válido"
+		);
+
+		// Free-form text is kept as written
+		let error = ApiError::bad_request("Bracket already generated");
+		assert_eq!(
+			error.localized_message(Locale::Es),
+			"Petición incorrecta: Bracket already generated"
+		);
+	}
+
 	#[test]
 	fn test_should_log_as_error() {
 		assert!(
@@ -467,13 +741,7 @@ mod tests {
 			}
 			.should_log_as_error()
 		);
-		assert!(
-			!ApiError::Validation {
-				message: "test".to_string(),
-				field: None
-			}
-			.should_log_as_error()
-		);
+		assert!(!ApiError::validation("test").should_log_as_error());
 		assert!(
 			!ApiError::NotFound {
 				resource: "test".to_string(),
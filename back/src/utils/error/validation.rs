@@ -2,11 +2,28 @@
 //!
 //! This module provides specific validation errors and utilities for
 //! validating user input, request data, and business logic constraints.
-
+//!
+//! Request bodies implement [`Validate`] next to their definition in
+//! `entities`; handlers take them through [`ValidatedJson`], which rejects an
+//! invalid body with every problem at once (422) before the handler runs.
+//! Rules that depend on stored data (e.g. whether a poll is still open) stay
+//! in the services.
+
+use actix_web::{FromRequest, HttpRequest, dev::Payload, web};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::ops::Deref;
+use std::pin::Pin;
 use thiserror::Error;
 
+use super::ApiError;
+use crate::utils::i18n::{self, Locale, Params};
+
+/// Key under which errors not tied to a field are reported
+pub const GENERAL_FIELD: &str = "_general";
+
 /// Validation error for field-specific validation failures
 #[derive(Error, Debug, Clone, Serialize, Deserialize)]
 #[error("Validation error: {message}")]
@@ -17,6 +34,9 @@ pub struct ValidationError {
 	pub field: Option<String>,
 	/// Error code for programmatic handling
 	pub code: String,
+	/// Values the message is built from, e.g. the allowed `min` and `max`
+	#[serde(default, skip_serializing_if = "Params::is_empty")]
+	pub params: Params,
 }
 
 impl ValidationError {
@@ -26,6 +46,7 @@ impl ValidationError {
 			message: message.to_string(),
 			field: None,
 			code: code.to_string(),
+			params: Params::new(),
 		}
 	}
 
@@ -35,7 +56,36 @@ impl ValidationError {
 			message: message.to_string(),
 			field: Some(field.to_string()),
 			code: code.to_string(),
+			params: Params::new(),
+		}
+	}
+
+	/// Add a value the message is built from
+	pub fn with_param(mut self, name: &str, value: impl Into<serde_json::Value>) -> Self {
+		self.params.insert(name.to_string(), value.into());
+		self
+	}
+
+	/// Catalog key of the message, e.g. `validation.length` for `LENGTH`
+	pub fn key(&self) -> String {
+		format!("validation.{}", self.code.to_ascii_lowercase())
+	}
+
+	/// Params of the message, including the field
+	pub fn message_params(&self) -> Params {
+		let mut params = self.params.clone();
+		if let Some(field) = &self.field {
+			params
+				.entry("field".to_string())
+				.or_insert_with(|| field.clone().into());
 		}
+		params
+	}
+
+	/// The message in `locale`, or as written when the catalog can't render it
+	pub fn localized(&self, locale: Locale) -> String {
+		i18n::translate(locale, &self.key(), &self.message_params())
+			.unwrap_or_else(|| self.message.clone())
 	}
 }
 
@@ -72,16 +122,49 @@ impl ValidationErrors {
 		self.errors.first()
 	}
 
+	/// Keep the error of a failed check
+	pub fn check<T>(&mut self, result: Result<T, ValidationError>) {
+		if let Err(error) = result {
+			self.add(error);
+		}
+	}
+
+	/// `Err(self)` when any check failed
+	pub fn into_result(self) -> ValidationResult<()> {
+		if self.has_errors() { Err(self) } else { Ok(()) }
+	}
+
+	/// One-line description: the first message and how many others follow
+	pub fn summary(&self) -> String {
+		match self.errors.as_slice() {
+			[] => "no errors".to_string(),
+			[only] => only.message.clone(),
+			[first, rest @ ..] => format!("{} (and {} more)", first.message, rest.len()),
+		}
+	}
+
 	/// Convert to a map of field -> error messages
 	pub fn to_field_map(&self) -> HashMap<String, Vec<String>> {
+		self.field_map(|error| error.message.clone())
+	}
+
+	/// Map of field -> error messages in `locale`
+	pub fn localized_field_map(&self, locale: Locale) -> HashMap<String, Vec<String>> {
+		self.field_map(|error| error.localized(locale))
+	}
+
+	fn field_map(
+		&self,
+		message: impl Fn(&ValidationError) -> String,
+	) -> HashMap<String, Vec<String>> {
 		let mut map = HashMap::new();
 
 		for error in &self.errors {
-			let field = error.field.as_deref().unwrap_or("_general");
+			let field = error.field.as_deref().unwrap_or(GENERAL_FIELD);
 			map
 				.entry(field.to_string())
 				.or_insert_with(Vec::new)
-				.push(error.message.clone());
+				.push(message(error));
 		}
 
 		map
@@ -97,6 +180,66 @@ impl Default for ValidationErrors {
 /// Validation result type
 pub type ValidationResult<T> = Result<T, ValidationErrors>;
 
+/// Rules a request body must satisfy, checked all at once
+///
+/// ```
+/// use liga_muertos_back::utils::error::validation::{Validate, ValidationErrors, ValidationResult, validators};
+///
+/// struct RenameData {
+///     name: String,
+/// }
+///
+/// impl Validate for RenameData {
+///     fn validate(&self) -> ValidationResult<()> {
+///         let mut errors = ValidationErrors::new();
+///         errors.check(validators::tournament_name(&self.name, "name"));
+///         errors.into_result()
+///     }
+/// }
+///
+/// assert!(RenameData { name: "Copa".into() }.validate().is_ok());
+/// assert!(RenameData { name: "".into() }.validate().is_err());
+/// ```
+pub trait Validate {
+	/// Every rule the value breaks, or `Ok(())`
+	fn validate(&self) -> ValidationResult<()>;
+}
+
+/// JSON body extractor that runs [`Validate`] before the handler
+///
+/// Parsing errors are reported like `web::Json`'s; a body breaking any rule
+/// is rejected with [`ApiError::ValidationErrors`].
+#[derive(Debug)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> ValidatedJson<T> {
+	pub fn into_inner(self) -> T {
+		self.0
+	}
+}
+
+impl<T> Deref for ValidatedJson<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		&self.0
+	}
+}
+
+impl<T: DeserializeOwned + Validate + 'static> FromRequest for ValidatedJson<T> {
+	type Error = actix_web::Error;
+	type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+	fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+		let json = web::Json::<T>::from_request(req, payload);
+		Box::pin(async move {
+			let value = json.await?.into_inner();
+			value.validate().map_err(ApiError::from)?;
+			Ok(ValidatedJson(value))
+		})
+	}
+}
+
 /// Common validation functions
 pub mod validators {
 	use super::ValidationError;
@@ -118,11 +261,15 @@ pub mod validators {
 	pub fn length(value: &str, min: usize, max: usize, field: &str) -> Result<(), ValidationError> {
 		let len = value.len();
 		if len < min || len > max {
-			Err(ValidationError::with_field(
-				&format!("{field} must be between {min} and {max} characters"),
-				field,
-				"LENGTH",
-			))
+			Err(
+				ValidationError::with_field(
+					&format!("{field} must be between {min} and {max} characters"),
+					field,
+					"LENGTH",
+				)
+				.with_param("min", min)
+				.with_param("max", max),
+			)
 		} else {
 			Ok(())
 		}
@@ -180,6 +327,32 @@ pub mod validators {
 		}
 	}
 
+	/// Validate absolute http(s) URL
+	pub fn url(value: &str, field: &str) -> Result<(), ValidationError> {
+		if is_valid_url(value) {
+			Ok(())
+		} else {
+			Err(ValidationError::with_field(
+				"Must be a valid http or https URL",
+				field,
+				"INVALID_URL",
+			))
+		}
+	}
+
+	/// Validate an https URL on a public host, for endpoints the server calls
+	pub fn public_https_url(value: &str, field: &str) -> Result<(), ValidationError> {
+		if is_public_https_url(value) {
+			Ok(())
+		} else {
+			Err(ValidationError::with_field(
+				"Must be an https URL on a public host",
+				field,
+				"UNSAFE_URL",
+			))
+		}
+	}
+
 	/// Validate UUID format
 	pub fn uuid_format(value: &str, field: &str) -> Result<(), ValidationError> {
 		// Simple UUID format validation
@@ -212,11 +385,15 @@ pub mod validators {
 		if value >= min && value <= max {
 			Ok(())
 		} else {
-			Err(ValidationError::with_field(
-				&format!("{field} must be between {min} and {max}"),
-				field,
-				"OUT_OF_RANGE",
-			))
+			Err(
+				ValidationError::with_field(
+					&format!("{field} must be between {min} and {max}"),
+					field,
+					"OUT_OF_RANGE",
+				)
+				.with_param("min", min)
+				.with_param("max", max),
+			)
 		}
 	}
 }
@@ -312,6 +489,23 @@ mod tests {
 		let field_map = errors.to_field_map();
 		assert!(field_map.contains_key("username"));
 		assert!(field_map.contains_key("email"));
+		assert_eq!(errors.summary(), "Username required (and 1 more)");
+	}
+
+	#[test]
+	fn test_validation_errors_collect_checks() {
+		let mut errors = ValidationErrors::new();
+		errors.check(length("ok", 1, 10, "name"));
+		assert!(errors.clone().into_result().is_ok());
+
+		errors.check(range(0, 1, 10, "parallel"));
+		errors.check(length("", 1, 10, "name"));
+		let errors = errors.into_result().unwrap_err();
+		assert_eq!(errors.errors.len(), 2);
+		assert_eq!(
+			errors.to_field_map()["parallel"],
+			vec!["parallel must be between 1 and 10"]
+		);
 	}
 
 	#[test]
@@ -363,6 +557,50 @@ mod tests {
 		assert!(!errors.errors.is_empty());
 	}
 
+	#[derive(Deserialize)]
+	struct Signup {
+		username: String,
+		email: String,
+	}
+
+	impl Validate for Signup {
+		fn validate(&self) -> ValidationResult<()> {
+			let mut errors = ValidationErrors::new();
+			errors.check(username(&self.username, "username"));
+			errors.check(email(&self.email, "email"));
+			errors.into_result()
+		}
+	}
+
+	async fn signup(body: ValidatedJson<Signup>) -> actix_web::HttpResponse {
+		actix_web::HttpResponse::Ok().body(body.username.clone())
+	}
+
+	#[actix_web::test]
+	async fn test_validated_json_rejects_before_the_handler() {
+		use crate::utils::error::ApiErrorResponse;
+		use actix_web::{App, http::StatusCode, test};
+
+		let app = test::init_service(App::new().route("/signup", web::post().to(signup))).await;
+
+		let req = test::TestRequest::post()
+			.uri("/signup")
+			.set_json(serde_json::json!({ "username": "catrina", "email": "catrina@example.com" }))
+			.to_request();
+		assert_eq!(test::call_and_read_body(&app, req).await, "catrina");
+
+		let req = test::TestRequest::post()
+			.uri("/signup")
+			.set_json(serde_json::json!({ "username": "-x", "email": "nope" }))
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+		let body: ApiErrorResponse = test::read_body_json(resp).await;
+		let details = body.details.unwrap();
+		assert!(details["username"].is_array());
+		assert!(details["email"].is_array());
+	}
+
 	#[test]
 	fn test_validation_macro() {
 		let username = "test";
@@ -0,0 +1,420 @@
+//! Translations of error and validation messages
+//!
+//! Every message the API returns has a stable key (`error.not_found`,
+//! `validation.length`, ...) and a set of named params. The catalogs below
+//! map keys to templates where `{name}` is replaced by the param of that name;
+//! English is the source language and matches the messages the code is written
+//! with. The locale is picked from `Accept-Language` by
+//! [`crate::middleware::locale`].
+//!
+//! Free-form text passed in by the code (e.g. the reason of a bad request)
+//! travels as the `message` param and is not translated.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Named values interpolated into a message template
+pub type Params = BTreeMap<String, Value>;
+
+/// Languages the API answers in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+	#[default]
+	En,
+	Es,
+}
+
+impl Locale {
+	/// ISO 639-1 code, as used in `Content-Language`
+	pub fn code(self) -> &'static str {
+		match self {
+			Locale::En => "en",
+			Locale::Es => "es",
+		}
+	}
+
+	/// Best supported locale for an `Accept-Language` header value
+	///
+	/// Ranges are tried by decreasing quality; only the primary subtag is
+	/// compared, so `es-MX` selects Spanish. Returns `None` when nothing
+	/// matches (a `*` range or an unsupported language).
+	pub fn negotiate(header: &str) -> Option<Locale> {
+		let mut ranges: Vec<(f32, &str)> = header
+			.split(',')
+			.filter_map(|range| {
+				let mut parts = range.split(';').map(str::trim);
+				let tag = parts.next().filter(|tag| !tag.is_empty())?;
+				let quality = parts
+					.find_map(|param| param.strip_prefix("q="))
+					.map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+				(quality > 0.0).then_some((quality, tag))
+			})
+			.collect();
+		// Stable, so equal qualities keep the caller's order
+		ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+		ranges.into_iter().find_map(|(_, tag)| {
+			let primary = tag.split(['-', '_']).next().unwrap_or(tag);
+			primary.parse().ok()
+		})
+	}
+
+	fn catalog(self) -> &'static [(&'static str, &'static str)] {
+		match self {
+			Locale::En => EN,
+			Locale::Es => ES,
+		}
+	}
+}
+
+impl fmt::Display for Locale {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.code())
+	}
+}
+
+impl FromStr for Locale {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"en" => Ok(Locale::En),
+			"es" => Ok(Locale::Es),
+			other => Err(format!("unsupported locale '{other}'")),
+		}
+	}
+}
+
+/// Render `key` in `locale`
+///
+/// `None` when the catalog has no such key or the template uses a param that
+/// wasn't given; callers then fall back to the message they started from.
+pub fn translate(locale: Locale, key: &str, params: &Params) -> Option<String> {
+	let template = locale
+		.catalog()
+		.iter()
+		.find(|(name, _)| *name == key)
+		.map(|(_, template)| *template)?;
+	interpolate(template, params)
+}
+
+/// Replace every `{name}` in `template` by its param
+fn interpolate(template: &str, params: &Params) -> Option<String> {
+	let mut output = String::with_capacity(template.len());
+	let mut rest = template;
+	while let Some(start) = rest.find('{') {
+		let end = start + rest[start..].find('}')?;
+		output.push_str(&rest[..start]);
+		match params.get(&rest[start + 1..end])? {
+			Value::String(value) => output.push_str(value),
+			value => output.push_str(&value.to_string()),
+		}
+		rest = &rest[end + 1..];
+	}
+	output.push_str(rest);
+	Some(output)
+}
+
+const EN: &[(&str, &str)] = &[
+	("error.database", "Database error: {message}"),
+	("error.validation", "Validation error: {message}"),
+	(
+		"error.validation_errors",
+		"Validation error: {message} (and {more} more)",
+	),
+	("error.authentication", "Authentication error: {message}"),
+	("error.authorization", "Authorization error: {message}"),
+	(
+		"error.not_found",
+		"Resource not found: {resource} with id {id}",
+	),
+	("error.conflict", "Conflict: {message}"),
+	("error.rate_limit", "Rate limit exceeded: {message}"),
+	(
+		"error.external_service",
+		"External service error: {service} - {message}",
+	),
+	("error.internal", "Internal server error: {message}"),
+	("error.bad_request", "Bad request: {message}"),
+	("error.json_parsing", "JSON parsing error: {message}"),
+	(
+		"error.payload_too_large",
+		"Payload too large: limit is {limit} bytes",
+	),
+	(
+		"error.unsupported_media_type",
+		"Unsupported media type: {message}",
+	),
+	("error.tournament", "Tournament error: {message}"),
+	("error.user", "User error: {message}"),
+	("validation.required", "{field} is required"),
+	(
+		"validation.length",
+		"{field} must be between {min} and {max} characters",
+	),
+	("validation.invalid_email", "Invalid email format"),
+	(
+		"validation.invalid_username",
+		"Username must be 3-50 characters, start with alphanumeric, and contain only alphanumeric, hyphens, or underscores",
+	),
+	(
+		"validation.weak_password",
+		"Password must be 8-128 characters with at least one lowercase, uppercase, and digit",
+	),
+	(
+		"validation.invalid_tournament_name",
+		"Tournament name must be 3-100 characters and not empty",
+	),
+	(
+		"validation.invalid_url",
+		"Must be a valid http or https URL",
+	),
+	(
+		"validation.unsafe_url",
+		"Must be an https URL on a public host",
+	),
+	("validation.invalid_uuid", "Invalid UUID format"),
+	(
+		"validation.not_positive",
+		"{field} must be a positive integer",
+	),
+	(
+		"validation.out_of_range",
+		"{field} must be between {min} and {max}",
+	),
+	("validation.invalid_timezone", "Unknown IANA timezone"),
+	(
+		"validation.option_count",
+		"A poll needs between {min} and {max} options",
+	),
+	(
+		"validation.closes_before_opens",
+		"A poll must close after it opens",
+	),
+	("validation.no_choice", "Pick at least one option"),
+	(
+		"validation.same_participant",
+		"A participant cannot play against itself",
+	),
+	(
+		"validation.not_a_participant",
+		"Not a participant of this tournament",
+	),
+	(
+		"validation.ends_before_starts",
+		"An availability window must end after it starts",
+	),
+	(
+		"validation.in_the_past",
+		"The proposed time must be in the future",
+	),
+	(
+		"validation.inconsistent_score",
+		"The predicted score must include both sides and agree with the predicted outcome",
+	),
+	(
+		"validation.username_or_email",
+		"Each row needs a username or an email",
+	),
+	(
+		"validation.user_mismatch",
+		"The username and the email belong to different users",
+	),
+	("validation.user_not_found", "No user matches this row"),
+	(
+		"validation.already_participant",
+		"This user is already registered in the tournament",
+	),
+	("validation.duplicate_row", "Same user as row {row}"),
+];
+
+const ES: &[(&str, &str)] = &[
+	("error.database", "Error de base de datos: {message}"),
+	("error.validation", "Error de validación: {message}"),
+	(
+		"error.validation_errors",
+		"Error de validación: {message} (y {more} más)",
+	),
+	("error.authentication", "Error de autenticación: {message}"),
+	("error.authorization", "Error de autorización: {message}"),
+	(
+		"error.not_found",
+		"Recurso no encontrado: {resource} con id {id}",
+	),
+	("error.conflict", "Conflicto: {message}"),
+	(
+		"error.rate_limit",
+		"Límite de peticiones excedido: {message}",
+	),
+	(
+		"error.external_service",
+		"Error del servicio externo: {service} - {message}",
+	),
+	("error.internal", "Error interno del servidor: {message}"),
+	("error.bad_request", "Petición incorrecta: {message}"),
+	("error.json_parsing", "Error al procesar el JSON: {message}"),
+	(
+		"error.payload_too_large",
+		"Cuerpo de la petición demasiado grande: el límite es de {limit} bytes",
+	),
+	(
+		"error.unsupported_media_type",
+		"Tipo de contenido no admitido: {message}",
+	),
+	("error.tournament", "Error del torneo: {message}"),
+	("error.user", "Error de usuario: {message}"),
+	("validation.required", "{field} es obligatorio"),
+	(
+		"validation.length",
+		"{field} debe tener entre {min} y {max} caracteres",
+	),
+	(
+		"validation.invalid_email",
+		"Formato de correo electrónico no válido",
+	),
+	(
+		"validation.invalid_username",
+		"El nombre de usuario debe tener de 3 a 50 caracteres, empezar con una letra o un número y contener solo letras, números, guiones o guiones bajos",
+	),
+	(
+		"validation.weak_password",
+		"La contraseña debe tener de 8 a 128 caracteres con al menos una minúscula, una mayúscula y un dígito",
+	),
+	(
+		"validation.invalid_tournament_name",
+		"El nombre del torneo debe tener de 3 a 100 caracteres y no estar vacío",
+	),
+	(
+		"validation.invalid_url",
+		"Debe ser una URL http o https válida",
+	),
+	(
+		"validation.unsafe_url",
+		"Debe ser una URL https de un host público",
+	),
+	("validation.invalid_uuid", "Formato de UUID no válido"),
+	(
+		"validation.not_positive",
+		"{field} debe ser un entero positivo",
+	),
+	(
+		"validation.out_of_range",
+		"{field} debe estar entre {min} y {max}",
+	),
+	(
+		"validation.invalid_timezone",
+		"Zona horaria IANA desconocida",
+	),
+	(
+		"validation.option_count",
+		"Una encuesta necesita entre {min} y {max} opciones",
+	),
+	(
+		"validation.closes_before_opens",
+		"Una encuesta debe cerrar después de abrir",
+	),
+	("validation.no_choice", "Elige al menos una opción"),
+	(
+		"validation.same_participant",
+		"Un participante no puede jugar contra sí mismo",
+	),
+	(
+		"validation.not_a_participant",
+		"No participa en este torneo",
+	),
+	(
+		"validation.ends_before_starts",
+		"Una ventana de disponibilidad debe terminar después de empezar",
+	),
+	(
+		"validation.in_the_past",
+		"La hora propuesta debe estar en el futuro",
+	),
+	(
+		"validation.inconsistent_score",
+		"El marcador pronosticado debe incluir ambos lados y coincidir con el resultado pronosticado",
+	),
+	(
+		"validation.username_or_email",
+		"Cada fila necesita un nombre de usuario o un correo electrónico",
+	),
+	(
+		"validation.user_mismatch",
+		"El nombre de usuario y el correo electrónico pertenecen a usuarios distintos",
+	),
+	(
+		"validation.user_not_found",
+		"Ningún usuario coincide con esta fila",
+	),
+	(
+		"validation.already_participant",
+		"Este usuario ya está inscrito en el torneo",
+	),
+	(
+		"validation.duplicate_row",
+		"Mismo usuario que la fila {row}",
+	),
+];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	fn params(value: Value) -> Params {
+		value
+			.as_object()
+			.cloned()
+			.unwrap_or_default()
+			.into_iter()
+			.collect()
+	}
+
+	#[test]
+	fn test_negotiate_follows_quality_and_primary_subtag() {
+		assert_eq!(
+			Locale::negotiate("es-MX,es;q=0.9,en;q=0.8"),
+			Some(Locale::Es)
+		);
+		assert_eq!(
+			Locale::negotiate("fr-FR, en;q=0.5, es;q=0.7"),
+			Some(Locale::Es)
+		);
+		assert_eq!(Locale::negotiate("en-US"), Some(Locale::En));
+		assert_eq!(Locale::negotiate("es;q=0, en;q=0.1"), Some(Locale::En));
+		assert_eq!(Locale::negotiate("fr, de"), None);
+		assert_eq!(Locale::negotiate("*"), None);
+		assert_eq!(Locale::negotiate(""), None);
+	}
+
+	#[test]
+	fn test_translate_interpolates_params() {
+		let values = params(json!({ "field": "name", "min": 3, "max": 100 }));
+		assert_eq!(
+			translate(Locale::Es, "validation.length", &values).unwrap(),
+			"name debe tener entre 3 y 100 caracteres"
+		);
+		assert_eq!(
+			translate(Locale::En, "validation.length", &values).unwrap(),
+			"name must be between 3 and 100 characters"
+		);
+		// A missing param or key leaves the caller's message in place
+		assert!(translate(Locale::Es, "validation.length", &Params::new()).is_none());
+		assert!(translate(Locale::Es, "validation.unknown", &values).is_none());
+	}
+
+	#[test]
+	fn test_catalogs_have_the_same_keys() {
+		let keys = |locale: Locale| {
+			let mut keys: Vec<&str> = locale.catalog().iter().map(|(key, _)| *key).collect();
+			keys.sort_unstable();
+			keys
+		};
+		assert_eq!(keys(Locale::En), keys(Locale::Es));
+	}
+}
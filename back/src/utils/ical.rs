@@ -0,0 +1,196 @@
+//! Minimal iCalendar (RFC 5545) writer for calendar subscription feeds
+//!
+//! Calendar apps match events by `UID` and keep the copy with the highest
+//! `SEQUENCE`, so an event must keep its UID for its whole life and bump its
+//! sequence whenever its time or status changes.
+
+use chrono::{DateTime, Utc};
+
+use crate::utils::constants;
+
+/// Longest content line allowed before folding, in octets
+const MAX_LINE_OCTETS: usize = 75;
+
+/// A calendar feed
+#[derive(Debug, Clone)]
+pub struct Calendar {
+	pub name: String,
+	pub events: Vec<CalendarEvent>,
+}
+
+/// A single `VEVENT`
+#[derive(Debug, Clone)]
+pub struct CalendarEvent {
+	/// Globally unique, stable identifier
+	pub uid: String,
+	/// Revision counter; bump it on every change
+	pub sequence: u32,
+	/// Last time the event changed
+	pub updated_at: DateTime<Utc>,
+	pub starts_at: DateTime<Utc>,
+	pub ends_at: DateTime<Utc>,
+	pub summary: String,
+	pub description: Option<String>,
+	pub status: EventStatus,
+}
+
+/// `STATUS` of an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventStatus {
+	Tentative,
+	Confirmed,
+	Cancelled,
+}
+
+impl EventStatus {
+	fn as_str(&self) -> &'static str {
+		match self {
+			EventStatus::Tentative => "TENTATIVE",
+			EventStatus::Confirmed => "CONFIRMED",
+			EventStatus::Cancelled => "CANCELLED",
+		}
+	}
+}
+
+impl Calendar {
+	/// Render the calendar as an `.ics` document (CRLF line endings)
+	pub fn render(&self) -> String {
+		let mut lines = vec![
+			"BEGIN:VCALENDAR".to_string(),
+			"VERSION:2.0".to_string(),
+			format!(
+				"PRODID:-//{}//Match schedule {}//EN",
+				constants::APP_NAME,
+				constants::APP_VERSION
+			),
+			"CALSCALE:GREGORIAN".to_string(),
+			"METHOD:PUBLISH".to_string(),
+			format!("X-WR-CALNAME:{}", escape(&self.name)),
+		];
+
+		for event in &self.events {
+			lines.push("BEGIN:VEVENT".to_string());
+			lines.push(format!("UID:{}", escape(&event.uid)));
+			lines.push(format!("SEQUENCE:{}", event.sequence));
+			lines.push(format!("DTSTAMP:{}", format_utc(&event.updated_at)));
+			lines.push(format!("LAST-MODIFIED:{}", format_utc(&event.updated_at)));
+			lines.push(format!("DTSTART:{}", format_utc(&event.starts_at)));
+			lines.push(format!("DTEND:{}", format_utc(&event.ends_at)));
+			lines.push(format!("SUMMARY:{}", escape(&event.summary)));
+			if let Some(description) = &event.description {
+				lines.push(format!("DESCRIPTION:{}", escape(description)));
+			}
+			lines.push(format!("STATUS:{}", event.status.as_str()));
+			lines.push("END:VEVENT".to_string());
+		}
+		lines.push("END:VCALENDAR".to_string());
+
+		lines
+			.iter()
+			.map(|line| fold(line))
+			.collect::<Vec<_>>()
+			.join("")
+	}
+}
+
+/// Format a timestamp as an iCalendar UTC date-time (`20250101T180000Z`)
+pub fn format_utc(timestamp: &DateTime<Utc>) -> String {
+	timestamp.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a TEXT value
+fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'\\' => escaped.push_str("\\\\"),
+			';' => escaped.push_str("\\;"),
+			',' => escaped.push_str("\\,"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => {}
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+/// Fold a content line at 75 octets (never inside a UTF-8 character) and
+/// terminate it with CRLF
+fn fold(line: &str) -> String {
+	let mut folded = String::with_capacity(line.len() + 8);
+	let mut octets = 0;
+	for c in line.chars() {
+		// Continuation lines start with a space, which counts towards the limit
+		if octets + c.len_utf8() > MAX_LINE_OCTETS {
+			folded.push_str("\r\n ");
+			octets = 1;
+		}
+		folded.push(c);
+		octets += c.len_utf8();
+	}
+	folded.push_str("\r\n");
+	folded
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::Duration;
+
+	fn event(status: EventStatus) -> CalendarEvent {
+		let starts_at = DateTime::parse_from_rfc3339("2025-11-02T02:00:00Z")
+			.unwrap()
+			.with_timezone(&Utc);
+		CalendarEvent {
+			uid: "match-m1@liga-muertos".to_string(),
+			sequence: 2,
+			updated_at: starts_at - Duration::days(1),
+			starts_at,
+			ends_at: starts_at + Duration::hours(1),
+			summary: "Round 1: catrina vs calaca".to_string(),
+			description: Some("Copa, Día de Muertos; final".to_string()),
+			status,
+		}
+	}
+
+	#[test]
+	fn test_render_calendar() {
+		let calendar = Calendar {
+			name: "Copa Catrina".to_string(),
+			events: vec![event(EventStatus::Confirmed)],
+		};
+		let ics = calendar.render();
+
+		assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+		assert!(ics.ends_with("END:VCALENDAR\r\n"));
+		assert!(ics.contains("\r\nUID:match-m1@liga-muertos\r\n"));
+		assert!(ics.contains("\r\nSEQUENCE:2\r\n"));
+		assert!(ics.contains("\r\nDTSTART:20251102T020000Z\r\n"));
+		assert!(ics.contains("\r\nDTEND:20251102T030000Z\r\n"));
+		assert!(ics.contains("\r\nDESCRIPTION:Copa\\, Día de Muertos\\; final\r\n"));
+		assert!(ics.contains("\r\nSTATUS:CONFIRMED\r\n"));
+	}
+
+	#[test]
+	fn test_cancelled_events_keep_their_uid() {
+		let calendar = Calendar {
+			name: "Copa Catrina".to_string(),
+			events: vec![event(EventStatus::Cancelled)],
+		};
+		let ics = calendar.render();
+
+		assert!(ics.contains("\r\nUID:match-m1@liga-muertos\r\n"));
+		assert!(ics.contains("\r\nSTATUS:CANCELLED\r\n"));
+	}
+
+	#[test]
+	fn test_long_lines_are_folded() {
+		let line = format!("SUMMARY:{}", "ñ".repeat(60));
+		let folded = fold(&line);
+
+		for part in folded.trim_end_matches("\r\n").split("\r\n") {
+			assert!(part.len() <= MAX_LINE_OCTETS);
+		}
+		assert_eq!(folded.replace("\r\n ", "").trim_end(), line);
+	}
+}
@@ -10,10 +10,10 @@
 //! use liga_muertos_back::utils::logging;
 //!
 //! // Initialize logging (call once at startup)
-//! logging::init();
+//! logging::init(&Default::default());
 //!
 //! // Application lifecycle
-//! logging::startup_info(4000);
+//! logging::startup_info("0.0.0.0", 4000);
 //! logging::server_ready(4000);
 //! logging::shutdown();
 //!
@@ -46,6 +46,13 @@
 //! logging::performance_metric("database_query", duration);
 //! ```
 //!
+//! ## Structured fields
+//! Event helpers attach their data as key-values (`event`, `tournament_id`,
+//! `user_id`, `operation`, `duration_ms`, ...). The text format appends them
+//! as `key=value`; the JSON format (`LOG_FORMAT=json`) writes one object per
+//! line with `timestamp`, `level`, `target`, `message`, `request_id`,
+//! `user_id` and every key-value as a field, ready for log aggregation.
+//!
 //! ## Request Debugging
 //! ```rust
 //! use liga_muertos_back::utils::logging;
@@ -54,62 +61,164 @@
 //! ```
 
 use env_logger::{Builder, Env, Target};
-use log::LevelFilter;
-use std::env;
+use log::kv::{self, VisitSource};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::str::FromStr;
+use std::time::Instant;
+
+use crate::config::LoggingConfig;
+use crate::middleware::request_id;
+use crate::utils::metrics;
+
+/// Log output format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+	/// Human-readable lines for terminals
+	#[default]
+	Text,
+	/// One JSON object per line for log aggregation
+	Json,
+}
+
+impl FromStr for LogFormat {
+	type Err = String;
+
+	fn from_str(value: &str) -> Result<Self, Self::Err> {
+		match value.trim().to_lowercase().as_str() {
+			"text" => Ok(LogFormat::Text),
+			"json" => Ok(LogFormat::Json),
+			other => Err(format!(
+				"unknown log format '{other}' (expected text or json)"
+			)),
+		}
+	}
+}
+
+/// Collects a record's key-values
+struct Fields(Vec<(String, Value)>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+	fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+		let value = if let Some(number) = value.to_u64() {
+			Value::from(number)
+		} else if let Some(number) = value.to_i64() {
+			Value::from(number)
+		} else if let Some(flag) = value.to_bool() {
+			Value::from(flag)
+		} else {
+			Value::from(value.to_string())
+		};
+		self.0.push((key.to_string(), value));
+		Ok(())
+	}
+}
+
+fn fields(record: &log::Record) -> Vec<(String, Value)> {
+	let mut fields = Fields(Vec::new());
+	let _ = record.key_values().visit(&mut fields);
+	fields.0
+}
+
+/// A record as a JSON object
+///
+/// The request id and user come from the request being handled; a `user_id`
+/// key-value on the record takes precedence.
+pub fn json_record(record: &log::Record) -> Value {
+	let mut object = Map::new();
+	object.insert(
+		"timestamp".to_string(),
+		Value::from(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+	);
+	object.insert("level".to_string(), Value::from(record.level().as_str()));
+	object.insert("target".to_string(), Value::from(record.target()));
+	object.insert(
+		"message".to_string(),
+		Value::from(record.args().to_string()),
+	);
+	object.insert(
+		"request_id".to_string(),
+		request_id::current().map_or(Value::Null, Value::from),
+	);
+	object.insert(
+		"user_id".to_string(),
+		request_id::current_user().map_or(Value::Null, Value::from),
+	);
+	for (key, value) in fields(record) {
+		object.insert(key, value);
+	}
+	Value::Object(object)
+}
 
 /// Initialize the logging system with enhanced configuration
 ///
 /// This function sets up logging with the following features:
-/// - Environment-based log level configuration (RUST_LOG)
+/// - Environment-based log level configuration (RUST_LOG), overridden by
+///   the configured level
 /// - Colored output for terminal
 /// - Timestamp formatting
 /// - Module path filtering
 /// - Configurable target (stdout/stderr)
-pub fn init() {
+/// - The request id on records logged while handling a request
+/// - Text or JSON output
+pub fn init(config: &LoggingConfig) {
 	let env = Env::default()
 		.filter_or("RUST_LOG", "liga_muertos_back=info,actix_web=info")
 		.write_style_or("RUST_LOG_STYLE", "auto");
 
 	let mut builder = Builder::from_env(env);
+	builder.target(Target::Stdout);
 
-	// Configure the log format
-	builder
-		.target(Target::Stdout)
-		.format_timestamp_secs()
-		.format_module_path(true)
-		.format_level(true);
-
-	// Set additional filtering based on environment
-	if let Ok(level) = env::var("LOG_LEVEL") {
-		let level_filter = match level.to_lowercase().as_str() {
-			"trace" => LevelFilter::Trace,
-			"debug" => LevelFilter::Debug,
-			"info" => LevelFilter::Info,
-			"warn" => LevelFilter::Warn,
-			"error" => LevelFilter::Error,
-			"off" => LevelFilter::Off,
-			_ => {
-				eprintln!("⚠️  Invalid LOG_LEVEL '{level}', using default");
-				LevelFilter::Info
-			}
-		};
-		builder.filter_level(level_filter);
+	match config.format {
+		// `[timestamp LEVEL module req=<id>] message key=value ...`
+		LogFormat::Text => builder.format(|buf, record| {
+			let level = buf.default_level_style(record.level());
+			let request = request_id::current()
+				.map(|id| format!(" req={id}"))
+				.unwrap_or_default();
+			let fields: String = fields(record)
+				.into_iter()
+				.map(|(key, value)| match value {
+					Value::String(text) => format!(" {key}={text}"),
+					other => format!(" {key}={other}"),
+				})
+				.collect();
+			writeln!(
+				buf,
+				"[{} {level}{:<5}{level:#} {}{request}] {}{fields}",
+				buf.timestamp_seconds(),
+				record.level(),
+				record.module_path().unwrap_or_default(),
+				record.args()
+			)
+		}),
+		LogFormat::Json => builder
+			.write_style(env_logger::WriteStyle::Never)
+			.format(|buf, record| writeln!(buf, "{}", json_record(record))),
+	};
+
+	// The configured level applies to every module
+	if let Some(level) = config.level {
+		builder.filter_level(level);
 	}
 
 	// Initialize the logger
 	builder.init();
 }
 
-/// Log application startup information
-pub fn startup_info(port: u16) {
+/// Log application startup information, with the configured bind address
+pub fn startup_info(host: &str, port: u16) {
 	log::info!("🦀 Starting La Liga de los Muertos backend");
-	log::info!("🌐 Server will bind to 0.0.0.0:{port}");
+	log::info!("🌐 Server will bind to {host}:{port}");
 	log::info!("📝 API documentation: https://la-liga-de-los-muertos.apidog.io");
 	log::debug!("🔧 Debug logging enabled");
 }
 
-/// Log database connection information
+/// Log database connection information (credentials in `url` are masked)
 pub fn database_info(url: &str, namespace: &str, database: &str) {
+	let url = crate::config::redact_url(url);
 	log::info!("🔌 Connected to SurrealDB at {url}");
 	log::info!("📊 Using namespace: {namespace} / database: {database}");
 }
@@ -124,6 +233,11 @@ pub fn schema_success() {
 	log::info!("✅ Database schema initialized successfully");
 }
 
+/// Log a schema migration that was just applied
+pub fn migration_applied(version: u32, name: &str) {
+	log::info!("📦 Applied migration {version:04} ({name})");
+}
+
 /// Log database connection failure
 pub fn database_error(error: &str) {
 	log::error!("❌ Failed to initialize database connection: {error}");
@@ -131,10 +245,24 @@ pub fn database_error(error: &str) {
 	log::error!("💡 Ensure SurrealDB is running and accessible at the configured URL");
 }
 
+/// Log a lost database connection
+pub fn database_connection_lost(error: &str) {
+	log::error!("🔌 Lost connection to SurrealDB: {error}");
+	log::warn!("🔄 Reconnecting to SurrealDB in the background...");
+}
+
+/// Log a restored database connection
+pub fn database_reconnected(outage: std::time::Duration, reconnects: u64) {
+	log::info!(
+		"✅ Reconnected to SurrealDB after {}s (reconnect #{reconnects})",
+		outage.as_secs()
+	);
+}
+
 /// Log server startup success
 pub fn server_ready(port: u16) {
 	log::info!("🚀 Server ready and listening on port {port}");
-	log::info!("🏥 Health check available at: http://localhost:{port}/health");
+	log::info!("🏥 Health check available at: http://localhost:{port}/v1/health/ready");
 }
 
 /// Log graceful shutdown
@@ -155,27 +283,65 @@ pub fn request_debug(method: &str, path: &str, user_agent: Option<&str>) {
 /// Log performance metrics
 pub fn performance_metric(operation: &str, duration_ms: u64) {
 	if duration_ms > 1000 {
-		log::warn!("⏰ Slow operation: {operation} took {duration_ms}ms");
+		log::warn!(
+			event = "performance_metric", operation, duration_ms, slow = true;
+			"⏰ Slow operation"
+		);
 	} else {
-		log::debug!("⚡ {operation}: {duration_ms}ms");
+		log::debug!(
+			event = "performance_metric", operation, duration_ms;
+			"⚡ Operation timed"
+		);
+	}
+}
+
+/// Timer for a database operation, logged with its duration when dropped
+///
+/// Records are tagged with the request id like any other, so slow queries can
+/// be traced back to the request that made them.
+pub struct DbSpan {
+	operation: &'static str,
+	started: Instant,
+}
+
+/// Start timing a database operation (e.g. `select`)
+pub fn db_span(operation: &'static str) -> DbSpan {
+	DbSpan {
+		operation,
+		started: Instant::now(),
+	}
+}
+
+impl Drop for DbSpan {
+	fn drop(&mut self) {
+		let elapsed = self.started.elapsed();
+		metrics::db_query(self.operation, elapsed);
+		performance_metric(
+			&format!("db {}", self.operation),
+			elapsed.as_millis() as u64,
+		);
 	}
 }
 
 /// Log authentication events
 pub fn auth_event(event: &str, user_id: Option<&str>) {
 	match user_id {
-		Some(id) => log::info!("🔐 Auth event: {event} for user {id}"),
-		None => log::info!("🔐 Auth event: {event}"),
+		Some(user_id) => log::info!(category = "auth", event, user_id; "🔐 Auth event"),
+		None => log::info!(category = "auth", event; "🔐 Auth event"),
 	}
 }
 
 /// Log tournament events
 pub fn tournament_event(event: &str, tournament_id: &str, user_id: Option<&str>) {
 	match user_id {
-		Some(id) => {
-			log::info!("🏆 Tournament event: {event} for tournament {tournament_id} by user {id}")
-		}
-		None => log::info!("🏆 Tournament event: {event} for tournament {tournament_id}"),
+		Some(user_id) => log::info!(
+			category = "tournament", event, tournament_id, user_id;
+			"🏆 Tournament event"
+		),
+		None => log::info!(
+			category = "tournament", event, tournament_id;
+			"🏆 Tournament event"
+		),
 	}
 }
 
@@ -188,11 +354,13 @@ mod tests {
 		// These tests just ensure the logging functions don't panic
 		// Actual log output would need integration tests
 
-		startup_info(4000);
+		startup_info("127.0.0.1", 4000);
 		database_info("ws://localhost:8000", "test", "test");
 		schema_init();
 		schema_success();
 		database_error("Connection failed");
+		database_connection_lost("Connection reset");
+		database_reconnected(std::time::Duration::from_secs(12), 1);
 		server_ready(4000);
 		shutdown();
 		request_debug("GET", "/health", Some("test-agent"));
@@ -203,19 +371,59 @@ mod tests {
 		auth_event("logout", None);
 		tournament_event("created", "tournament123", Some("user456"));
 		tournament_event("started", "tournament123", None);
+		drop(db_span("select"));
 	}
 
 	#[test]
-	fn test_log_level_parsing() {
-		// Test that invalid log levels don't crash
-		unsafe {
-			std::env::set_var("LOG_LEVEL", "invalid");
-		}
-		// This would normally output a warning, but we can't easily test that
-		// without capturing stderr, so we just ensure it doesn't panic
+	fn test_log_format_parsing() {
+		assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+		assert_eq!(" Text ".parse::<LogFormat>(), Ok(LogFormat::Text));
+		assert!("yaml".parse::<LogFormat>().is_err());
+	}
 
-		unsafe {
-			std::env::remove_var("LOG_LEVEL");
-		}
+	#[test]
+	fn test_json_record_carries_structured_fields() {
+		let pairs: &[(&str, &str)] = &[("event", "created"), ("tournament_id", "copa")];
+		let record = log::Record::builder()
+			.args(format_args!("🏆 Tournament event"))
+			.level(log::Level::Info)
+			.target("liga_muertos_back::services")
+			.key_values(&pairs)
+			.build();
+
+		let json = json_record(&record);
+		assert_eq!(json["level"], "INFO");
+		assert_eq!(json["target"], "liga_muertos_back::services");
+		assert_eq!(json["message"], "🏆 Tournament event");
+		assert_eq!(json["event"], "created");
+		assert_eq!(json["tournament_id"], "copa");
+		assert!(json["request_id"].is_null());
+		assert!(json["timestamp"].as_str().unwrap().ends_with('Z'));
+
+		let duration: &[(&str, u64)] = &[("duration_ms", 42)];
+		let record = log::Record::builder()
+			.args(format_args!("⚡ Operation timed"))
+			.key_values(&duration)
+			.build();
+		assert_eq!(json_record(&record)["duration_ms"], 42);
+	}
+
+	#[actix_web::test]
+	async fn test_json_record_includes_request_context() {
+		let scope = request_id::RequestScope {
+			id: "trace-log".to_string(),
+			user_id: Some("user_7".to_string()),
+		};
+		let json = scope
+			.run(async {
+				json_record(
+					&log::Record::builder()
+						.args(format_args!("inside a request"))
+						.build(),
+				)
+			})
+			.await;
+		assert_eq!(json["request_id"], "trace-log");
+		assert_eq!(json["user_id"], "user_7");
 	}
 }
@@ -0,0 +1,216 @@
+//! Prometheus metrics
+//!
+//! Counters and histograms live in one process-wide registry, like the
+//! database client, because they're recorded from places that don't see the
+//! [`crate::AppState`] (the database handle, the connection supervisor, the
+//! logging helpers). `GET /metrics`
+//! renders the registry in the Prometheus text format.
+//!
+//! ## Metrics
+//! - `http_requests_total{method, route, status}`
+//! - `http_request_duration_seconds{method, route}`
+//! - `db_query_duration_seconds{operation}`, timed by [`crate::db::Database`]
+//!   for every query and labelled with its leading statement (`select`, ...)
+//! - `db_connection_up`, set by the [`crate::supervisor::Supervisor`]
+//! - `db_connection_state{state}`, `db_reconnects` (sampled when scraped)
+//! - `live_subscribers`, `live_topics` (sampled when scraped)
+//! - `webhook_deliveries_total{outcome}`
+//! - `tournaments_created_total{source}`, `matches_reported_total`
+//!
+//! Routes are labelled with their pattern (`/v1/matches/{id}`), never the
+//! raw path, so ids don't turn into label values.
+
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use prometheus::{
+	Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+	Registry, TextEncoder,
+};
+
+/// Content type of the text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Route label for requests that didn't match any route
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Latency buckets in seconds, from a cache hit to a timed-out request
+const LATENCY_BUCKETS: &[f64] = &[
+	0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Every collector the backend exposes
+pub struct Metrics {
+	registry: Registry,
+	pub http_requests: IntCounterVec,
+	pub http_duration: HistogramVec,
+	pub db_duration: HistogramVec,
+	pub db_connection_up: IntGauge,
+	pub db_connection_state: IntGaugeVec,
+	pub db_reconnects: IntGauge,
+	pub live_subscribers: IntGauge,
+	pub live_topics: IntGauge,
+	pub webhook_deliveries: IntCounterVec,
+	pub tournaments_created: IntCounterVec,
+	pub matches_reported: IntCounter,
+}
+
+impl Metrics {
+	fn new() -> prometheus::Result<Self> {
+		let registry = Registry::new();
+		let latency =
+			|name: &str, help: &str| HistogramOpts::new(name, help).buckets(LATENCY_BUCKETS.to_vec());
+
+		let metrics = Self {
+			http_requests: IntCounterVec::new(
+				Opts::new("http_requests_total", "HTTP requests handled"),
+				&["method", "route", "status"],
+			)?,
+			http_duration: HistogramVec::new(
+				latency("http_request_duration_seconds", "HTTP request latency"),
+				&["method", "route"],
+			)?,
+			db_duration: HistogramVec::new(
+				latency("db_query_duration_seconds", "Database query latency"),
+				&["operation"],
+			)?,
+			db_connection_up: IntGauge::new(
+				"db_connection_up",
+				"Whether the database connection is up (1) or not (0)",
+			)?,
+			db_connection_state: IntGaugeVec::new(
+				Opts::new(
+					"db_connection_state",
+					"Supervisor state of the database connection (1 for the current one)",
+				),
+				&["state"],
+			)?,
+			db_reconnects: IntGauge::new(
+				"db_reconnects",
+				"Database connection recoveries since startup",
+			)?,
+			live_subscribers: IntGauge::new(
+				"live_subscribers",
+				"WebSocket clients subscribed to live topics",
+			)?,
+			live_topics: IntGauge::new("live_topics", "Live topics with a channel open")?,
+			webhook_deliveries: IntCounterVec::new(
+				Opts::new("webhook_deliveries_total", "Finished webhook deliveries"),
+				&["outcome"],
+			)?,
+			tournaments_created: IntCounterVec::new(
+				Opts::new("tournaments_created_total", "Tournaments created"),
+				&["source"],
+			)?,
+			matches_reported: IntCounter::new("matches_reported_total", "Match scores reported")?,
+			registry,
+		};
+
+		let registry = &metrics.registry;
+		registry.register(Box::new(metrics.http_requests.clone()))?;
+		registry.register(Box::new(metrics.http_duration.clone()))?;
+		registry.register(Box::new(metrics.db_duration.clone()))?;
+		registry.register(Box::new(metrics.db_connection_up.clone()))?;
+		registry.register(Box::new(metrics.db_connection_state.clone()))?;
+		registry.register(Box::new(metrics.db_reconnects.clone()))?;
+		registry.register(Box::new(metrics.live_subscribers.clone()))?;
+		registry.register(Box::new(metrics.live_topics.clone()))?;
+		registry.register(Box::new(metrics.webhook_deliveries.clone()))?;
+		registry.register(Box::new(metrics.tournaments_created.clone()))?;
+		registry.register(Box::new(metrics.matches_reported.clone()))?;
+		Ok(metrics)
+	}
+
+	/// Everything registered, in the Prometheus text format
+	pub fn render(&self) -> String {
+		let mut buffer = Vec::new();
+		if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+			log::error!("Failed to encode metrics: {e}");
+		}
+		String::from_utf8(buffer).unwrap_or_default()
+	}
+}
+
+static METRICS: LazyLock<Metrics> =
+	LazyLock::new(|| Metrics::new().expect("metric definitions are valid"));
+
+/// The process-wide collectors
+pub fn global() -> &'static Metrics {
+	&METRICS
+}
+
+/// Record a handled HTTP request
+pub fn http_request(method: &str, route: &str, status: u16, elapsed: Duration) {
+	let metrics = global();
+	metrics
+		.http_requests
+		.with_label_values(&[method, route, &status.to_string()])
+		.inc();
+	metrics
+		.http_duration
+		.with_label_values(&[method, route])
+		.observe(elapsed.as_secs_f64());
+}
+
+/// Record how long a database operation took (e.g. `select`)
+pub fn db_query(operation: &str, elapsed: Duration) {
+	global()
+		.db_duration
+		.with_label_values(&[operation])
+		.observe(elapsed.as_secs_f64());
+}
+
+/// Record whether the database connection is currently usable
+pub fn db_connection_up(up: bool) {
+	global().db_connection_up.set(up as i64);
+}
+
+/// Record the final outcome of a webhook delivery (`delivered` or `failed`)
+pub fn webhook_delivery(outcome: &str) {
+	global()
+		.webhook_deliveries
+		.with_label_values(&[outcome])
+		.inc();
+}
+
+/// Count a new tournament; `source` is `api` or `import`
+pub fn tournament_created(source: &str) {
+	global()
+		.tournaments_created
+		.with_label_values(&[source])
+		.inc();
+}
+
+/// Count a reported match score
+pub fn match_reported() {
+	global().matches_reported.inc();
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_recorded_values_are_rendered() {
+		http_request(
+			"GET",
+			"/v1/metrics-test/{id}",
+			200,
+			Duration::from_millis(12),
+		);
+		db_query("metrics_test.find", Duration::from_millis(3));
+		webhook_delivery("delivered");
+
+		let text = global().render();
+		assert!(text.contains(
+			r#"http_requests_total{method="GET",route="/v1/metrics-test/{id}",status="200"} 1"#
+		));
+		assert!(text.contains(
+			r#"http_request_duration_seconds_bucket{method="GET",route="/v1/metrics-test/{id}",le="0.025"} 1"#
+		));
+		assert!(text.contains(r#"db_query_duration_seconds_count{operation="metrics_test.find"} 1"#));
+		assert!(text.contains("# TYPE db_connection_up gauge"));
+		assert!(text.contains("# TYPE webhook_deliveries_total counter"));
+		assert!(text.contains("# TYPE live_subscribers gauge"));
+	}
+}
@@ -5,12 +5,15 @@
 //! error handling, and other cross-cutting concerns.
 
 pub mod error;
+pub mod i18n;
+pub mod ical;
 pub mod logging;
+pub mod metrics;
 
 // Re-export modules for clean imports
 // Usage: use liga_muertos_back::utils::logging;
-//        logging::init();
-//        logging::startup_info(port);
+//        logging::init(&config.logging);
+//        logging::startup_info(&host, port);
 
 /// Application constants and configuration values
 pub mod constants {
@@ -56,6 +59,8 @@ pub mod constants {
 
 /// Validation utilities
 pub mod validation {
+	use std::net::IpAddr;
+
 	/// Check if an email address is valid (basic validation)
 	pub fn is_valid_email(email: &str) -> bool {
 		email.contains('@')
@@ -120,6 +125,83 @@ pub mod validation {
 		has_lowercase && has_uppercase && has_digit
 	}
 
+	/// Check if a URL is a valid absolute http(s) URL (e.g. a webhook endpoint)
+	pub fn is_valid_url(url: &str) -> bool {
+		const MAX_LENGTH: usize = 2048;
+
+		let rest = match url
+			.strip_prefix("https://")
+			.or_else(|| url.strip_prefix("http://"))
+		{
+			Some(rest) => rest,
+			None => return false,
+		};
+
+		let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+		url.len() <= MAX_LENGTH
+			&& !host.is_empty()
+			&& url.is_ascii()
+			&& !url.chars().any(char::is_whitespace)
+	}
+
+	/// Check if a URL is safe to call from the server (e.g. a webhook endpoint)
+	///
+	/// It must be https and its host must not be `localhost` or an address on
+	/// a loopback, private or link-local network. Hostnames are only checked
+	/// by name here; what they resolve to is checked when connecting.
+	pub fn is_public_https_url(url: &str) -> bool {
+		if !is_valid_url(url) || !url.starts_with("https://") {
+			return false;
+		}
+
+		// Parsing normalizes hosts such as `0x7f.1` into plain addresses
+		let Some(host) = reqwest::Url::parse(url)
+			.ok()
+			.and_then(|url| url.host_str().map(str::to_lowercase))
+		else {
+			return false;
+		};
+		match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+			Ok(ip) => is_public_ip(ip),
+			Err(_) => {
+				let name = host.trim_end_matches('.');
+				name != "localhost" && !name.ends_with(".localhost")
+			}
+		}
+	}
+
+	/// Check if an address is reachable on the public internet
+	///
+	/// Loopback, private, link-local, shared (CGNAT), unspecified, broadcast,
+	/// multicast and documentation ranges are not.
+	pub fn is_public_ip(ip: IpAddr) -> bool {
+		match ip {
+			IpAddr::V4(ip) => {
+				let [first, second, ..] = ip.octets();
+				let shared = first == 100 && (second & 0xc0) == 64;
+				!(ip.is_loopback()
+					|| ip.is_private()
+					|| ip.is_link_local()
+					|| ip.is_unspecified()
+					|| ip.is_broadcast()
+					|| ip.is_multicast()
+					|| ip.is_documentation()
+					|| shared
+					|| first == 0)
+			}
+			IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+				Some(ip) => is_public_ip(ip.into()),
+				None => {
+					!(ip.is_loopback()
+						|| ip.is_unspecified()
+						|| ip.is_multicast()
+						|| ip.is_unique_local()
+						|| ip.is_unicast_link_local())
+				}
+			},
+		}
+	}
+
 	/// Validate that required fields are present in a collection
 	pub fn validate_required_fields(fields: &[(&str, Option<&str>)]) -> Result<(), Vec<String>> {
 		let missing_fields: Vec<String> = fields
@@ -180,6 +262,36 @@ pub mod validation {
 			assert!(!is_valid_password("Pass1")); // too short
 		}
 
+		#[test]
+		fn test_url_validation() {
+			assert!(is_valid_url("https://example.com/hooks/liga"));
+			assert!(is_valid_url("http://localhost:8080"));
+			assert!(!is_valid_url("ftp://example.com"));
+			assert!(!is_valid_url("https://"));
+			assert!(!is_valid_url("https://exa mple.com"));
+			assert!(!is_valid_url("example.com"));
+		}
+
+		#[test]
+		fn test_public_https_url_validation() {
+			assert!(is_public_https_url("https://hooks.example.com/liga"));
+			assert!(is_public_https_url("https://93.184.216.34:8443/hook"));
+			assert!(!is_public_https_url("http://hooks.example.com/liga"));
+			assert!(!is_public_https_url("https://localhost:8080"));
+			assert!(!is_public_https_url("https://api.localhost/hook"));
+			assert!(!is_public_https_url("https://127.0.0.1/hook"));
+			assert!(!is_public_https_url("https://0x7f.1/hook"));
+			assert!(!is_public_https_url("https://10.0.0.8/hook"));
+			assert!(!is_public_https_url("https://192.168.1.20/hook"));
+			assert!(!is_public_https_url(
+				"https://169.254.169.254/latest/meta-data"
+			));
+			assert!(!is_public_https_url("https://[::1]/hook"));
+			assert!(!is_public_https_url("https://[fe80::1]/hook"));
+			assert!(!is_public_https_url("https://[::ffff:10.0.0.1]/hook"));
+			assert!(!is_public_https_url("https://user@192.168.0.1/hook"));
+		}
+
 		#[test]
 		fn test_required_fields_validation() {
 			let fields = vec![
@@ -205,8 +317,13 @@ pub mod validation {
 }
 
 /// Time and date utilities
+///
+/// Everything is stored and compared in UTC; IANA timezones are only used to
+/// render timestamps for a given viewer.
 pub mod time {
-	use chrono::{DateTime, Duration, Utc};
+	use chrono::{DateTime, Duration, SecondsFormat, Utc};
+	use chrono_tz::Tz;
+	use serde::{Deserialize, Serialize};
 
 	/// Get current UTC timestamp
 	pub fn now_utc() -> DateTime<Utc> {
@@ -238,6 +355,63 @@ pub mod time {
 		*timestamp > Utc::now()
 	}
 
+	/// Parse an IANA timezone name (e.g. "America/Mexico_City")
+	pub fn parse_timezone(name: &str) -> Option<Tz> {
+		name.trim().parse().ok()
+	}
+
+	/// Render a UTC timestamp as RFC 3339 in the given timezone
+	pub fn format_in_timezone(timestamp: &DateTime<Utc>, timezone: Tz) -> String {
+		timestamp
+			.with_timezone(&timezone)
+			.to_rfc3339_opts(SecondsFormat::Secs, false)
+	}
+
+	/// Half-open UTC time range `[starts_at, ends_at)`
+	#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+	pub struct TimeSlot {
+		pub starts_at: DateTime<Utc>,
+		pub ends_at: DateTime<Utc>,
+	}
+
+	impl TimeSlot {
+		pub fn new(starts_at: DateTime<Utc>, ends_at: DateTime<Utc>) -> Self {
+			Self { starts_at, ends_at }
+		}
+
+		/// Slot of the given length starting at `starts_at`
+		pub fn starting_at(starts_at: DateTime<Utc>, minutes: u32) -> Self {
+			Self::new(starts_at, starts_at + Duration::minutes(i64::from(minutes)))
+		}
+
+		/// Check whether two slots share any instant (touching ends don't count)
+		pub fn overlaps(&self, other: &TimeSlot) -> bool {
+			self.starts_at < other.ends_at && other.starts_at < self.ends_at
+		}
+
+		/// Check whether another slot fits entirely inside this one
+		pub fn contains(&self, other: &TimeSlot) -> bool {
+			self.starts_at <= other.starts_at && other.ends_at <= self.ends_at
+		}
+
+		/// Split this range into consecutive slots of the given length
+		///
+		/// A trailing remainder shorter than `minutes` is dropped.
+		pub fn split(&self, minutes: u32) -> Vec<TimeSlot> {
+			let mut slots = Vec::new();
+			if minutes == 0 {
+				return slots;
+			}
+
+			let mut slot = TimeSlot::starting_at(self.starts_at, minutes);
+			while slot.ends_at <= self.ends_at {
+				slots.push(slot);
+				slot = TimeSlot::starting_at(slot.ends_at, minutes);
+			}
+			slots
+		}
+	}
+
 	#[cfg(test)]
 	mod tests {
 		use super::*;
@@ -256,6 +430,35 @@ pub mod time {
 			let formatted = format_timestamp(&now);
 			assert!(formatted.contains("UTC"));
 		}
+
+		#[test]
+		fn test_timezone_rendering() {
+			let timestamp = DateTime::parse_from_rfc3339("2025-11-02T02:30:00Z")
+				.unwrap()
+				.with_timezone(&Utc);
+
+			let mexico = parse_timezone("America/Mexico_City").unwrap();
+			assert_eq!(
+				format_in_timezone(&timestamp, mexico),
+				"2025-11-01T20:30:00-06:00"
+			);
+			assert!(parse_timezone("Mars/Olympus_Mons").is_none());
+		}
+
+		#[test]
+		fn test_time_slots() {
+			let start = now_utc();
+			let window = TimeSlot::starting_at(start, 150);
+
+			let slots = window.split(60);
+			assert_eq!(slots.len(), 2);
+			assert_eq!(slots[1].starts_at, start + Duration::minutes(60));
+			assert!(window.contains(&slots[1]));
+
+			assert!(!slots[0].overlaps(&slots[1]));
+			assert!(slots[0].overlaps(&TimeSlot::starting_at(start + Duration::minutes(30), 60)));
+			assert!(window.split(0).is_empty());
+		}
 	}
 }
 
@@ -0,0 +1,966 @@
+//! Route-level tests against the in-memory database (`test-utils` harness)
+
+use std::sync::Arc;
+
+use actix_web::middleware::from_fn;
+use actix_web::{App, http::StatusCode, test, web};
+use serde_json::{Value, json};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use liga_muertos_back::config::{Config, Secret};
+use liga_muertos_back::entities::User;
+use liga_muertos_back::middleware::rate_limit;
+use liga_muertos_back::services::jobs::JobQueue;
+use liga_muertos_back::services::twitch::{TwitchClient, TwitchConfig};
+use liga_muertos_back::test_utils::{self, factories};
+use liga_muertos_back::{AppState, DB, migrations, routes};
+
+macro_rules! app {
+	() => {
+		app!(AppState::new(Config::default()))
+	};
+	($state:expr) => {
+		test::init_service(
+			App::new()
+				.wrap(from_fn(test_utils::fake_auth))
+				.app_data(web::Data::new($state))
+				.configure(routes::entry),
+		)
+		.await
+	};
+}
+
+#[actix_web::test]
+async fn test_migrations_apply_once() {
+	test_utils::setup();
+
+	// A second run finds nothing to do
+	migrations::run().await.unwrap();
+
+	let mut response = DB
+		.query("SELECT VALUE version FROM _migration ORDER BY version")
+		.await
+		.unwrap();
+	let versions: Vec<u32> = response.take(0).unwrap();
+	let expected: Vec<u32> = migrations::MIGRATIONS.iter().map(|m| m.version).collect();
+	assert_eq!(versions, expected);
+}
+
+#[actix_web::test]
+async fn test_ready_once_database_and_migrations_are() {
+	test_utils::setup();
+	let mut config = Config::default();
+	config.auth.clerk_secret_key = Some(Secret::new("sk_test_liga"));
+	let app = app!(AppState::new(config));
+
+	let req = test::TestRequest::get()
+		.uri("/v1/health/ready")
+		.to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(resp.status(), StatusCode::OK);
+
+	let body: Value = test::read_body_json(resp).await;
+	assert_eq!(body["status"], "ready");
+	assert_eq!(body["checks"]["database"]["status"], "up");
+	assert!(body["checks"]["database"]["latency_ms"].is_u64());
+	assert_eq!(body["checks"]["migrations"]["status"], "up");
+	assert_eq!(body["checks"]["twitch"]["status"], "disabled");
+}
+
+#[actix_web::test]
+async fn test_unpublished_tournaments_are_only_visible_to_their_organizer() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let viewer = factories::user("viewer").await;
+	let tournament = factories::tournament(&organizer, false).await;
+	let app = app!();
+	let uri = format!("/v1/tournaments/{}/matches", tournament.id.key());
+
+	let req = test::TestRequest::get().uri(&uri).to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::NOT_FOUND
+	);
+
+	let req = test::TestRequest::get()
+		.uri(&uri)
+		.insert_header(test_utils::signed_in(&viewer))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::NOT_FOUND
+	);
+
+	let req = test::TestRequest::get()
+		.uri(&uri)
+		.insert_header(test_utils::signed_in(&organizer))
+		.to_request();
+	assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_players_join_and_leave_published_tournaments() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let player = factories::user("player").await;
+	let app = app!();
+
+	let req = test::TestRequest::post()
+		.uri("/v1/tournaments")
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(json!({ "name": "Copa Catrina", "description": "Torneo de prueba" }))
+		.to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(resp.status(), StatusCode::CREATED);
+	let created: Value = test::read_body_json(resp).await;
+	assert_eq!(created["data"]["published"], false);
+	let key = serde_json::from_value::<surrealdb::RecordId>(created["data"]["id"].clone())
+		.unwrap()
+		.key()
+		.to_string();
+	let participants = format!("/v1/tournaments/{key}/participants");
+
+	// Drafts are not open for registration
+	let join = || {
+		test::TestRequest::post()
+			.uri(&participants)
+			.insert_header(test_utils::signed_in(&player))
+			.to_request()
+	};
+	assert_eq!(
+		test::call_service(&app, join()).await.status(),
+		StatusCode::NOT_FOUND
+	);
+
+	let req = test::TestRequest::patch()
+		.uri(&format!("/v1/tournaments/{key}"))
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(json!({ "published": true }))
+		.to_request();
+	let updated: Value = test::call_and_read_body_json(&app, req).await;
+	assert_eq!(updated["data"]["published"], true);
+
+	assert_eq!(
+		test::call_service(&app, join()).await.status(),
+		StatusCode::CREATED
+	);
+	assert_eq!(
+		test::call_service(&app, join()).await.status(),
+		StatusCode::CONFLICT
+	);
+
+	let req = test::TestRequest::get().uri(&participants).to_request();
+	let listed: Value = test::call_and_read_body_json(&app, req).await;
+	assert_eq!(listed["data"].as_array().unwrap().len(), 1);
+
+	let req = test::TestRequest::delete()
+		.uri(&format!("{participants}/me"))
+		.insert_header(test_utils::signed_in(&player))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::NO_CONTENT
+	);
+
+	let req = test::TestRequest::get().uri(&participants).to_request();
+	let listed: Value = test::call_and_read_body_json(&app, req).await;
+	assert!(listed["data"].as_array().unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_organizer_changes_and_clears_the_season() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let app = app!();
+
+	let req = test::TestRequest::post()
+		.uri("/v1/tournaments")
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(json!({ "name": "Copa Catrina", "description": "", "season": "2025" }))
+		.to_request();
+	let created: Value = test::call_and_read_body_json(&app, req).await;
+	let key = serde_json::from_value::<surrealdb::RecordId>(created["data"]["id"].clone())
+		.unwrap()
+		.key()
+		.to_string();
+	let update = |changes: Value| {
+		test::TestRequest::patch()
+			.uri(&format!("/v1/tournaments/{key}"))
+			.insert_header(test_utils::signed_in(&organizer))
+			.set_json(changes)
+			.to_request()
+	};
+
+	// Leaving the season out keeps it
+	let updated: Value =
+		test::call_and_read_body_json(&app, update(json!({ "name": "Copa Calaca" }))).await;
+	assert_eq!(updated["data"]["season"], "2025");
+
+	let updated: Value =
+		test::call_and_read_body_json(&app, update(json!({ "season": "2026" }))).await;
+	assert_eq!(updated["data"]["season"], "2026");
+
+	let updated: Value = test::call_and_read_body_json(&app, update(json!({ "season": null }))).await;
+	assert!(updated["data"]["season"].is_null());
+	assert_eq!(updated["data"]["name"], "Copa Calaca");
+}
+
+#[actix_web::test]
+async fn test_organizer_creates_matches() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let player = factories::user("player").await;
+	let tournament = factories::tournament(&organizer, true).await;
+	let app = app!();
+	let uri = format!("/v1/tournaments/{}/matches", tournament.id.key());
+	let body = json!({ "round": 1, "position": 1, "home": null, "away": null, "scheduled_at": null });
+
+	let req = test::TestRequest::post()
+		.uri(&uri)
+		.insert_header(test_utils::signed_in(&player))
+		.set_json(&body)
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::FORBIDDEN
+	);
+
+	let req = test::TestRequest::post()
+		.uri(&uri)
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(&body)
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::CREATED
+	);
+
+	// Published, so anyone can list them
+	let req = test::TestRequest::get().uri(&uri).to_request();
+	let listed: Value = test::call_and_read_body_json(&app, req).await;
+	assert_eq!(listed["data"].as_array().unwrap().len(), 1);
+}
+
+#[actix_web::test]
+async fn test_match_webhooks_are_queued_as_jobs() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let tournament = factories::tournament(&organizer, true).await;
+	let state = AppState {
+		jobs: JobQueue::deferred(),
+		..AppState::new(Config::default())
+	};
+	let app = app!(state.clone());
+
+	let req = test::TestRequest::post()
+		.uri(&format!("/v1/tournaments/{}/webhooks", tournament.id.key()))
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(json!({ "url": "https://hooks.example.com/liga", "events": ["match.scheduled"] }))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::CREATED
+	);
+
+	let req = test::TestRequest::post()
+		.uri(&format!("/v1/tournaments/{}/matches", tournament.id.key()))
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(json!({
+			"round": 1,
+			"position": 1,
+			"home": null,
+			"away": null,
+			"scheduled_at": "2030-11-02T20:00:00Z",
+		}))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::CREATED
+	);
+
+	// Recorded right away, pushed only when the queue runs
+	assert_eq!(state.jobs.pending_names(), vec!["webhook_delivery"]);
+	let mut response = DB
+		.query("SELECT VALUE status FROM webhook_delivery WHERE webhook.tournament = $tournament")
+		.bind(("tournament", tournament.id.clone()))
+		.await
+		.unwrap();
+	let statuses: Vec<String> = response.take(0).unwrap();
+	assert_eq!(statuses, vec!["pending"]);
+}
+
+#[actix_web::test]
+async fn test_confirmed_results_are_final_and_score_predictions() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let home = factories::user("home").await;
+	let away = factories::user("away").await;
+	let viewer = factories::user("viewer").await;
+	let tournament = factories::tournament(&organizer, true).await;
+	let home_player = factories::participant(&tournament, &home).await;
+	let away_player = factories::participant(&tournament, &away).await;
+	let app = app!();
+
+	let req = test::TestRequest::post()
+		.uri(&format!("/v1/tournaments/{}/matches", tournament.id.key()))
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(json!({ "round": 1, "position": 1, "home": home_player.id, "away": away_player.id }))
+		.to_request();
+	let created: Value = test::call_and_read_body_json(&app, req).await;
+	let game: surrealdb::RecordId = serde_json::from_value(created["data"]["id"].clone()).unwrap();
+	let uri = format!("/v1/matches/{}", game.key());
+	let act = |user: &User, action: &str, body: Value| {
+		test::TestRequest::post()
+			.uri(&format!("{uri}/{action}"))
+			.insert_header(test_utils::signed_in(user))
+			.set_json(body)
+			.to_request()
+	};
+	let predict = || {
+		test::TestRequest::put()
+			.uri(&format!("{uri}/predictions/me"))
+			.insert_header(test_utils::signed_in(&viewer))
+			.set_json(json!({ "outcome": "home", "home_score": 2, "away_score": 1 }))
+			.to_request()
+	};
+
+	assert_eq!(
+		test::call_service(&app, predict()).await.status(),
+		StatusCode::OK
+	);
+	let resp = test::call_service(&app, act(&organizer, "start", json!({}))).await;
+	assert_eq!(resp.status(), StatusCode::OK);
+	assert_eq!(
+		test::call_service(&app, act(&organizer, "start", json!({})))
+			.await
+			.status(),
+		StatusCode::CONFLICT
+	);
+	assert_eq!(
+		test::call_service(&app, predict()).await.status(),
+		StatusCode::CONFLICT
+	);
+
+	let score = json!({ "home_score": 2, "away_score": 1 });
+	let resp = test::call_service(&app, act(&home, "report", score.clone())).await;
+	assert_eq!(resp.status(), StatusCode::OK);
+	let resp = test::call_service(&app, act(&away, "confirm", json!({}))).await;
+	assert_eq!(resp.status(), StatusCode::OK);
+
+	let mut response = DB
+		.query("SELECT VALUE points FROM prediction WHERE match = $match")
+		.bind(("match", game.clone()))
+		.await
+		.unwrap();
+	let points: Vec<u32> = response.take(0).unwrap();
+	assert_eq!(points, vec![5]);
+
+	// Final: no second confirmation, new report or cancellation
+	for (user, action, body) in [
+		(&organizer, "confirm", json!({})),
+		(&home, "report", score),
+		(&organizer, "cancel", json!({})),
+	] {
+		assert_eq!(
+			test::call_service(&app, act(user, action, body))
+				.await
+				.status(),
+			StatusCode::CONFLICT,
+			"{action}"
+		);
+	}
+}
+
+#[actix_web::test]
+async fn test_participant_import_reports_unknown_users() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let player = factories::user("player").await;
+	let tournament = factories::tournament(&organizer, true).await;
+	let app = app!();
+	let uri = format!(
+		"/v1/tournaments/{}/participants/import?dry_run=true",
+		tournament.id.key()
+	);
+
+	let req = test::TestRequest::post()
+		.uri(&uri)
+		.insert_header(test_utils::signed_in(&organizer))
+		.insert_header(("Content-Type", "text/csv"))
+		.set_payload(format!("username\n{}\nnobody_here\n", player.username))
+		.to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+	let report: Value = test::read_body_json(resp).await;
+	assert_eq!(report["data"]["valid"], 1);
+	assert_eq!(report["data"]["invalid"], 1);
+
+	let req = test::TestRequest::post()
+		.uri(&uri)
+		.insert_header(test_utils::signed_in(&organizer))
+		.insert_header(("Content-Type", "text/csv"))
+		.set_payload(format!("username\n{}\n", player.username))
+		.to_request();
+	assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+	let req = test::TestRequest::post()
+		.uri(&uri)
+		.insert_header(test_utils::signed_in(&organizer))
+		.insert_header(("Content-Type", "text/plain"))
+		.set_payload(format!("username\n{}\n", player.username))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::UNSUPPORTED_MEDIA_TYPE
+	);
+}
+
+#[actix_web::test]
+async fn test_archive_export_imports_as_a_new_tournament() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let player = factories::user("player").await;
+	let tournament = factories::tournament(&organizer, true).await;
+	factories::participant(&tournament, &player).await;
+	let app = app!();
+
+	let req = test::TestRequest::get()
+		.uri(&format!("/v1/tournaments/{}/export", tournament.id.key()))
+		.insert_header(test_utils::signed_in(&organizer))
+		.to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(
+		resp.headers().get("content-disposition").unwrap(),
+		format!(
+			"attachment; filename=\"tournament-{}.json\"",
+			tournament.id.key()
+		)
+		.as_str()
+	);
+	let archive: Value = test::read_body_json(resp).await;
+	assert_eq!(archive["participants"].as_array().unwrap().len(), 1);
+	// Players are identified by id and username, never by email
+	let exported = &archive["participants"][0];
+	assert_eq!(exported["user"], player.id.to_string());
+	assert_eq!(exported["username"], player.username);
+	assert!(exported.get("email").is_none());
+	assert!(!archive.to_string().contains(&player.email));
+
+	let other = factories::user("organizer").await;
+	let req = test::TestRequest::post()
+		.uri("/v1/tournaments/import")
+		.insert_header(test_utils::signed_in(&other))
+		.insert_header(("Content-Type", "application/json"))
+		.set_payload("{\n  \"format\": }")
+		.to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+	let error: Value = test::read_body_json(resp).await;
+	assert_eq!(error["error_code"], "JSON_PARSING_ERROR");
+	assert_eq!(error["details"]["line"], 2);
+	assert_eq!(error["details"]["path"], "/v1/tournaments/import");
+
+	let req = test::TestRequest::post()
+		.uri("/v1/tournaments/import")
+		.insert_header(test_utils::signed_in(&other))
+		.set_json(&archive)
+		.to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(resp.status(), StatusCode::CREATED);
+	let report: Value = test::read_body_json(resp).await;
+	let imported = &report["data"]["tournament"];
+	assert_eq!(imported["name"], archive["tournament"]["name"]);
+	assert_eq!(imported["published"], false);
+
+	// The participant was matched to the same local user
+	let id: surrealdb::RecordId = serde_json::from_value(imported["id"].clone()).unwrap();
+	let mut response = DB
+		.query("SELECT VALUE user_id FROM participant WHERE tournament = $tournament")
+		.bind(("tournament", id))
+		.await
+		.unwrap();
+	let users: Vec<surrealdb::RecordId> = response.take(0).unwrap();
+	assert_eq!(users, vec![player.id]);
+
+	// Unmatched participants are reported without their email
+	let mut unknown = archive.clone();
+	unknown["participants"][0]["username"] = json!("ghost");
+	unknown["participants"][0]["email"] = json!("ghost@example.com");
+	let req = test::TestRequest::post()
+		.uri("/v1/tournaments/import")
+		.insert_header(test_utils::signed_in(&other))
+		.set_json(&unknown)
+		.to_request();
+	let resp = test::call_service(&app, req).await;
+	assert!(resp.status().is_client_error());
+	let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+	assert!(body.contains("row 1 (ghost)"), "got {body}");
+	assert!(!body.contains("ghost@example.com"));
+}
+
+#[actix_web::test]
+async fn test_organizer_manages_webhooks() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let player = factories::user("player").await;
+	let tournament = factories::tournament(&organizer, true).await;
+	let app = app!();
+	let uri = format!("/v1/tournaments/{}/webhooks", tournament.id.key());
+
+	let req = test::TestRequest::get().uri(&uri).to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::UNAUTHORIZED
+	);
+
+	let req = test::TestRequest::post()
+		.uri(&uri)
+		.insert_header(test_utils::signed_in(&player))
+		.set_json(json!({ "url": "https://hooks.example.com/liga" }))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::FORBIDDEN
+	);
+
+	// Endpoints on the internal network are refused
+	for url in [
+		"http://hooks.example.com/liga",
+		"https://169.254.169.254/latest",
+	] {
+		let req = test::TestRequest::post()
+			.uri(&uri)
+			.insert_header(test_utils::signed_in(&organizer))
+			.set_json(json!({ "url": url }))
+			.to_request();
+		let resp = test::call_service(&app, req).await;
+		assert!(resp.status().is_client_error());
+		let body: Value = test::read_body_json(resp).await;
+		assert_eq!(
+			body["details"]["url"][0],
+			"Must be an https URL on a public host"
+		);
+	}
+
+	let req = test::TestRequest::post()
+		.uri(&uri)
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(json!({ "url": "https://hooks.example.com/liga", "events": ["match.confirmed"] }))
+		.to_request();
+	let resp = test::call_service(&app, req).await;
+	assert_eq!(resp.status(), StatusCode::CREATED);
+	let created: Value = test::read_body_json(resp).await;
+	assert_eq!(created["data"]["secret"].as_str().unwrap().len(), 40);
+	let key = serde_json::from_value::<surrealdb::RecordId>(created["data"]["id"].clone())
+		.unwrap()
+		.key()
+		.to_string();
+
+	let req = test::TestRequest::patch()
+		.uri(&format!("{uri}/{key}"))
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(json!({ "active": false }))
+		.to_request();
+	let updated: Value = test::call_and_read_body_json(&app, req).await;
+	assert_eq!(updated["data"]["active"], false);
+
+	let req = test::TestRequest::get()
+		.uri(&uri)
+		.insert_header(test_utils::signed_in(&organizer))
+		.to_request();
+	let listed: Value = test::call_and_read_body_json(&app, req).await;
+	assert_eq!(listed["data"].as_array().unwrap().len(), 1);
+	assert!(listed["data"][0].get("secret").is_none());
+
+	let req = test::TestRequest::delete()
+		.uri(&format!("{uri}/{key}"))
+		.insert_header(test_utils::signed_in(&organizer))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::NO_CONTENT
+	);
+
+	let req = test::TestRequest::get()
+		.uri(&format!("{uri}/{key}/deliveries"))
+		.insert_header(test_utils::signed_in(&organizer))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::NOT_FOUND
+	);
+}
+
+#[actix_web::test]
+async fn test_imported_participants_are_announced_to_webhooks() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let player = factories::user("player").await;
+	let rival = factories::user("rival").await;
+	let tournament = factories::tournament(&organizer, true).await;
+	let state = AppState {
+		jobs: JobQueue::deferred(),
+		..AppState::new(Config::default())
+	};
+	let app = app!(state.clone());
+
+	let req = test::TestRequest::post()
+		.uri(&format!("/v1/tournaments/{}/webhooks", tournament.id.key()))
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(json!({ "url": "https://hooks.example.com/liga", "events": ["participant.joined"] }))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::CREATED
+	);
+
+	let req = test::TestRequest::post()
+		.uri(&format!(
+			"/v1/tournaments/{}/participants/import",
+			tournament.id.key()
+		))
+		.insert_header(test_utils::signed_in(&organizer))
+		.insert_header(("Content-Type", "text/csv"))
+		.set_payload(format!(
+			"username\n{}\n{}\n",
+			player.username, rival.username
+		))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::CREATED
+	);
+
+	assert_eq!(
+		state.jobs.pending_names(),
+		vec!["webhook_delivery", "webhook_delivery"]
+	);
+	let mut response = DB
+		.query("SELECT VALUE payload FROM webhook_delivery WHERE webhook.tournament = $tournament")
+		.bind(("tournament", tournament.id.clone()))
+		.await
+		.unwrap();
+	let payloads: Vec<Value> = response.take(0).unwrap();
+	let mut users: Vec<surrealdb::RecordId> = payloads
+		.iter()
+		.map(|payload| {
+			assert_eq!(payload["event"], "participant.joined");
+			serde_json::from_value(payload["data"]["user_id"].clone()).unwrap()
+		})
+		.collect();
+	users.sort_by_key(|user| user.to_string());
+	let mut expected = vec![player.id, rival.id];
+	expected.sort_by_key(|user| user.to_string());
+	assert_eq!(users, expected);
+}
+
+#[actix_web::test]
+async fn test_joins_get_the_strict_rate_limit() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let player = factories::user("player").await;
+	let rival = factories::user("rival").await;
+	let tournament = factories::tournament(&organizer, true).await;
+	let config = Config::default();
+	let strict = config
+		.rate_limit
+		.routes
+		.iter()
+		.find(|rule| rule.name == "participant_joins")
+		.unwrap()
+		.capacity;
+	let app = test::init_service(
+		App::new()
+			.wrap(from_fn(rate_limit::enforce))
+			.wrap(from_fn(test_utils::fake_auth))
+			.app_data(web::Data::new(AppState::new(config)))
+			.configure(routes::entry),
+	)
+	.await;
+	let join = |user: &User| {
+		test::TestRequest::post()
+			.uri(&format!(
+				"/v1/tournaments/{}/participants",
+				tournament.id.key()
+			))
+			.insert_header(test_utils::signed_in(user))
+			.to_request()
+	};
+
+	// Repeated joins are refused, but every attempt spends the budget
+	assert_eq!(
+		test::call_service(&app, join(&player)).await.status(),
+		StatusCode::CREATED
+	);
+	for _ in 1..strict {
+		assert_eq!(
+			test::call_service(&app, join(&player)).await.status(),
+			StatusCode::CONFLICT
+		);
+	}
+	assert_eq!(
+		test::call_service(&app, join(&player)).await.status(),
+		StatusCode::TOO_MANY_REQUESTS
+	);
+
+	// Other users have their own bucket
+	assert_eq!(
+		test::call_service(&app, join(&rival)).await.status(),
+		StatusCode::CREATED
+	);
+}
+
+#[actix_web::test]
+async fn test_tournament_changes_are_announced_to_webhooks() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let player = factories::user("player").await;
+	let tournament = factories::tournament(&organizer, false).await;
+	let state = AppState {
+		jobs: JobQueue::deferred(),
+		..AppState::new(Config::default())
+	};
+	let app = app!(state.clone());
+	let uri = format!("/v1/tournaments/{}", tournament.id.key());
+
+	let req = test::TestRequest::post()
+		.uri(&format!("{uri}/webhooks"))
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(json!({ "url": "https://hooks.example.com/liga" }))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::CREATED
+	);
+
+	for changes in [
+		json!({ "name": "Copa Calaca" }),
+		json!({ "published": true }),
+	] {
+		let req = test::TestRequest::patch()
+			.uri(&uri)
+			.insert_header(test_utils::signed_in(&organizer))
+			.set_json(changes)
+			.to_request();
+		assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+	}
+
+	let req = test::TestRequest::post()
+		.uri(&format!("{uri}/participants"))
+		.insert_header(test_utils::signed_in(&player))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::CREATED
+	);
+	let req = test::TestRequest::delete()
+		.uri(&format!("{uri}/participants/me"))
+		.insert_header(test_utils::signed_in(&player))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::NO_CONTENT
+	);
+
+	let mut response = DB
+		.query("SELECT VALUE event FROM webhook_delivery WHERE webhook.tournament = $tournament")
+		.bind(("tournament", tournament.id.clone()))
+		.await
+		.unwrap();
+	let mut events: Vec<String> = response.take(0).unwrap();
+	events.sort();
+	assert_eq!(
+		events,
+		vec![
+			"participant.joined",
+			"participant.left",
+			"tournament.published",
+			"tournament.updated"
+		]
+	);
+}
+
+#[actix_web::test]
+async fn test_only_finished_deliveries_are_replayed() {
+	test_utils::setup();
+	let organizer = factories::user("organizer").await;
+	let tournament = factories::tournament(&organizer, true).await;
+	let state = AppState {
+		jobs: JobQueue::deferred(),
+		..AppState::new(Config::default())
+	};
+	let app = app!(state.clone());
+	let uri = format!("/v1/tournaments/{}/webhooks", tournament.id.key());
+
+	let req = test::TestRequest::post()
+		.uri(&uri)
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(json!({ "url": "https://hooks.example.com/liga" }))
+		.to_request();
+	let created: Value = test::call_and_read_body_json(&app, req).await;
+	let webhook: surrealdb::RecordId = serde_json::from_value(created["data"]["id"].clone()).unwrap();
+
+	let req = test::TestRequest::post()
+		.uri(&format!("/v1/tournaments/{}/matches", tournament.id.key()))
+		.insert_header(test_utils::signed_in(&organizer))
+		.set_json(json!({ "round": 1, "position": 1, "home": null, "away": null, "scheduled_at": "2030-11-02T20:00:00Z" }))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::CREATED
+	);
+
+	let mut response = DB
+		.query("SELECT VALUE id FROM webhook_delivery WHERE webhook = $webhook")
+		.bind(("webhook", webhook.clone()))
+		.await
+		.unwrap();
+	let deliveries: Vec<surrealdb::RecordId> = response.take(0).unwrap();
+	let replay = || {
+		test::TestRequest::post()
+			.uri(&format!(
+				"{uri}/{}/deliveries/{}/replay",
+				webhook.key(),
+				deliveries[0].key()
+			))
+			.insert_header(test_utils::signed_in(&organizer))
+			.to_request()
+	};
+
+	// Still pending: its first run hasn't happened yet
+	assert_eq!(
+		test::call_service(&app, replay()).await.status(),
+		StatusCode::CONFLICT
+	);
+	assert_eq!(state.jobs.pending(), 1);
+
+	DB.query("UPDATE $id SET status = 'failed'")
+		.bind(("id", deliveries[0].clone()))
+		.await
+		.unwrap();
+	assert_eq!(
+		test::call_service(&app, replay()).await.status(),
+		StatusCode::ACCEPTED
+	);
+	assert_eq!(state.jobs.pending(), 2);
+
+	// The replay put it back to pending, so a second one is refused
+	assert_eq!(
+		test::call_service(&app, replay()).await.status(),
+		StatusCode::CONFLICT
+	);
+
+	// Left pending by a run that never finished, e.g. across a restart
+	DB.query("UPDATE $id SET queued_at = time::now() - 1d")
+		.bind(("id", deliveries[0].clone()))
+		.await
+		.unwrap();
+	assert_eq!(
+		test::call_service(&app, replay()).await.status(),
+		StatusCode::ACCEPTED
+	);
+	assert_eq!(state.jobs.pending(), 3);
+}
+
+/// Helix stand-in answering for the Twitch user `login` with the given token
+async fn mount_twitch_user(server: &MockServer, token: &str, id: &str, login: &str) {
+	Mock::given(method("GET"))
+		.and(path("/helix/users"))
+		.and(header("Authorization", format!("Bearer {token}").as_str()))
+		.respond_with(ResponseTemplate::new(200).set_body_json(json!({
+			"data": [{ "id": id, "login": login, "display_name": login }]
+		})))
+		.mount(server)
+		.await;
+}
+
+#[actix_web::test]
+async fn test_twitch_accounts_are_linked_once() {
+	test_utils::setup();
+	let server = MockServer::start().await;
+	Mock::given(method("POST"))
+		.and(path("/oauth2/token"))
+		.respond_with(ResponseTemplate::new(200).set_body_json(json!({
+			"access_token": "app-token",
+			"expires_in": 3600,
+		})))
+		.mount(&server)
+		.await;
+	Mock::given(method("GET"))
+		.and(path("/helix/streams"))
+		.respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": [] })))
+		.mount(&server)
+		.await;
+	let streamer = test_utils::unique_key("tw");
+	mount_twitch_user(&server, "first-token", &format!("{streamer}_1"), "catrina").await;
+	mount_twitch_user(&server, "second-token", &format!("{streamer}_2"), "calaca").await;
+
+	let state = AppState {
+		twitch: Some(Arc::new(TwitchClient::new(TwitchConfig {
+			client_id: "client-id".to_string(),
+			client_secret: Secret::new("client-secret"),
+			api_url: format!("{}/helix", server.uri()),
+			auth_url: format!("{}/oauth2", server.uri()),
+		}))),
+		..AppState::new(Config::default())
+	};
+	let app = app!(state);
+	let user = factories::user("streamer").await;
+	let other = factories::user("viewer").await;
+	let link = |user: &User, token: &str| {
+		test::TestRequest::post()
+			.uri("/v1/twitch/link")
+			.insert_header(test_utils::signed_in(user))
+			.set_json(json!({ "access_token": token }))
+			.to_request()
+	};
+	let me = |user: &User| {
+		test::TestRequest::get()
+			.uri("/v1/twitch/me")
+			.insert_header(test_utils::signed_in(user))
+			.to_request()
+	};
+
+	let req = test::TestRequest::post()
+		.uri("/v1/twitch/link")
+		.set_json(json!({ "access_token": "first-token" }))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::UNAUTHORIZED
+	);
+
+	let resp = test::call_service(&app, link(&user, "first-token")).await;
+	assert_eq!(resp.status(), StatusCode::CREATED);
+
+	// Linking again replaces the previous account
+	let resp = test::call_service(&app, link(&user, "second-token")).await;
+	assert_eq!(resp.status(), StatusCode::CREATED);
+	let account: Value = test::call_and_read_body_json(&app, me(&user)).await;
+	assert_eq!(account["data"]["login"], "calaca");
+	let mut response = DB
+		.query("SELECT VALUE login FROM twitch_account WHERE user = $user")
+		.bind(("user", user.id.clone()))
+		.await
+		.unwrap();
+	let logins: Vec<String> = response.take(0).unwrap();
+	assert_eq!(logins, vec!["calaca"]);
+
+	// A Twitch account belongs to a single user
+	let resp = test::call_service(&app, link(&other, "second-token")).await;
+	assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+	let req = test::TestRequest::delete()
+		.uri("/v1/twitch/link")
+		.insert_header(test_utils::signed_in(&user))
+		.to_request();
+	assert_eq!(
+		test::call_service(&app, req).await.status(),
+		StatusCode::NO_CONTENT
+	);
+	assert_eq!(
+		test::call_service(&app, me(&user)).await.status(),
+		StatusCode::NOT_FOUND
+	);
+}